@@ -0,0 +1,60 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    // Generate the C header of `src/capi.rs`.
+    #[cfg(feature = "capi")]
+    {
+        println!("cargo:rerun-if-changed=src/capi.rs");
+        let config = cbindgen::Config {
+            language: cbindgen::Language::C,
+            include_guard: Some("PARABOX_H".into()),
+            cpp_compat: true,
+            documentation_style: cbindgen::DocumentationStyle::C99,
+            autogen_warning: Some(
+                "/* Generated by the build script with the `capi` feature. Do not edit. */".into(),
+            ),
+            ..Default::default()
+        };
+        cbindgen::Builder::new()
+            .with_src("src/capi.rs")
+            .with_config(config)
+            .generate()
+            .expect("Failed to generate the C header")
+            .write_to_file("include/parabox.h");
+    }
+
+    // Generate the gRPC service of `proto/parabox.proto` for messages in `src/grpc.rs`.
+    #[cfg(feature = "grpc")]
+    {
+        use tonic_build::manual::{Builder, Method, Service};
+
+        let method = |name: &str, route: &str, input: &str, output: &str| {
+            Method::builder()
+                .name(name)
+                .route_name(route)
+                .input_type(format!("crate::grpc::{input}"))
+                .output_type(format!("crate::grpc::{output}"))
+                .codec_path("tonic::codec::ProstCodec")
+        };
+        let service = Service::builder()
+            .name("Parabox")
+            .package("parabox")
+            .method(
+                method("solve", "Solve", "SolveRequest", "SolveEvent")
+                    .server_streaming()
+                    .build(),
+            )
+            .method(method("move_", "Move", "MoveRequest", "MoveResponse").build())
+            .method(
+                method(
+                    "validate",
+                    "Validate",
+                    "ValidateRequest",
+                    "ValidateResponse",
+                )
+                .build(),
+            )
+            .build();
+        Builder::new().build_client(false).compile(&[service]);
+    }
+}
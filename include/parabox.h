@@ -0,0 +1,77 @@
+#ifndef PARABOX_H
+#define PARABOX_H
+
+/* Generated by the build script with the `capi` feature. Do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// A level being played, created by `pbx_parse`.
+typedef struct PbxGame PbxGame;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// The message of the last error on this thread, or NULL if none. It is valid until the next
+// call failing on this thread.
+const char *pbx_last_error(void);
+
+// Free a string returned by the library. Does nothing on NULL.
+//
+// # Safety
+// `s` must be NULL or returned by the library, and not freed yet.
+void pbx_string_free(char *s);
+
+// Parse a level in the text format, returning NULL on failure.
+//
+// # Safety
+// `text` must be a valid NUL-terminated string.
+struct PbxGame *pbx_parse(const char *text);
+
+// Free a level. Does nothing on NULL.
+//
+// # Safety
+// `game` must be NULL or returned by `pbx_parse`, and not freed yet.
+void pbx_free(struct PbxGame *game);
+
+// Move the player by a LURD character, case-insensitive. Returns 1 if it pushes something, 0 if
+// it does not, or -1 if the move is invalid, leaving the level unchanged.
+//
+// # Safety
+// `game` must be returned by `pbx_parse` and not freed.
+int32_t pbx_go(struct PbxGame *game, char dir);
+
+// Restart the level from its initial state.
+//
+// # Safety
+// `game` must be returned by `pbx_parse` and not freed.
+void pbx_reset(struct PbxGame *game);
+
+// Whether the current state wins the level.
+//
+// # Safety
+// `game` must be returned by `pbx_parse` and not freed.
+bool pbx_is_success(const struct PbxGame *game);
+
+// Solve the level from the current state by breadth-first search, expanding at most
+// `max_nodes` states, or without a limit if it is 0. Returns the solution in LURD, or NULL if
+// the level is unsolvable or the limit is reached.
+//
+// # Safety
+// `game` must be returned by `pbx_parse` and not freed.
+char *pbx_solve(const struct PbxGame *game, uint64_t max_nodes);
+
+// Render the current state in the text format.
+//
+// # Safety
+// `game` must be returned by `pbx_parse` and not freed.
+char *pbx_render(const struct PbxGame *game);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* PARABOX_H */
@@ -0,0 +1,138 @@
+//! Integration with the Bevy engine, for embedding levels into games.
+//!
+//! `ParaboxPlugin` applies `MoveInput` events to the `Level` resource, and reports each move by
+//! a `LevelMoved` or `MoveRejected` event. Entities drawing things of the level can carry a
+//! `Piece` component, which follows the thing it is at when it is moved.
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//! use parabox_solver::bevy::{Level, LevelMoved, MoveInput, ParaboxPlugin};
+//! use parabox_solver::{Direction, Game};
+//!
+//! fn log_moves(mut moved: EventReader<LevelMoved>) {
+//!     for event in moved.read() {
+//!         println!("{}", event.outcome);
+//!     }
+//! }
+//!
+//! let game = "0\n#####\n#pb.#\n#####\n".parse::<Game>().unwrap();
+//! let mut app = App::new();
+//! app.add_plugins(ParaboxPlugin)
+//!     .insert_resource(Level::new(game))
+//!     .add_systems(Update, log_moves);
+//! app.world_mut().send_event(MoveInput(Direction::Right));
+//! app.update();
+//! ```
+
+use ::bevy::app::{App, Plugin, Update};
+use ::bevy::ecs::prelude::*;
+
+use crate::{Direction, Error, Game, GlobalPos, MoveOutcome, State};
+
+/// The level being played, with its current state and moves.
+#[derive(Resource, Debug, Clone)]
+pub struct Level {
+    /// The level at its initial state.
+    pub game: Game,
+    pub state: State,
+    /// Moves applied to the initial state, leading to `state`.
+    pub moves: Vec<Direction>,
+}
+
+impl Level {
+    pub fn new(game: Game) -> Self {
+        let state = game.state.clone();
+        Self {
+            game,
+            state,
+            moves: Vec::new(),
+        }
+    }
+
+    /// Whether the current state wins the level.
+    pub fn is_success(&self) -> bool {
+        self.state.is_success_on(&self.game.config)
+    }
+
+    /// Restart from the initial state.
+    pub fn reset(&mut self) {
+        self.state = self.game.state.clone();
+        self.moves.clear();
+    }
+}
+
+/// A request to move the player.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveInput(pub Direction);
+
+/// A move applied to the `Level`.
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct LevelMoved {
+    pub outcome: MoveOutcome,
+    /// Whether the level is won after the move.
+    pub is_success: bool,
+}
+
+/// A move which is not possible, leaving the `Level` unchanged.
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct MoveRejected {
+    pub dir: Direction,
+    pub error: Error,
+}
+
+/// The location of the thing drawn by an entity, updated as the thing is moved.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Piece(pub GlobalPos);
+
+/// Adds events of moves, and systems applying `MoveInput` to the `Level` resource if any, then
+/// updating `Piece`s, in `Update`.
+pub struct ParaboxPlugin;
+
+impl Plugin for ParaboxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<MoveInput>()
+            .add_event::<LevelMoved>()
+            .add_event::<MoveRejected>()
+            .add_systems(Update, (apply_moves, follow_moves).chain());
+    }
+}
+
+/// Apply `MoveInput` events to the `Level` in order, reporting each of them.
+pub fn apply_moves(
+    level: Option<ResMut<Level>>,
+    mut inputs: EventReader<MoveInput>,
+    mut moved: EventWriter<LevelMoved>,
+    mut rejected: EventWriter<MoveRejected>,
+) {
+    let Some(mut level) = level else {
+        inputs.clear();
+        return;
+    };
+    for &MoveInput(dir) in inputs.read() {
+        match level.state.go_with_outcome(dir) {
+            Ok(outcome) => {
+                level.moves.push(dir);
+                let is_success = level.is_success();
+                moved.write(LevelMoved {
+                    outcome,
+                    is_success,
+                });
+            }
+            Err(error) => {
+                rejected.write(MoveRejected { dir, error });
+            }
+        }
+    }
+}
+
+/// Move `Piece`s at things moved by `LevelMoved` events along with them.
+pub fn follow_moves(mut moved: EventReader<LevelMoved>, mut pieces: Query<&mut Piece>) {
+    for event in moved.read() {
+        for mut piece in &mut pieces {
+            let cur = piece.0;
+            if let Some(thing) = event.outcome.moved.iter().find(|thing| thing.from == cur) {
+                piece.0 = thing.to;
+            }
+        }
+    }
+}
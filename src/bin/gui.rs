@@ -0,0 +1,467 @@
+//! A desktop viewer of levels, behind the `gui` feature.
+//!
+//! `gui [path]` opens the level, where arrow keys or WASD move, clicking a cell of the board of
+//! the player walks there, Z undoes, Y redoes, and R resets. The solver runs in the background
+//! from the current state with live progress, and found solutions can be played back.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use eframe::egui::{self, Align2, Color32, FontId, Key, Rect, Sense, Stroke, Vec2 as EVec2};
+use parabox_solver::solve::{BfsSearch, Progress, SolveOutcome, SolverOptions};
+use parabox_solver::{
+    solve, BoardId, Direction, Game, GlobalPos, NestedRenderer, Session, State, Step, Theme, Vec2,
+};
+
+/// The size of cells in points.
+const CELL_SIZE: f32 = 32.0;
+/// The gap between boards in points.
+const GAP: f32 = 24.0;
+/// The delay between moves of played back solutions.
+const PLAYBACK_DELAY: Duration = Duration::from_millis(200);
+/// The count of attempted moves between progress reports of the solver.
+const PROGRESS_BULK: u64 = 1 << 14;
+
+enum SolverMessage {
+    Progress(Progress),
+    Done(SolveOutcome),
+}
+
+/// The solver running in the background.
+struct SolverJob {
+    cancel: Arc<AtomicBool>,
+    messages: Receiver<SolverMessage>,
+    progress: Progress,
+    start: Instant,
+    /// Steps of the session when the solver started.
+    from_steps: Vec<Step>,
+}
+
+struct Viewer {
+    path: String,
+    session: Option<Session>,
+    /// The current state of the session.
+    state: Option<State>,
+    status: String,
+    solver: Option<SolverJob>,
+    /// The found solution, from the state after steps of the session.
+    solution: Option<(Vec<Step>, Vec<Direction>)>,
+    /// Moves being played back, and when the last one is played.
+    playback: Option<(std::vec::IntoIter<Direction>, Instant)>,
+    theme: Theme,
+}
+
+impl Viewer {
+    fn new(path: Option<String>) -> Self {
+        let mut viewer = Self {
+            path: path.clone().unwrap_or_default(),
+            session: None,
+            state: None,
+            status: String::new(),
+            solver: None,
+            solution: None,
+            playback: None,
+            theme: Theme::default(),
+        };
+        if path.is_some() {
+            viewer.load();
+        }
+        viewer
+    }
+
+    fn load(&mut self) {
+        let loaded = std::fs::read_to_string(&self.path)
+            .context("Failed to read the map")
+            .and_then(|text| text.parse::<Game>().context("Failed to parse the map"));
+        match loaded {
+            Ok(game) => {
+                self.cancel_solver();
+                self.state = Some(game.state.clone());
+                self.session = Some(Session::new(game));
+                self.solution = None;
+                self.playback = None;
+                self.status = format!("Loaded {}", self.path);
+            }
+            Err(err) => self.status = format!("{err:#}"),
+        }
+    }
+
+    fn refresh(&mut self) {
+        let Some(session) = &self.session else { return };
+        match session.states() {
+            Ok(mut states) => self.state = states.pop(),
+            Err(err) => self.status = format!("{err:#}"),
+        }
+    }
+
+    fn go(&mut self, dir: Direction) -> Result<()> {
+        let (Some(session), Some(state)) = (&mut self.session, &mut self.state) else {
+            return Ok(());
+        };
+        state.go(dir)?;
+        session.push(Step::Go(dir));
+        if state.is_success_on(&session.game.config) {
+            self.status = format!("Solved in {} moves", session.steps.len());
+        }
+        Ok(())
+    }
+
+    fn undo(&mut self) {
+        if let Some(session) = &mut self.session {
+            session.undo();
+            self.refresh();
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(session) = &mut self.session {
+            session.redo();
+            self.refresh();
+        }
+    }
+
+    fn reset(&mut self) {
+        if let Some(session) = &mut self.session {
+            session.push(Step::Reset);
+            self.refresh();
+        }
+    }
+
+    fn start_solver(&mut self, ctx: &egui::Context) {
+        let (Some(session), Some(state)) = (&self.session, &self.state) else {
+            return;
+        };
+        let game = Game {
+            state: state.clone(),
+            ..session.game.clone()
+        };
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (sender, messages) = mpsc::channel();
+        let options = SolverOptions {
+            cancel: Some(cancel.clone()),
+            ..SolverOptions::default()
+        };
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let on_progress = |progress: &Progress| {
+                if progress.steps.is_multiple_of(PROGRESS_BULK) {
+                    let _ = sender.send(SolverMessage::Progress(*progress));
+                    ctx.request_repaint();
+                }
+            };
+            let outcome = BfsSearch::new(game).run(&options, on_progress);
+            let _ = sender.send(SolverMessage::Done(outcome));
+            ctx.request_repaint();
+        });
+        self.solver = Some(SolverJob {
+            cancel,
+            messages,
+            progress: Progress::default(),
+            start: Instant::now(),
+            from_steps: session.steps.clone(),
+        });
+        self.solution = None;
+        self.status = "Solving".into();
+    }
+
+    fn cancel_solver(&mut self) {
+        if let Some(job) = self.solver.take() {
+            job.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn poll_solver(&mut self) {
+        let Some(job) = &mut self.solver else { return };
+        let mut done = None;
+        for message in job.messages.try_iter() {
+            match message {
+                SolverMessage::Progress(progress) => job.progress = progress,
+                SolverMessage::Done(outcome) => done = Some(outcome),
+            }
+        }
+        let Some(outcome) = done else { return };
+        let from_steps = std::mem::take(&mut job.from_steps);
+        self.solver = None;
+        self.status = match outcome {
+            SolveOutcome::Solved(solution) => {
+                let status = format!(
+                    "Found a solution of {} moves and {} pushes in {:?}",
+                    solution.moves.len(),
+                    solution.pushes,
+                    solution.duration,
+                );
+                self.solution = Some((from_steps, solution.moves));
+                status
+            }
+            SolveOutcome::Unsolvable(_) => "Unsolvable".into(),
+            SolveOutcome::LimitReached(limit, _) => format!("Limit reached: {limit:?}"),
+            SolveOutcome::Cancelled(_) => "Cancelled".into(),
+            SolveOutcome::Unsupported(reason) => format!("Unsupported: {reason}"),
+        };
+    }
+
+    /// Play back the found solution from the state it is found at.
+    fn play_solution(&mut self) {
+        let (Some(session), Some((from_steps, moves))) = (&mut self.session, &self.solution) else {
+            return;
+        };
+        session.steps = from_steps.clone();
+        session.redo.clear();
+        self.playback = Some((moves.clone().into_iter(), Instant::now()));
+        self.refresh();
+    }
+
+    fn step_playback(&mut self, ctx: &egui::Context) {
+        let Some((moves, last)) = &mut self.playback else {
+            return;
+        };
+        if last.elapsed() < PLAYBACK_DELAY {
+            ctx.request_repaint_after(PLAYBACK_DELAY - last.elapsed());
+            return;
+        }
+        *last = Instant::now();
+        let Some(dir) = moves.next() else {
+            self.playback = None;
+            return;
+        };
+        if let Err(err) = self.go(dir) {
+            self.status = format!("Playback failed: {err}");
+            self.playback = None;
+        }
+        ctx.request_repaint_after(PLAYBACK_DELAY);
+    }
+
+    fn handle_keys(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let pressed = |keys: &[Key]| ctx.input(|i| keys.iter().any(|&key| i.key_pressed(key)));
+        let moves = [
+            ([Key::ArrowLeft, Key::A], Direction::Left),
+            ([Key::ArrowRight, Key::D], Direction::Right),
+            ([Key::ArrowUp, Key::W], Direction::Up),
+            ([Key::ArrowDown, Key::S], Direction::Down),
+        ];
+        for (keys, dir) in moves {
+            if pressed(&keys) {
+                self.playback = None;
+                if let Err(err) = self.go(dir) {
+                    self.status = format!("Cannot move {dir:?}: {err}");
+                }
+            }
+        }
+        if pressed(&[Key::Z]) {
+            self.undo();
+        }
+        if pressed(&[Key::Y]) {
+            self.redo();
+        }
+        if pressed(&[Key::R]) {
+            self.reset();
+        }
+    }
+
+    fn toolbar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Level:");
+            let edit = ui.text_edit_singleline(&mut self.path);
+            if ui.button("Load").clicked()
+                || (edit.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)))
+            {
+                self.load();
+            }
+        });
+        ui.horizontal(|ui| {
+            let loaded = self.session.is_some();
+            if ui.add_enabled(loaded, egui::Button::new("Undo")).clicked() {
+                self.undo();
+            }
+            if ui.add_enabled(loaded, egui::Button::new("Redo")).clicked() {
+                self.redo();
+            }
+            if ui.add_enabled(loaded, egui::Button::new("Reset")).clicked() {
+                self.reset();
+            }
+            ui.separator();
+            if self.solver.is_some() {
+                if ui.button("Cancel").clicked() {
+                    self.cancel_solver();
+                    self.status = "Cancelled".into();
+                }
+            } else if ui.add_enabled(loaded, egui::Button::new("Solve")).clicked() {
+                self.start_solver(ui.ctx());
+            }
+            let playable = self.solution.is_some() && self.playback.is_none();
+            if ui
+                .add_enabled(playable, egui::Button::new("Play solution"))
+                .clicked()
+            {
+                self.play_solution();
+            }
+        });
+        if let Some(job) = &self.solver {
+            let progress = &job.progress;
+            ui.label(format!(
+                "Elapsed: {:.1?} Depth: {} Expanded: {} Frontier: {} Memory: {} MiB",
+                job.start.elapsed(),
+                progress.depth,
+                progress.expanded,
+                progress.frontier,
+                progress.memory_bytes >> 20,
+            ));
+        }
+        if let Some(session) = &self.session {
+            ui.label(format!("Moves: {}", session.steps.len()));
+        }
+        ui.label(&self.status);
+    }
+
+    /// Draw all boards side by side, returning the clicked cell if any.
+    fn boards(&self, ui: &mut egui::Ui) -> Option<GlobalPos> {
+        let (session, state) = (self.session.as_ref()?, self.state.as_ref()?);
+        let config = &session.game.config;
+        let glyphs = &self.theme.glyphs;
+        let colors = &self.theme.colors;
+        let color = |rgb: [u8; 3]| Color32::from_rgb(rgb[0], rgb[1], rgb[2]);
+        // Every board as lines of glyphs, by a renderer without nesting and scaling.
+        let boards = (0..state.to_compact_string().split('|').count())
+            .map(|id| {
+                let board_id = BoardId::try_from(id).unwrap();
+                let renderer = NestedRenderer {
+                    depth: 0,
+                    cell_size: 1,
+                    root: Some(board_id),
+                    theme: self.theme,
+                };
+                let text = renderer.render(state, config);
+                let lines = text
+                    .lines()
+                    .map(|line| line.chars().collect::<Vec<_>>())
+                    .collect::<Vec<_>>();
+                (board_id, lines)
+            })
+            .collect::<Vec<_>>();
+        let width = boards
+            .iter()
+            .map(|(_, lines)| line_width(lines) + GAP)
+            .sum::<f32>();
+        let height = boards
+            .iter()
+            .map(|(_, lines)| lines.len() as f32 * CELL_SIZE)
+            .fold(0.0, f32::max)
+            + GAP;
+        let (response, painter) = ui.allocate_painter(EVec2::new(width, height), Sense::click());
+
+        let font = FontId::monospace(CELL_SIZE * 0.6);
+        let mut clicked = None;
+        let mut left = response.rect.left();
+        for (board_id, lines) in boards {
+            let top = response.rect.top() + GAP;
+            let label_pos = egui::pos2(left, top - GAP / 2.0);
+            let label = format!("{:X}", board_id as usize);
+            painter.text(
+                label_pos,
+                Align2::LEFT_CENTER,
+                label,
+                font.clone(),
+                Color32::GRAY,
+            );
+            for (row, line) in lines.iter().enumerate() {
+                for (col, &ch) in line.iter().enumerate() {
+                    let min =
+                        egui::pos2(left + col as f32 * CELL_SIZE, top + row as f32 * CELL_SIZE);
+                    let rect = Rect::from_min_size(min, EVec2::splat(CELL_SIZE));
+                    let (fill, text) = match ch {
+                        _ if ch == glyphs.wall => (colors.wall, None),
+                        _ if ch == glyphs.box_cell => (colors.box_cell, None),
+                        _ if ch == glyphs.player => (colors.player, None),
+                        _ if ch == glyphs.box_target => (colors.box_target, None),
+                        _ if ch == glyphs.player_target => (colors.player_target, None),
+                        _ if ch.is_ascii_hexdigit() => {
+                            let id = ch.to_digit(16).unwrap() as usize;
+                            (colors.boards[id % colors.boards.len()], Some(ch))
+                        }
+                        _ => (colors.empty, None),
+                    };
+                    painter.rect_filled(rect, 0.0, color(fill));
+                    painter.rect_stroke(rect, 0.0, Stroke::new(0.5, Color32::from_gray(160)));
+                    if let Some(text) = text {
+                        painter.text(
+                            rect.center(),
+                            Align2::CENTER_CENTER,
+                            text,
+                            font.clone(),
+                            Color32::WHITE,
+                        );
+                    }
+                    let is_clicked = response.clicked()
+                        && response
+                            .interact_pointer_pos()
+                            .is_some_and(|pos| rect.contains(pos));
+                    if is_clicked {
+                        clicked = Some(GlobalPos {
+                            board_id,
+                            pos: Vec2(row as u8, col as u8),
+                        });
+                    }
+                }
+            }
+            left += line_width(&lines) + GAP;
+        }
+        clicked
+    }
+}
+
+/// The width of a board of lines of glyphs in points.
+fn line_width(lines: &[Vec<char>]) -> f32 {
+    lines[0].len() as f32 * CELL_SIZE
+}
+
+impl eframe::App for Viewer {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.poll_solver();
+        self.step_playback(ctx);
+        self.handle_keys(ctx);
+        egui::CentralPanel::default().show(ctx, |ui| {
+            self.toolbar(ui);
+            ui.separator();
+            egui::ScrollArea::both().show(ui, |ui| {
+                let Some(target) = self.boards(ui) else {
+                    return;
+                };
+                let Some(state) = &self.state else { return };
+                match solve::walk_to(state, target) {
+                    Some(moves) => {
+                        self.playback = None;
+                        for dir in moves {
+                            if let Err(err) = self.go(dir) {
+                                self.status = format!("Cannot move {dir:?}: {err}");
+                                break;
+                            }
+                        }
+                    }
+                    None => self.status = "Unreachable without pushing".into(),
+                }
+            });
+        });
+        if self.solver.is_some() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.cancel_solver();
+    }
+}
+
+fn main() -> eframe::Result {
+    let path = std::env::args().nth(1);
+    eframe::run_native(
+        "Parabox solver",
+        eframe::NativeOptions::default(),
+        Box::new(|_cc| Ok(Box::new(Viewer::new(path)))),
+    )
+}
@@ -0,0 +1,193 @@
+//! Compact binary encoding of levels, for embedding many levels into other programs or
+//! transmitting them.
+//!
+//! The encoding is the following bytes:
+//!
+//! - The magic `PBOX`, then the version, currently 1.
+//! - The count of boards, then the size of each board as a byte of `height << 4 | width`.
+//! - Cells of all boards in row-major order, as codes of the same bit width, packed from the
+//!   most significant bit and zero-padded to whole bytes. Codes are 0 for empty, 1 for walls, 2
+//!   for boxes and the player, and `3 + id` for the board `id`. The bit width is the least one
+//!   holding `3 + board_cnt` codes.
+//! - The location of the player, then the player target, as two bytes of board id, and
+//!   `row << 4 | col`. A missing player target is two `0xFF` bytes.
+//! - The count of box targets as a little-endian `u16`, then their locations.
+//!
+//! Unlike share strings, the encoding is not compressed, thus cheap to decode.
+
+use anyhow::{ensure, Context, Result};
+
+use crate::parse::build_game;
+use crate::{Board, BoardId, Cell, Game, GlobalPos, Vec2, MAX_BOARD_CNT, MAX_BOARD_WIDTH};
+
+const MAGIC: &[u8; 4] = b"PBOX";
+const VERSION: u8 = 1;
+/// The bytes of the player target of levels without it.
+const NO_PLAYER_TARGET: u8 = 0xFF;
+
+const _: () = assert!(MAX_BOARD_CNT <= 16 && MAX_BOARD_WIDTH <= 16);
+
+/// The bit width of cell codes of levels with `board_cnt` boards.
+fn code_bits(board_cnt: usize) -> u32 {
+    usize::BITS - (3 + board_cnt - 1).leading_zeros()
+}
+
+impl Game {
+    /// Encode the level into compact bytes, which can be decoded by `Game::from_bytes`.
+    ///
+    /// Metadata are not encoded.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let state = &self.state;
+        let board_cnt = state.boards.len();
+        let mut buf = MAGIC.to_vec();
+        buf.extend([VERSION, board_cnt as u8]);
+        buf.extend(state.boards.iter().map(|b| b.height << 4 | b.width));
+
+        let bits = code_bits(board_cnt);
+        let (mut acc, mut acc_bits) = (0u32, 0u32);
+        for id in 0..board_cnt {
+            let board_id = BoardId::try_from(id).unwrap();
+            for (_, cell) in state.board_cells(board_id) {
+                let code = match cell {
+                    Cell::Empty => 0,
+                    Cell::Wall => 1,
+                    Cell::Box => 2,
+                    Cell::Board(id) => 3 + id as u32,
+                };
+                acc = acc << bits | code;
+                acc_bits += bits;
+                while acc_bits >= 8 {
+                    acc_bits -= 8;
+                    buf.push((acc >> acc_bits) as u8);
+                }
+                acc &= (1 << acc_bits) - 1;
+            }
+        }
+        if acc_bits != 0 {
+            buf.push((acc << (8 - acc_bits)) as u8);
+        }
+
+        let config = &self.config;
+        let push_gpos = |buf: &mut Vec<u8>, gpos: GlobalPos| {
+            buf.extend([gpos.board_id as u8, gpos.pos.0 << 4 | gpos.pos.1]);
+        };
+        push_gpos(&mut buf, state.player);
+        match config.player_target {
+            Some(gpos) => push_gpos(&mut buf, gpos),
+            None => buf.extend([NO_PLAYER_TARGET; 2]),
+        }
+        buf.extend((config.box_targets.len() as u16).to_le_bytes());
+        for &gpos in config.box_targets.iter() {
+            push_gpos(&mut buf, gpos);
+        }
+        buf
+    }
+
+    /// Decode a level from bytes returned by `Game::to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader(bytes.iter());
+        ensure!(bytes.starts_with(MAGIC), "Not a level in the binary format");
+        reader.0.nth(MAGIC.len() - 1);
+
+        let version = reader.byte()?;
+        ensure!(version == VERSION, "Unsupported version: {version}");
+        let board_cnt = reader.byte()? as usize;
+        ensure!(
+            0 < board_cnt && board_cnt < MAX_BOARD_CNT,
+            "Invalid count of boards"
+        );
+        let mut sizes = Vec::with_capacity(board_cnt);
+        for board_id in 0..board_cnt {
+            let size = reader.byte()?;
+            let (height, width) = (size >> 4, size & 0xF);
+            ensure!(
+                0 < height.min(width) && (height.max(width) as usize) < MAX_BOARD_WIDTH,
+                "Invalid size of board {board_id}",
+            );
+            sizes.push((height, width));
+        }
+
+        let bits = code_bits(board_cnt);
+        let (mut acc, mut acc_bits) = (0u32, 0u32);
+        let mut boards = Vec::with_capacity(board_cnt);
+        let mut walls = Vec::with_capacity(board_cnt);
+        for (height, width) in sizes {
+            let size = height as usize * width as usize;
+            let mut grid = Vec::with_capacity(size);
+            let mut board_walls = Vec::with_capacity(size);
+            for _ in 0..size {
+                while acc_bits < bits {
+                    acc = acc << 8 | reader.byte()? as u32;
+                    acc_bits += 8;
+                }
+                acc_bits -= bits;
+                let code = acc >> acc_bits;
+                acc &= (1 << acc_bits) - 1;
+                let cell = match code {
+                    // Walls are stored separately.
+                    0 | 1 => Cell::Empty,
+                    2 => Cell::Box,
+                    _ => {
+                        let id = (code - 3) as usize;
+                        ensure!(id < board_cnt, "Board id {id} out of bound {board_cnt}");
+                        Cell::Board(id.try_into().unwrap())
+                    }
+                };
+                grid.push(cell);
+                board_walls.push(code == 1);
+            }
+            boards.push(Board {
+                height,
+                width,
+                grid: grid.into(),
+            });
+            walls.push(board_walls.into_boxed_slice());
+        }
+        ensure!(acc == 0, "Invalid padding of cells");
+
+        let player = reader.gpos(&boards)?;
+        let player_target = if reader.0.as_slice().starts_with(&[NO_PLAYER_TARGET]) {
+            ensure!(
+                (0..2).all(|_| reader.byte().ok() == Some(NO_PLAYER_TARGET)),
+                "Invalid player target",
+            );
+            None
+        } else {
+            Some(reader.gpos(&boards)?)
+        };
+        let box_target_cnt = u16::from_le_bytes([reader.byte()?, reader.byte()?]);
+        let box_targets = (0..box_target_cnt)
+            .map(|_| reader.gpos(&boards))
+            .collect::<Result<Vec<_>>>()?;
+        ensure!(reader.0.len() == 0, "Trailing data");
+        ensure!(
+            boards[player.board_id as usize][player.pos] == Cell::Box,
+            "Player is not a box",
+        );
+        build_game(boards, walls, player, player_target, box_targets)
+    }
+}
+
+struct Reader<'a>(std::slice::Iter<'a, u8>);
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8> {
+        self.0.next().copied().context("Unexpected end of data")
+    }
+
+    fn gpos(&mut self, boards: &[Board]) -> Result<GlobalPos> {
+        let (id, pos) = (self.byte()? as usize, self.byte()?);
+        let (row, col) = (pos >> 4, pos & 0xF);
+        let board = boards
+            .get(id)
+            .with_context(|| format!("Board id {id} out of bound {}", boards.len()))?;
+        ensure!(
+            row < board.height && col < board.width,
+            "Position out of board {id}",
+        );
+        Ok(GlobalPos {
+            board_id: id.try_into().unwrap(),
+            pos: Vec2(row, col),
+        })
+    }
+}
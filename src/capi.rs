@@ -0,0 +1,189 @@
+//! C ABI for embedding the engine in other languages, behind the `capi` feature.
+//!
+//! Build the shared library with `cargo rustc --lib --release --features capi --crate-type
+//! cdylib`. The header `include/parabox.h` is generated by the build script from this module
+//! when the feature is enabled.
+//!
+//! Strings are NUL-terminated UTF-8. Strings returned by the library are owned by the caller and
+//! must be freed by `pbx_string_free`. Functions failing set a message returned by
+//! `pbx_last_error` of the same thread.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+
+use crate::solve::{self, SolveOutcome, SolverOptions};
+use crate::{Direction, Game, State};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(msg: impl std::fmt::Display) {
+    // Messages never contain NUL except from malformed input, which is replaced.
+    let msg = CString::new(msg.to_string().replace('\0', "\\0")).unwrap();
+    LAST_ERROR.with(|err| *err.borrow_mut() = Some(msg));
+}
+
+fn into_c_string(s: String) -> *mut c_char {
+    CString::new(s).unwrap().into_raw()
+}
+
+/// A level being played, created by `pbx_parse`.
+pub struct PbxGame {
+    /// The level at its initial state.
+    game: Game,
+    state: State,
+}
+
+impl PbxGame {
+    /// The level at the current state.
+    fn current(&self) -> Game {
+        Game {
+            config: self.game.config.clone(),
+            state: self.state.clone(),
+            metadata: self.game.metadata.clone(),
+        }
+    }
+}
+
+/// The message of the last error on this thread, or NULL if none. It is valid until the next
+/// call failing on this thread.
+#[no_mangle]
+pub extern "C" fn pbx_last_error() -> *const c_char {
+    LAST_ERROR.with(|err| {
+        err.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |msg| msg.as_ptr())
+    })
+}
+
+/// Free a string returned by the library. Does nothing on NULL.
+///
+/// # Safety
+/// `s` must be NULL or returned by the library, and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn pbx_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Parse a level in the text format, returning NULL on failure.
+///
+/// # Safety
+/// `text` must be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn pbx_parse(text: *const c_char) -> *mut PbxGame {
+    let text = match CStr::from_ptr(text).to_str() {
+        Ok(text) => text,
+        Err(err) => {
+            set_error(err);
+            return ptr::null_mut();
+        }
+    };
+    match text.parse::<Game>() {
+        Ok(game) => Box::into_raw(Box::new(PbxGame {
+            state: game.state.clone(),
+            game,
+        })),
+        Err(err) => {
+            set_error(format_args!("{err:#}"));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Free a level. Does nothing on NULL.
+///
+/// # Safety
+/// `game` must be NULL or returned by `pbx_parse`, and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn pbx_free(game: *mut PbxGame) {
+    if !game.is_null() {
+        drop(Box::from_raw(game));
+    }
+}
+
+/// Move the player by a LURD character, case-insensitive. Returns 1 if it pushes something, 0 if
+/// it does not, or -1 if the move is invalid, leaving the level unchanged.
+///
+/// # Safety
+/// `game` must be returned by `pbx_parse` and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn pbx_go(game: *mut PbxGame, dir: c_char) -> i32 {
+    let game = &mut *game;
+    let dir = match (dir as u8).to_ascii_lowercase() {
+        b'l' => Direction::Left,
+        b'u' => Direction::Up,
+        b'r' => Direction::Right,
+        b'd' => Direction::Down,
+        _ => {
+            set_error(format_args!("Invalid direction: {:?}", dir as u8 as char));
+            return -1;
+        }
+    };
+    match game.state.go(dir) {
+        Ok(pushed) => pushed as i32,
+        Err(err) => {
+            set_error(err);
+            -1
+        }
+    }
+}
+
+/// Restart the level from its initial state.
+///
+/// # Safety
+/// `game` must be returned by `pbx_parse` and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn pbx_reset(game: *mut PbxGame) {
+    let game = &mut *game;
+    game.state = game.game.state.clone();
+}
+
+/// Whether the current state wins the level.
+///
+/// # Safety
+/// `game` must be returned by `pbx_parse` and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn pbx_is_success(game: *const PbxGame) -> bool {
+    let game = &*game;
+    game.state.is_success_on(&game.game.config)
+}
+
+/// Solve the level from the current state by breadth-first search, expanding at most
+/// `max_nodes` states, or without a limit if it is 0. Returns the solution in LURD, or NULL if
+/// the level is unsolvable or the limit is reached.
+///
+/// # Safety
+/// `game` must be returned by `pbx_parse` and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn pbx_solve(game: *const PbxGame, max_nodes: u64) -> *mut c_char {
+    let game = (*game).current();
+    let options = SolverOptions {
+        max_nodes: (max_nodes != 0).then_some(max_nodes as usize),
+        ..SolverOptions::default()
+    };
+    match solve::bfs_with_options(game.clone(), &options, |_| {}) {
+        SolveOutcome::Solved(solution) => into_c_string(game.to_lurd(&solution.moves).unwrap()),
+        SolveOutcome::Unsolvable(_) => {
+            set_error("The level is unsolvable");
+            ptr::null_mut()
+        }
+        SolveOutcome::LimitReached(limit, _) => {
+            set_error(format_args!("The limit of {limit:?} is reached"));
+            ptr::null_mut()
+        }
+        SolveOutcome::Cancelled(_) | SolveOutcome::Unsupported(_) => unreachable!(),
+    }
+}
+
+/// Render the current state in the text format.
+///
+/// # Safety
+/// `game` must be returned by `pbx_parse` and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn pbx_render(game: *const PbxGame) -> *mut c_char {
+    into_c_string((*game).current().to_string())
+}
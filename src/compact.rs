@@ -0,0 +1,108 @@
+//! Single-line encoding of states, for logs and bug reports.
+//!
+//! Boards are written in the order of their ids with glyphs of the default `GlyphMap`, rows
+//! separated by `/` and boards by `|`, like `#####/#p.1#/#####|###/#.#/###`. Targets are not
+//! part of states, thus not included.
+
+use anyhow::{bail, ensure, Context, Result};
+
+use crate::glyph::Glyph;
+use crate::parse::build_game;
+use crate::{
+    Board, BoardId, Cell, GlobalPos, GlyphMap, State, Vec2, MAX_BOARD_CNT, MAX_BOARD_WIDTH,
+};
+
+const BOARD_SEP: char = '|';
+const ROW_SEP: char = '/';
+
+impl State {
+    /// Encode the state into a single line, which can be decoded by
+    /// `State::from_compact_string`.
+    pub fn to_compact_string(&self) -> String {
+        let glyphs = GlyphMap::default();
+        let mut out = String::new();
+        for id in 0..self.boards.len() {
+            let board_id = BoardId::try_from(id).unwrap();
+            if id != 0 {
+                out.push(BOARD_SEP);
+            }
+            for (pos, cell) in self.board_cells(board_id) {
+                if pos.1 == 0 && pos.0 != 0 {
+                    out.push(ROW_SEP);
+                }
+                if (GlobalPos { board_id, pos }) == self.player {
+                    out.push(glyphs.player);
+                } else {
+                    out.push(glyphs.cell(cell));
+                }
+            }
+        }
+        out
+    }
+
+    /// Decode a state from a string returned by `State::to_compact_string`.
+    ///
+    /// Surrounding whitespaces are ignored.
+    pub fn from_compact_string(s: &str) -> Result<Self> {
+        let glyphs = GlyphMap::default();
+        let mut boards = Vec::new();
+        let mut walls = Vec::new();
+        let mut player = None;
+        for (id, text) in s.trim().split(BOARD_SEP).enumerate() {
+            ensure!(id < MAX_BOARD_CNT, "Too many boards");
+            let board_id = BoardId::try_from(id).unwrap();
+            let rows = text.split(ROW_SEP).collect::<Vec<_>>();
+            let width = rows[0].chars().count();
+            ensure!(
+                0 < width && width < MAX_BOARD_WIDTH && rows.len() < MAX_BOARD_WIDTH,
+                "Invalid size of board {id:X}",
+            );
+            let (mut grid, mut board_walls) = (Vec::new(), Vec::new());
+            for (row, line) in rows.iter().enumerate() {
+                ensure!(
+                    line.chars().count() == width,
+                    "Width mismatch of board {id:X}, row {row}, expecting width {width}",
+                );
+                for (col, ch) in line.chars().enumerate() {
+                    let gpos = GlobalPos {
+                        board_id,
+                        pos: Vec2(row as u8, col as u8),
+                    };
+                    let cell = match glyphs.lookup(ch) {
+                        Some(Glyph::Player) => {
+                            ensure!(player.is_none(), "Multiple players");
+                            player = Some(gpos);
+                            Cell::Box
+                        }
+                        // Walls are stored separately.
+                        Some(Glyph::Cell(Cell::Wall)) => Cell::Empty,
+                        Some(Glyph::Cell(cell)) => cell,
+                        _ => bail!("Invalid cell: {ch:?}"),
+                    };
+                    grid.push(cell);
+                    board_walls.push(ch == glyphs.wall);
+                }
+            }
+            boards.push(Board {
+                height: rows.len() as u8,
+                width: width as u8,
+                grid: grid.into(),
+            });
+            walls.push(board_walls.into_boxed_slice());
+        }
+        for board in &boards {
+            for (_, cell) in board.cells() {
+                if let Cell::Board(id) = cell {
+                    ensure!(
+                        (id as usize) < boards.len(),
+                        "Board id {:X} out of bound {}",
+                        id as usize,
+                        boards.len(),
+                    );
+                }
+            }
+        }
+        let player = player.context("Missing the player")?;
+        Ok(build_game(boards, walls, player, None, Vec::new())?.state)
+    }
+}
@@ -0,0 +1,343 @@
+//! Linting of map files with source locations, for editor integrations.
+//!
+//! `lint` checks the text of a map in the strict format, locating each mistake by a span of the
+//! text, and then runs `Game::validate` on the parsed level. Diagnostics carry stable codes and
+//! optional fix-it hints, and can be formatted in JSON by `Diagnostic::to_json`.
+
+use std::fmt;
+
+use crate::glyph::Glyph;
+use crate::{Cell, GlobalPos, GlyphMap, LintDiagnostic, MAX_BOARD_WIDTH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The map cannot be parsed, or the level cannot be solved.
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+        }
+    }
+}
+
+/// A range of characters on a line of the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Span {
+    /// The 1-based line number.
+    pub line: usize,
+    /// The 0-based character offset of the start in the line.
+    pub start: usize,
+    /// The 0-based character offset of the end in the line, exclusive.
+    pub end: usize,
+}
+
+/// A mistake found by `lint`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The location, or `None` for the level as a whole.
+    pub span: Option<Span>,
+    pub message: String,
+    /// A stable kebab-case identifier of the kind, like `undefined-board`.
+    pub code: &'static str,
+    /// A suggested fix.
+    pub hint: Option<String>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(span) = self.span {
+            write!(f, "{}:{}: ", span.line, span.start + 1)?;
+        }
+        write!(
+            f,
+            "{}[{}]: {}",
+            self.severity.name(),
+            self.code,
+            self.message
+        )?;
+        if let Some(hint) = &self.hint {
+            write!(f, " (hint: {hint})")?;
+        }
+        Ok(())
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out += "\\\"",
+            '\\' => out += "\\\\",
+            '\n' => out += "\\n",
+            ch if ch.is_control() => out += &format!("\\u{:04x}", ch as u32),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
+
+impl Diagnostic {
+    /// Format as a JSON object of `severity`, `code`, `message`, `hint` or `null`, and `span`
+    /// of `line`, `start` and `end`, or `null`.
+    pub fn to_json(&self) -> String {
+        let span = match self.span {
+            Some(Span { line, start, end }) => {
+                format!("{{\"line\":{line},\"start\":{start},\"end\":{end}}}")
+            }
+            None => "null".to_owned(),
+        };
+        let hint = self.hint.as_deref().map_or("null".to_owned(), json_string);
+        format!(
+            "{{\"severity\":\"{}\",\"code\":\"{}\",\"message\":{},\"hint\":{hint},\"span\":{span}}}",
+            self.severity.name(),
+            self.code,
+            json_string(&self.message),
+        )
+    }
+}
+
+/// A board of the text, with locations of its lines.
+struct BoardSource {
+    id_span: Span,
+    /// Rows as their spans and cells.
+    rows: Vec<(Span, Vec<char>)>,
+}
+
+impl BoardSource {
+    fn cell_span(&self, row: usize, col: usize) -> Option<Span> {
+        let (span, cells) = self.rows.get(row)?;
+        (col < cells.len()).then(|| Span {
+            line: span.line,
+            start: span.start + col,
+            end: span.start + col + 1,
+        })
+    }
+}
+
+/// Lines of the text after stripping comments like `Game::from_str`, with the span of the
+/// remaining content, or `None` for blank lines. Lines of only comments are skipped.
+fn content_lines(text: &str) -> impl Iterator<Item = Option<(Span, &str)>> {
+    text.lines().enumerate().filter_map(|(i, raw)| {
+        let line = raw.trim_start();
+        // Metadata entries are checked by the parser, and other comments are ignored.
+        if line.starts_with("#!") || line.starts_with(';') {
+            return None;
+        }
+        let content = line.split(';').next().unwrap().trim_end();
+        if content.is_empty() {
+            return Some(None);
+        }
+        let start = raw.chars().count() - line.chars().count();
+        let span = Span {
+            line: i + 1,
+            start,
+            end: start + content.chars().count(),
+        };
+        Some(Some((span, content)))
+    })
+}
+
+/// Lint a map in the strict format of `Game::from_str`.
+///
+/// Diagnostics of the text come first in the order of locations, then those of the whole text.
+/// If the map is parsed, diagnostics of `Game::validate` follow, located at their cells or
+/// boards where possible.
+pub fn lint(text: &str) -> Vec<Diagnostic> {
+    let glyphs = GlyphMap::default();
+    let mut diags = Vec::new();
+    let mut error = |span: Option<Span>, code, message: String, hint: Option<String>| {
+        diags.push(Diagnostic {
+            severity: Severity::Error,
+            span,
+            message,
+            code,
+            hint,
+        });
+    };
+
+    let mut boards = Vec::<BoardSource>::new();
+    let mut lines = content_lines(text).peekable();
+    while let Some(line) = lines.next() {
+        let Some((span, content)) = line else {
+            continue;
+        };
+        if content.starts_with(['_', '=']) {
+            let fields = content[1..].split_whitespace().collect::<Vec<_>>();
+            let is_valid = matches!(&fields[..], [id, row, col]
+                if usize::from_str_radix(id, 16).is_ok() && row.parse::<u8>().is_ok() && col.parse::<u8>().is_ok());
+            if !is_valid {
+                error(
+                    Some(span),
+                    "invalid-target-line",
+                    format!("invalid target line {content:?}"),
+                    Some("write covered targets like `_ <board> <row> <col>`".to_owned()),
+                );
+            }
+            continue;
+        }
+
+        let expected = boards.len();
+        match usize::from_str_radix(content, 16) {
+            Ok(id) if id == expected => {}
+            _ => error(
+                Some(span),
+                "invalid-board-id",
+                format!("expecting board {expected:X}, found {content:?}"),
+                Some(format!("renumber the board as {expected:X}")),
+            ),
+        }
+        let mut board = BoardSource {
+            id_span: span,
+            rows: Vec::new(),
+        };
+        while let Some(Some((span, row))) = lines.next_if(Option::is_some) {
+            board.rows.push((span, row.chars().collect()));
+        }
+        if board.rows.is_empty() {
+            error(
+                Some(span),
+                "missing-board-content",
+                format!("board {expected:X} has no rows"),
+                None,
+            );
+        }
+        boards.push(board);
+    }
+
+    let mut player = None;
+    for (id, board) in boards.iter().enumerate() {
+        let width = board.rows.first().map_or(0, |(_, cells)| cells.len());
+        if width >= MAX_BOARD_WIDTH || board.rows.len() >= MAX_BOARD_WIDTH {
+            error(
+                Some(board.id_span),
+                "board-too-big",
+                format!("board {id:X} is larger than {0}x{0}", MAX_BOARD_WIDTH - 1),
+                None,
+            );
+        }
+        for (row, (span, cells)) in board.rows.iter().enumerate() {
+            if cells.len() != width {
+                error(
+                    Some(*span),
+                    "width-mismatch",
+                    format!(
+                        "row {row} of board {id:X} has {} cells, expecting {width}",
+                        cells.len(),
+                    ),
+                    Some(format!("pad or trim the row to {width} cells")),
+                );
+            }
+            for (col, &ch) in cells.iter().enumerate() {
+                let span = board.cell_span(row, col);
+                match glyphs.lookup(ch) {
+                    None => error(span, "invalid-cell", format!("invalid cell {ch:?}"), None),
+                    Some(Glyph::Player | Glyph::PlayerOnBoxTarget) => {
+                        if player.replace(span).is_some() {
+                            error(
+                                span,
+                                "multiple-players",
+                                "the player is placed more than once".to_owned(),
+                                Some("keep only one player".to_owned()),
+                            );
+                        }
+                    }
+                    Some(Glyph::Cell(Cell::Board(inner))) if inner as usize >= boards.len() => {
+                        let hint = format!(
+                            "define boards up to {inner} after board {:X}",
+                            boards.len() - 1,
+                        );
+                        error(
+                            span,
+                            "undefined-board",
+                            format!("board {inner} is referenced but never defined"),
+                            Some(hint),
+                        );
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+    if player.is_none() && !boards.is_empty() {
+        error(
+            None,
+            "missing-player",
+            "the level has no player".to_owned(),
+            Some("place the player with `p`".to_owned()),
+        );
+    }
+
+    diags.sort_by_key(|diag| (diag.span.is_none(), diag.span));
+
+    let game = match text.parse::<crate::Game>() {
+        Ok(game) => game,
+        Err(err) => {
+            // Report failures not located above, like invalid metadata.
+            if diags.is_empty() {
+                diags.push(Diagnostic {
+                    severity: Severity::Error,
+                    span: None,
+                    message: format!("{err:#}"),
+                    code: "parse-error",
+                    hint: None,
+                });
+            }
+            return diags;
+        }
+    };
+
+    let cell_span = |gpos: GlobalPos| {
+        let board = boards.get(gpos.board_id as usize)?;
+        board.cell_span(gpos.pos.0 as usize, gpos.pos.1 as usize)
+    };
+    for lint in game.validate() {
+        let (span, code, hint) = match lint {
+            LintDiagnostic::NotEnoughBoxes { boxes, targets } => (
+                None,
+                "not-enough-boxes",
+                Some(format!(
+                    "add {} boxes or remove box targets",
+                    targets - boxes
+                )),
+            ),
+            LintDiagnostic::UndefinedBoard { at, board_id } => (
+                cell_span(at),
+                "undefined-board",
+                Some(format!("define board {board_id}")),
+            ),
+            LintDiagnostic::UnreachableBoard(board_id) => (
+                boards.get(board_id as usize).map(|board| board.id_span),
+                "unreachable-board",
+                Some(format!(
+                    "refer to board {board_id} from a board of the player, or remove it"
+                )),
+            ),
+            LintDiagnostic::TargetInWall(gpos) => (
+                cell_span(gpos),
+                "target-in-wall",
+                Some("move the target off the wall".to_owned()),
+            ),
+            LintDiagnostic::PlayerOnTarget(gpos) => (cell_span(gpos), "player-on-target", None),
+        };
+        let severity = if lint.is_error() {
+            Severity::Error
+        } else {
+            Severity::Warning
+        };
+        diags.push(Diagnostic {
+            severity,
+            span,
+            message: lint.to_string(),
+            code,
+            hint,
+        });
+    }
+    diags
+}
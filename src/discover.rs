@@ -0,0 +1,176 @@
+//! Discovery of levels made in the level editor of an installed Patrick's Parabox.
+//!
+//! Levels are searched in `custom_levels` of the save data of the game, including those under
+//! Proton prefixes on Linux, and in Steam Workshop downloads of every Steam library. Each of
+//! them is a `.txt` file in the format of `Game::from_parabox`, or a directory of such files
+//! for Workshop items.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::Game;
+
+/// The app id of Patrick's Parabox on Steam.
+const STEAM_APP_ID: &str = "1260520";
+/// The save data of the game relative to `LocalLow` of Windows.
+const WINDOWS_SAVE_DIR: &str = "Patrick Traynor/Patrick's Parabox";
+const CUSTOM_LEVELS_DIR: &str = "custom_levels";
+/// The depth of directories searched for levels, enough for Workshop items and folders of
+/// custom levels.
+const MAX_DEPTH: usize = 3;
+
+/// A level found by `find_levels`.
+#[derive(Debug, Clone)]
+pub struct FoundLevel {
+    pub path: PathBuf,
+    /// The path relative to the searched directory without the extension, like
+    /// `2834010947/level`.
+    pub name: String,
+    pub game: Game,
+}
+
+/// Levels found by `find_levels`, with files failed to import.
+#[derive(Debug, Default)]
+pub struct Discovery {
+    pub levels: Vec<FoundLevel>,
+    /// Files in the format of the game which cannot be imported, with the reasons, usually for
+    /// using unsupported features.
+    pub skipped: Vec<(PathBuf, String)>,
+}
+
+fn home_dir() -> Option<PathBuf> {
+    let var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    std::env::var_os(var)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Root directories of Steam libraries, from the default installation and its
+/// `libraryfolders.vdf`.
+fn steam_libraries(home: Option<&Path>) -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    if cfg!(windows) {
+        for var in ["ProgramFiles(x86)", "ProgramFiles"] {
+            if let Some(dir) = std::env::var_os(var) {
+                roots.push(Path::new(&dir).join("Steam"));
+            }
+        }
+    } else if let Some(home) = home {
+        if cfg!(target_os = "macos") {
+            roots.push(home.join("Library/Application Support/Steam"));
+        } else {
+            roots.push(home.join(".local/share/Steam"));
+            roots.push(home.join(".steam/steam"));
+            roots.push(home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam"));
+        }
+    }
+
+    let mut libraries = Vec::new();
+    for root in roots.into_iter().filter(|root| root.is_dir()) {
+        let vdf = root.join("steamapps/libraryfolders.vdf");
+        // Entries are lines like `"path"		"/mnt/games/SteamLibrary"`.
+        let paths = std::fs::read_to_string(vdf).unwrap_or_default();
+        let paths = paths.lines().filter_map(|line| {
+            let value = line.trim().strip_prefix("\"path\"")?.trim();
+            let value = value.strip_prefix('"')?.strip_suffix('"')?;
+            Some(PathBuf::from(value.replace("\\\\", "\\")))
+        });
+        libraries.push(root.clone());
+        libraries.extend(paths);
+    }
+    libraries
+}
+
+/// Existing directories which may contain levels of the installed game, in the order of the
+/// save data of the game, then Workshop downloads.
+pub fn game_level_dirs() -> Vec<PathBuf> {
+    let home = home_dir();
+    let mut dirs = Vec::new();
+    if let Some(home) = &home {
+        if cfg!(windows) {
+            dirs.push(home.join("AppData/LocalLow").join(WINDOWS_SAVE_DIR));
+        } else if cfg!(target_os = "macos") {
+            let support = home.join("Library/Application Support");
+            dirs.push(support.join("com.PatrickTraynor.PatricksParabox"));
+            dirs.push(support.join("Patrick Traynor/Patrick's Parabox"));
+        } else {
+            let config = std::env::var_os("XDG_CONFIG_HOME")
+                .filter(|dir| !dir.is_empty())
+                .map_or_else(|| home.join(".config"), PathBuf::from);
+            dirs.push(config.join("unity3d/Patrick Traynor/Patrick's Parabox"));
+        }
+    }
+    let libraries = steam_libraries(home.as_deref());
+    if !cfg!(windows) && !cfg!(target_os = "macos") {
+        for library in &libraries {
+            let prefix = library.join("steamapps/compatdata").join(STEAM_APP_ID);
+            let local_low = prefix.join("pfx/drive_c/users/steamuser/AppData/LocalLow");
+            dirs.push(local_low.join(WINDOWS_SAVE_DIR));
+        }
+    }
+    for dir in &mut dirs {
+        dir.push(CUSTOM_LEVELS_DIR);
+    }
+    dirs.extend(libraries.iter().map(|library| {
+        library
+            .join("steamapps/workshop/content")
+            .join(STEAM_APP_ID)
+    }));
+
+    let mut found = Vec::<PathBuf>::new();
+    for dir in dirs {
+        if dir.is_dir() && !found.contains(&dir) {
+            found.push(dir);
+        }
+    }
+    found
+}
+
+fn collect_files(dir: &Path, depth: usize, files: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read the directory {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            if depth < MAX_DEPTH {
+                collect_files(&path, depth + 1, files)?;
+            }
+        } else if path.extension().is_some_and(|ext| ext == "txt") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Find levels in the format of the game under `dirs`, like those by `game_level_dirs`.
+///
+/// Text files not in the format, like notes of Workshop items, are ignored. Levels are sorted
+/// by paths.
+pub fn find_levels(dirs: &[PathBuf]) -> Result<Discovery> {
+    let mut discovery = Discovery::default();
+    for dir in dirs {
+        let mut files = Vec::new();
+        collect_files(dir, 1, &mut files)?;
+        files.sort();
+        for path in files {
+            let text = match std::fs::read_to_string(&path) {
+                Ok(text) if text.trim_start().starts_with("version ") => text,
+                _ => continue,
+            };
+            match Game::from_parabox(&text) {
+                Ok(game) => {
+                    let name = path
+                        .strip_prefix(dir)
+                        .unwrap()
+                        .with_extension("")
+                        .to_string_lossy()
+                        .replace('\\', "/");
+                    discovery.levels.push(FoundLevel { path, name, game });
+                }
+                Err(err) => discovery.skipped.push((path, format!("{err:#}"))),
+            }
+        }
+    }
+    Ok(discovery)
+}
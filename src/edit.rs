@@ -0,0 +1,150 @@
+//! Editing levels by their components, for generators, analyses of modified levels, and
+//! level editors.
+
+use anyhow::{ensure, Result};
+
+use crate::parse::build_game;
+use crate::{Board, BoardId, Cell, Game, GlobalPos};
+
+/// What to paint on a cell by `Game::paint`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Paint {
+    /// Clear the cell, including its wall and targets.
+    Empty,
+    Wall,
+    Box,
+    /// Move the player to the cell, leaving an empty cell behind.
+    Player,
+    Board(BoardId),
+    /// Toggle the box target on the cell.
+    BoxTarget,
+    /// Toggle the player target on the cell, which moves it if it is elsewhere.
+    PlayerTarget,
+}
+
+impl Game {
+    /// Paint a cell of the level, returning the edited level. Painting walls, boxes or board
+    /// cells replaces the content of the cell, keeping its targets. Metadata are kept.
+    ///
+    /// Fails if the cell is out of boards, the player would be overwritten, or the edited level
+    /// is invalid.
+    pub fn paint(&self, gpos: GlobalPos, paint: Paint) -> Result<Game> {
+        let mut parts = Parts::new(self);
+        let board = parts
+            .boards
+            .get(gpos.board_id as usize)
+            .filter(|board| gpos.pos.0 < board.height && gpos.pos.1 < board.width);
+        ensure!(board.is_some(), "Position out of boards");
+        ensure!(
+            gpos != parts.player || !matches!(paint, Paint::Empty | Paint::Wall | Paint::Board(_)),
+            "Cannot overwrite the player",
+        );
+
+        let config = &self.config;
+        let mut player_target = config.player_target;
+        let mut box_targets = config.box_targets.to_vec();
+        match paint {
+            Paint::Empty => {
+                parts.set_cell(gpos, Cell::Empty);
+                parts.set_wall(gpos, false);
+                box_targets.retain(|&target| target != gpos);
+                player_target = player_target.filter(|&target| target != gpos);
+            }
+            Paint::Wall => {
+                parts.set_cell(gpos, Cell::Empty);
+                parts.set_wall(gpos, true);
+            }
+            Paint::Box => {
+                parts.set_cell(gpos, Cell::Box);
+                parts.set_wall(gpos, false);
+            }
+            Paint::Player => {
+                parts.set_cell(parts.player, Cell::Empty);
+                parts.set_cell(gpos, Cell::Box);
+                parts.set_wall(gpos, false);
+                parts.player = gpos;
+            }
+            Paint::Board(id) => {
+                parts.set_cell(gpos, Cell::Board(id));
+                parts.set_wall(gpos, false);
+            }
+            Paint::BoxTarget => {
+                let len = box_targets.len();
+                box_targets.retain(|&target| target != gpos);
+                if box_targets.len() == len {
+                    box_targets.push(gpos);
+                }
+            }
+            Paint::PlayerTarget => {
+                player_target = (player_target != Some(gpos)).then_some(gpos);
+            }
+        }
+
+        let mut edited = build_game(
+            parts.boards,
+            parts.walls,
+            parts.player,
+            player_target,
+            box_targets,
+        )?;
+        edited.metadata = self.metadata.clone();
+        Ok(edited)
+    }
+}
+
+/// Editable components of a level.
+pub(crate) struct Parts {
+    pub boards: Vec<Board>,
+    pub walls: Vec<Box<[bool]>>,
+    pub player: GlobalPos,
+}
+
+impl Parts {
+    pub fn new(game: &Game) -> Self {
+        let state = &game.state;
+        Self {
+            boards: state.boards.iter().map(|board| (**board).clone()).collect(),
+            walls: state.walls.to_vec(),
+            player: state.player,
+        }
+    }
+
+    /// All cells with whether they are walls, in order.
+    pub fn cells(&self) -> Vec<(GlobalPos, Cell, bool)> {
+        let mut cells = Vec::new();
+        for (id, board) in self.boards.iter().enumerate() {
+            let board_id = BoardId::try_from(id).unwrap();
+            for (pos, cell) in board.cells() {
+                let is_wall = self.walls[id][board.grid_index(pos)];
+                cells.push((GlobalPos { board_id, pos }, cell, is_wall));
+            }
+        }
+        cells
+    }
+
+    pub fn set_cell(&mut self, gpos: GlobalPos, cell: Cell) {
+        let board = &mut self.boards[gpos.board_id as usize];
+        let idx = board.grid_index(gpos.pos);
+        board.grid[idx] = cell;
+    }
+
+    pub fn set_wall(&mut self, gpos: GlobalPos, is_wall: bool) {
+        let idx = self.boards[gpos.board_id as usize].grid_index(gpos.pos);
+        self.walls[gpos.board_id as usize][idx] = is_wall;
+    }
+
+    /// Build a level with targets and metadata of `game`, or `None` if it is invalid.
+    pub fn build(self, game: &Game) -> Option<Game> {
+        let config = &game.config;
+        let mut edited = build_game(
+            self.boards,
+            self.walls,
+            self.player,
+            config.player_target,
+            config.box_targets.to_vec(),
+        )
+        .ok()?;
+        edited.metadata = game.metadata.clone();
+        Some(edited)
+    }
+}
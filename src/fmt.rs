@@ -1,19 +1,143 @@
 use std::fmt;
 
-use crate::{Cell, Game, GlobalPos, State};
+use crate::{BoardId, Cell, ColorMap, Config, Game, GlobalPos, GlyphMap, State, Theme, Vec2};
 
+/// Formatted in the text format with the default `GlyphMap`, which is parsed back losslessly
+/// by `Game::from_str` as long as metadata values are valid. See `Game::to_text`.
+///
+/// Comments are written after metadata entries as `#!` lines. Uncovered targets are overlaid
+/// on boards, and covered ones are listed after boards by lines of the target kind, board id,
+/// row and column, like `_ 0 2 3` for a box target under a box. Levels without a player target,
+/// like imported Sokoban levels, have a `; player-target: none` metadata entry.
 impl fmt::Display for Game {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // FIXME
-        self.state.fmt(f)
+        self.display_with(&GlyphMap::default()).fmt(f)
     }
 }
 
 impl fmt::Display for State {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for (id, board) in self.boards.iter().enumerate() {
-            id.fmt(f)?;
-            for (pos, cell) in board.cells() {
+        self.display_with(&GlyphMap::default()).fmt(f)
+    }
+}
+
+struct DisplayWith<'a, T> {
+    value: &'a T,
+    glyphs: &'a GlyphMap,
+}
+
+impl Game {
+    /// Format like `Display`, but with custom glyphs, which is parsed back by
+    /// `Game::parse_with_glyphs`.
+    pub fn display_with<'a>(&'a self, glyphs: &'a GlyphMap) -> impl fmt::Display + 'a {
+        DisplayWith {
+            value: self,
+            glyphs,
+        }
+    }
+}
+
+impl State {
+    /// Format like `Display`, but with custom glyphs.
+    pub fn display_with<'a>(&'a self, glyphs: &'a GlyphMap) -> impl fmt::Display + 'a {
+        DisplayWith {
+            value: self,
+            glyphs,
+        }
+    }
+}
+
+impl fmt::Display for DisplayWith<'_, Game> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (game, glyphs) = (self.value, self.glyphs);
+        let (state, config, metadata) = (&game.state, &game.config, &game.metadata);
+        let rules = metadata
+            .rules
+            .iter()
+            .map(|(name, toggle)| format!("{name}={toggle}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let entries = [
+            ("title", metadata.title.as_deref()),
+            ("author", metadata.author.as_deref()),
+            ("rules", Some(&*rules).filter(|rules| !rules.is_empty())),
+            (
+                "player-target",
+                config.player_target.is_none().then_some("none"),
+            ),
+        ];
+        let mut has_header = false;
+        for (key, value) in entries {
+            let Some(value) = value else { continue };
+            writeln!(f, "; {key}: {value}")?;
+            has_header = true;
+        }
+        for comment in &metadata.comments {
+            writeln!(f, "#! {comment}")?;
+            has_header = true;
+        }
+        if has_header {
+            "\n".fmt(f)?;
+        }
+
+        let mut covered = Vec::new();
+        for id in 0..state.boards.len() {
+            let board_id = id.try_into().unwrap();
+            write!(f, "{id:X}")?;
+            for (pos, cell) in state.board_cells(board_id) {
+                if pos.1 == 0 {
+                    "\n".fmt(f)?;
+                }
+                let gpos = GlobalPos { board_id, pos };
+                let is_player = gpos == state.player;
+                let target = if Some(gpos) == config.player_target {
+                    Some('=')
+                } else if config.box_targets.contains(&gpos) {
+                    Some('_')
+                } else {
+                    None
+                };
+                let combined = match target {
+                    Some('_') if is_player => glyphs.player_on_box_target,
+                    Some('_') if cell == Cell::Box => glyphs.box_on_target,
+                    _ => None,
+                };
+                let ch = match (combined, target) {
+                    (Some(ch), _) => ch,
+                    (None, Some('=')) if cell == Cell::Empty => glyphs.player_target,
+                    (None, Some(_)) if cell == Cell::Empty => glyphs.box_target,
+                    (None, target) => {
+                        if let Some(target) = target {
+                            covered.push((target, gpos));
+                        }
+                        if is_player {
+                            glyphs.player
+                        } else {
+                            glyphs.cell(cell)
+                        }
+                    }
+                };
+                ch.fmt(f)?;
+            }
+            "\n\n".fmt(f)?;
+        }
+        for (target, gpos) in covered {
+            writeln!(
+                f,
+                "{target} {} {} {}",
+                gpos.board_id, gpos.pos.0, gpos.pos.1
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for DisplayWith<'_, State> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (state, glyphs) = (self.value, self.glyphs);
+        for id in 0..state.boards.len() {
+            write!(f, "{id:X}")?;
+            for (pos, cell) in state.board_cells(id.try_into().unwrap()) {
                 if pos.1 == 0 {
                     "\n".fmt(f)?;
                 }
@@ -21,10 +145,10 @@ impl fmt::Display for State {
                     board_id: id.try_into().unwrap(),
                     pos,
                 };
-                if gpos == self.player {
-                    "p".fmt(f)?;
+                if gpos == state.player {
+                    glyphs.player.fmt(f)?;
                 } else {
-                    cell.fmt(f)?;
+                    glyphs.cell(cell).fmt(f)?;
                 }
             }
             "\n\n".fmt(f)?;
@@ -33,13 +157,362 @@ impl fmt::Display for State {
     }
 }
 
+/// Characters of `State::display_styled` for terminals.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RenderStyle {
+    /// Glyphs of the text format, by the default `GlyphMap`.
+    #[default]
+    Ascii,
+    /// Box-drawing characters connecting adjacent walls, half-block shading for empty targets,
+    /// and circled digits for board references.
+    Unicode,
+}
+
+impl RenderStyle {
+    pub const ALL: [Self; 2] = [Self::Ascii, Self::Unicode];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ascii => "ascii",
+            Self::Unicode => "unicode",
+        }
+    }
+}
+
+impl std::str::FromStr for RenderStyle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|style| style.name() == s)
+            .ok_or_else(|| anyhow::anyhow!("Unknown render style {s:?}"))
+    }
+}
+
+/// How `State::display_on_with` arranges boards.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BoardLayout {
+    /// Boards one after another, like the text format.
+    #[default]
+    Vertical,
+    /// Boards side by side in columns, wrapped into more rows of boards if the total width
+    /// exceeds `max_width`, like the width of the terminal.
+    Horizontal { max_width: Option<usize> },
+}
+
+/// Options of `State::display_on_with`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayOptions {
+    pub style: RenderStyle,
+    pub layout: BoardLayout,
+    pub theme: Theme,
+    /// Whether to print row and column indices beside boards, and positions of the player and
+    /// board references after them, for pointing at cells in bug reports.
+    pub ruler: bool,
+}
+
+impl State {
+    /// Format like `Display`, but overlay targets of `config` for players, with colors if
+    /// supported by the terminal. Unfilled targets are yellow, showing the target glyph if
+    /// empty, and filled ones are green, or colors of `DisplayOptions::theme`. A summary line of
+    /// filled targets follows boards.
+    ///
+    /// Unlike `Game`'s `Display`, this is not parsable.
+    pub fn display_on<'a>(&'a self, config: &'a Config) -> impl fmt::Display + 'a {
+        self.display_on_with(config, DisplayOptions::default())
+    }
+
+    /// Format like `State::display_on`, with characters of `style`.
+    pub fn display_styled<'a>(
+        &'a self,
+        config: &'a Config,
+        style: RenderStyle,
+    ) -> impl fmt::Display + 'a {
+        self.display_on_with(
+            config,
+            DisplayOptions {
+                style,
+                ..DisplayOptions::default()
+            },
+        )
+    }
+
+    /// Format like `State::display_on`, with characters and the layout of `options`.
+    pub fn display_on_with<'a>(
+        &'a self,
+        config: &'a Config,
+        options: DisplayOptions,
+    ) -> impl fmt::Display + 'a {
+        DisplayOn {
+            state: self,
+            config,
+            options,
+        }
+    }
+}
+
+struct DisplayOn<'a> {
+    state: &'a State,
+    config: &'a Config,
+    options: DisplayOptions,
+}
+
+impl DisplayOn<'_> {
+    /// The characters of a cell, and of box and player targets shown on it if it is empty.
+    fn chars(&self, gpos: GlobalPos, cell: Cell) -> (char, char, char) {
+        let glyphs = self.options.theme.glyphs;
+        let state = self.state;
+        if self.options.style == RenderStyle::Ascii {
+            let ch = if gpos == state.player {
+                glyphs.player
+            } else {
+                glyphs.cell(cell)
+            };
+            return (ch, glyphs.box_target, glyphs.player_target);
+        }
+        let ch = match cell {
+            _ if gpos == state.player => '●',
+            Cell::Empty => ' ',
+            Cell::Box => '■',
+            // Circled digits, where 0 is separated from the others in Unicode.
+            Cell::Board(id) => match id as u32 {
+                0 => '⓪',
+                n => char::from_u32('①' as u32 + n - 1).unwrap(),
+            },
+            Cell::Wall => {
+                let pos = gpos.pos;
+                let board = &state[gpos.board_id];
+                let is_wall = |row: Option<u8>, col: Option<u8>| match (row, col) {
+                    (Some(row), Some(col)) if row < board.height && col < board.width => state
+                        .is_wall(GlobalPos {
+                            board_id: gpos.board_id,
+                            pos: Vec2(row, col),
+                        }),
+                    _ => false,
+                };
+                let up = is_wall(pos.0.checked_sub(1), Some(pos.1));
+                let down = is_wall(pos.0.checked_add(1), Some(pos.1));
+                let left = is_wall(Some(pos.0), pos.1.checked_sub(1));
+                let right = is_wall(Some(pos.0), pos.1.checked_add(1));
+                match (up, down, left, right) {
+                    (false, false, false, false) => '□',
+                    (true, false, false, false) => '╵',
+                    (false, true, false, false) => '╷',
+                    (false, false, true, false) => '╴',
+                    (false, false, false, true) => '╶',
+                    (true, true, false, false) => '│',
+                    (false, false, true, true) => '─',
+                    (true, false, false, true) => '└',
+                    (true, false, true, false) => '┘',
+                    (false, true, false, true) => '┌',
+                    (false, true, true, false) => '┐',
+                    (true, true, false, true) => '├',
+                    (true, true, true, false) => '┤',
+                    (true, false, true, true) => '┴',
+                    (false, true, true, true) => '┬',
+                    (true, true, true, true) => '┼',
+                }
+            }
+        };
+        (ch, '░', '▒')
+    }
+}
+
+impl DisplayOn<'_> {
+    /// The count of columns of row indices before lines of a board, including a space.
+    fn ruler_width(&self, board_id: BoardId) -> usize {
+        if !self.options.ruler {
+            return 0;
+        }
+        let max_row = self.state[board_id].height.saturating_sub(1);
+        max_row.to_string().len() + 1
+    }
+
+    /// The count of columns of lines of a board, except for the id header.
+    fn board_width(&self, board_id: BoardId) -> usize {
+        self.ruler_width(board_id) + self.state[board_id].width as usize
+    }
+
+    /// Lines of a board, starting with its id, and counts of filled and all targets on it.
+    fn board_lines(&self, board_id: BoardId) -> (Vec<String>, usize, usize) {
+        let (state, config) = (self.state, self.config);
+        let colors = &self.options.theme.colors;
+        let (mut filled, mut total) = (0usize, 0usize);
+        let mut lines = vec![format!("{:X}", board_id as usize)];
+        let ruler_width = self.ruler_width(board_id);
+        if self.options.ruler {
+            // Column indices, with tens on a line above units if any.
+            let width = state[board_id].width;
+            let digit = |col: u8, unit: u8| {
+                if unit > 1 && (col == 0 || !col.is_multiple_of(unit)) {
+                    ' '
+                } else {
+                    char::from(b'0' + col / unit % 10)
+                }
+            };
+            let units: &[u8] = match width {
+                0..=10 => &[1],
+                11..=100 => &[10, 1],
+                _ => &[100, 10, 1],
+            };
+            for &unit in units {
+                let mut line = " ".repeat(ruler_width);
+                line.extend((0..width).map(|col| digit(col, unit)));
+                lines.push(line.trim_end().to_owned());
+            }
+        }
+        for (pos, cell) in state.board_cells(board_id) {
+            if pos.1 == 0 {
+                lines.push(if ruler_width == 0 {
+                    String::new()
+                } else {
+                    format!("{:>1$} ", pos.0, ruler_width - 1)
+                });
+            }
+            let line = lines.last_mut().unwrap();
+            let gpos = GlobalPos { board_id, pos };
+            let is_player = gpos == state.player;
+            let (ch, box_target, player_target) = self.chars(gpos, cell);
+            // Whether the cell is a target, and whether it is filled. See
+            // `State::is_success_on`.
+            let target = if Some(gpos) == config.player_target {
+                Some((player_target, is_player))
+            } else if config.box_targets.contains(&gpos) {
+                let is_filled =
+                    cell.is_box_like() && !(is_player && config.player_target.is_none());
+                Some((box_target, is_filled))
+            } else {
+                None
+            };
+            match target {
+                None => line.push(ch),
+                Some((_, true)) => {
+                    filled += 1;
+                    total += 1;
+                    let color = ColorMap::to_ansi256(colors.filled_target);
+                    *line += &console::style(ch).color256(color).bold().to_string();
+                }
+                Some((target_ch, false)) => {
+                    total += 1;
+                    let ch = if cell == Cell::Empty { target_ch } else { ch };
+                    let color = ColorMap::to_ansi256(colors.unfilled_target);
+                    *line += &console::style(ch).color256(color).to_string();
+                }
+            }
+        }
+        (lines, filled, total)
+    }
+}
+
+impl fmt::Display for DisplayOn<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        /// Spaces between columns of boards.
+        const GAP: usize = 2;
+
+        let state = self.state;
+        let (mut filled, mut total) = (0usize, 0usize);
+        // Rows of boards, each with its lines and width. Every character of boards is of a
+        // single column, thus widths are not affected by colors.
+        let mut rows: Vec<Vec<(Vec<String>, usize)>> = Vec::new();
+        let mut row_width = 0;
+        for id in 0..state.boards.len() {
+            let board_id = id.try_into().unwrap();
+            let (lines, board_filled, board_total) = self.board_lines(board_id);
+            filled += board_filled;
+            total += board_total;
+            let width = self.board_width(board_id);
+            let new_row = match (self.options.layout, rows.last()) {
+                (_, None) | (BoardLayout::Vertical, _) => true,
+                (BoardLayout::Horizontal { max_width }, Some(_)) => {
+                    max_width.is_some_and(|max_width| row_width + GAP + width > max_width)
+                }
+            };
+            if new_row {
+                rows.push(Vec::new());
+                row_width = width;
+            } else {
+                row_width += GAP + width;
+            }
+            rows.last_mut().unwrap().push((lines, width));
+        }
+
+        for row in &rows {
+            let height = row.iter().map(|(lines, _)| lines.len()).max().unwrap_or(0);
+            for i in 0..height {
+                // Boards after the last one with this line are skipped, without padding.
+                let cnt = row.iter().rposition(|(lines, _)| i < lines.len()).unwrap() + 1;
+                let mut line = String::new();
+                for (j, (lines, width)) in row[..cnt].iter().enumerate() {
+                    let (text, text_width) = match lines.get(i) {
+                        // The id header.
+                        Some(text) if i == 0 => (&**text, text.len()),
+                        Some(text) => (&**text, *width),
+                        None => ("", 0),
+                    };
+                    line += text;
+                    if j + 1 < cnt {
+                        line.extend(std::iter::repeat_n(' ', width - text_width + GAP));
+                    }
+                }
+                writeln!(f, "{line}")?;
+            }
+            "\n".fmt(f)?;
+        }
+        writeln!(f, "Targets: {filled}/{total} filled")?;
+        if self.options.ruler {
+            writeln!(f, "Player: {:?}", state.player)?;
+            let mut refs = Vec::new();
+            for id in 0..state.boards.len() {
+                let board_id: BoardId = id.try_into().unwrap();
+                for (pos, cell) in state[board_id].cells() {
+                    if let Cell::Board(inner) = cell {
+                        refs.push((inner, GlobalPos { board_id, pos }));
+                    }
+                }
+            }
+            refs.sort();
+            for (inner, gpos) in refs {
+                writeln!(f, "Board {:X}: {gpos:?}", inner as usize)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 impl fmt::Display for Cell {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Cell::Empty => ".".fmt(f),
-            Cell::Wall => "#".fmt(f),
-            Cell::Box => "b".fmt(f),
-            Cell::Board(id) => id.fmt(f),
+        GlyphMap::default().cell(*self).fmt(f)
+    }
+}
+
+impl Game {
+    /// Format the level in the text format like `Display`, checking that it can be parsed back
+    /// by `Game::from_str`.
+    ///
+    /// Fails if metadata values or comments are not single-line, or rules contain separators.
+    pub fn to_text(&self) -> anyhow::Result<String> {
+        let metadata = &self.metadata;
+        let entries = [("title", &metadata.title), ("author", &metadata.author)]
+            .into_iter()
+            .filter_map(|(key, value)| Some((key, value.as_ref()?)))
+            .chain(metadata.comments.iter().map(|comment| ("comment", comment)));
+        for (key, value) in entries {
+            anyhow::ensure!(
+                !value.contains(['\n', '\r']) && value.trim() == value,
+                "Invalid metadata {key}: {value:?}",
+            );
         }
+        anyhow::ensure!(
+            metadata
+                .rules
+                .iter()
+                .flat_map(|(name, toggle)| [name, toggle])
+                .all(|s| !s.is_empty()
+                    && !s.contains(|c: char| c == ',' || c == '=' || c.is_whitespace())),
+            "Invalid rules: {:?}",
+            metadata.rules,
+        );
+        Ok(self.to_string())
     }
 }
@@ -0,0 +1,126 @@
+//! Random playtesting, checking invariants of the engine after every move.
+
+use anyhow::{ensure, Context, Result};
+
+use crate::zobrist::splitmix64;
+use crate::{BoardId, Cell, Direction, Game, GlobalPos, State};
+
+/// The result of a successful `Game::fuzz`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzReport {
+    /// The random moves made, all of which are legal.
+    pub moves: Vec<Direction>,
+    /// The count of moves which push something other than the player itself.
+    pub pushes: usize,
+    /// Whether the fuzzing stopped early because no move is legal.
+    pub stuck: bool,
+}
+
+impl Game {
+    /// Make up to `steps` random legal moves chosen by `seed`, checking invariants of the engine
+    /// after every move: boards are consistent with walls and references, the player is a
+    /// single box, the incremental hash is up to date, illegal moves change nothing, and the
+    /// text format round-trips.
+    ///
+    /// The error reports the moves leading to the violation, for reproducing it.
+    pub fn fuzz(&self, seed: u64, steps: usize) -> Result<FuzzReport> {
+        let mut rng = seed;
+        let mut state = self.state.clone();
+        let mut report = FuzzReport {
+            moves: Vec::with_capacity(steps),
+            pushes: 0,
+            stuck: false,
+        };
+        let context = |moves: &[Direction]| {
+            let lurd = self.to_lurd(moves).unwrap_or_default();
+            format!("Invariant violated after {} moves: {lurd:?}", moves.len())
+        };
+        self.check_invariants(&state)
+            .with_context(|| context(&report.moves))?;
+        for _ in 0..steps {
+            let mut legal = Vec::with_capacity(4);
+            for dir in Direction::ALL {
+                let mut next = state.clone();
+                match next.go(dir) {
+                    Ok(pushed) => legal.push((dir, pushed, next)),
+                    Err(_) => ensure!(
+                        next.to_string() == state.to_string()
+                            && next.zobrist_hash() == state.zobrist_hash(),
+                        "{}: illegal move {dir:?} changes the state",
+                        context(&report.moves),
+                    ),
+                }
+            }
+            if legal.is_empty() {
+                report.stuck = true;
+                break;
+            }
+            rng = splitmix64(rng);
+            let (dir, pushed, next) = legal.swap_remove((rng % legal.len() as u64) as usize);
+            state = next;
+            report.moves.push(dir);
+            report.pushes += pushed as usize;
+            self.check_invariants(&state)
+                .with_context(|| context(&report.moves))?;
+        }
+        Ok(report)
+    }
+
+    fn check_invariants(&self, state: &State) -> Result<()> {
+        let board_cnt = state.boards.len();
+        let mut referenced = vec![false; board_cnt];
+        for id in 0..board_cnt {
+            let board_id = BoardId::try_from(id).unwrap();
+            for (pos, cell) in state[board_id].cells() {
+                let gpos = GlobalPos { board_id, pos };
+                ensure!(
+                    !state.is_wall(gpos) || cell == Cell::Empty,
+                    "Wall at {gpos:?} holds {cell:?}",
+                );
+                if let Cell::Board(inner) = cell {
+                    let inner = inner as usize;
+                    ensure!(inner < board_cnt, "Unknown board {inner} at {gpos:?}");
+                    ensure!(
+                        !std::mem::replace(&mut referenced[inner], true),
+                        "Board {inner} is referenced more than once",
+                    );
+                }
+            }
+        }
+
+        let player = state.player;
+        let board = &state[player.board_id];
+        ensure!(
+            (player.board_id as usize) < board_cnt
+                && player.pos.0 < board.height
+                && player.pos.1 < board.width,
+            "Player is out of bound at {player:?}",
+        );
+        ensure!(
+            state[player] == Cell::Box && !state.is_wall(player),
+            "Player at {player:?} is not a box",
+        );
+
+        let mut rehashed = state.clone();
+        rehashed.rehash();
+        ensure!(
+            rehashed.zobrist_hash() == state.zobrist_hash(),
+            "Stale Zobrist hash",
+        );
+
+        let game = Game {
+            config: self.config.clone(),
+            state: state.clone(),
+            metadata: self.metadata.clone(),
+        };
+        let text = game.to_string();
+        let reparsed = text
+            .parse::<Game>()
+            .with_context(|| format!("Failed to reparse:\n{text}"))?;
+        ensure!(
+            reparsed.to_string() == text && reparsed.state.player == player,
+            "Text format does not round-trip:\n{text}",
+        );
+        Ok(())
+    }
+}
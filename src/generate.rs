@@ -0,0 +1,207 @@
+//! Random generation of solvable levels.
+//!
+//! Levels are sampled by randomly placing walls, boxes, boards and targets, and kept only if
+//! `bfs` proves them solvable with enough pushes. Obviously broken samples are rejected by
+//! `Game::validate` before solving.
+//!
+//! Levels can be further evolved towards a difficulty by `Mutation`s with `hill_climb`.
+
+use anyhow::{bail, ensure, Result};
+
+use crate::parse::build_game;
+use crate::solve::{bfs_with_options, Solution, SolveOutcome, SolverOptions};
+use crate::zobrist::splitmix64;
+use crate::{Board, BoardId, Cell, Game, GlobalPos, Vec2, MAX_BOARD_CNT, MAX_BOARD_WIDTH};
+
+mod mutate;
+
+pub use mutate::{hill_climb, ClimbOptions, ClimbResult, Mutation};
+
+/// The percentage of cells sampled as walls.
+const WALL_PERCENT: u64 = 20;
+
+/// Constraints of generated levels.
+#[derive(Debug, Clone)]
+pub struct GenerateOptions {
+    /// The count of boards. Boards other than the first one are placed inside earlier boards,
+    /// so that all boards are reachable.
+    pub board_cnt: usize,
+    /// The height of every board.
+    pub height: usize,
+    /// The width of every board.
+    pub width: usize,
+    /// The count of boxes, and also box targets.
+    pub box_cnt: usize,
+    /// The minimal count of pushes of the push-optimal solution.
+    pub min_pushes: usize,
+    /// The maximal count of sampled levels before giving up.
+    pub max_attempts: usize,
+    /// The seed of the generation. The same options always give the same level.
+    pub seed: u64,
+    /// Limits of solving each sample. Samples reaching limits are rejected.
+    pub solver_options: SolverOptions,
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        Self {
+            board_cnt: 1,
+            height: 6,
+            width: 6,
+            box_cnt: 2,
+            min_pushes: 4,
+            max_attempts: 1000,
+            seed: 0,
+            solver_options: SolverOptions {
+                max_nodes: Some(100_000),
+                ..SolverOptions::default()
+            },
+        }
+    }
+}
+
+/// A generated level with its solution.
+#[derive(Debug, Clone)]
+pub struct GeneratedLevel {
+    pub game: Game,
+    /// The push-optimal solution found by `bfs`.
+    pub solution: Solution,
+    /// The count of sampled levels, including the returned one.
+    pub attempts: usize,
+}
+
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = splitmix64(self.0);
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Take a random element out of `items`.
+    fn take<T>(&mut self, items: &mut Vec<T>) -> T {
+        let i = self.below(items.len());
+        items.swap_remove(i)
+    }
+}
+
+/// Generate a random solvable level satisfying `options`.
+///
+/// Fails if the options are invalid, or no sample satisfies them within
+/// `GenerateOptions::max_attempts` attempts.
+pub fn generate(options: &GenerateOptions) -> Result<GeneratedLevel> {
+    let GenerateOptions {
+        board_cnt,
+        height,
+        width,
+        box_cnt,
+        ..
+    } = *options;
+    ensure!(
+        0 < board_cnt && board_cnt < MAX_BOARD_CNT,
+        "Invalid count of boards: {board_cnt}",
+    );
+    ensure!(
+        0 < height.min(width) && height.max(width) < MAX_BOARD_WIDTH,
+        "Invalid size of boards: {height}x{width}",
+    );
+    ensure!(
+        needed_cells(options) <= board_cnt * height * width,
+        "Boards are too small for {box_cnt} boxes",
+    );
+
+    let mut rng = Rng(options.seed);
+    for attempt in 1..=options.max_attempts {
+        let Some(game) = sample(options, &mut rng) else {
+            continue;
+        };
+        if game.validate().iter().any(|diag| diag.is_error()) {
+            continue;
+        }
+        match bfs_with_options(game.clone(), &options.solver_options, |_| {}) {
+            SolveOutcome::Solved(solution) if solution.pushes >= options.min_pushes => {
+                return Ok(GeneratedLevel {
+                    game,
+                    solution,
+                    attempts: attempt,
+                });
+            }
+            _ => {}
+        }
+    }
+    bail!(
+        "No level satisfies the constraints in {} attempts",
+        options.max_attempts,
+    )
+}
+
+/// The count of non-wall cells for the player, boxes, references to boards, and targets, which
+/// are all distinct.
+fn needed_cells(options: &GenerateOptions) -> usize {
+    1 + options.box_cnt + (options.board_cnt - 1) + options.box_cnt + 1
+}
+
+/// Sample a random level, or `None` if there are not enough non-wall cells.
+fn sample(options: &GenerateOptions, rng: &mut Rng) -> Option<Game> {
+    let (height, width) = (options.height, options.width);
+    let mut boards = Vec::with_capacity(options.board_cnt);
+    let mut walls = Vec::with_capacity(options.board_cnt);
+    let mut free = Vec::new();
+    for id in 0..options.board_cnt {
+        let board_id = BoardId::try_from(id).unwrap();
+        let board_walls = (0..height * width)
+            .map(|_| (rng.below(100) as u64) < WALL_PERCENT)
+            .collect::<Box<[bool]>>();
+        for (idx, &is_wall) in board_walls.iter().enumerate() {
+            if !is_wall {
+                let pos = Vec2((idx / width) as u8, (idx % width) as u8);
+                free.push(GlobalPos { board_id, pos });
+            }
+        }
+        boards.push(Board {
+            height: height as u8,
+            width: width as u8,
+            grid: vec![Cell::Empty; height * width].into(),
+        });
+        walls.push(board_walls);
+    }
+
+    if free.len() < needed_cells(options) {
+        return None;
+    }
+    let mut place = |gpos: GlobalPos, cell: Cell| {
+        let board = &mut boards[gpos.board_id as usize];
+        let idx = board.grid_index(gpos.pos);
+        board.grid[idx] = cell;
+    };
+
+    let player = rng.take(&mut free);
+    place(player, Cell::Box);
+    for _ in 0..options.box_cnt {
+        place(rng.take(&mut free), Cell::Box);
+    }
+    for id in 1..options.board_cnt {
+        // Inside an earlier board.
+        let candidates = free
+            .iter()
+            .enumerate()
+            .filter(|(_, gpos)| (gpos.board_id as usize) < id)
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            return None;
+        }
+        let i = candidates[rng.below(candidates.len())];
+        place(free.swap_remove(i), Cell::Board(id.try_into().unwrap()));
+    }
+    let box_targets = (0..options.box_cnt)
+        .map(|_| rng.take(&mut free))
+        .collect::<Vec<_>>();
+    let player_target = rng.take(&mut free);
+
+    build_game(boards, walls, player, Some(player_target), box_targets).ok()
+}
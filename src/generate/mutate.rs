@@ -0,0 +1,147 @@
+//! Mutations of levels, and hill climbing towards a difficulty score by mutations.
+
+use super::Rng;
+use crate::edit::Parts;
+use crate::solve::{difficulty, DifficultyScore};
+use crate::{Cell, Game, GlobalPos};
+
+/// A kind of random edit of a level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mutation {
+    /// Turn an empty cell without targets into a wall.
+    AddWall,
+    /// Turn a wall into an empty cell.
+    RemoveWall,
+    /// Move a box, other than the player, to an empty cell.
+    MoveBox,
+    /// Swap a board reference with another cell which is neither a wall nor the player.
+    SwapReference,
+}
+
+impl Mutation {
+    pub const ALL: [Self; 4] = [
+        Self::AddWall,
+        Self::RemoveWall,
+        Self::MoveBox,
+        Self::SwapReference,
+    ];
+
+    /// Apply the mutation at random cells chosen by `seed`, returning the mutated level, or
+    /// `None` if it is not applicable, eg. removing walls from a level without walls.
+    ///
+    /// Metadata are kept. Mutations compose by applying them to results of others.
+    pub fn apply(self, game: &Game, seed: u64) -> Option<Game> {
+        let mut rng = Rng(seed);
+        let mut parts = Parts::new(game);
+        let cells = parts.cells();
+        let pick = |rng: &mut Rng, pred: &dyn Fn(&(GlobalPos, Cell, bool)) -> bool| {
+            let candidates = cells.iter().filter(|c| pred(c)).collect::<Vec<_>>();
+            (!candidates.is_empty()).then(|| *candidates[rng.below(candidates.len())])
+        };
+        let is_target = |gpos: GlobalPos| {
+            game.config.player_target == Some(gpos) || game.config.box_targets.contains(&gpos)
+        };
+        let is_empty =
+            |&(_, cell, is_wall): &(GlobalPos, Cell, bool)| cell == Cell::Empty && !is_wall;
+
+        match self {
+            Mutation::AddWall => {
+                let (gpos, ..) = pick(&mut rng, &|c| is_empty(c) && !is_target(c.0))?;
+                parts.set_wall(gpos, true);
+            }
+            Mutation::RemoveWall => {
+                let (gpos, ..) = pick(&mut rng, &|&(_, _, is_wall)| is_wall)?;
+                parts.set_wall(gpos, false);
+            }
+            Mutation::MoveBox => {
+                let player = parts.player;
+                let (from, ..) = pick(&mut rng, &|&(gpos, cell, _)| {
+                    cell == Cell::Box && gpos != player
+                })?;
+                let (to, ..) = pick(&mut rng, &is_empty)?;
+                parts.set_cell(from, Cell::Empty);
+                parts.set_cell(to, Cell::Box);
+            }
+            Mutation::SwapReference => {
+                let (from, cell, _) =
+                    pick(&mut rng, &|&(_, cell, _)| matches!(cell, Cell::Board(_)))?;
+                let player = parts.player;
+                let (to, other, _) = pick(&mut rng, &|&(gpos, _, is_wall)| {
+                    !is_wall && gpos != player && gpos != from
+                })?;
+                parts.set_cell(from, other);
+                parts.set_cell(to, cell);
+            }
+        }
+        parts.build(game)
+    }
+}
+
+/// Options of `hill_climb`.
+#[derive(Debug, Clone)]
+pub struct ClimbOptions {
+    /// Mutations to try, chosen uniformly at random in each iteration.
+    pub mutations: Vec<Mutation>,
+    /// The `DifficultyScore::score` to approach.
+    pub target_score: f64,
+    /// Stop once the score is within this distance to the target.
+    pub tolerance: f64,
+    /// The maximal count of tried mutations.
+    pub max_iterations: usize,
+    /// The seed of chosen mutations. The same options always give the same result.
+    pub seed: u64,
+}
+
+impl Default for ClimbOptions {
+    fn default() -> Self {
+        Self {
+            mutations: Mutation::ALL.to_vec(),
+            target_score: 20.0,
+            tolerance: 1.0,
+            max_iterations: 100,
+            seed: 0,
+        }
+    }
+}
+
+/// The result of `hill_climb`.
+#[derive(Debug, Clone)]
+pub struct ClimbResult {
+    /// The closest level to the target score found.
+    pub game: Game,
+    pub score: DifficultyScore,
+    /// The count of tried mutations.
+    pub iterations: usize,
+}
+
+/// Evolve a level towards `ClimbOptions::target_score` by random mutations, keeping a mutated
+/// level only if its score is strictly closer to the target. Unsolvable levels are never kept,
+/// unless the initial level is unsolvable.
+///
+/// Each iteration estimates the difficulty by `difficulty`, thus solves the mutated level, which
+/// may take much time on large levels.
+pub fn hill_climb(game: Game, options: &ClimbOptions) -> ClimbResult {
+    let distance = |score: &DifficultyScore| (score.score - options.target_score).abs();
+    let mut rng = Rng(options.seed);
+    let mut best = ClimbResult {
+        score: difficulty(&game),
+        game,
+        iterations: 0,
+    };
+    while best.iterations < options.max_iterations
+        && !options.mutations.is_empty()
+        && distance(&best.score) > options.tolerance
+    {
+        best.iterations += 1;
+        let mutation = options.mutations[rng.below(options.mutations.len())];
+        let Some(mutated) = mutation.apply(&best.game, rng.next_u64()) else {
+            continue;
+        };
+        let score = difficulty(&mutated);
+        if distance(&score) < distance(&best.score) {
+            best.game = mutated;
+            best.score = score;
+        }
+    }
+    best
+}
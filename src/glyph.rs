@@ -0,0 +1,115 @@
+//! Configurable characters of cells and targets in the text format.
+
+use anyhow::{ensure, Result};
+
+use crate::{BoardId, Cell};
+
+/// Characters of cells and targets, for reading and writing alternate notations of maps.
+///
+/// Board references are always hexadecimal digits, and covered targets which cannot be written
+/// by glyphs are always listed in lines after boards. See `Display for Game`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlyphMap {
+    pub empty: char,
+    pub wall: char,
+    pub box_cell: char,
+    pub player: char,
+    pub box_target: char,
+    pub player_target: char,
+    /// A box on a box target, if supported by the notation.
+    pub box_on_target: Option<char>,
+    /// The player on a box target, if supported by the notation.
+    pub player_on_box_target: Option<char>,
+}
+
+impl Default for GlyphMap {
+    fn default() -> Self {
+        Self {
+            empty: '.',
+            wall: '#',
+            box_cell: 'b',
+            player: 'p',
+            box_target: '_',
+            player_target: '=',
+            box_on_target: None,
+            player_on_box_target: None,
+        }
+    }
+}
+
+/// The meaning of a glyph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Glyph {
+    Cell(Cell),
+    Player,
+    BoxTarget,
+    PlayerTarget,
+    BoxOnTarget,
+    PlayerOnBoxTarget,
+}
+
+impl GlyphMap {
+    /// The common Sokoban notation, where floors are `-` instead of spaces, and the player
+    /// target, which Sokoban does not have, is `=`.
+    pub fn sokoban() -> Self {
+        Self {
+            empty: '-',
+            wall: '#',
+            box_cell: '$',
+            player: '@',
+            box_target: '.',
+            player_target: '=',
+            box_on_target: Some('*'),
+            player_on_box_target: Some('+'),
+        }
+    }
+
+    fn entries(&self) -> impl Iterator<Item = (char, Glyph)> {
+        [
+            (Some(self.empty), Glyph::Cell(Cell::Empty)),
+            (Some(self.wall), Glyph::Cell(Cell::Wall)),
+            (Some(self.box_cell), Glyph::Cell(Cell::Box)),
+            (Some(self.player), Glyph::Player),
+            (Some(self.box_target), Glyph::BoxTarget),
+            (Some(self.player_target), Glyph::PlayerTarget),
+            (self.box_on_target, Glyph::BoxOnTarget),
+            (self.player_on_box_target, Glyph::PlayerOnBoxTarget),
+        ]
+        .into_iter()
+        .filter_map(|(ch, glyph)| Some((ch?, glyph)))
+    }
+
+    /// Check that glyphs are distinct, and do not conflict with other syntax of the text format.
+    pub fn validate(&self) -> Result<()> {
+        let chars = self.entries().map(|(ch, _)| ch).collect::<Vec<_>>();
+        for (i, &ch) in chars.iter().enumerate() {
+            ensure!(!chars[..i].contains(&ch), "Duplicated glyph {ch:?}");
+            ensure!(
+                !ch.is_whitespace() && !matches!(ch, '0'..='9' | 'A'..='F' | ';' | '!' | '['),
+                "Reserved glyph {ch:?}",
+            );
+        }
+        Ok(())
+    }
+
+    pub(crate) fn lookup(&self, ch: char) -> Option<Glyph> {
+        if let Some(digit) = ch.to_digit(16).filter(|_| !ch.is_ascii_lowercase()) {
+            return Some(Glyph::Cell(Cell::Board(
+                BoardId::try_from(digit as usize).unwrap(),
+            )));
+        }
+        self.entries()
+            .find_map(|(glyph_ch, glyph)| (glyph_ch == ch).then_some(glyph))
+    }
+
+    pub(crate) fn cell(&self, cell: Cell) -> char {
+        match cell {
+            Cell::Empty => self.empty,
+            Cell::Wall => self.wall,
+            Cell::Box => self.box_cell,
+            Cell::Board(id) => char::from_digit(id as u32, 16)
+                .unwrap()
+                .to_ascii_uppercase(),
+        }
+    }
+}
@@ -0,0 +1,314 @@
+//! `grpc [--port <port>]`: a gRPC server of the service in `proto/parabox.proto`, mirroring
+//! endpoints of `serve` for embedding the solver in other services.
+//!
+//! Messages are defined here by `prost` to match the proto file, and the service is generated
+//! from them by the build script, thus building needs no `protoc`.
+
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use parabox_solver::solve::{solver_by_name, SolveOutcome, SolverOptions, SOLVER_NAMES};
+use parabox_solver::{Direction, Game};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+use tonic::{Request, Response, Status};
+
+use crate::{parse_level, parse_moves};
+
+include!(concat!(env!("OUT_DIR"), "/parabox.Parabox.rs"));
+
+use parabox_server::{Parabox, ParaboxServer};
+
+/// The minimal interval between progress events of `Solve`.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SolveRequest {
+    #[prost(string, tag = "1")]
+    pub level: String,
+    #[prost(string, tag = "2")]
+    pub moves: String,
+    #[prost(string, tag = "3")]
+    pub solver: String,
+    #[prost(uint64, optional, tag = "4")]
+    pub max_nodes: Option<u64>,
+    #[prost(uint64, optional, tag = "5")]
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Progress {
+    #[prost(uint64, tag = "1")]
+    pub steps: u64,
+    #[prost(uint64, tag = "2")]
+    pub expanded: u64,
+    #[prost(uint64, tag = "3")]
+    pub frontier: u64,
+    #[prost(uint64, tag = "4")]
+    pub depth: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SolveResult {
+    #[prost(bool, tag = "1")]
+    pub solved: bool,
+    #[prost(string, tag = "2")]
+    pub solution: String,
+    #[prost(uint64, tag = "3")]
+    pub moves: u64,
+    #[prost(uint64, tag = "4")]
+    pub pushes: u64,
+    #[prost(uint64, tag = "5")]
+    pub nodes: u64,
+    #[prost(uint64, tag = "6")]
+    pub duration_ms: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Oneof)]
+pub enum Event {
+    #[prost(message, tag = "1")]
+    Progress(Progress),
+    #[prost(message, tag = "2")]
+    Result(SolveResult),
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SolveEvent {
+    #[prost(oneof = "Event", tags = "1, 2")]
+    pub event: Option<Event>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MoveRequest {
+    #[prost(string, tag = "1")]
+    pub level: String,
+    #[prost(string, tag = "2")]
+    pub moves: String,
+    #[prost(string, tag = "3")]
+    pub r#move: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MoveResponse {
+    #[prost(string, tag = "1")]
+    pub moves: String,
+    #[prost(bool, tag = "2")]
+    pub pushed: bool,
+    #[prost(bool, tag = "3")]
+    pub solved: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ValidateRequest {
+    #[prost(string, tag = "1")]
+    pub level: String,
+    #[prost(string, tag = "2")]
+    pub moves: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Diagnostic {
+    #[prost(bool, tag = "1")]
+    pub is_error: bool,
+    #[prost(string, tag = "2")]
+    pub message: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ValidateResponse {
+    #[prost(string, tag = "1")]
+    pub format: String,
+    #[prost(message, repeated, tag = "2")]
+    pub diagnostics: Vec<Diagnostic>,
+    #[prost(bool, tag = "3")]
+    pub solved: bool,
+    #[prost(uint64, tag = "4")]
+    pub pushes: u64,
+}
+
+pub fn grpc(mut args: impl Iterator<Item = String>) -> Result<()> {
+    let mut port = 50051u16;
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--port" => {
+                port = args
+                    .next()
+                    .context("Missing argument of --port")?
+                    .parse()
+                    .context("Invalid port")?;
+            }
+            _ => anyhow::bail!("Unknown option {flag:?}"),
+        }
+    }
+    let runtime = tokio::runtime::Runtime::new().context("Failed to start the runtime")?;
+    runtime.block_on(async {
+        let addr = ([127, 0, 0, 1], port).into();
+        eprintln!("Listening on {addr}");
+        tonic::transport::Server::builder()
+            .add_service(ParaboxServer::new(Service))
+            .serve(addr)
+            .await
+            .with_context(|| format!("Failed to serve on port {port}"))
+    })
+}
+
+fn invalid(err: anyhow::Error) -> Status {
+    Status::invalid_argument(format!("{err:#}"))
+}
+
+/// Parse the level, with the state after replaying the moves.
+fn load(level: &str, moves: &str) -> Result<(Game, Vec<Direction>)> {
+    let (mut game, _) = parse_level(level.as_bytes()).context("Failed to parse the level")?;
+    let moves = parse_moves(moves)?;
+    for (i, &dir) in moves.iter().enumerate() {
+        game.state
+            .go(dir)
+            .with_context(|| format!("Invalid move {} of {dir:?}", i + 1))?;
+    }
+    Ok((game, moves))
+}
+
+fn to_lurd(moves: &[Direction]) -> String {
+    moves
+        .iter()
+        .map(|dir| match dir {
+            Direction::Left => 'L',
+            Direction::Up => 'U',
+            Direction::Right => 'R',
+            Direction::Down => 'D',
+        })
+        .collect()
+}
+
+/// Solve on the current thread, sending events until the receiver is dropped.
+fn solve(req: SolveRequest, tx: mpsc::Sender<Result<SolveEvent, Status>>) -> Result<()> {
+    let (game, _) = load(&req.level, &req.moves)?;
+    let name = if req.solver.is_empty() {
+        "bfs"
+    } else {
+        &req.solver
+    };
+    let solver = solver_by_name(name)
+        .with_context(|| format!("Unknown solver {name:?}, expecting one of {SOLVER_NAMES:?}"))?;
+    let cancel = Arc::new(AtomicBool::new(false));
+    let options = SolverOptions {
+        max_nodes: req.max_nodes.map(|n| n as usize),
+        max_duration: req.timeout_ms.map(Duration::from_millis),
+        cancel: Some(cancel.clone()),
+        ..SolverOptions::default()
+    };
+
+    // Stop solving once the client disconnects.
+    let mut last_report = Instant::now();
+    let outcome = solver.solve(&game, &options, &mut |progress| {
+        if last_report.elapsed() < PROGRESS_INTERVAL {
+            return;
+        }
+        last_report = Instant::now();
+        let event = Event::Progress(Progress {
+            steps: progress.steps,
+            expanded: progress.expanded as u64,
+            frontier: progress.frontier as u64,
+            depth: progress.depth as u64,
+        });
+        let event = SolveEvent { event: Some(event) };
+        if tx.blocking_send(Ok(event)).is_err() {
+            cancel.store(true, Ordering::Relaxed);
+        }
+    });
+    let result = match outcome {
+        SolveOutcome::Solved(solution) => SolveResult {
+            solved: true,
+            solution: game.to_lurd(&solution.moves).context("Invalid solution")?,
+            moves: solution.moves.len() as u64,
+            pushes: solution.pushes as u64,
+            nodes: solution.nodes_expanded as u64,
+            duration_ms: solution.duration.as_millis() as u64,
+        },
+        _ => SolveResult::default(),
+    };
+    let event = SolveEvent {
+        event: Some(Event::Result(result)),
+    };
+    // The client may have disconnected.
+    let _ = tx.blocking_send(Ok(event));
+    Ok(())
+}
+
+struct Service;
+
+#[tonic::async_trait]
+impl Parabox for Service {
+    type SolveStream = Pin<Box<dyn Stream<Item = Result<SolveEvent, Status>> + Send>>;
+
+    async fn solve(
+        &self,
+        request: Request<SolveRequest>,
+    ) -> Result<Response<Self::SolveStream>, Status> {
+        let req = request.into_inner();
+        // Fail early for invalid requests, before the stream starts.
+        load(&req.level, &req.moves).map_err(invalid)?;
+        let (tx, rx) = mpsc::channel(16);
+        tokio::task::spawn_blocking(move || {
+            if let Err(err) = solve(req, tx.clone()) {
+                let _ = tx.blocking_send(Err(invalid(err)));
+            }
+        });
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+
+    async fn move_(&self, request: Request<MoveRequest>) -> Result<Response<MoveResponse>, Status> {
+        let req = request.into_inner();
+        let (mut game, mut moves) = load(&req.level, &req.moves).map_err(invalid)?;
+        let dir = match parse_moves(&req.r#move).map_err(invalid)?[..] {
+            [dir] => dir,
+            _ => {
+                return Err(Status::invalid_argument(
+                    "Field \"move\" should be exactly one move",
+                ))
+            }
+        };
+        let pushed = game
+            .state
+            .go(dir)
+            .map_err(|err| Status::invalid_argument(format!("Illegal move: {err}")))?;
+        moves.push(dir);
+        Ok(Response::new(MoveResponse {
+            moves: to_lurd(&moves),
+            pushed,
+            solved: game.state.is_success_on(&game.config),
+        }))
+    }
+
+    async fn validate(
+        &self,
+        request: Request<ValidateRequest>,
+    ) -> Result<Response<ValidateResponse>, Status> {
+        let req = request.into_inner();
+        let (game, format) = parse_level(req.level.as_bytes())
+            .context("Failed to parse the level")
+            .map_err(invalid)?;
+        let moves = parse_moves(&req.moves).map_err(invalid)?;
+        let report = game
+            .verify(&moves)
+            .map_err(|err| Status::invalid_argument(err.to_string()))?;
+        let diagnostics = game
+            .validate()
+            .iter()
+            .map(|diag| Diagnostic {
+                is_error: diag.is_error(),
+                message: diag.to_string(),
+            })
+            .collect();
+        Ok(Response::new(ValidateResponse {
+            format: format.to_owned(),
+            diagnostics,
+            solved: report.is_success,
+            pushes: report.pushes as u64,
+        }))
+    }
+}
@@ -0,0 +1,137 @@
+//! Export of solutions as standalone HTML pages, which replay them frame by frame.
+//!
+//! Frames are rendered ahead of time in the text format and embedded with the level, thus the
+//! page needs neither the engine nor network access. A small script steps through frames by
+//! buttons, a slider, or arrow keys.
+
+use std::fmt::Write;
+
+use anyhow::Result;
+
+use crate::{Direction, Game, Metadata};
+
+const TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{{TITLE}}</title>
+<style>
+body { font-family: sans-serif; margin: 2em; }
+pre { font-size: 1.5em; line-height: 1.1; }
+#moves { font-family: monospace; word-break: break-all; }
+#moves .current { background: #fc6; }
+</style>
+</head>
+<body>
+<h1>{{TITLE}}</h1>
+<div>
+<button id="first">&laquo;</button>
+<button id="prev">&lsaquo;</button>
+<button id="play">Play</button>
+<button id="next">&rsaquo;</button>
+<button id="last">&raquo;</button>
+<input id="slider" type="range" min="0" value="0">
+<span id="step"></span>
+</div>
+<p id="moves"></p>
+<pre id="frame"></pre>
+<details><summary>Level</summary><pre id="level"></pre></details>
+<script>
+const level = {{LEVEL}};
+const moves = {{MOVES}};
+const frames = {{FRAMES}};
+let cur = 0, timer = null;
+const $ = (id) => document.getElementById(id);
+$("slider").max = frames.length - 1;
+function show(i) {
+  cur = Math.max(0, Math.min(frames.length - 1, i));
+  $("frame").textContent = frames[cur];
+  $("slider").value = cur;
+  $("step").textContent = `Step ${cur}/${frames.length - 1}`;
+  const span = document.createElement("span");
+  span.className = "current";
+  span.textContent = moves.slice(cur - 1, cur);
+  $("moves").replaceChildren(moves.slice(0, Math.max(cur - 1, 0)), span, moves.slice(cur));
+}
+function toggle() {
+  if (timer !== null) {
+    clearInterval(timer);
+    timer = null;
+  } else {
+    if (cur === frames.length - 1) show(0);
+    timer = setInterval(() => {
+      show(cur + 1);
+      if (cur === frames.length - 1) toggle();
+    }, 300);
+  }
+  $("play").textContent = timer === null ? "Play" : "Pause";
+}
+$("first").onclick = () => show(0);
+$("prev").onclick = () => show(cur - 1);
+$("next").onclick = () => show(cur + 1);
+$("last").onclick = () => show(frames.length - 1);
+$("play").onclick = toggle;
+$("slider").oninput = () => show(Number($("slider").value));
+document.onkeydown = (e) => {
+  if (e.key === "ArrowLeft") show(cur - 1);
+  else if (e.key === "ArrowRight") show(cur + 1);
+  else if (e.key === " ") { toggle(); e.preventDefault(); }
+};
+$("level").textContent = level;
+show(0);
+</script>
+</body>
+</html>
+"#;
+
+/// Quote a string as a JavaScript string literal which is safe inside `<script>`.
+fn js_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out += "\\\"",
+            '\\' => out += "\\\\",
+            '\n' => out += "\\n",
+            // Avoid closing the script by `</script>`, or opening comments.
+            '<' => out += "\\u003c",
+            ch if ch.is_control() => write!(out, "\\u{:04x}", ch as u32).unwrap(),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
+
+pub(crate) fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl Game {
+    /// Generate a standalone HTML page replaying `moves` from the initial state frame by frame,
+    /// with the level embedded in the text format.
+    ///
+    /// Fails if any move is invalid.
+    pub fn to_html(&self, moves: &[Direction]) -> Result<String> {
+        let lurd = self.to_lurd(moves)?;
+        let mut frame = Game {
+            config: self.config.clone(),
+            state: self.state.clone(),
+            metadata: Metadata::default(),
+        };
+        let mut frames = vec![js_string(&frame.to_string())];
+        for &dir in moves {
+            frame.state.go(dir)?;
+            frames.push(js_string(&frame.to_string()));
+        }
+        let title = self.metadata.title.as_deref().unwrap_or("Solution");
+        Ok(TEMPLATE
+            .replace("{{TITLE}}", &html_escape(title))
+            .replace("{{LEVEL}}", &js_string(&self.to_string()))
+            .replace("{{MOVES}}", &js_string(&lurd))
+            .replace("{{FRAMES}}", &format!("[\n{}\n]", frames.join(",\n"))))
+    }
+}
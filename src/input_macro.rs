@@ -0,0 +1,143 @@
+//! Export of moves as timed key presses for auto-input tools, to watch solutions played in the
+//! game itself.
+//!
+//! Moves are pressed as arrow keys, the default controls of the game. Macros wait for
+//! `MacroOptions::start_delay` first, for focusing the window of the game.
+
+use std::fmt::Write;
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::{Direction, Game};
+
+/// Formats of `Game::to_input_macro`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MacroFormat {
+    /// A script of AutoHotkey v2 for Windows.
+    #[default]
+    AutoHotkey,
+    /// A shell script running `xdotool` for X11.
+    Xdotool,
+    /// A timeline of `time_ms,event,key` rows with a header, where events are `down` and `up`
+    /// at milliseconds from the start, for tools replaying recorded inputs.
+    Csv,
+}
+
+impl MacroFormat {
+    pub const ALL: [Self; 3] = [Self::AutoHotkey, Self::Xdotool, Self::Csv];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::AutoHotkey => "ahk",
+            Self::Xdotool => "xdotool",
+            Self::Csv => "csv",
+        }
+    }
+}
+
+impl std::str::FromStr for MacroFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.name() == s)
+            .ok_or_else(|| anyhow::anyhow!("Unknown macro format {s:?}"))
+    }
+}
+
+/// Options of `Game::to_input_macro`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MacroOptions {
+    pub format: MacroFormat,
+    /// The time between presses of consecutive moves, long enough for the game to animate a
+    /// move.
+    pub delay: Duration,
+    /// The time each key is held down, since the game may miss instant presses.
+    pub hold: Duration,
+    pub start_delay: Duration,
+}
+
+impl Default for MacroOptions {
+    fn default() -> Self {
+        Self {
+            format: MacroFormat::default(),
+            delay: Duration::from_millis(200),
+            hold: Duration::from_millis(50),
+            start_delay: Duration::from_secs(3),
+        }
+    }
+}
+
+fn key_name(dir: Direction) -> &'static str {
+    match dir {
+        Direction::Left => "Left",
+        Direction::Up => "Up",
+        Direction::Right => "Right",
+        Direction::Down => "Down",
+    }
+}
+
+impl Game {
+    /// Write `moves` from the initial state as a macro pressing keys of them, with the moves in
+    /// LURD in a comment.
+    ///
+    /// Fails if any move is invalid.
+    pub fn to_input_macro(&self, moves: &[Direction], options: &MacroOptions) -> Result<String> {
+        let lurd = self.to_lurd(moves)?;
+        let start = options.start_delay.as_millis();
+        let hold = options.hold.as_millis();
+        // The time after releasing a key before pressing the next one.
+        let gap = options.delay.saturating_sub(options.hold).as_millis();
+        let header = match &self.metadata.title {
+            Some(title) => format!("{title}, {} moves: {lurd}", moves.len()),
+            None => format!("{} moves: {lurd}", moves.len()),
+        };
+
+        let mut out = String::new();
+        let w = &mut out;
+        match options.format {
+            MacroFormat::AutoHotkey => {
+                writeln!(w, "; {header}").unwrap();
+                writeln!(w, "#Requires AutoHotkey v2.0").unwrap();
+                // Events with a press duration work with games reading raw inputs.
+                writeln!(w, "SendMode \"Event\"").unwrap();
+                writeln!(w, "SetKeyDelay 0, {hold}").unwrap();
+                writeln!(w, "Sleep {start}").unwrap();
+                for &dir in moves {
+                    writeln!(w, "Send \"{{{}}}\"", key_name(dir)).unwrap();
+                    writeln!(w, "Sleep {gap}").unwrap();
+                }
+            }
+            MacroFormat::Xdotool => {
+                let secs = |ms: u128| format!("{}.{:03}", ms / 1000, ms % 1000);
+                writeln!(w, "#!/bin/sh").unwrap();
+                writeln!(w, "# {header}").unwrap();
+                writeln!(w, "set -e").unwrap();
+                writeln!(w, "sleep {}", secs(start)).unwrap();
+                for &dir in moves {
+                    let key = key_name(dir);
+                    writeln!(
+                        w,
+                        "xdotool keydown {key} sleep {} keyup {key} sleep {}",
+                        secs(hold),
+                        secs(gap),
+                    )
+                    .unwrap();
+                }
+            }
+            MacroFormat::Csv => {
+                writeln!(w, "time_ms,event,key").unwrap();
+                let mut time = start;
+                for &dir in moves {
+                    let key = key_name(dir);
+                    writeln!(w, "{time},down,{key}").unwrap();
+                    writeln!(w, "{},up,{key}", time + hold).unwrap();
+                    time += hold + gap;
+                }
+            }
+        }
+        Ok(out)
+    }
+}
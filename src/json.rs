@@ -0,0 +1,281 @@
+//! Conversion from and to a structured JSON level format, for web frontends and external tools.
+
+use std::collections::BTreeMap;
+
+use anyhow::{ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::parse::build_game;
+use crate::{
+    Board, BoardId, Cell, Game, GlobalPos, Metadata, Vec2, MAX_BOARD_CNT, MAX_BOARD_WIDTH,
+};
+
+const VERSION: u32 = 1;
+const ATTEMPT_ORDER: [&str; 3] = ["push", "enter", "eat"];
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Level {
+    version: u32,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rules: Option<Rules>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    comments: Vec<String>,
+    boards: Vec<BoardDef>,
+    player: Location,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    player_target: Option<Location>,
+    #[serde(default)]
+    box_targets: Vec<Location>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Rules {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    attempt_order: Option<Vec<String>>,
+    #[serde(flatten)]
+    toggles: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BoardDef {
+    height: usize,
+    width: usize,
+    #[serde(default)]
+    walls: Vec<[usize; 2]>,
+    #[serde(default)]
+    boxes: Vec<[usize; 2]>,
+    #[serde(default)]
+    references: Vec<Reference>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Reference {
+    pos: [usize; 2],
+    board: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Location {
+    board: usize,
+    pos: [usize; 2],
+}
+
+impl Location {
+    fn from_gpos(gpos: GlobalPos) -> Self {
+        Self {
+            board: gpos.board_id as usize,
+            pos: [gpos.pos.0 as usize, gpos.pos.1 as usize],
+        }
+    }
+
+    fn to_gpos(&self, boards: &[Board]) -> Result<GlobalPos> {
+        let board = boards
+            .get(self.board)
+            .with_context(|| format!("Board id {} out of bound {}", self.board, boards.len()))?;
+        Ok(GlobalPos {
+            board_id: self.board.try_into().unwrap(),
+            pos: board_pos(board, self.pos)?,
+        })
+    }
+}
+
+fn board_pos(board: &Board, [row, col]: [usize; 2]) -> Result<Vec2> {
+    ensure!(
+        row < board.height as usize && col < board.width as usize,
+        "Position {:?} out of bound {}x{}",
+        [row, col],
+        board.height,
+        board.width,
+    );
+    Ok(Vec2(row as _, col as _))
+}
+
+impl Game {
+    /// Parse a level in JSON.
+    ///
+    /// A level is an object of the following fields, where positions are `[row, col]` pairs
+    /// counting from the top-left corner, and board ids are indices into `boards`:
+    ///
+    /// ```json
+    /// {
+    ///   "version": 1,
+    ///   "metadata": { "title": "Sokoban", "author": "Someone" },
+    ///   "rules": { "attempt_order": ["push", "enter", "eat"], "pull": "off" },
+    ///   "comments": ["Designed for the tutorial"],
+    ///   "boards": [
+    ///     {
+    ///       "height": 3,
+    ///       "width": 4,
+    ///       "walls": [[0, 0], [0, 1]],
+    ///       "boxes": [[1, 1]],
+    ///       "references": [{ "pos": [2, 3], "board": 0 }]
+    ///     }
+    ///   ],
+    ///   "player": { "board": 0, "pos": [1, 0] },
+    ///   "player_target": { "board": 0, "pos": [2, 0] },
+    ///   "box_targets": [{ "board": 0, "pos": [1, 3] }]
+    /// }
+    /// ```
+    ///
+    /// - `version` must be 1.
+    /// - `metadata` is an optional object of strings, where `title` and `author` are kept in
+    ///   `Game::metadata`, and others are ignored.
+    /// - `rules` is optional. `attempt_order` must be the default order shown above if present.
+    ///   Other rules are string toggles kept in `Metadata::rules`.
+    /// - `comments` is an optional array of strings kept in `Metadata::comments`.
+    /// - `player_target` is optional. Without it, the player may end anywhere, and does not fill
+    ///   box targets, like in Sokoban.
+    /// - `walls`, `boxes` and `references` of boards are optional, and default to empty. Each cell
+    ///   holds at most one of them, and the player must be on an empty cell.
+    pub fn from_json(s: &str) -> Result<Self> {
+        let level = serde_json::from_str::<Level>(s).context("Invalid JSON level")?;
+        ensure!(
+            level.version == VERSION,
+            "Unsupported version: {}",
+            level.version,
+        );
+        let (attempt_order, rules) = level
+            .rules
+            .map_or((None, BTreeMap::new()), |r| (r.attempt_order, r.toggles));
+        if let Some(order) = &attempt_order {
+            ensure!(
+                order.iter().eq(ATTEMPT_ORDER.iter()),
+                "Unsupported attempt order: {order:?}",
+            );
+        }
+
+        let board_cnt = level.boards.len();
+        ensure!(board_cnt < MAX_BOARD_CNT, "Too many boards");
+        let mut boards = Vec::with_capacity(board_cnt);
+        let mut walls = Vec::with_capacity(board_cnt);
+        for (board_id, def) in level.boards.iter().enumerate() {
+            ensure!(
+                0 < def.width.min(def.height) && def.width.max(def.height) < MAX_BOARD_WIDTH,
+                "Invalid size of board {board_id}",
+            );
+            let mut board = Board {
+                height: def.height as _,
+                width: def.width as _,
+                grid: vec![Cell::Empty; def.height * def.width].into(),
+            };
+            let mut board_walls = vec![false; board.grid.len()];
+            let mut occupied = vec![false; board.grid.len()];
+            let mut occupy = |board: &Board, pos| -> Result<usize> {
+                let idx = board.grid_index(board_pos(board, pos)?);
+                ensure!(
+                    !std::mem::replace(&mut occupied[idx], true),
+                    "Multiple objects at {pos:?} of board {board_id}",
+                );
+                Ok(idx)
+            };
+            for &pos in &def.walls {
+                board_walls[occupy(&board, pos)?] = true;
+            }
+            for &pos in &def.boxes {
+                let idx = occupy(&board, pos)?;
+                board.grid[idx] = Cell::Box;
+            }
+            for r in &def.references {
+                let id = BoardId::try_from(r.board)
+                    .ok()
+                    .filter(|&id| (id as usize) < board_cnt)
+                    .with_context(|| format!("Board id {} out of bound {board_cnt}", r.board))?;
+                let idx = occupy(&board, r.pos)?;
+                board.grid[idx] = Cell::Board(id);
+            }
+            boards.push(board);
+            walls.push(board_walls.into_boxed_slice());
+        }
+
+        let player = level.player.to_gpos(&boards).context("Invalid player")?;
+        let idx = boards[player.board_id as usize].grid_index(player.pos);
+        ensure!(
+            !walls[player.board_id as usize][idx]
+                && boards[player.board_id as usize].grid[idx] == Cell::Empty,
+            "Player is not on an empty cell",
+        );
+        boards[player.board_id as usize].grid[idx] = Cell::Box;
+        let player_target = level
+            .player_target
+            .map(|loc| loc.to_gpos(&boards))
+            .transpose()
+            .context("Invalid player target")?;
+        let box_targets = level
+            .box_targets
+            .iter()
+            .map(|loc| loc.to_gpos(&boards))
+            .collect::<Result<Vec<_>>>()
+            .context("Invalid box target")?;
+        let mut game = build_game(boards, walls, player, player_target, box_targets)?;
+        let mut metadata = level.metadata;
+        game.metadata = Metadata {
+            title: metadata.remove("title"),
+            author: metadata.remove("author"),
+            rules,
+            comments: level.comments,
+        };
+        Ok(game)
+    }
+
+    /// Serialize the level in the JSON format described in `Game::from_json`.
+    pub fn to_json(&self) -> String {
+        let state = &self.state;
+        let boards = (0..state.boards.len())
+            .map(|id| {
+                let board_id = BoardId::try_from(id).unwrap();
+                let board = &state[board_id];
+                let mut def = BoardDef {
+                    height: board.height as usize,
+                    width: board.width as usize,
+                    walls: Vec::new(),
+                    boxes: Vec::new(),
+                    references: Vec::new(),
+                };
+                for (pos, cell) in state.board_cells(board_id) {
+                    let gpos = GlobalPos { board_id, pos };
+                    let pos = [pos.0 as usize, pos.1 as usize];
+                    match cell {
+                        Cell::Empty => {}
+                        Cell::Wall => def.walls.push(pos),
+                        Cell::Box if gpos == state.player => {}
+                        Cell::Box => def.boxes.push(pos),
+                        Cell::Board(id) => def.references.push(Reference {
+                            pos,
+                            board: id as usize,
+                        }),
+                    }
+                }
+                def
+            })
+            .collect();
+        let metadata = &self.metadata;
+        let level = Level {
+            version: VERSION,
+            metadata: [("title", &metadata.title), ("author", &metadata.author)]
+                .into_iter()
+                .filter_map(|(key, value)| Some((key.to_owned(), value.clone()?)))
+                .collect(),
+            rules: (!metadata.rules.is_empty()).then(|| Rules {
+                attempt_order: None,
+                toggles: metadata.rules.clone(),
+            }),
+            comments: metadata.comments.clone(),
+            boards,
+            player: Location::from_gpos(state.player),
+            player_target: self.config.player_target.map(Location::from_gpos),
+            box_targets: self
+                .config
+                .box_targets
+                .iter()
+                .map(|&gpos| Location::from_gpos(gpos))
+                .collect(),
+        };
+        serde_json::to_string_pretty(&level).unwrap()
+    }
+}
@@ -0,0 +1,113 @@
+//! Level packs, holding multiple named levels in one file.
+//!
+//! A pack file is a sequence of levels, each started by a header line `[name]` and followed by
+//! the level in the text format parsed by `Game::from_str`. Blank lines around levels are
+//! ignored.
+//!
+//! ```text
+//! [First]
+//! 0
+//! p.b_=
+//!
+//! [Second]
+//! 0
+//! ...
+//! ```
+
+use std::str::FromStr;
+
+use anyhow::{bail, ensure, Context, Result};
+
+use crate::Game;
+
+/// Named levels in order, with unique names.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LevelPack {
+    levels: Vec<(String, Game)>,
+}
+
+impl LevelPack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    /// Append a level. The name must be non-empty, unique, and fit in one line without
+    /// surrounding whitespaces.
+    pub fn push(&mut self, name: impl Into<String>, game: Game) -> Result<()> {
+        let name = name.into();
+        ensure!(
+            !name.is_empty() && name.trim() == name && !name.contains(['\n', '\r', ']']),
+            "Invalid level name: {name:?}",
+        );
+        ensure!(self.get(&name).is_none(), "Duplicated level name: {name:?}");
+        self.levels.push((name, game));
+        Ok(())
+    }
+
+    /// Iterate over levels with their names, in order.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&str, &Game)> + '_ {
+        self.levels.iter().map(|(name, game)| (&**name, game))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Game> {
+        self.levels
+            .iter()
+            .find_map(|(n, game)| (n == name).then_some(game))
+    }
+
+    /// Format the pack in the pack file format, which can be parsed back by `LevelPack::from_str`.
+    ///
+    /// Fails if any level cannot be expressed in the text format. See `Game::to_text`.
+    pub fn to_text(&self) -> Result<String> {
+        let mut out = String::new();
+        for (name, game) in &self.levels {
+            let text = game
+                .to_text()
+                .with_context(|| format!("Failed to format level {name:?}"))?;
+            out += &format!("[{name}]\n{text}");
+        }
+        Ok(out)
+    }
+}
+
+impl FromStr for LevelPack {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pack = Self::new();
+        let mut cur: Option<(&str, String)> = None;
+        let finish = |pack: &mut Self, (name, text): (&str, String)| {
+            let game = text
+                .trim()
+                .parse::<Game>()
+                .with_context(|| format!("Invalid level {name:?}"))?;
+            pack.push(name, game)
+        };
+        for line in s.lines() {
+            let trimmed = line.trim();
+            if let Some(name) = trimmed.strip_prefix('[') {
+                let name = name.strip_suffix(']').context("Unclosed level header")?;
+                if let Some(level) = cur.replace((name, String::new())) {
+                    finish(&mut pack, level)?;
+                }
+            } else if let Some((_, text)) = &mut cur {
+                *text += line;
+                text.push('\n');
+            } else if !trimmed.is_empty() {
+                bail!("Missing level header before {trimmed:?}");
+            }
+        }
+        if let Some(level) = cur {
+            finish(&mut pack, level)?;
+        }
+        Ok(pack)
+    }
+}
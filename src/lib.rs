@@ -1,12 +1,68 @@
+use std::collections::BTreeMap;
 use std::hash::{Hash, Hasher};
 use std::mem;
 use std::ops::{Index, IndexMut};
+use std::sync::Arc;
 
 use arrayvec::ArrayVec;
 
+#[cfg(feature = "bevy")]
+pub mod bevy;
+mod binary;
+#[cfg(feature = "capi")]
+pub mod capi;
+mod compact;
+mod diagnostic;
+mod discover;
+mod edit;
 mod fmt;
+mod fuzz;
+pub mod generate;
+mod glyph;
+mod html;
+mod input_macro;
+#[cfg(feature = "serde")]
+mod json;
+mod level_pack;
+mod lint;
+mod native;
+mod nested;
+mod pack;
 mod parse;
+#[cfg(feature = "image")]
+mod raster;
+mod session;
+mod share;
 pub mod solve;
+mod stats;
+mod symmetry;
+mod theme;
+mod tiled;
+mod trim;
+mod verify;
+mod walkthrough;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+mod xsb;
+mod zobrist;
+
+pub use diagnostic::{lint, Diagnostic, Severity, Span};
+pub use discover::{find_levels, game_level_dirs, Discovery, FoundLevel};
+pub use edit::Paint;
+pub use fmt::{BoardLayout, DisplayOptions, RenderStyle};
+pub use fuzz::FuzzReport;
+pub use glyph::GlyphMap;
+pub use input_macro::{MacroFormat, MacroOptions};
+pub use level_pack::LevelPack;
+pub use lint::LintDiagnostic;
+pub use nested::NestedRenderer;
+pub use pack::PackedState;
+pub use parse::{Padding, ParseOptions, ParseWarning, ReadError};
+pub use session::{Session, Step};
+pub use stats::{BoardStats, LevelStats};
+pub use theme::{ColorMap, Theme};
+pub use verify::{VerifyError, VerifyReport};
+pub use walkthrough::{MoveOutcome, Moved};
 
 pub const MAX_BOARD_CNT: usize = 16;
 pub const MAX_BOARD_WIDTH: usize = 16;
@@ -64,9 +120,31 @@ impl std::fmt::Debug for BoardId {
     }
 }
 
+/// Formatted as a hexadecimal digit, like the text format.
 impl std::fmt::Display for BoardId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        (*self as usize).fmt(f)
+        write!(f, "{:X}", *self as usize)
+    }
+}
+
+/// A set of boards, packed into a bitset to avoid allocations on hot paths.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct BoardSet(u16);
+
+// Ensure all board ids fit in the bitset.
+const _: () = assert!(MAX_BOARD_CNT <= u16::BITS as usize);
+
+impl BoardSet {
+    pub(crate) fn contains(self, board_id: BoardId) -> bool {
+        self.0 & (1u16 << board_id as u8) != 0
+    }
+
+    /// Insert a board, returns if it is newly inserted.
+    pub(crate) fn insert(&mut self, board_id: BoardId) -> bool {
+        let bit = 1u16 << board_id as u8;
+        let is_new = self.0 & bit == 0;
+        self.0 |= bit;
+        is_new
     }
 }
 
@@ -74,6 +152,19 @@ impl std::fmt::Display for BoardId {
 pub struct Game {
     pub config: Config,
     pub state: State,
+    pub metadata: Metadata,
+}
+
+/// Provenance and rules of a level, from the optional header of map files.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    /// Rule toggles by name, like `pull=off`. They are preserved for tools, but solvers only
+    /// implement the default rules of the game.
+    pub rules: BTreeMap<String, String>,
+    /// Comments in map files, in order. Inline comments are included, but not their locations.
+    pub comments: Vec<String>,
 }
 
 impl Game {
@@ -84,16 +175,46 @@ impl Game {
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Config {
-    player_target: GlobalPos,
+    /// `None` for Sokoban levels, where the player may end anywhere, and does not fill box
+    /// targets.
+    player_target: Option<GlobalPos>,
     box_targets: Box<[GlobalPos]>,
+    /// Non-identity symmetries of the level, shared by all clones.
+    symmetries: Arc<[symmetry::Symmetry]>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone)]
 pub struct State {
     pub(crate) player: GlobalPos,
-    boards: Box<[Board]>,
+    /// The static wall layer, indexed by board id and then grid index.
+    /// It never changes during the game, thus is shared by all states.
+    walls: Arc<[Box<[bool]>]>,
+    /// Boards are shared between clones and copied on write, since a move usually changes
+    /// only one or two of them.
+    boards: Box<[Arc<Board>]>,
+    /// The Zobrist hash of the dynamic content. See `State::zobrist_hash`.
+    zobrist: u64,
+}
+
+impl PartialEq for State {
+    fn eq(&self, other: &Self) -> bool {
+        // NB. Walls are not compared. See `Hash` impl below.
+        self.zobrist == other.zobrist && self.player == other.player && self.boards == other.boards
+    }
+}
+
+impl Eq for State {}
+
+impl Hash for State {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // NB. We only hashing states from the same game, thus walls are always the same.
+        // The Zobrist hash covers all the dynamic content.
+        state.write_u64(self.zobrist);
+    }
 }
 
+/// A board of dynamic cells. Walls are stored separately in `State::walls`, and the
+/// corresponding cells here are always `Cell::Empty`.
 #[derive(Debug, Clone)]
 pub struct Board {
     height: u8,
@@ -251,37 +372,53 @@ impl Index<BoardId> for State {
         &self.boards[idx as usize]
     }
 }
-impl IndexMut<BoardId> for State {
-    fn index_mut(&mut self, idx: BoardId) -> &mut Self::Output {
-        &mut self.boards[idx as usize]
-    }
-}
 
 impl Index<GlobalPos> for State {
     type Output = Cell;
     fn index(&self, gpos: GlobalPos) -> &Self::Output {
+        if self.is_wall(gpos) {
+            return &Cell::Wall;
+        }
         &self[gpos.board_id][gpos.pos]
     }
 }
-impl IndexMut<GlobalPos> for State {
-    fn index_mut(&mut self, gpos: GlobalPos) -> &mut Self::Output {
-        &mut self[gpos.board_id][gpos.pos]
-    }
-}
 
 impl State {
+    fn is_wall(&self, gpos: GlobalPos) -> bool {
+        let board = &self[gpos.board_id];
+        self.walls[gpos.board_id as usize][board.grid_index(gpos.pos)]
+    }
+
+    /// Iterate over all cells of a board, including walls.
+    fn board_cells(&self, board_id: BoardId) -> impl Iterator<Item = (Vec2, Cell)> + '_ {
+        let walls = &self.walls[board_id as usize];
+        self[board_id]
+            .cells()
+            .zip(walls.iter())
+            .map(|((pos, cell), &is_wall)| (pos, if is_wall { Cell::Wall } else { cell }))
+    }
+
     pub fn is_success_on(&self, config: &Config) -> bool {
-        config.player_target == self.player
-            && config
+        match config.player_target {
+            Some(player_target) => {
+                player_target == self.player
+                    && config
+                        .box_targets
+                        .iter()
+                        .all(|&gpos| self[gpos].is_box_like())
+            }
+            None => config
                 .box_targets
                 .iter()
-                .all(|&gpos| self[gpos].is_box_like())
+                .all(|&gpos| gpos != self.player && self[gpos].is_box_like()),
+        }
     }
 
     // TODO: Use bitset operations?
     pub fn trivially_reachable_locations(&self) -> impl Iterator<Item = GlobalPos> + '_ {
         let player = self.player;
         let board = &self[player.board_id];
+        let walls = &self.walls[player.board_id as usize];
         let mut queue = ArrayVec::<Vec2, MAX_BOARD_SIZE>::new();
         let mut visited = [false; MAX_BOARD_SIZE];
         let mut cursor = 0usize;
@@ -291,8 +428,10 @@ impl State {
             let pos = queue[cursor];
             for dir in Direction::ALL {
                 let Some(new_pos) = board.sibling_pos(pos, dir) else { continue };
-                if board[new_pos] == Cell::Empty
-                    && !mem::replace(&mut visited[board.grid_index(new_pos)], true)
+                let idx = board.grid_index(new_pos);
+                if !walls[idx]
+                    && board[new_pos] == Cell::Empty
+                    && !mem::replace(&mut visited[idx], true)
                 {
                     queue.push(new_pos);
                 }
@@ -318,7 +457,7 @@ impl State {
     }
 
     fn sibling(&self, mut gpos: GlobalPos, dir: Direction) -> Option<GlobalPos> {
-        let mut visited = ArrayVec::<_, MAX_PUSH_SEQ_LEN>::new();
+        let mut exited = BoardSet::default();
         loop {
             if let Some(pos) = self[gpos.board_id].sibling_pos(gpos.pos, dir) {
                 return Some(GlobalPos {
@@ -326,28 +465,41 @@ impl State {
                     board_id: gpos.board_id,
                 });
             };
-            gpos = self.get_board_box_pos(gpos.board_id)?;
-            if visited.contains(&gpos) {
+            if !exited.insert(gpos.board_id) {
                 // TODO: Infinity.
                 return None;
             }
-            visited.push(gpos);
+            gpos = self.get_board_box_pos(gpos.board_id)?;
         }
     }
 
+    /// Get the player location.
+    pub fn player(&self) -> GlobalPos {
+        self.player
+    }
+
     /// Set the player location.
     /// The target location must be either empty, or the current location.
     pub fn set_player(&mut self, new_gpos: GlobalPos) {
         let prev_gpos = self.player;
-        let cell = mem::take(&mut self[prev_gpos]);
-        let replaced = mem::replace(&mut self[new_gpos], cell);
+        let cell = self.replace_cell(prev_gpos, Cell::Empty);
+        let replaced = self.replace_cell(new_gpos, cell);
         assert_eq!(replaced, Cell::Empty);
-        self.player = new_gpos;
+        self.relocate_player(new_gpos);
     }
 
     /// Move the player towards a specific direction,
     /// returns if it moves something other than itself.
     pub fn go(&mut self, dir: Direction) -> Result<bool> {
+        Ok(self.push_seq(dir)?.len() > 2)
+    }
+
+    /// Move the player towards a specific direction, returning the push sequence: the start
+    /// location of the player, then where each moved thing is moved to, in order.
+    pub(crate) fn push_seq(
+        &mut self,
+        dir: Direction,
+    ) -> Result<ArrayVec<GlobalPos, MAX_PUSH_SEQ_LEN>> {
         let start_gpos = self.player;
         let mut cur_gpos = start_gpos;
         let mut cur_dir = dir;
@@ -362,16 +514,18 @@ impl State {
 
             match self[cur_gpos] {
                 // Accumulate the push sequence.
-                Cell::Box | Cell::Board(_) => push_seq.push(cur_gpos),
+                Cell::Box | Cell::Board(_) => {
+                    push_seq.try_push(cur_gpos).map_err(|_| Error::Stuck)?;
+                }
                 // Push.
                 Cell::Empty => {
                     let mut cell = Cell::Empty;
-                    push_seq.push(cur_gpos);
+                    push_seq.try_push(cur_gpos).map_err(|_| Error::Stuck)?;
                     for &gpos in &push_seq {
-                        cell = mem::replace(&mut self[gpos], cell);
+                        cell = self.replace_cell(gpos, cell);
                     }
-                    self.player = push_seq[1];
-                    return Ok(push_seq.len() > 2);
+                    self.relocate_player(push_seq[1]);
+                    return Ok(push_seq);
                 }
                 // Back pressure for entering.
                 Cell::Wall => loop {
@@ -423,9 +577,8 @@ impl State {
     }
 
     fn inner_sibling(&self, board_id: BoardId, push_dir: Direction) -> InnerSibling {
-        let board = &self[board_id];
-        let pos = board.inner_sibling_pos(push_dir);
-        match board[pos] {
+        let pos = self[board_id].inner_sibling_pos(push_dir);
+        match self[GlobalPos { board_id, pos }] {
             Cell::Wall => InnerSibling::Wall,
             Cell::Empty | Cell::Box | Cell::Board(_) => {
                 InnerSibling::NonWall(GlobalPos { board_id, pos })
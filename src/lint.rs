@@ -0,0 +1,143 @@
+//! Static checks of levels for mistakes of level designers.
+
+use std::fmt;
+
+use crate::{BoardId, Cell, Game, GlobalPos, State};
+
+/// A suspicious construct of a level, found by `Game::validate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LintDiagnostic {
+    /// There are fewer box-like cells than box targets, excluding the player and the player
+    /// target.
+    NotEnoughBoxes { boxes: usize, targets: usize },
+    /// A cell refers to a board which does not exist.
+    UndefinedBoard { at: GlobalPos, board_id: BoardId },
+    /// A board which the player can neither enter nor exit into, directly or indirectly.
+    UnreachableBoard(BoardId),
+    /// A target is inside a wall, thus can never be filled.
+    TargetInWall(GlobalPos),
+    /// The player starts on a target.
+    PlayerOnTarget(GlobalPos),
+}
+
+impl LintDiagnostic {
+    /// Whether the diagnostic makes the level unsolvable, rather than merely suspicious.
+    pub fn is_error(&self) -> bool {
+        matches!(
+            self,
+            Self::NotEnoughBoxes { .. } | Self::UndefinedBoard { .. } | Self::TargetInWall(_)
+        )
+    }
+}
+
+impl fmt::Display for LintDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fmt_gpos =
+            |gpos: &GlobalPos| format!("{} ({}, {})", gpos.board_id, gpos.pos.0, gpos.pos.1);
+        match self {
+            Self::NotEnoughBoxes { boxes, targets } => {
+                write!(f, "only {boxes} boxes for {targets} box targets")
+            }
+            Self::UndefinedBoard { at, board_id } => {
+                write!(f, "undefined board {board_id} at {}", fmt_gpos(at))
+            }
+            Self::UnreachableBoard(board_id) => write!(f, "board {board_id} is unreachable"),
+            Self::TargetInWall(gpos) => write!(f, "target at {} is in a wall", fmt_gpos(gpos)),
+            Self::PlayerOnTarget(gpos) => {
+                write!(f, "the player starts on the target at {}", fmt_gpos(gpos))
+            }
+        }
+    }
+}
+
+impl Game {
+    /// Check the level for likely mistakes, in the order of the variants of `LintDiagnostic`.
+    ///
+    /// Unlike parse errors, diagnostics do not prevent the level from being played or solved.
+    pub fn validate(&self) -> Vec<LintDiagnostic> {
+        let (state, config) = (&self.state, &self.config);
+        let board_cnt = state.boards.len();
+        let mut diagnostics = Vec::new();
+
+        let mut boxes = 0usize;
+        let mut undefined = Vec::new();
+        for (id, board) in state.boards.iter().enumerate() {
+            let board_id = id.try_into().unwrap();
+            for (pos, cell) in board.cells() {
+                let gpos = GlobalPos { board_id, pos };
+                match cell {
+                    Cell::Box if gpos != state.player => boxes += 1,
+                    Cell::Board(inner) if inner as usize >= board_cnt => {
+                        undefined.push(LintDiagnostic::UndefinedBoard {
+                            at: gpos,
+                            board_id: inner,
+                        });
+                    }
+                    Cell::Board(_) => boxes += 1,
+                    _ => {}
+                }
+            }
+        }
+
+        let targets = config
+            .box_targets
+            .iter()
+            .filter(|&&gpos| Some(gpos) != config.player_target)
+            .count();
+        if boxes < targets {
+            diagnostics.push(LintDiagnostic::NotEnoughBoxes { boxes, targets });
+        }
+        diagnostics.extend(undefined);
+
+        let components = board_components(state);
+        let player_component = components[state.player.board_id as usize];
+        diagnostics.extend(
+            (0..board_cnt)
+                .filter(|&id| components[id] != player_component)
+                .map(|id| LintDiagnostic::UnreachableBoard(id.try_into().unwrap())),
+        );
+
+        let all_targets = config.player_target.iter().chain(config.box_targets.iter());
+        diagnostics.extend(
+            all_targets
+                .clone()
+                .filter(|&&gpos| state.is_wall(gpos))
+                .map(|&gpos| LintDiagnostic::TargetInWall(gpos)),
+        );
+        if all_targets.clone().any(|&gpos| gpos == state.player) {
+            diagnostics.push(LintDiagnostic::PlayerOnTarget(state.player));
+        }
+
+        diagnostics
+    }
+}
+
+/// Label boards by connected components, where boards are connected if one refers to the
+/// other, that is, the player may enter or exit between them. Labels are the smallest board id
+/// of each component. References to undefined boards are ignored.
+pub(crate) fn board_components(state: &State) -> Vec<usize> {
+    let board_cnt = state.boards.len();
+    let mut neighbors = vec![Vec::new(); board_cnt];
+    for (id, board) in state.boards.iter().enumerate() {
+        for (_, cell) in board.cells() {
+            if let Cell::Board(inner) = cell {
+                if (inner as usize) < board_cnt {
+                    neighbors[id].push(inner as usize);
+                    neighbors[inner as usize].push(id);
+                }
+            }
+        }
+    }
+
+    let mut components = vec![usize::MAX; board_cnt];
+    for root in 0..board_cnt {
+        let mut stack = vec![root];
+        while let Some(id) = stack.pop() {
+            if components[id] == usize::MAX {
+                components[id] = root;
+                stack.extend(&neighbors[id]);
+            }
+        }
+    }
+    components
+}
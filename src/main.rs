@@ -1,15 +1,52 @@
-use std::time::Instant;
+use std::collections::BTreeSet;
+use std::hash::Hasher;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
 use console::{Key, Term};
-use indicatif::{ProgressBar, ProgressStyle};
-use parabox_solver::{solve, Direction, Game};
+use indicatif::{HumanBytes, HumanCount, ProgressBar, ProgressStyle};
+use parabox_solver::generate::{self, ClimbOptions, GenerateOptions};
+use parabox_solver::solve::{
+    self, solver_by_name, BfsSearch, DeadlockDetector, Hint, Progress, Solution, SolveOutcome,
+    Solver, SolverOptions, SOLVER_NAMES,
+};
+use parabox_solver::{
+    BoardId, BoardLayout, Direction, DisplayOptions, Game, GlobalPos, MacroOptions, NestedRenderer,
+    Paint, RenderStyle, Session, State, Step, Vec2,
+};
+
+#[cfg(feature = "grpc")]
+mod grpc;
+#[cfg(feature = "serde")]
+mod serve;
+
+/// The file recording solved levels in directories of `browse`.
+const PROGRESS_FILE: &str = ".parabox-progress";
+
+/// The time limit of solving for hints in interactive play.
+const HINT_DURATION: Duration = Duration::from_secs(2);
 
 enum Action {
     Exit,
     Go(Direction),
     Undo,
+    Redo,
     Reset,
+    Hint,
+    Save,
+    /// Export moves, with states after each move if set.
+    Export(bool),
+    ToggleDeadlockWarnings,
+    /// Select a location with a cursor, and walk to it without pushing.
+    GoTo,
+    CycleRenderStyle,
+    ToggleBoardLayout,
+    /// Toggle `DisplayOptions::ruler`.
+    ToggleRuler,
 }
 
 impl TryFrom<Key> for Action {
@@ -17,91 +54,1606 @@ impl TryFrom<Key> for Action {
 
     fn try_from(key: Key) -> Result<Self, Self::Error> {
         Ok(match key {
-            Key::ArrowLeft | Key::Char('a') => Self::Go(Direction::Left),
-            Key::ArrowRight | Key::Char('d') => Self::Go(Direction::Right),
-            Key::ArrowUp | Key::Char('w') => Self::Go(Direction::Up),
-            Key::ArrowDown | Key::Char('s') => Self::Go(Direction::Down),
+            Key::ArrowLeft | Key::Char('a') | Key::Char('h') => Self::Go(Direction::Left),
+            Key::ArrowRight | Key::Char('d') | Key::Char('l') => Self::Go(Direction::Right),
+            Key::ArrowUp | Key::Char('w') | Key::Char('k') => Self::Go(Direction::Up),
+            Key::ArrowDown | Key::Char('s') | Key::Char('j') => Self::Go(Direction::Down),
             Key::Escape | Key::Char('q') => Self::Exit,
             Key::Char('z') => Self::Undo,
+            // Ctrl-R.
+            Key::Char('y') | Key::Char('\u{12}') => Self::Redo,
             Key::Char('r') => Self::Reset,
+            Key::Char('?') => Self::Hint,
+            Key::Char('x') => Self::ToggleDeadlockWarnings,
+            Key::Char('g') => Self::GoTo,
+            Key::Char('v') => Self::CycleRenderStyle,
+            Key::Char('o') => Self::ToggleBoardLayout,
+            Key::Char('i') => Self::ToggleRuler,
+            // Ctrl-S.
+            Key::Char('\u{13}') => Self::Save,
+            Key::Char('e') => Self::Export(false),
+            Key::Char('E') => Self::Export(true),
             _ => return Err(()),
         })
     }
 }
 
+/// The file caching the best known solution of the level, named by a hash of its compact bytes
+/// to keep file names short, in `$PARABOX_CACHE_DIR`, or `$XDG_CACHE_HOME/parabox-solver`, or
+/// `~/.cache/parabox-solver`. Collisions are harmless since cached solutions are verified.
+fn solution_cache_path(game: &Game) -> Option<PathBuf> {
+    let env_dir = |key| std::env::var_os(key).filter(|dir| !dir.is_empty());
+    let dir = env_dir("PARABOX_CACHE_DIR")
+        .map(PathBuf::from)
+        .or_else(|| Some(PathBuf::from(env_dir("XDG_CACHE_HOME")?).join("parabox-solver")))
+        .or_else(|| Some(PathBuf::from(env_dir("HOME")?).join(".cache/parabox-solver")))?;
+    let mut h = fxhash::FxHasher64::default();
+    h.write(&game.to_bytes());
+    Some(dir.join(format!("{:016x}.lurd", h.finish())))
+}
+
+/// Load the cached solution of the level, if any and valid.
+fn load_cached_solution(game: &Game) -> Option<Vec<Direction>> {
+    let text = std::fs::read_to_string(solution_cache_path(game)?).ok()?;
+    let moves = parse_moves(&text).ok()?;
+    game.verify(&moves).ok()?.is_success.then_some(moves)
+}
+
+/// Cache the solution of the level, if it has fewer moves than the cached one.
+fn cache_solution(game: &Game, moves: &[Direction]) -> Result<()> {
+    if load_cached_solution(game).is_some_and(|cached| cached.len() <= moves.len()) {
+        return Ok(());
+    }
+    let path = solution_cache_path(game).context("Unknown cache directory")?;
+    std::fs::create_dir_all(path.parent().unwrap())
+        .context("Failed to create the cache directory")?;
+    let lurd = game.to_lurd(moves).context("Invalid solution")?;
+    std::fs::write(&path, lurd + "\n")
+        .with_context(|| format!("Failed to write the solution to {}", path.display()))
+}
+
+/// Write moves in LURD into the file, followed by the state after each move if `annotated` is
+/// set, as blocks of a `;` comment line of the step and the move, then the state.
+fn export_moves(game: &Game, moves: &[Direction], path: &str, annotated: bool) -> Result<()> {
+    let lurd = game.to_lurd(moves).context("Invalid moves")?;
+    let mut out = format!("{lurd}\n");
+    if annotated {
+        out.push('\n');
+        let mut state = game.state.clone();
+        for (i, (&dir, ch)) in moves.iter().zip(lurd.chars()).enumerate() {
+            state.go(dir)?;
+            out += &format!("; step {}: {ch}\n{state}", i + 1);
+        }
+    }
+    std::fs::write(path, out).with_context(|| format!("Failed to write moves to {path}"))
+}
+
+/// Write the solution in LURD, or a JSON report of the solution if `json` is set.
+fn write_solution(
+    mut w: impl Write,
+    game: &Game,
+    solution: Option<&Solution>,
+    json: bool,
+) -> Result<()> {
+    let lurd = solution
+        .map(|solution| game.to_lurd(&solution.moves))
+        .transpose()
+        .context("Invalid solution")?;
+    match (solution, lurd) {
+        (Some(solution), Some(lurd)) if json => writeln!(
+            w,
+            "{{\"solved\":true,\"solution\":\"{lurd}\",\"moves\":{},\"pushes\":{},\"nodes\":{},\"duration_ms\":{}}}",
+            solution.moves.len(),
+            solution.pushes,
+            solution.nodes_expanded,
+            solution.duration.as_millis(),
+        )?,
+        (Some(_), Some(lurd)) => writeln!(w, "{lurd}")?,
+        _ if json => writeln!(w, "{{\"solved\":false}}")?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Parse moves in the LURD notation, ignoring the case and whitespaces.
+fn parse_moves(s: &str) -> Result<Vec<Direction>> {
+    s.chars()
+        .filter(|ch| !ch.is_whitespace())
+        .map(|ch| {
+            Ok(match ch.to_ascii_uppercase() {
+                'L' => Direction::Left,
+                'U' => Direction::Up,
+                'R' => Direction::Right,
+                'D' => Direction::Down,
+                _ => anyhow::bail!("Invalid move: {ch:?}"),
+            })
+        })
+        .collect()
+}
+
+/// Parse a board layout, `vertical`, or `horizontal` wrapped at the width of the terminal.
+fn parse_layout(s: &str) -> Result<BoardLayout> {
+    Ok(match s {
+        "vertical" => BoardLayout::Vertical,
+        "horizontal" => BoardLayout::Horizontal {
+            max_width: Term::stdout().size_checked().map(|(_, w)| w as usize),
+        },
+        _ => anyhow::bail!("Unknown layout {s:?}, expecting vertical or horizontal"),
+    })
+}
+
+/// Parse a delay like `200ms`, `1.5s`, or `200` in milliseconds.
+fn parse_delay(s: &str) -> Result<Duration> {
+    let parse = |num: &str| {
+        num.parse::<f64>()
+            .ok()
+            .filter(|x| x.is_finite() && *x >= 0.0)
+    };
+    let secs = if let Some(ms) = s.strip_suffix("ms") {
+        parse(ms).map(|ms| ms / 1000.0)
+    } else if let Some(secs) = s.strip_suffix('s') {
+        parse(secs)
+    } else {
+        parse(s).map(|ms| ms / 1000.0)
+    };
+    secs.map(Duration::from_secs_f64)
+        .with_context(|| format!("Invalid delay: {s:?}"))
+}
+
+/// The renderer of `--braille`, with square cells of 4x4 dots, that is 2 characters in a line.
+fn braille_renderer() -> NestedRenderer {
+    NestedRenderer {
+        depth: 2,
+        cell_size: 4,
+        ..NestedRenderer::default()
+    }
+}
+
+/// `replay <path> --moves <moves> [--delay <delay>] [--no-tty] [--nested | --braille]
+/// [--style <style>] [--layout <layout>] [--ruler]` animates moves step by step, stopping at the
+/// first invalid one. `--no-tty` prints all frames to stdout without delays, `--nested` draws
+/// inner boards inside the board of the player, `--braille` draws two levels of them with
+/// Braille patterns for big levels, and `--style` and `--layout` select a `RenderStyle` and a
+/// layout of `parse_layout` by name. `--ruler` prints indices of rows and columns, and
+/// positions of the player and board references. `--html <path>` writes a page replaying the
+/// moves instead, `--walkthrough <path>` writes an annotated text walkthrough instead, and
+/// `--macro <path>` writes a macro pressing keys of the moves in the game instead, in the
+/// format named by `--macro-format` with `--macro-delay` between moves.
+fn replay(mut args: impl Iterator<Item = String>) -> Result<()> {
+    let path = args.next().context("Missing map file argument")?;
+    let (mut moves, mut delay, mut tty) = (None, Duration::from_millis(200), true);
+    let (mut nested, mut options, mut html_path) = (None, DisplayOptions::default(), None);
+    let (mut braille, mut walkthrough_path) = (false, None);
+    let (mut macro_path, mut macro_options) = (None, MacroOptions::default());
+    while let Some(flag) = args.next() {
+        let mut value = || {
+            args.next()
+                .with_context(|| format!("Missing argument of {flag}"))
+        };
+        match flag.as_str() {
+            "--moves" => moves = Some(value()?),
+            "--delay" => delay = parse_delay(&value()?)?,
+            "--no-tty" => tty = false,
+            "--nested" => nested = Some(NestedRenderer::default()),
+            "--braille" => {
+                nested = Some(braille_renderer());
+                braille = true;
+            }
+            "--style" => options.style = value()?.parse()?,
+            "--layout" => options.layout = parse_layout(&value()?)?,
+            "--ruler" => options.ruler = true,
+            "--html" => html_path = Some(value()?),
+            "--walkthrough" => walkthrough_path = Some(value()?),
+            "--macro" => macro_path = Some(value()?),
+            "--macro-format" => macro_options.format = value()?.parse()?,
+            "--macro-delay" => macro_options.delay = parse_delay(&value()?)?,
+            _ => anyhow::bail!("Unknown option {flag:?}"),
+        }
+    }
+    let text = moves.context("Missing --moves")?;
+    let moves = parse_moves(&text)?;
+    let chars = text.chars().filter(|ch| !ch.is_whitespace());
+    let map_data = std::fs::read_to_string(path).context("Failed to read the map")?;
+    let game = map_data
+        .parse::<Game>()
+        .context("Failed to parse the map")?;
+    if let Some(path) = html_path {
+        std::fs::write(&path, game.to_html(&moves)?)
+            .with_context(|| format!("Failed to write {path}"))?;
+        return Ok(());
+    }
+    if let Some(path) = walkthrough_path {
+        std::fs::write(&path, game.walkthrough(&moves)?)
+            .with_context(|| format!("Failed to write {path}"))?;
+        return Ok(());
+    }
+    if let Some(path) = macro_path {
+        std::fs::write(&path, game.to_input_macro(&moves, &macro_options)?)
+            .with_context(|| format!("Failed to write {path}"))?;
+        return Ok(());
+    }
+
+    let term = Term::stderr();
+    // The header of the frame after the `cur`-th move, with the move highlighted.
+    let header = |cur: usize, failed: bool| {
+        let mut out = format!("Step {}/{}: ", cur + 1, moves.len());
+        for (i, ch) in chars.clone().enumerate() {
+            match (i == cur, tty) {
+                (false, _) => out.push(ch),
+                (true, true) if failed => out += &console::style(ch).red().bold().to_string(),
+                (true, true) => out += &console::style(ch).reverse().to_string(),
+                (true, false) => out += &format!("[{ch}]"),
+            }
+        }
+        out
+    };
+    let show = |header: &str, state: &State| -> Result<()> {
+        let frame = match &nested {
+            Some(renderer) if braille => renderer.render_braille(state, &game.config),
+            Some(renderer) => renderer.render(state, &game.config),
+            None => state.display_on_with(&game.config, options).to_string(),
+        };
+        if tty {
+            term.clear_screen()?;
+            eprintln!("{header}\n{frame}");
+        } else {
+            println!("{header}\n{frame}");
+        }
+        Ok(())
+    };
+
+    let mut state = game.state.clone();
+    show(&format!("Step 0/{}", moves.len()), &state)?;
+    for (step, &dir) in moves.iter().enumerate() {
+        if tty {
+            std::thread::sleep(delay);
+        }
+        if let Err(err) = state.go(dir) {
+            show(&header(step, true), &state)?;
+            anyhow::bail!("Move {} ({dir:?}) failed: {err}", step + 1);
+        }
+        show(&header(step, false), &state)?;
+    }
+    if state.is_success_on(&game.config) {
+        eprintln!("Success");
+    } else {
+        eprintln!("Not solved");
+    }
+    Ok(())
+}
+
+/// `eval <path> (--moves <moves> | --moves-file <path>) [--require-win] [--nested | --braille]
+/// [--style <style>] [--layout <layout>] [--ruler]` applies moves non-interactively, and prints
+/// the final state and whether the level is won. Fails on invalid moves, or if the level is not
+/// won and `--require-win` is set. Other options are like `replay`.
+fn eval(mut args: impl Iterator<Item = String>) -> Result<()> {
+    let path = args.next().context("Missing map file argument")?;
+    let (mut moves, mut require_win, mut nested) = (None, false, None);
+    let (mut options, mut braille) = (DisplayOptions::default(), false);
+    while let Some(flag) = args.next() {
+        let mut value = || {
+            args.next()
+                .with_context(|| format!("Missing argument of {flag}"))
+        };
+        match flag.as_str() {
+            "--moves" => moves = Some(value()?),
+            "--moves-file" => {
+                let path = value()?;
+                let text = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read moves from {path}"))?;
+                moves = Some(text);
+            }
+            "--require-win" => require_win = true,
+            "--nested" => nested = Some(NestedRenderer::default()),
+            "--braille" => {
+                nested = Some(braille_renderer());
+                braille = true;
+            }
+            "--style" => options.style = value()?.parse()?,
+            "--layout" => options.layout = parse_layout(&value()?)?,
+            "--ruler" => options.ruler = true,
+            _ => anyhow::bail!("Unknown option {flag:?}"),
+        }
+    }
+    let moves = parse_moves(&moves.context("Missing --moves or --moves-file")?)?;
+    let map_data = std::fs::read_to_string(path).context("Failed to read the map")?;
+    let game = map_data
+        .parse::<Game>()
+        .context("Failed to parse the map")?;
+
+    let report = game.verify(&moves)?;
+    match &nested {
+        Some(renderer) if braille => {
+            println!(
+                "{}",
+                renderer.render_braille(&report.final_state, &game.config)
+            );
+        }
+        Some(renderer) => println!("{}", renderer.render(&report.final_state, &game.config)),
+        None => println!(
+            "{}",
+            report.final_state.display_on_with(&game.config, options)
+        ),
+    }
+    println!("Moves: {} Pushes: {}", moves.len(), report.pushes);
+    println!("Won: {}", report.is_success);
+    anyhow::ensure!(report.is_success || !require_win, "The level is not won");
+    Ok(())
+}
+
 fn main() -> Result<()> {
+    if std::env::args().nth(1).as_deref() == Some("replay") {
+        return replay(std::env::args().skip(2));
+    }
+
+    if std::env::args().nth(1).as_deref() == Some("eval") {
+        return eval(std::env::args().skip(2));
+    }
+
+    if std::env::args().nth(1).as_deref() == Some("analyze") {
+        let path = std::env::args()
+            .nth(2)
+            .context("Missing map file argument")?;
+        return analyze(&path);
+    }
+
+    if std::env::args().nth(1).as_deref() == Some("lint") {
+        return lint(std::env::args().skip(2));
+    }
+
+    if std::env::args().nth(1).as_deref() == Some("fuzz") {
+        return fuzz(std::env::args().skip(2));
+    }
+
+    if std::env::args().nth(1).as_deref() == Some("dataset") {
+        return dataset(std::env::args().skip(2));
+    }
+
+    if std::env::args().nth(1).as_deref() == Some("daily") {
+        return daily(std::env::args().skip(2));
+    }
+
+    if std::env::args().nth(1).as_deref() == Some("render") {
+        #[cfg(feature = "image")]
+        return render(std::env::args().skip(2));
+        #[cfg(not(feature = "image"))]
+        anyhow::bail!("render requires the `image` feature");
+    }
+
+    if std::env::args().nth(1).as_deref() == Some("race") {
+        return race(std::env::args().skip(2));
+    }
+
+    if std::env::args().nth(1).as_deref() == Some("convert") {
+        return convert(std::env::args().skip(2));
+    }
+
+    if std::env::args().nth(1).as_deref() == Some("edit") {
+        return edit(std::env::args().skip(2));
+    }
+
+    if std::env::args().nth(1).as_deref() == Some("serve") {
+        #[cfg(feature = "serde")]
+        return serve::serve(std::env::args().skip(2));
+        #[cfg(not(feature = "serde"))]
+        anyhow::bail!("serve requires the `serde` feature");
+    }
+
+    if std::env::args().nth(1).as_deref() == Some("grpc") {
+        #[cfg(feature = "grpc")]
+        return grpc::grpc(std::env::args().skip(2));
+        #[cfg(not(feature = "grpc"))]
+        anyhow::bail!("grpc requires the `grpc` feature");
+    }
+
+    if std::env::args().nth(1).as_deref() == Some("play") {
+        let dir = std::env::args()
+            .nth(2)
+            .context("Missing level directory argument")?;
+        if dir == "--game" {
+            return browse_game();
+        }
+        return browse(Path::new(&dir));
+    }
+
+    // `--resume <path>` resumes the play saved in the session file, and saves it on exit.
+    if std::env::args().nth(1).as_deref() == Some("--resume") {
+        let path = std::env::args()
+            .nth(2)
+            .context("Missing session file argument")?;
+        let session = Session::load(&path)?;
+        return play(session, path, true).map(drop);
+    }
+
     let path = std::env::args()
         .nth(1)
         .context("Missing map file argument")?;
-    let map_data = std::fs::read_to_string(path).context("Failed to read the map")?;
+    let map_data = std::fs::read_to_string(&path).context("Failed to read the map")?;
     let game = map_data
         .parse::<Game>()
         .context("Failed to parse the map")?;
 
+    // `--validate` reports likely mistakes of the level, and fails on errors.
+    if std::env::args().nth(2).as_deref() == Some("--validate") {
+        let diagnostics = game.validate();
+        for diag in &diagnostics {
+            let level = if diag.is_error() { "error" } else { "warning" };
+            eprintln!("{level}: {diag}");
+        }
+        anyhow::ensure!(
+            !diagnostics.iter().any(|diag| diag.is_error()),
+            "The level is invalid",
+        );
+        return Ok(());
+    }
+
     if std::env::args().nth(2).as_deref() == Some("--solve") {
         let style = ProgressStyle::with_template(
-            "{spinner} Elapsed: {elapsed_precise} Searched: {human_pos} Speed: {per_sec}",
+            "{spinner} Elapsed: {elapsed_precise} Searched: {human_pos} Speed: {per_sec} {msg}",
         )
         .unwrap();
         let pb = ProgressBar::new_spinner().with_style(style);
 
         const BULK: u64 = 1 << 16;
-        let mut counter = 0u64;
+        let (mut counter, mut max_depth) = (0u64, 0usize);
         let inst = Instant::now();
-        let ret = solve::bfs(game, || {
-            counter += 1;
-            if counter % BULK == 0 {
+        let mut on_progress = |progress: &Progress| {
+            counter = progress.steps;
+            max_depth = max_depth.max(progress.depth);
+            if counter.is_multiple_of(BULK) {
                 pb.set_position(counter);
+                // Stored states are either expanded, or in the frontier.
+                pb.set_message(format!(
+                    "Depth: {} Frontier: {} Stored: {} Memory: {}",
+                    progress.depth,
+                    HumanCount(progress.frontier as u64),
+                    HumanCount((progress.expanded + progress.frontier) as u64),
+                    HumanBytes(progress.memory_bytes as u64),
+                ));
+            }
+        };
+        // The first Ctrl-C cancels the search to report statistics, and the second one exits
+        // immediately.
+        let cancel = Arc::new(AtomicBool::new(false));
+        {
+            let cancel = cancel.clone();
+            ctrlc::set_handler(move || {
+                if cancel.swap(true, Ordering::Relaxed) {
+                    std::process::exit(130);
+                }
+            })
+            .context("Failed to set the Ctrl-C handler")?;
+        }
+        // Options after `--solve`:
+        // - `--solver <name>` uses a specific solver instead of `bfs`.
+        // - `--graph <path>` dumps the graph explored by `bfs`, in JSON if the path ends with
+        //   `.json`, or in DOT otherwise.
+        // - `--json` prints a JSON report instead of the bare LURD solution.
+        // - `--output <path>` writes the solution or the report to the file instead of stdout.
+        // - `--html <path>` additionally writes a page replaying the solution.
+        // - `--walkthrough <path>` additionally writes an annotated walkthrough of the solution.
+        // - `--macro <path>` additionally writes a macro pressing keys of the solution in the
+        //   game, with `--macro-format` and `--macro-delay` like `replay`.
+        let (mut solver_name, mut graph_path, mut output_path, mut json) =
+            (None, None, None, false);
+        let (mut html_path, mut walkthrough_path) = (None, None);
+        let (mut macro_path, mut macro_options) = (None, MacroOptions::default());
+        let mut args = std::env::args().skip(3);
+        while let Some(flag) = args.next() {
+            let mut value = || {
+                args.next()
+                    .with_context(|| format!("Missing argument of {flag}"))
+            };
+            match flag.as_str() {
+                "--solver" => solver_name = Some(value()?),
+                "--graph" => graph_path = Some(value()?),
+                "--output" => output_path = Some(value()?),
+                "--json" => json = true,
+                "--html" => html_path = Some(value()?),
+                "--walkthrough" => walkthrough_path = Some(value()?),
+                "--macro" => macro_path = Some(value()?),
+                "--macro-format" => macro_options.format = value()?.parse()?,
+                "--macro-delay" => macro_options.delay = parse_delay(&value()?)?,
+                _ => anyhow::bail!("Unknown option {flag:?}"),
+            }
+        }
+
+        let outcome = if let Some(name) = solver_name {
+            anyhow::ensure!(
+                graph_path.is_none(),
+                "Dumping graphs is only supported by the default solver",
+            );
+            let solver = solver_by_name(&name).with_context(|| {
+                format!("Unknown solver {name:?}, expecting one of {SOLVER_NAMES:?}")
+            })?;
+            let options = SolverOptions {
+                cancel: Some(cancel),
+                ..SolverOptions::default()
+            };
+            solver.solve(&game, &options, &mut on_progress)
+        } else {
+            let options = SolverOptions {
+                record_graph: graph_path.is_some(),
+                cancel: Some(cancel),
+                ..SolverOptions::default()
+            };
+            let mut search = BfsSearch::new(game.clone());
+            let outcome = search.run(&options, &mut on_progress);
+            if let Some(path) = graph_path {
+                let file = std::io::BufWriter::new(
+                    std::fs::File::create(&path).context("Failed to create the graph file")?,
+                );
+                if path.ends_with(".json") {
+                    search.write_json(file)?;
+                } else {
+                    search.write_dot(file)?;
+                }
             }
-        });
-        let elapsed = inst.elapsed();
+            outcome
+        };
         pb.set_position(counter);
+        if let SolveOutcome::Cancelled(stats) = &outcome {
+            pb.abandon();
+            eprintln!(
+                "Interrupted after {:?}: expanded {} states, stored {} states, reached depth {max_depth}",
+                stats.elapsed, stats.expanded, stats.states,
+            );
+            anyhow::bail!("Interrupted");
+        }
+        if let SolveOutcome::Unsupported(reason) = &outcome {
+            pb.abandon();
+            anyhow::bail!("The level is not supported by the solver: {reason}");
+        }
         pb.finish();
-        eprintln!("Finished in {:?}", elapsed);
-        eprintln!("{:?}", ret);
+        eprintln!("Finished in {:?}", inst.elapsed());
+        let ret = outcome.into_solution();
+
+        let mut out: Box<dyn Write> = match &output_path {
+            Some(path) => Box::new(std::io::BufWriter::new(
+                std::fs::File::create(path).context("Failed to create the output file")?,
+            )),
+            None => Box::new(std::io::stdout().lock()),
+        };
+        write_solution(&mut out, &game, ret.as_ref(), json)?;
+        out.flush()?;
+        if let Some(solution) = &ret {
+            if let Err(err) = cache_solution(&game, &solution.moves) {
+                eprintln!("Warning: {err:#}");
+            }
+            if let Some(path) = html_path {
+                std::fs::write(&path, game.to_html(&solution.moves)?)
+                    .with_context(|| format!("Failed to write {path}"))?;
+            }
+            if let Some(path) = walkthrough_path {
+                std::fs::write(&path, game.walkthrough(&solution.moves)?)
+                    .with_context(|| format!("Failed to write {path}"))?;
+            }
+            if let Some(path) = macro_path {
+                std::fs::write(&path, game.to_input_macro(&solution.moves, &macro_options)?)
+                    .with_context(|| format!("Failed to write {path}"))?;
+            }
+        }
+        anyhow::ensure!(ret.is_some(), "No solution found");
         return Ok(());
     }
 
-    let mut history = vec![game.state];
+    // `--session <path>` resumes the play saved in the file if it exists, and saves it on exit.
+    let session_path = (std::env::args().nth(2).as_deref() == Some("--session"))
+        .then(|| std::env::args().nth(3).context("Missing argument"))
+        .transpose()?;
+    let session = match &session_path {
+        Some(path) if std::path::Path::new(path).exists() => {
+            let session = Session::load(path)?;
+            anyhow::ensure!(session.game == game, "Session is of a different level");
+            session
+        }
+        _ => Session::new(game),
+    };
+    let autosave = session_path.is_some();
+    play(
+        session,
+        session_path.unwrap_or_else(|| format!("{path}.session")),
+        autosave,
+    )
+    .map(drop)
+}
+
+/// `analyze <path>` prints statistics, dead squares and lint diagnostics of the level.
+fn analyze(path: &str) -> Result<()> {
+    let map_data = std::fs::read_to_string(path).context("Failed to read the map")?;
+    let game = map_data
+        .parse::<Game>()
+        .context("Failed to parse the map")?;
+    let stats = game.stats();
+    let fmt_ids = |ids: &[BoardId]| {
+        ids.iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    println!("Boards: {}", stats.boards.len());
+    for (id, board) in stats.boards.iter().enumerate() {
+        print!(
+            "  {id}: {}x{}, {} walls",
+            board.height, board.width, board.walls,
+        );
+        if !board.contains.is_empty() {
+            print!(", contains {}", fmt_ids(&board.contains));
+        }
+        let contained_in = stats
+            .boards
+            .iter()
+            .enumerate()
+            .filter(|(_, outer)| outer.contains.iter().any(|&inner| inner as usize == id))
+            .map(|(outer, _)| outer.try_into().unwrap())
+            .collect::<Vec<BoardId>>();
+        if !contained_in.is_empty() {
+            print!(", inside {}", fmt_ids(&contained_in));
+        }
+        println!();
+    }
+    println!("Boxes: {}", stats.boxes);
+    println!("Box targets: {}", stats.box_targets);
+    println!(
+        "Player target: {}",
+        if stats.has_player_target { "yes" } else { "no" },
+    );
+    println!(
+        "Estimated state space: 2^{:.1} states",
+        stats.state_space_log2,
+    );
+
+    let dead = solve::dead_squares(&game)
+        .iter()
+        .map(|gpos| format!("{} ({}, {})", gpos.board_id, gpos.pos.0, gpos.pos.1))
+        .collect::<Vec<_>>();
+    println!("Dead squares: {}", dead.len());
+    for gpos in &dead {
+        println!("  {gpos}");
+    }
+
+    let diagnostics = game.validate();
+    println!("Lints: {}", diagnostics.len());
+    for diag in &diagnostics {
+        let level = if diag.is_error() { "error" } else { "warning" };
+        println!("  {level}: {diag}");
+    }
+    Ok(())
+}
+
+/// The level created by `edit` for a missing file.
+const NEW_LEVEL: &str = "0\n#######\n#.....#\n#.....#\n#..p..#\n#.....#\n#.....#\n#######\n";
+
+/// Format the level in `format`, which is one of `grid` for the text format, `parabox`,
+/// `xsb`, `share`, `binary` or `json`, or `tmx` or `tiled-json` for maps of Tiled, which are
+/// not parsed back.
+fn format_level(game: &Game, format: &str) -> Result<Vec<u8>> {
+    Ok(match format {
+        "grid" => game.to_text()?.into_bytes(),
+        "parabox" => game.to_parabox().into_bytes(),
+        "xsb" => game.to_xsb()?.into_bytes(),
+        "share" => (game.encode_share_string() + "\n").into_bytes(),
+        "binary" => game.to_bytes(),
+        #[cfg(feature = "serde")]
+        "json" => game.to_json().into_bytes(),
+        #[cfg(not(feature = "serde"))]
+        "json" => anyhow::bail!("JSON requires the `serde` feature"),
+        "tmx" => game.to_tmx().into_bytes(),
+        #[cfg(feature = "serde")]
+        "tiled-json" => game.to_tiled_json().into_bytes(),
+        #[cfg(not(feature = "serde"))]
+        "tiled-json" => anyhow::bail!("Tiled JSON requires the `serde` feature"),
+        _ => anyhow::bail!("Unknown format {format:?}"),
+    })
+}
+
+#[cfg(feature = "serde")]
+fn parse_json(text: &str) -> Result<Game> {
+    Game::from_json(text)
+}
+
+#[cfg(not(feature = "serde"))]
+fn parse_json(_text: &str) -> Result<Game> {
+    anyhow::bail!("JSON requires the `serde` feature")
+}
+
+/// Parse a level in any format of `format_level`, returning it with the detected format.
+fn parse_level(data: &[u8]) -> Result<(Game, &'static str)> {
+    if data.starts_with(b"PBOX") {
+        return Ok((Game::from_bytes(data)?, "binary"));
+    }
+    let text = std::str::from_utf8(data).context("Unknown binary format")?;
+    let trimmed = text.trim();
+    let is_share = !trimmed.is_empty()
+        && trimmed
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_');
+    let is_xsb = text.lines().any(|line| {
+        let line = line.trim_end();
+        line.contains('#')
+            && line.contains(['@', '+'])
+            && line.chars().all(|ch| "#@+$*.-_ ".contains(ch))
+    });
+    let (game, format) = if trimmed.starts_with('{') {
+        (parse_json(text)?, "json")
+    } else if trimmed.starts_with("version ") {
+        (Game::from_parabox(text)?, "parabox")
+    } else if is_share && !trimmed.chars().all(|ch| ch.is_ascii_digit()) {
+        (Game::decode_share_string(trimmed)?, "share")
+    } else if is_xsb {
+        (Game::from_xsb(text)?, "xsb")
+    } else {
+        (text.parse::<Game>()?, "grid")
+    };
+    Ok((game, format))
+}
+
+/// Today's date in UTC as `YYYY-MM-DD`.
+fn today() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    // Convert days since the epoch to the proleptic Gregorian calendar, counting in 400-year
+    // eras starting from 0000-03-01.
+    let days = (secs / 86400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// `daily [--seed <seed>] [--difficulty easy|medium|hard] [--solution <path>]` generates a
+/// solvable level determined by the seed, which defaults to today's date in UTC, and evolves it
+/// towards the difficulty, `medium` by default. The level is printed, and the solution is
+/// hidden unless written to the path.
+fn daily(mut args: impl Iterator<Item = String>) -> Result<()> {
+    let (mut seed, mut level, mut solution_path) = (today(), "medium".to_owned(), None);
+    while let Some(flag) = args.next() {
+        let mut value = || {
+            args.next()
+                .with_context(|| format!("Missing argument of {flag}"))
+        };
+        match flag.as_str() {
+            "--seed" => seed = value()?,
+            "--difficulty" => level = value()?,
+            "--solution" => solution_path = Some(value()?),
+            _ => anyhow::bail!("Unknown option {flag:?}"),
+        }
+    }
+    // Board count, board size, box count, minimal pushes, and the target difficulty score.
+    let (board_cnt, size, box_cnt, min_pushes, target_score) = match level.as_str() {
+        "easy" => (1, 6, 2, 4, 15.0),
+        "medium" => (1, 7, 3, 8, 30.0),
+        "hard" => (2, 5, 3, 10, 45.0),
+        _ => anyhow::bail!("Unknown difficulty {level:?}, expecting easy, medium or hard"),
+    };
+    // FNV-1a, which is stable across platforms and versions.
+    let seed_num = seed.bytes().fold(0xcbf2_9ce4_8422_2325u64, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x100_0000_01b3)
+    });
+
+    let options = GenerateOptions {
+        board_cnt,
+        height: size,
+        width: size,
+        box_cnt,
+        min_pushes,
+        seed: seed_num,
+        ..GenerateOptions::default()
+    };
+    let generated = generate::generate(&options).context("Failed to generate a level")?;
+    let climb_options = ClimbOptions {
+        target_score,
+        tolerance: target_score / 10.0,
+        max_iterations: 30,
+        seed: seed_num,
+        ..ClimbOptions::default()
+    };
+    let mut game = generate::hill_climb(generated.game, &climb_options).game;
+    game.metadata.title = Some(format!("Daily {seed}"));
+    // Mutations keep only solvable levels, since the generated one is solvable.
+    let solution = solve::bfs(game.clone(), |_| {}).context("The level is unsolvable")?;
+    let score = solve::difficulty(&game);
+
+    print!("{}", game.to_text()?);
+    eprintln!(
+        "Difficulty: {level}, score {:.1}, {} pushes",
+        score.score, solution.pushes,
+    );
+    if let Some(path) = solution_path {
+        std::fs::write(&path, game.to_lurd(&solution.moves)? + "\n")
+            .with_context(|| format!("Failed to write the solution to {path}"))?;
+    }
+    Ok(())
+}
+
+/// `render <path> [--output <path>] [--cell-size <pixels>] [--depth <depth>]` renders the level
+/// into a PNG file by `NestedRenderer`, `<path>.png` by default. If the path is a directory,
+/// every `.map` file in it is rendered into a PNG file of the same name, as thumbnails.
+#[cfg(feature = "image")]
+fn render(mut args: impl Iterator<Item = String>) -> Result<()> {
+    let path = PathBuf::from(args.next().context("Missing map file argument")?);
+    let mut output_path = None;
+    let mut renderer = NestedRenderer {
+        cell_size: 16,
+        ..NestedRenderer::default()
+    };
+    while let Some(flag) = args.next() {
+        let mut value = || {
+            args.next()
+                .with_context(|| format!("Missing argument of {flag}"))
+        };
+        match flag.as_str() {
+            "--output" => output_path = Some(PathBuf::from(value()?)),
+            "--cell-size" => renderer.cell_size = value()?.parse().context("Invalid cell size")?,
+            "--depth" => renderer.depth = value()?.parse().context("Invalid depth")?,
+            _ => anyhow::bail!("Unknown option {flag:?}"),
+        }
+    }
+    anyhow::ensure!(renderer.cell_size > 0, "Invalid cell size");
+
+    let render_one = |path: &Path, output_path: &Path| -> Result<()> {
+        let data = std::fs::read(path).context("Failed to read the map")?;
+        let (game, _) =
+            parse_level(&data).with_context(|| format!("Failed to parse {}", path.display()))?;
+        renderer.save_png(&game.state, &game.config, output_path)
+    };
+    if !path.is_dir() {
+        let output_path = output_path.unwrap_or_else(|| path.with_extension("png"));
+        return render_one(&path, &output_path);
+    }
+    anyhow::ensure!(
+        output_path.is_none(),
+        "--output is not supported for directories"
+    );
+    let mut paths = std::fs::read_dir(&path)
+        .context("Failed to read the level directory")?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<_>>>()?;
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "map"));
+    paths.sort();
+    for path in &paths {
+        render_one(path, &path.with_extension("png"))?;
+    }
+    eprintln!("Rendered {} levels", paths.len());
+    Ok(())
+}
+
+/// `race <path> --algorithms <names> [--parallel] [--timeout <secs>]` runs solvers of the
+/// comma-separated names, all of `SOLVER_NAMES` by default, one after another or in parallel
+/// threads, and compares their outcomes. Fails if any solution is invalid, or push-optimal
+/// solutions disagree on the count of pushes.
+fn race(mut args: impl Iterator<Item = String>) -> Result<()> {
+    let path = args.next().context("Missing map file argument")?;
+    let mut names = SOLVER_NAMES.map(str::to_owned).to_vec();
+    let (mut parallel, mut options) = (false, SolverOptions::default());
+    while let Some(flag) = args.next() {
+        let mut value = || {
+            args.next()
+                .with_context(|| format!("Missing argument of {flag}"))
+        };
+        match flag.as_str() {
+            "--algorithms" => names = value()?.split(',').map(str::to_owned).collect(),
+            "--parallel" => parallel = true,
+            "--timeout" => {
+                let value = value()?;
+                let secs = value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|x| x.is_finite() && *x >= 0.0)
+                    .with_context(|| format!("Invalid timeout: {value:?}"))?;
+                options.max_duration = Some(Duration::from_secs_f64(secs));
+            }
+            _ => anyhow::bail!("Unknown option {flag:?}"),
+        }
+    }
+    let map_data = std::fs::read_to_string(path).context("Failed to read the map")?;
+    let game = map_data
+        .parse::<Game>()
+        .context("Failed to parse the map")?;
+    let solvers = names
+        .iter()
+        .map(|name| {
+            solver_by_name(name).with_context(|| {
+                format!("Unknown solver {name:?}, expecting one of {SOLVER_NAMES:?}")
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let run = |solver: &(dyn Solver + Send + Sync)| {
+        let inst = Instant::now();
+        let outcome = solver.solve(&game, &options, &mut |_| {});
+        (outcome, inst.elapsed())
+    };
+    let results = if parallel {
+        std::thread::scope(|scope| {
+            let handles = solvers
+                .iter()
+                .map(|solver| scope.spawn(|| run(&**solver)))
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        })
+    } else {
+        solvers.iter().map(|solver| run(&**solver)).collect()
+    };
+
+    let mut push_optimal = Vec::new();
+    for (name, (outcome, elapsed)) in names.iter().zip(&results) {
+        let desc = match outcome {
+            SolveOutcome::Solved(solution) => {
+                let report = game
+                    .verify(&solution.moves)
+                    .with_context(|| format!("Invalid solution of {name}"))?;
+                anyhow::ensure!(report.is_success, "Unsuccessful solution of {name}");
+                // `bfs-moves` is optimal in moves, rather than pushes.
+                if solution.is_optimal && name != "bfs-moves" {
+                    push_optimal.push((name, solution.pushes));
+                }
+                format!(
+                    "solved, {} moves, {} pushes, {} nodes{}",
+                    solution.moves.len(),
+                    solution.pushes,
+                    solution.nodes_expanded,
+                    if solution.is_optimal { ", optimal" } else { "" },
+                )
+            }
+            SolveOutcome::Unsolvable(ret) => format!("unsolvable, {} states", ret.stats.states),
+            SolveOutcome::LimitReached(limit, stats) => {
+                format!("{limit:?} limit reached, {} nodes", stats.expanded)
+            }
+            SolveOutcome::Cancelled(stats) => format!("cancelled, {} nodes", stats.expanded),
+            SolveOutcome::Unsupported(reason) => format!("unsupported, {reason}"),
+        };
+        println!("{name:<16}{:>12.3?}  {desc}", elapsed);
+    }
+
+    let first = names
+        .iter()
+        .zip(&results)
+        .filter(|(_, (outcome, _))| matches!(outcome, SolveOutcome::Solved(_)))
+        .min_by_key(|(_, (_, elapsed))| *elapsed);
+    match first {
+        Some((name, _)) => println!("First solved by {name}"),
+        None => println!("No solution found"),
+    }
+    if let Some(&(_, pushes)) = push_optimal.first() {
+        anyhow::ensure!(
+            push_optimal.iter().all(|&(_, other)| other == pushes),
+            "Push-optimal solutions disagree: {push_optimal:?}",
+        );
+        println!("Push-optimal solutions agree on {pushes} pushes");
+    }
+    Ok(())
+}
+
+/// `fuzz <path> [--seed <seed>] [--steps <n>]` makes random legal moves, checking invariants of
+/// the engine after every move. Fails with the moves leading to the first violation.
+fn fuzz(mut args: impl Iterator<Item = String>) -> Result<()> {
+    let path = args.next().context("Missing map file argument")?;
+    let (mut seed, mut steps) = (0u64, 10_000usize);
+    while let Some(flag) = args.next() {
+        let mut value = || {
+            args.next()
+                .with_context(|| format!("Missing argument of {flag}"))
+        };
+        match flag.as_str() {
+            "--seed" => seed = value()?.parse().context("Invalid seed")?,
+            "--steps" => steps = value()?.parse().context("Invalid step count")?,
+            _ => anyhow::bail!("Unknown option {flag:?}"),
+        }
+    }
+    let map_data = std::fs::read_to_string(path).context("Failed to read the map")?;
+    let game = map_data
+        .parse::<Game>()
+        .context("Failed to parse the map")?;
+    let report = game.fuzz(seed, steps)?;
+    println!(
+        "{} moves, {} pushes, all invariants held",
+        report.moves.len(),
+        report.pushes,
+    );
+    if report.stuck {
+        println!("Stopped early since no move is legal");
+    }
+    Ok(())
+}
+
+/// `lint <path> [--json]` reports mistakes of the map in the strict format with their locations,
+/// and fails on errors. `--json` prints an array of objects by `Diagnostic::to_json` for editor
+/// integrations instead.
+fn lint(mut args: impl Iterator<Item = String>) -> Result<()> {
+    let path = args.next().context("Missing map file argument")?;
+    let mut json = false;
+    for flag in args {
+        match flag.as_str() {
+            "--json" => json = true,
+            _ => anyhow::bail!("Unknown option {flag:?}"),
+        }
+    }
+    let map_data = std::fs::read_to_string(&path).context("Failed to read the map")?;
+    let diagnostics = parabox_solver::lint(&map_data);
+    if json {
+        let objects = diagnostics.iter().map(|diag| diag.to_json());
+        println!("[{}]", objects.collect::<Vec<_>>().join(","));
+    } else {
+        for diag in &diagnostics {
+            eprintln!("{path}:{diag}");
+        }
+    }
+    anyhow::ensure!(
+        !diagnostics
+            .iter()
+            .any(|diag| diag.severity == parabox_solver::Severity::Error),
+        "The map is invalid",
+    );
+    Ok(())
+}
+
+/// `dataset <path> --output <dir> [--max-states <n>]` explores all states reachable by moves,
+/// and writes them with transitions and distances to success as NumPy arrays into the
+/// directory, for machine learning. See `solve::Dataset` for the format.
+fn dataset(mut args: impl Iterator<Item = String>) -> Result<()> {
+    let path = args.next().context("Missing map file argument")?;
+    let (mut output_path, mut options) = (None, solve::DatasetOptions::default());
+    while let Some(flag) = args.next() {
+        let mut value = || {
+            args.next()
+                .with_context(|| format!("Missing argument of {flag}"))
+        };
+        match flag.as_str() {
+            "--output" => output_path = Some(value()?),
+            "--max-states" => {
+                options.max_states = value()?.parse().context("Invalid state count")?;
+            }
+            _ => anyhow::bail!("Unknown option {flag:?}"),
+        }
+    }
+    let output_path = output_path.context("Missing --output")?;
+    let map_data = std::fs::read_to_string(path).context("Failed to read the map")?;
+    let game = map_data
+        .parse::<Game>()
+        .context("Failed to parse the map")?;
+    let dataset = solve::dataset(&game, &options)?;
+    dataset.write_npy(Path::new(&output_path))?;
+    let solvable = dataset.distances.iter().filter(|&&d| d >= 0).count();
+    println!(
+        "{} states ({solvable} solvable), {} transitions, distance {} from the initial state",
+        dataset.len(),
+        dataset.transitions.len(),
+        dataset.distances[0],
+    );
+    Ok(())
+}
+
+/// `convert <path> --to <format> [--output <path>]` converts the level from any format of
+/// `format_level`, which is detected automatically, to `format`, and writes it to stdout or
+/// the output path.
+fn convert(mut args: impl Iterator<Item = String>) -> Result<()> {
+    let path = args.next().context("Missing map file argument")?;
+    let (mut format, mut output_path) = (None, None);
+    while let Some(flag) = args.next() {
+        let mut value = || {
+            args.next()
+                .with_context(|| format!("Missing argument of {flag}"))
+        };
+        match flag.as_str() {
+            "--to" => format = Some(value()?),
+            "--output" => output_path = Some(value()?),
+            _ => anyhow::bail!("Unknown option {flag:?}"),
+        }
+    }
+    let format = format.context("Missing --to")?;
+    let data = std::fs::read(&path).context("Failed to read the map")?;
+    let (game, detected) = parse_level(&data).context("Failed to parse the map")?;
+    eprintln!("Detected format: {detected}");
+    let data = format_level(&game, &format)?;
+    match output_path {
+        Some(path) => std::fs::write(&path, data)
+            .with_context(|| format!("Failed to write the level to {path}"))?,
+        None => std::io::stdout().write_all(&data)?,
+    }
+    Ok(())
+}
+
+/// Print the level with the cursor highlighted in the text format, where each board is a block
+/// of lines starting with its id.
+fn print_with_cursor(game: &Game, cursor: GlobalPos) {
+    let text = game.to_string();
+    let mut board_row = None;
+    let mut block_start = true;
+    for line in text.lines() {
+        let is_block_start = std::mem::replace(&mut block_start, line.is_empty());
+        if is_block_start && line == format!("{:X}", cursor.board_id as usize) {
+            board_row = Some(0u8);
+        } else if line.is_empty() {
+            board_row = None;
+        } else if let Some(row) = &mut board_row {
+            if *row == cursor.pos.0 {
+                let col = cursor.pos.1 as usize;
+                let (before, after) = line.split_at(col);
+                let mut after = after.chars();
+                let ch = after.next().unwrap();
+                let ch = console::style(ch).reverse();
+                eprintln!("{before}{ch}{}", after.as_str());
+                *row += 1;
+                continue;
+            }
+            *row += 1;
+        }
+        eprintln!("{line}");
+    }
+    eprintln!(
+        "Cursor: board {} ({}, {})",
+        cursor.board_id, cursor.pos.0, cursor.pos.1
+    );
+}
+
+/// Move the cursor by arrow keys, or switch to the next board by Tab. Returns whether the key
+/// is handled.
+fn move_cursor(game: &Game, cursor: &mut GlobalPos, key: &Key) -> bool {
+    let stats = game.stats();
+    let board = &stats.boards[cursor.board_id as usize];
+    match key {
+        Key::ArrowUp => cursor.pos.0 = cursor.pos.0.saturating_sub(1),
+        Key::ArrowDown => cursor.pos.0 = (cursor.pos.0 + 1).min(board.height - 1),
+        Key::ArrowLeft => cursor.pos.1 = cursor.pos.1.saturating_sub(1),
+        Key::ArrowRight => cursor.pos.1 = (cursor.pos.1 + 1).min(board.width - 1),
+        Key::Tab => {
+            let board_id = (cursor.board_id as usize + 1) % stats.boards.len();
+            *cursor = GlobalPos {
+                board_id: board_id.try_into().unwrap(),
+                pos: Vec2(0, 0),
+            };
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// `edit <path> [--format <format>] [--output <path>]` edits the level in any format of
+/// `format_level`, or creates one if the file does not exist, and saves it in `format`, which
+/// defaults to `grid`, into the output path, which defaults to the input path.
+///
+/// Arrow keys move the cursor, Tab switches boards, and characters of the text format paint
+/// the cell under the cursor, where `0`-`9` and `A`-`F` are board cells. `t` test-plays the
+/// level, Ctrl-S saves it, and Esc quits.
+fn edit(mut args: impl Iterator<Item = String>) -> Result<()> {
+    let path = args.next().context("Missing map file argument")?;
+    let (mut format, mut output_path) = ("grid".to_owned(), None);
+    while let Some(flag) = args.next() {
+        let mut value = || {
+            args.next()
+                .with_context(|| format!("Missing argument of {flag}"))
+        };
+        match flag.as_str() {
+            "--format" => format = value()?,
+            "--output" => output_path = Some(value()?),
+            _ => anyhow::bail!("Unknown option {flag:?}"),
+        }
+    }
+    let output_path = output_path.unwrap_or_else(|| path.clone());
+    // Fail early on unknown formats.
+    let mut game = NEW_LEVEL.parse::<Game>().unwrap();
+    format_level(&game, &format)?;
+    if Path::new(&path).exists() {
+        let data = std::fs::read(&path).context("Failed to read the map")?;
+        game = parse_level(&data).context("Failed to parse the map")?.0;
+    }
+
+    let term = Term::stderr();
+    let mut cursor = GlobalPos {
+        board_id: BoardId::try_from(0).unwrap(),
+        pos: Vec2(0, 0),
+    };
+    let mut message = String::new();
+    loop {
+        term.clear_screen()?;
+        print_with_cursor(&game, cursor);
+        for diag in game.validate() {
+            let level = if diag.is_error() { "error" } else { "warning" };
+            eprintln!("{level}: {diag}");
+        }
+        if !message.is_empty() {
+            eprintln!("{}", std::mem::take(&mut message));
+        }
+
+        let key = term.read_key()?;
+        if move_cursor(&game, &mut cursor, &key) {
+            continue;
+        }
+        let paint = match key {
+            Key::Escape => break,
+            Key::Char('t') => {
+                term.clear_screen()?;
+                let session_path = format!("{path}.session");
+                play(Session::new(game.clone()), session_path, false)?;
+                eprintln!("Press any key to return to the editor");
+                term.read_key()?;
+                continue;
+            }
+            // Ctrl-S.
+            Key::Char('\u{13}') => {
+                let data = format_level(&game, &format)?;
+                std::fs::write(&output_path, data)
+                    .with_context(|| format!("Failed to write the level to {output_path}"))?;
+                message = format!("Saved to {output_path}");
+                continue;
+            }
+            Key::Char('.') => Paint::Empty,
+            Key::Char('#') => Paint::Wall,
+            Key::Char('b') => Paint::Box,
+            Key::Char('p') => Paint::Player,
+            Key::Char('_') => Paint::BoxTarget,
+            Key::Char('=') => Paint::PlayerTarget,
+            Key::Char(ch @ ('0'..='9' | 'A'..='F')) => {
+                Paint::Board(BoardId::try_from(ch.to_digit(16).unwrap() as usize).unwrap())
+            }
+            _ => continue,
+        };
+        match game.paint(cursor, paint) {
+            Ok(edited) => game = edited,
+            Err(err) => message = format!("Error: {err:#}"),
+        }
+    }
+    Ok(())
+}
+
+/// `play <dir>` lists `.map` levels in the directory to select and play one after another.
+/// Solved levels are recorded by file names in the progress file in the directory.
+fn browse(dir: &Path) -> Result<()> {
+    let mut paths = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read the directory {}", dir.display()))?
+        .map(|entry| Ok(entry?.path()))
+        .filter(|path| {
+            path.as_ref().map_or(true, |path| {
+                path.extension().is_some_and(|ext| ext == "map")
+            })
+        })
+        .collect::<std::io::Result<Vec<_>>>()?;
+    paths.sort();
+    anyhow::ensure!(!paths.is_empty(), "No levels in {}", dir.display());
+    let levels = paths
+        .into_iter()
+        .map(|path| {
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            (name, path)
+        })
+        .collect();
+    browse_levels(
+        levels,
+        &dir.join(PROGRESS_FILE),
+        |path| {
+            std::fs::read_to_string(path)
+                .context("Failed to read the map")
+                .and_then(|data| data.parse::<Game>().context("Failed to parse the map"))
+        },
+        |_, path| format!("{}.session", path.display()),
+        None,
+    )
+}
+
+/// `play --game` lists levels made in the level editor of the installed game, including
+/// downloads of Steam Workshop, like `play <dir>`. Since directories of the game are left
+/// untouched, the progress file and sessions are kept in the current directory.
+fn browse_game() -> Result<()> {
+    let dirs = parabox_solver::game_level_dirs();
+    anyhow::ensure!(
+        !dirs.is_empty(),
+        "No level directories of the game are found"
+    );
+    let discovery = parabox_solver::find_levels(&dirs)?;
+    anyhow::ensure!(
+        !discovery.levels.is_empty(),
+        "No importable levels in {}, with {} skipped",
+        dirs.iter()
+            .map(|dir| dir.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+        discovery.skipped.len(),
+    );
+    let message = (!discovery.skipped.is_empty()).then(|| {
+        let skipped = discovery
+            .skipped
+            .iter()
+            .map(|(path, err)| format!("Skipped {}: {err}\n", path.display()))
+            .collect::<String>();
+        skipped.trim_end().to_owned()
+    });
+    let levels = discovery
+        .levels
+        .into_iter()
+        .map(|level| (level.name, level.path))
+        .collect();
+    browse_levels(
+        levels,
+        Path::new(PROGRESS_FILE),
+        |path| {
+            std::fs::read_to_string(path)
+                .context("Failed to read the level")
+                .and_then(|data| Game::from_parabox(&data).context("Failed to import the level"))
+        },
+        |name, _| format!("{}.session", name.replace('/', "_")),
+        message,
+    )
+}
+
+/// List levels by names and paths to select and play one after another, loading them by
+/// `load`. Solved levels are recorded by names in the progress file.
+fn browse_levels(
+    levels: Vec<(String, PathBuf)>,
+    progress_path: &Path,
+    load: impl Fn(&Path) -> Result<Game>,
+    session_path: impl Fn(&str, &Path) -> String,
+    mut message: Option<String>,
+) -> Result<()> {
+    let mut solved = std::fs::read_to_string(progress_path)
+        .map(|text| text.lines().map(str::to_owned).collect::<BTreeSet<_>>())
+        .unwrap_or_default();
 
     let term = Term::stderr();
+    let mut cursor = 0usize;
+    loop {
+        term.clear_screen()?;
+        for (i, (name, _)) in levels.iter().enumerate() {
+            let selected = if i == cursor { '>' } else { ' ' };
+            let marker = if solved.contains(name) { 'x' } else { ' ' };
+            eprintln!("{selected} [{marker}] {name}");
+        }
+        eprintln!("\nSelect with arrow keys, play with Enter, quit with q");
+        if let Some(message) = message.take() {
+            eprintln!("{message}");
+        }
+
+        match term.read_key()? {
+            Key::ArrowUp | Key::Char('w') => cursor = cursor.saturating_sub(1),
+            Key::ArrowDown | Key::Char('s') => cursor = (cursor + 1).min(levels.len() - 1),
+            Key::Enter => {
+                let (name, path) = &levels[cursor];
+                let game = match load(path) {
+                    Ok(game) => game,
+                    Err(err) => {
+                        message = Some(format!("Failed to load {name}: {err:#}"));
+                        continue;
+                    }
+                };
+                term.clear_screen()?;
+                if play(Session::new(game), session_path(name, path), false)? {
+                    solved.insert(name.clone());
+                    let text = solved
+                        .iter()
+                        .map(|name| format!("{name}\n"))
+                        .collect::<String>();
+                    std::fs::write(progress_path, text)
+                        .context("Failed to write the progress file")?;
+                    cursor = (cursor + 1).min(levels.len() - 1);
+                }
+                eprintln!("Press any key to return to the list");
+                term.read_key()?;
+            }
+            Key::Escape | Key::Char('q') => break,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Play interactively from the session. Ctrl-S saves the session into `session_path`, and
+/// enables `autosave`, which saves the session on exit. Moves since the last reset are
+/// exported by `e`, or with states by `E`, next to the session file with the extension `.lurd`.
+///
+/// Returns whether the level is solved.
+fn play(mut session: Session, session_path: String, mut autosave: bool) -> Result<bool> {
+    let game = session.game.clone();
+    let export_path = format!(
+        "{}.lurd",
+        session_path
+            .strip_suffix(".session")
+            .unwrap_or(&session_path),
+    );
+    let mut history = session.states()?;
+    let save = |session: &mut Session| -> Result<()> {
+        session
+            .bookmarks
+            .retain(|_, &mut count| count <= session.steps.len());
+        session.save(&session_path)
+    };
+
+    let term = Term::stderr();
+    let deadlock = DeadlockDetector::new(&game);
+    let mut warn_deadlocks = true;
+    let mut options = DisplayOptions::default();
+    let mut solved = false;
+    // The move count of the best known solution, as the par.
+    let par = load_cached_solution(&game).map(|moves| moves.len());
     loop {
         let mut state = history.last().cloned().unwrap();
-        eprintln!("{}", state);
+        eprintln!("{}", state.display_on_with(&game.config, options));
+
+        // Moves from the initial state, since the last reset.
+        let moves = session
+            .steps
+            .rsplit(|&step| step == Step::Reset)
+            .next()
+            .unwrap()
+            .iter()
+            .map(|&step| match step {
+                Step::Go(dir) => dir,
+                Step::Reset => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        match par {
+            Some(par) => eprintln!("Moves: {} (par {par})", moves.len()),
+            None => eprintln!("Moves: {}", moves.len()),
+        }
 
         if state.is_success_on(&game.config) {
-            eprintln!("Success");
+            match par {
+                Some(par) if moves.len() < par => eprintln!("Success, under par!"),
+                Some(par) if moves.len() == par => eprintln!("Success, at par!"),
+                Some(par) => eprintln!("Success, {} moves over par", moves.len() - par),
+                None => eprintln!("Success"),
+            }
+            if let Err(err) = cache_solution(&game, &moves) {
+                eprintln!("Warning: {err:#}");
+            }
+            solved = true;
             break;
         }
+        if warn_deadlocks && deadlock.is_dead(&state) {
+            eprintln!("Warning: this position can no longer be solved, undo with `z`");
+        }
 
+        // Digits before an action repeat it, like `5l` in vi.
+        let mut count = None::<usize>;
         let action = loop {
-            if let Ok(action) = Action::try_from(term.read_key()?) {
-                break action;
+            match term.read_key()? {
+                Key::Char(ch @ '0'..='9') if ch != '0' || count.is_some() => {
+                    let digit = ch.to_digit(10).unwrap() as usize;
+                    let n = count.unwrap_or(0).saturating_mul(10).saturating_add(digit);
+                    count = Some(n);
+                    eprintln!("Repeat: {n}");
+                }
+                key => {
+                    if let Ok(action) = Action::try_from(key) {
+                        break action;
+                    }
+                }
             }
         };
+        let count = count.unwrap_or(1);
 
         match action {
             Action::Exit => break,
             Action::Go(dir) => {
-                let msg = match state.go(dir) {
-                    Ok(pushed) => {
-                        history.push(state);
-                        pushed.to_string()
+                // Stop repeating on the first error.
+                let mut msg = String::new();
+                for _ in 0..count {
+                    match state.go(dir) {
+                        Ok(pushed) => {
+                            history.push(state.clone());
+                            session.push(Step::Go(dir));
+                            msg = pushed.to_string();
+                        }
+                        Err(err) => {
+                            msg = err.to_string();
+                            break;
+                        }
                     }
-                    Err(err) => err.to_string(),
-                };
+                }
                 eprintln!("{msg}");
             }
             Action::Undo => {
-                if history.len() >= 2 {
+                for _ in 0..count {
+                    if session.undo().is_none() {
+                        break;
+                    }
                     history.pop();
                 }
             }
+            Action::Redo => {
+                for _ in 0..count {
+                    match session.redo() {
+                        Some(Step::Go(dir)) => {
+                            state.go(dir)?;
+                        }
+                        Some(Step::Reset) => state = history[0].clone(),
+                        None => break,
+                    }
+                    history.push(state.clone());
+                }
+            }
             Action::Reset => {
                 history.push(history[0].clone());
+                session.push(Step::Reset);
+            }
+            Action::Hint => {
+                let options = SolverOptions {
+                    max_duration: Some(HINT_DURATION),
+                    ..SolverOptions::default()
+                };
+                match solve::hint(&game, &state, &options) {
+                    Hint::Move(dir) => eprintln!("Hint: {dir:?}"),
+                    Hint::Solved => eprintln!("Hint: already solved"),
+                    Hint::Unsolvable => eprintln!("Hint: no solution from here"),
+                    Hint::Unknown => eprintln!("Hint: no solution found in time"),
+                }
+            }
+            Action::Save => {
+                save(&mut session)?;
+                autosave = true;
+                eprintln!("Saved to {session_path}");
+            }
+            Action::Export(annotated) => {
+                export_moves(&game, &moves, &export_path, annotated)?;
+                eprintln!("Exported {} moves to {export_path}", moves.len());
+            }
+            Action::ToggleDeadlockWarnings => {
+                warn_deadlocks = !warn_deadlocks;
+                let toggle = if warn_deadlocks { "on" } else { "off" };
+                eprintln!("Deadlock warnings: {toggle}");
+            }
+            Action::CycleRenderStyle => {
+                let i = RenderStyle::ALL
+                    .iter()
+                    .position(|&s| s == options.style)
+                    .unwrap();
+                options.style = RenderStyle::ALL[(i + 1) % RenderStyle::ALL.len()];
+            }
+            Action::ToggleBoardLayout => {
+                options.layout = match options.layout {
+                    BoardLayout::Vertical => BoardLayout::Horizontal {
+                        max_width: Some(term.size().1 as usize),
+                    },
+                    BoardLayout::Horizontal { .. } => BoardLayout::Vertical,
+                };
+            }
+            Action::ToggleRuler => options.ruler = !options.ruler,
+            Action::GoTo => {
+                let view = Game {
+                    config: game.config.clone(),
+                    state: state.clone(),
+                    metadata: game.metadata.clone(),
+                };
+                let mut cursor = state.player();
+                let walk = loop {
+                    print_with_cursor(&view, cursor);
+                    eprintln!("Go to: arrow keys and Tab select, Enter walks, Esc cancels");
+                    let key = term.read_key()?;
+                    if move_cursor(&view, &mut cursor, &key) {
+                        continue;
+                    }
+                    match key {
+                        Key::Escape => break None,
+                        Key::Enter => match solve::walk_to(&state, cursor) {
+                            Some(walk) => break Some(walk),
+                            None => eprintln!("Unreachable without pushing"),
+                        },
+                        _ => {}
+                    }
+                };
+                for dir in walk.into_iter().flatten() {
+                    state.go(dir)?;
+                    history.push(state.clone());
+                    session.push(Step::Go(dir));
+                }
             }
         }
     }
 
-    Ok(())
+    if autosave {
+        save(&mut session)?;
+    }
+
+    Ok(solved)
 }
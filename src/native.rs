@@ -0,0 +1,401 @@
+//! Conversion from and to the level format of Patrick's Parabox, as used by its level editor.
+//!
+//! A level is a `version 4` header ended by `#`, followed by object lines. Objects are nested
+//! by leading tabs inside their parent `Block`, and are located by `x` from the left and `y`
+//! from the bottom. Only features with equivalents in this crate are supported: enterable
+//! blocks, solid blocks as boxes, exit references, walls and buttons.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+
+use crate::parse::build_game;
+use crate::{Board, BoardId, Cell, Game, GlobalPos, Vec2, MAX_BOARD_WIDTH};
+
+/// The attempt order of movement rules of this crate.
+const DEFAULT_ATTEMPT_ORDER: &str = "push,enter,eat,possess";
+
+/// Fields of an object line, following the object name.
+struct Fields<'a>(&'a [&'a str]);
+
+impl Fields<'_> {
+    fn get<T: FromStr>(&self, i: usize, name: &str) -> Result<T> {
+        let field = self
+            .0
+            .get(i)
+            .with_context(|| format!("Missing field {name}"))?;
+        field
+            .parse()
+            .ok()
+            .with_context(|| format!("Invalid field {name}: {field:?}"))
+    }
+
+    /// Get a boolean flag, which may be omitted by older versions.
+    fn flag(&self, i: usize, name: &str) -> Result<bool> {
+        if i >= self.0.len() {
+            return Ok(false);
+        }
+        Ok(self.get::<u8>(i, name)? != 0)
+    }
+}
+
+/// An enterable block being parsed.
+struct BlockDef {
+    height: u8,
+    width: u8,
+    grid: Vec<Cell>,
+    walls: Vec<bool>,
+    /// Whether each cell is occupied by a wall or an object.
+    occupied: Vec<bool>,
+}
+
+impl BlockDef {
+    fn index(&self, pos: Vec2) -> usize {
+        pos.0 as usize * self.width as usize + pos.1 as usize
+    }
+}
+
+#[derive(Default)]
+struct Parser {
+    blocks: Vec<BlockDef>,
+    block_ids: HashMap<i64, BoardId>,
+    /// References with the referred block ids, resolved after all blocks are parsed.
+    refs: Vec<(GlobalPos, i64)>,
+    player: Option<GlobalPos>,
+    player_target: Option<GlobalPos>,
+    box_targets: Vec<GlobalPos>,
+    /// Parents of the current line by depth, or `None` for objects which cannot contain others.
+    parents: Vec<Option<BoardId>>,
+}
+
+impl Parser {
+    fn occupy(&mut self, gpos: GlobalPos, cell: Cell, is_wall: bool) -> Result<()> {
+        let block = &mut self.blocks[gpos.board_id as usize];
+        let idx = block.index(gpos.pos);
+        ensure!(!block.occupied[idx], "Overlapping objects");
+        block.occupied[idx] = true;
+        block.grid[idx] = cell;
+        block.walls[idx] = is_wall;
+        Ok(())
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<()> {
+        let depth = line.chars().take_while(|&ch| ch == '\t').count();
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+        let (&kind, fields) = tokens.split_first().unwrap();
+        let fields = Fields(fields);
+        ensure!(depth <= self.parents.len(), "Invalid indentation");
+        self.parents.truncate(depth);
+
+        // The location of this object in its parent, if any.
+        let gpos = match self.parents.last() {
+            None => None,
+            Some(None) => bail!("Objects inside a non-enterable object"),
+            Some(&Some(board_id)) => {
+                let block = &self.blocks[board_id as usize];
+                let (x, y) = (fields.get::<i64>(0, "x")?, fields.get::<i64>(1, "y")?);
+                ensure!(
+                    (0..block.width as i64).contains(&x) && (0..block.height as i64).contains(&y),
+                    "Position ({x}, {y}) out of bound",
+                );
+                let pos = Vec2((block.height as i64 - 1 - y) as u8, x as u8);
+                Some(GlobalPos { board_id, pos })
+            }
+        };
+        let placed_gpos = || gpos.context("Top-level objects must be blocks");
+
+        let mut board_id = None;
+        match kind {
+            "Block" => board_id = self.parse_block(&fields, gpos)?,
+            "Ref" => {
+                let gpos = placed_gpos()?;
+                ensure!(
+                    fields.flag(3, "exitblock")?,
+                    "Clone references are not supported",
+                );
+                ensure!(
+                    !fields.flag(4, "infexit")? && !fields.flag(6, "infenter")?,
+                    "Infinite references are not supported",
+                );
+                ensure!(
+                    !fields.flag(9, "player")?,
+                    "Enterable players are not supported",
+                );
+                ensure!(
+                    !fields.flag(12, "fliph")?,
+                    "Flipped blocks are not supported",
+                );
+                ensure!(
+                    !fields.flag(13, "floatinspace")?,
+                    "Floating blocks are not supported",
+                );
+                // The board id is filled later.
+                self.occupy(gpos, Cell::Empty, false)?;
+                self.refs.push((gpos, fields.get(2, "id")?));
+            }
+            "Wall" => {
+                ensure!(!fields.flag(2, "player")?, "Wall players are not supported");
+                self.occupy(placed_gpos()?, Cell::Empty, true)?;
+            }
+            "Floor" => {
+                let gpos = placed_gpos()?;
+                match fields.0.get(2).copied() {
+                    Some("Button") => self.box_targets.push(gpos),
+                    Some("PlayerButton") => {
+                        ensure!(self.player_target.is_none(), "Multiple player targets");
+                        self.player_target = Some(gpos);
+                    }
+                    ty => bail!("Unsupported floor: {ty:?}"),
+                }
+            }
+            _ => bail!("Unsupported object: {kind:?}"),
+        }
+        self.parents.push(board_id);
+        Ok(())
+    }
+
+    /// Parse a block at `gpos` in its parent, returning its board id if it is enterable.
+    fn parse_block(&mut self, fields: &Fields, gpos: Option<GlobalPos>) -> Result<Option<BoardId>> {
+        let id = fields.get::<i64>(2, "id")?;
+        let is_solid = fields.flag(9, "fillwithwalls")?;
+        let is_player = fields.flag(10, "player")?;
+        ensure!(
+            !fields.flag(13, "fliph")?,
+            "Flipped blocks are not supported",
+        );
+        ensure!(
+            !fields.flag(14, "floatinspace")?,
+            "Floating blocks are not supported",
+        );
+
+        // Solid blocks can neither be entered nor be referenced, thus are plain boxes.
+        if is_solid {
+            let gpos = gpos.context("Top-level solid block")?;
+            self.occupy(gpos, Cell::Box, false)?;
+            if is_player {
+                ensure!(self.player.is_none(), "Multiple players");
+                self.player = Some(gpos);
+            }
+            return Ok(None);
+        }
+
+        ensure!(!is_player, "Enterable players are not supported");
+        let width = fields.get::<u8>(3, "width")?;
+        let height = fields.get::<u8>(4, "height")?;
+        ensure!(
+            (1..MAX_BOARD_WIDTH as u8).contains(&width)
+                && (1..MAX_BOARD_WIDTH as u8).contains(&height),
+            "Invalid block size {width}x{height}",
+        );
+        let board_id =
+            BoardId::try_from(self.blocks.len()).map_err(|()| anyhow!("Too many blocks"))?;
+        ensure!(
+            self.block_ids.insert(id, board_id).is_none(),
+            "Duplicated block id {id}",
+        );
+        let size = width as usize * height as usize;
+        self.blocks.push(BlockDef {
+            height,
+            width,
+            grid: vec![Cell::Empty; size],
+            walls: vec![false; size],
+            occupied: vec![false; size],
+        });
+        if let Some(gpos) = gpos {
+            self.occupy(gpos, Cell::Board(board_id), false)?;
+        }
+        Ok(Some(board_id))
+    }
+
+    fn finish(mut self) -> Result<Game> {
+        for (gpos, id) in std::mem::take(&mut self.refs) {
+            let board_id = *self
+                .block_ids
+                .get(&id)
+                .with_context(|| format!("Reference to unknown block {id}"))?;
+            let block = &mut self.blocks[gpos.board_id as usize];
+            let idx = block.index(gpos.pos);
+            block.grid[idx] = Cell::Board(board_id);
+        }
+
+        // Exits are ambiguous otherwise.
+        let mut is_placed = vec![false; self.blocks.len()];
+        for cell in self.blocks.iter().flat_map(|block| &block.grid) {
+            if let Cell::Board(id) = *cell {
+                ensure!(
+                    !std::mem::replace(&mut is_placed[id as usize], true),
+                    "Block {id} is placed multiple times",
+                );
+            }
+        }
+
+        let (boards, walls) = self
+            .blocks
+            .into_iter()
+            .map(|block| {
+                let board = Board {
+                    height: block.height,
+                    width: block.width,
+                    grid: block.grid.into(),
+                };
+                (board, block.walls.into())
+            })
+            .unzip();
+        build_game(
+            boards,
+            walls,
+            self.player.context("Missing player")?,
+            Some(self.player_target.context("Missing player target")?),
+            self.box_targets,
+        )
+    }
+}
+
+impl Game {
+    /// Parse a level in the `version 4` format of Patrick's Parabox.
+    ///
+    /// Enterable blocks become boards in the order of their definitions, and each of them can
+    /// be placed at most once, either nested in its parent or by an exit reference. Solid
+    /// blocks become boxes.
+    pub fn from_parabox(s: &str) -> Result<Self> {
+        let mut lines = s.lines().filter(|line| !line.trim().is_empty());
+        ensure!(
+            lines.next().map(str::trim) == Some("version 4"),
+            "Expecting version 4",
+        );
+        loop {
+            let line = lines.next().context("Missing end of header")?.trim();
+            if line == "#" {
+                break;
+            }
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "draw_style" | "custom_level_music" | "custom_level_palette" => {}
+                "attempt_order" if value.trim() == DEFAULT_ATTEMPT_ORDER => {}
+                _ => bail!("Unsupported header: {line:?}"),
+            }
+        }
+
+        let mut parser = Parser::default();
+        for (i, line) in lines.enumerate() {
+            parser
+                .parse_line(line)
+                .with_context(|| format!("Object {}: {:?}", i + 1, line.trim()))?;
+        }
+        parser.finish()
+    }
+}
+
+/// Serializer of a game, writing blocks recursively.
+struct Writer<'a> {
+    game: &'a Game,
+    /// The exit location of each board, if any.
+    exits: Vec<Option<GlobalPos>>,
+    is_written: Vec<bool>,
+    /// The next id of solid blocks, after ids of boards.
+    next_solid_id: usize,
+    out: String,
+}
+
+impl Writer<'_> {
+    /// Write a board as a block at `(x, y)` in its parent, or at the top level if `None`.
+    fn write_block(&mut self, board_id: BoardId, loc: Option<(usize, usize)>, depth: usize) {
+        let state = &self.game.state;
+        let board = &state[board_id];
+        let (x, y) = loc.map_or((-1, -1), |(x, y)| (x as i64, y as i64));
+        let indent = "\t".repeat(depth);
+        writeln!(
+            self.out,
+            "{indent}Block {x} {y} {} {} {} 0.6 0.8 1 1 0 0 0 0 0 0 0",
+            board_id as usize, board.width, board.height,
+        )
+        .unwrap();
+        self.is_written[board_id as usize] = true;
+
+        let indent = "\t".repeat(depth + 1);
+        let height = board.height as usize;
+        let mut floors = String::new();
+        for (pos, cell) in state.board_cells(board_id) {
+            let gpos = GlobalPos { board_id, pos };
+            let (x, y) = (pos.1 as usize, height - 1 - pos.0 as usize);
+            match cell {
+                Cell::Empty => {}
+                Cell::Wall => writeln!(self.out, "{indent}Wall {x} {y} 0 0 0").unwrap(),
+                Cell::Box => {
+                    let id = self.next_solid_id;
+                    self.next_solid_id += 1;
+                    let (hue, is_player) = if gpos == state.player {
+                        (0.9, 1)
+                    } else {
+                        (0.1, 0)
+                    };
+                    writeln!(
+                        self.out,
+                        "{indent}Block {x} {y} {id} 1 1 {hue} 0.8 1 1 1 {is_player} {is_player} 0 0 0 0",
+                    )
+                    .unwrap();
+                }
+                Cell::Board(id) => {
+                    let is_exit = self.exits[id as usize] == Some(gpos);
+                    if is_exit && !self.is_written[id as usize] {
+                        self.write_block(id, Some((x, y)), depth + 1);
+                    } else {
+                        // Only the exit reference of a written block, or a clone.
+                        writeln!(
+                            self.out,
+                            "{indent}Ref {x} {y} {} {} 0 0 0 0 -1 0 0 0 0 0 0",
+                            id as usize, is_exit as u8,
+                        )
+                        .unwrap();
+                    }
+                }
+            }
+            let config = &self.game.config;
+            let floor = if Some(gpos) == config.player_target {
+                Some("PlayerButton")
+            } else if config.box_targets.contains(&gpos) {
+                Some("Button")
+            } else {
+                None
+            };
+            if let Some(floor) = floor {
+                writeln!(floors, "{indent}Floor {x} {y} {floor}").unwrap();
+            }
+        }
+        self.out += &floors;
+    }
+}
+
+impl Game {
+    /// Serialize the level in the `version 4` format of Patrick's Parabox.
+    ///
+    /// Boards become enterable blocks with the same ids, nested at their exit locations if
+    /// possible, or referenced by exit references otherwise. Other locations of boards become
+    /// clone references. Boxes and the player become solid blocks.
+    pub fn to_parabox(&self) -> String {
+        let board_cnt = self.state.boards.len();
+        let ids = || (0..board_cnt).map(|id| BoardId::try_from(id).unwrap());
+        let mut writer = Writer {
+            game: self,
+            exits: ids().map(|id| self.state.get_board_box_pos(id)).collect(),
+            is_written: vec![false; board_cnt],
+            next_solid_id: board_cnt,
+            out: "version 4\n#\n".to_owned(),
+        };
+        // Boards without exits must be at the top level. Then boards only placed inside
+        // themselves, directly or indirectly, are written from the one with the minimal id.
+        for id in ids() {
+            if writer.exits[id as usize].is_none() {
+                writer.write_block(id, None, 0);
+            }
+        }
+        for id in ids() {
+            if !writer.is_written[id as usize] {
+                writer.write_block(id, None, 0);
+            }
+        }
+        writer.out
+    }
+}
@@ -0,0 +1,170 @@
+//! Rendering of boards with inner boards drawn inside cells of their references, like the
+//! visuals of the game.
+
+use crate::{BoardId, Cell, ColorMap, Config, GlobalPos, State, Theme, Vec2};
+
+/// Renders a board as text where every cell is a square block of characters, and inner boards
+/// are drawn scaled down inside blocks of their references, up to a depth.
+///
+/// Blocks are sampled at the center of each character, thus inner boards larger than the block
+/// lose some cells. Empty box targets show the target glyph.
+#[derive(Debug, Clone)]
+pub struct NestedRenderer {
+    /// The count of levels of inner boards to draw. Board references deeper than it are drawn
+    /// as their hexadecimal ids.
+    pub depth: usize,
+    /// The count of characters of each side of cells of the root board.
+    pub cell_size: usize,
+    /// The board to draw, or the board containing the player if `None`.
+    pub root: Option<BoardId>,
+    pub theme: Theme,
+}
+
+impl Default for NestedRenderer {
+    fn default() -> Self {
+        Self {
+            depth: 1,
+            cell_size: 5,
+            root: None,
+            theme: Theme::default(),
+        }
+    }
+}
+
+/// What a point of a rendered board shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Sampled {
+    Cell(Cell),
+    Player,
+    BoxTarget,
+    PlayerTarget,
+}
+
+impl NestedRenderer {
+    /// Render the root board of `state`, as lines ending with newlines.
+    pub fn render(&self, state: &State, config: &Config) -> String {
+        let (height, width) = self.size(state);
+        let mut out = String::with_capacity((width + 1) * height);
+        for y in 0..height {
+            for x in 0..width {
+                let ch = match self.sample_at(state, config, (height, width), y, x) {
+                    Sampled::Cell(cell) => self.theme.glyphs.cell(cell),
+                    Sampled::Player => self.theme.glyphs.player,
+                    Sampled::BoxTarget => self.theme.glyphs.box_target,
+                    Sampled::PlayerTarget => self.theme.glyphs.player_target,
+                };
+                out.push(ch);
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Render the root board of `state` with Braille patterns of 2x4 dots per character, where
+    /// `cell_size` is in dots, thus boards fit in fewer characters with more levels of nesting.
+    ///
+    /// Dots are raised for walls, boxes, the player, and board references not drawn as boards.
+    /// Characters with dots of the player are colored by the theme if supported by the terminal.
+    pub fn render_braille(&self, state: &State, config: &Config) -> String {
+        /// Bits of dots in a Braille pattern, by rows and columns.
+        const DOTS: [[u8; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+        let size = self.size(state);
+        let (height, width) = size;
+        let player_color = ColorMap::to_ansi256(self.theme.colors.player);
+        let mut out = String::new();
+        for y in (0..height).step_by(4) {
+            for x in (0..width).step_by(2) {
+                let (mut bits, mut has_player) = (0u8, false);
+                for (dy, row) in DOTS.iter().enumerate() {
+                    for (dx, bit) in row.iter().enumerate() {
+                        let (y, x) = (y + dy, x + dx);
+                        if y >= height || x >= width {
+                            continue;
+                        }
+                        let raised = match self.sample_at(state, config, size, y, x) {
+                            Sampled::Cell(cell) => cell != Cell::Empty,
+                            Sampled::Player => {
+                                has_player = true;
+                                true
+                            }
+                            Sampled::BoxTarget | Sampled::PlayerTarget => false,
+                        };
+                        if raised {
+                            bits |= bit;
+                        }
+                    }
+                }
+                let ch = char::from_u32(0x2800 + bits as u32).unwrap();
+                if has_player {
+                    out += &console::style(ch).color256(player_color).to_string();
+                } else {
+                    out.push(ch);
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// The height and width of the output, in units of `cell_size`.
+    pub(crate) fn size(&self, state: &State) -> (usize, usize) {
+        let board = &state[self.root.unwrap_or(state.player.board_id)];
+        (
+            board.height as usize * self.cell_size,
+            board.width as usize * self.cell_size,
+        )
+    }
+
+    /// Sample the center of the unit `(y, x)` of the output of `size`.
+    pub(crate) fn sample_at(
+        &self,
+        state: &State,
+        config: &Config,
+        (height, width): (usize, usize),
+        y: usize,
+        x: usize,
+    ) -> Sampled {
+        let root = self.root.unwrap_or(state.player.board_id);
+        let fy = (y as f64 + 0.5) / height as f64;
+        let fx = (x as f64 + 0.5) / width as f64;
+        sample(state, config, root, self.depth, fy, fx)
+    }
+}
+
+/// Sample the fractional location `(fy, fx)` in `[0, 1)` of the board.
+fn sample(
+    state: &State,
+    config: &Config,
+    board_id: BoardId,
+    depth: usize,
+    fy: f64,
+    fx: f64,
+) -> Sampled {
+    let board = &state[board_id];
+    let (fy, fx) = (fy * board.height as f64, fx * board.width as f64);
+    let (row, col) = (fy as u8, fx as u8);
+    let gpos = GlobalPos {
+        board_id,
+        pos: Vec2(row, col),
+    };
+    if gpos == state.player {
+        return Sampled::Player;
+    }
+    if state.is_wall(gpos) {
+        return Sampled::Cell(Cell::Wall);
+    }
+    match state[gpos] {
+        Cell::Board(inner) if depth > 0 => sample(
+            state,
+            config,
+            inner,
+            depth - 1,
+            fy - row as f64,
+            fx - col as f64,
+        ),
+        Cell::Empty if Some(gpos) == config.player_target => Sampled::PlayerTarget,
+        Cell::Empty if config.box_targets.contains(&gpos) => Sampled::BoxTarget,
+        cell => Sampled::Cell(cell),
+    }
+}
@@ -0,0 +1,255 @@
+//! Compact state representation for the visited sets of solvers.
+
+use std::hash::{Hash, Hasher};
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+use crate::zobrist::packed_hash;
+use crate::{BoardId, Cell, GlobalPos, State, Vec2, MAX_BOARD_CNT, MAX_BOARD_WIDTH};
+
+/// Boards with ids below this can be packed into a nibble, along with `Empty` and `Box`.
+const MAX_NIBBLE_BOARD_CNT: usize = 16 - 2;
+
+/// A packed `State` with two cells per byte when possible.
+///
+/// It only contains the dynamic content, thus can only be unpacked into a state of the same game.
+#[derive(Debug, Clone)]
+pub struct PackedState {
+    player: GlobalPos,
+    cells: Box<[u8]>,
+    /// The Zobrist hash, equal to `State::zobrist_hash` of the original state.
+    hash: u64,
+}
+
+impl PartialEq for PackedState {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash && self.player == other.player && self.cells == other.cells
+    }
+}
+
+impl Eq for PackedState {}
+
+impl Hash for PackedState {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+pub(crate) fn encode_cell(cell: Cell) -> u8 {
+    match cell {
+        Cell::Empty => 0,
+        Cell::Box => 1,
+        Cell::Board(id) => 2 + id as u8,
+        Cell::Wall => unreachable!("Walls are not stored in boards"),
+    }
+}
+
+fn decode_cell(code: u8) -> Cell {
+    match code {
+        0 => Cell::Empty,
+        1 => Cell::Box,
+        _ => Cell::Board(BoardId::try_from(code as usize - 2).unwrap()),
+    }
+}
+
+impl PackedState {
+    /// A hash which is stable across runs and platforms, for on-disk or approximate sets.
+    /// It is the Zobrist hash of the state, thus costs nothing.
+    pub fn stable_hash(&self) -> u64 {
+        self.hash
+    }
+
+    pub(crate) fn cells_len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub(crate) fn player(&self) -> GlobalPos {
+        self.player
+    }
+
+    pub(crate) fn cells(&self) -> &[u8] {
+        &self.cells
+    }
+
+    /// Reassemble a packed state from parts of another one.
+    pub(crate) fn from_parts(player: GlobalPos, cells: Box<[u8]>, hash: u64) -> Self {
+        Self {
+            player,
+            cells,
+            hash,
+        }
+    }
+
+    pub(crate) fn write_to(&self, w: &mut impl Write) -> io::Result<()> {
+        write_gpos(w, self.player)?;
+        w.write_all(&self.cells)
+    }
+
+    /// Read a packed state written by `write_to`, with `cells_len` bytes of cells.
+    pub(crate) fn read_from(r: &mut impl Read, cells_len: usize) -> io::Result<Self> {
+        let player = read_gpos(r)?;
+        let mut cells = vec![0u8; cells_len].into_boxed_slice();
+        r.read_exact(&mut cells)?;
+        let hash = packed_hash(player, &cells);
+        Ok(Self {
+            player,
+            cells,
+            hash,
+        })
+    }
+
+    /// Estimate the memory of a hash map entry with this state as the key, and `V` as the value.
+    pub(crate) fn estimated_entry_size<V>(&self) -> usize {
+        // Plus a hash and an index for `IndexMap`.
+        std::mem::size_of::<(Self, V)>() + self.cells.len() + 2 * std::mem::size_of::<usize>()
+    }
+}
+
+impl State {
+    pub(crate) fn is_nibble_packable(&self) -> bool {
+        self.boards.len() <= MAX_NIBBLE_BOARD_CNT
+    }
+
+    /// Pack the dynamic content of this state.
+    pub fn pack(&self) -> PackedState {
+        let cells = self.pack_cells();
+        debug_assert_eq!(self.zobrist, packed_hash(self.player, &cells));
+        PackedState {
+            player: self.player,
+            cells,
+            hash: self.zobrist,
+        }
+    }
+
+    pub(crate) fn pack_cells(&self) -> Box<[u8]> {
+        let codes = self
+            .boards
+            .iter()
+            .flat_map(|board| board.grid.iter())
+            .map(|&cell| encode_cell(cell));
+        if self.is_nibble_packable() {
+            let mut cells = Vec::new();
+            let mut codes = codes.fuse();
+            while let Some(lo) = codes.next() {
+                let hi = codes.next().unwrap_or(0);
+                cells.push(lo | hi << 4);
+            }
+            cells.into()
+        } else {
+            codes.collect()
+        }
+    }
+
+    /// Whether `gpos` is inside a board of this state, and not in a wall.
+    pub(crate) fn is_open(&self, gpos: GlobalPos) -> bool {
+        let Some(board) = self.boards.get(gpos.board_id as usize) else {
+            return false;
+        };
+        gpos.pos.0 < board.height
+            && gpos.pos.1 < board.width
+            && !self.walls[gpos.board_id as usize][board.grid_index(gpos.pos)]
+    }
+
+    /// Whether `packed`, maybe read from untrusted data, can be unpacked into this state: its
+    /// codes are valid, walls are empty, the player is on its box, and it has the same boxes and
+    /// references of boards, which moves never change.
+    pub(crate) fn is_valid_packed(&self, packed: &PackedState) -> bool {
+        let len = self
+            .boards
+            .iter()
+            .map(|board| board.grid.len())
+            .sum::<usize>();
+        let is_nibble = self.is_nibble_packable();
+        let expected_len = if is_nibble { len.div_ceil(2) } else { len };
+        if packed.cells.len() != expected_len || !self.is_open(packed.player) {
+            return false;
+        }
+        // The padding nibble of an odd count of cells is zero.
+        if is_nibble && len % 2 == 1 && packed.cells[len / 2] >> 4 != 0 {
+            return false;
+        }
+        let code_at = |i: usize| {
+            if is_nibble {
+                packed.cells[i / 2] >> (i % 2 * 4) & 0xF
+            } else {
+                packed.cells[i]
+            }
+        };
+
+        let max_code = 2 + self.boards.len();
+        let mut counts = [0isize; 2 + MAX_BOARD_CNT];
+        let mut offset = 0usize;
+        for (board_id, board) in self.boards.iter().enumerate() {
+            let walls = &self.walls[board_id];
+            for (i, &cell) in board.grid.iter().enumerate() {
+                let code = code_at(offset + i) as usize;
+                if code >= max_code {
+                    return false;
+                }
+                let is_player = packed.player.board_id as usize == board_id
+                    && board.grid_index(packed.player.pos) == i;
+                // The player is a box itself.
+                if (walls[i] && code != 0) || (is_player && code != 1) {
+                    return false;
+                }
+                counts[code] += 1;
+                counts[encode_cell(cell) as usize] -= 1;
+            }
+            offset += board.grid.len();
+        }
+        // Empty cells are balanced by the others.
+        counts[1..].iter().all(|&cnt| cnt == 0)
+    }
+
+    /// Overwrite the dynamic content of this state by a packed state from the same game.
+    pub fn unpack_from(&mut self, packed: &PackedState) {
+        self.unpack_parts(packed.player, &packed.cells, packed.hash);
+    }
+
+    /// Like `unpack_from`, but with parts of a packed state.
+    pub(crate) fn unpack_parts(&mut self, player: GlobalPos, cells: &[u8], hash: u64) {
+        let is_nibble = self.is_nibble_packable();
+        let decode = |i: usize| {
+            decode_cell(if is_nibble {
+                cells[i / 2] >> (i % 2 * 4) & 0xF
+            } else {
+                cells[i]
+            })
+        };
+        let mut offset = 0usize;
+        for board in self.boards.iter_mut() {
+            let len = board.grid.len();
+            // Keep unchanged boards shared.
+            if (0..len).any(|i| board.grid[i] != decode(offset + i)) {
+                let board = Arc::make_mut(board);
+                for (i, cell) in board.grid.iter_mut().enumerate() {
+                    *cell = decode(offset + i);
+                }
+            }
+            offset += len;
+        }
+        self.player = player;
+        self.zobrist = hash;
+    }
+}
+
+pub(crate) fn write_gpos(w: &mut impl Write, gpos: GlobalPos) -> io::Result<()> {
+    w.write_all(&[gpos.board_id as u8, gpos.pos.0, gpos.pos.1])
+}
+
+pub(crate) fn read_gpos(r: &mut impl Read) -> io::Result<GlobalPos> {
+    let mut buf = [0u8; 3];
+    r.read_exact(&mut buf)?;
+    let board_id = BoardId::try_from(buf[0] as usize)
+        .map_err(|()| io::Error::new(io::ErrorKind::InvalidData, "Invalid board id"))?;
+    if buf[1..].iter().any(|&x| x as usize >= MAX_BOARD_WIDTH) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Invalid position",
+        ));
+    }
+    Ok(GlobalPos {
+        board_id,
+        pos: Vec2(buf[1], buf[2]),
+    })
+}
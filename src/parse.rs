@@ -1,114 +1,505 @@
+use std::io::{self, BufRead};
+use std::iter::Peekable;
 use std::str::FromStr;
+use std::sync::Arc;
 
 use anyhow::{anyhow, bail, ensure, Context, Result};
 
+use crate::glyph::Glyph;
+use crate::symmetry::find_symmetries;
 use crate::{
-    Board, BoardId, Cell, Config, Game, GlobalPos, State, Vec2, MAX_BOARD_CNT, MAX_BOARD_WIDTH,
+    Board, BoardId, Cell, Config, Game, GlobalPos, GlyphMap, Metadata, State, Vec2, MAX_BOARD_CNT,
+    MAX_BOARD_WIDTH,
 };
 
 impl FromStr for Game {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut lines = s.lines().map(|line| line.trim());
-
-        let mut boards = Vec::new();
-        let mut player = None;
-        let mut player_target = None;
-        let mut box_targets = Vec::new();
-        let mut max_board_id = BoardId::default();
-
-        while let Some(id_line) = lines.next() {
-            let board_id = id_line
-                .parse::<usize>()?
-                .try_into()
-                .map_err(|()| anyhow!("Too many boards"))?;
-            ensure!(
-                board_id as usize == boards.len(),
-                "Invalid board id: {board_id}"
-            );
+        Self::parse_with_glyphs(s, &GlyphMap::default())
+    }
+}
 
-            let line = lines.next().context("Missing board content")?;
-            let width = line.chars().count();
-
-            let mut grid = Vec::new();
-            let mut parse_line = |i: usize, line: &str| -> Result<_> {
-                for (j, ch) in line.chars().enumerate() {
-                    let gpos = GlobalPos {
-                        board_id,
-                        pos: Vec2(i as _, j as _),
-                    };
-                    let cell = match ch {
-                        '.' => Cell::Empty,
-                        '#' => Cell::Wall,
-                        'b' => Cell::Box,
-                        'p' => {
-                            ensure!(player.is_none(), "Multiple players");
-                            player = Some(gpos);
-                            Cell::Box
-                        }
-                        '_' => {
-                            box_targets.push(gpos);
-                            Cell::Empty
-                        }
-                        '=' => {
-                            ensure!(player_target.is_none(), "Multiple player targets");
-                            player_target = Some(gpos);
-                            Cell::Empty
-                        }
-                        '0'..='9' => {
-                            let board_id = BoardId::try_from(ch as usize - b'0' as usize).unwrap();
-                            max_board_id = max_board_id.max(board_id);
-                            Cell::Board(board_id)
-                        }
-                        _ => bail!("Invalid cell: {ch:?}",),
-                    };
-                    grid.push(cell);
-                }
-                Ok(())
+impl Game {
+    /// Parse a map like `Game::from_str`, but with custom glyphs.
+    pub fn parse_with_glyphs(s: &str, glyphs: &GlyphMap) -> Result<Self> {
+        parse_lines(s.lines().map(str::to_owned), glyphs)
+    }
+
+    /// Parse a map like `Game::from_str`, but reading lines incrementally from `reader`.
+    ///
+    /// Failures of reading are reported as `ReadError::Io`, even if the partial input is also
+    /// invalid.
+    pub fn from_reader(reader: impl BufRead) -> Result<Self, ReadError> {
+        let mut error = None;
+        let lines = reader
+            .lines()
+            .map_while(|line| line.map_err(|err| error = Some(err)).ok());
+        let ret = parse_lines(lines, &GlyphMap::default());
+        match error {
+            Some(err) => Err(ReadError::Io(err)),
+            None => ret.map_err(ReadError::Parse),
+        }
+    }
+}
+
+/// An error of `Game::from_reader`.
+#[derive(Debug)]
+pub enum ReadError {
+    /// Failed to read the input.
+    Io(io::Error),
+    /// The input is not a valid map.
+    Parse(anyhow::Error),
+}
+
+impl std::fmt::Display for ReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReadError::Io(err) => write!(f, "Failed to read: {err}"),
+            ReadError::Parse(err) => write!(f, "Invalid map: {err:#}"),
+        }
+    }
+}
+
+impl std::error::Error for ReadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReadError::Io(err) => Some(err),
+            ReadError::Parse(err) => Some(err.as_ref()),
+        }
+    }
+}
+
+/// Parse lines of a map in the strict format, after comments are stripped.
+fn parse_lines(lines: impl Iterator<Item = String>, glyphs: &GlyphMap) -> Result<Game> {
+    glyphs.validate()?;
+    let mut comments = Vec::new();
+    let mut lines = lines
+        .filter_map(|line| strip_comment(&line, &mut comments).map(str::to_owned))
+        .peekable();
+    let (mut metadata, has_player_target) = parse_metadata(&mut lines)?;
+
+    let mut boards = Vec::new();
+    let mut walls = Vec::new();
+    let mut player = None;
+    let mut player_target = None;
+    let mut box_targets = Vec::new();
+    let mut max_board_id = BoardId::default();
+
+    let mut covered_targets = Vec::new();
+
+    while let Some(id_line) = lines.next() {
+        // Blank lines may be left by removed comments.
+        if id_line.is_empty() {
+            continue;
+        }
+
+        // Covered targets, like `_ 0 2 3`. See `Display for Game`.
+        if let Some(kind @ ('_' | '=')) = id_line.chars().next() {
+            let fields = id_line[1..].split_whitespace().collect::<Vec<_>>();
+            let &[board_id, row, col] = &*fields else {
+                bail!("Invalid target line: {id_line:?}");
             };
+            let board_id = usize::from_str_radix(board_id, 16)?;
+            let pos = Vec2(row.parse()?, col.parse()?);
+            covered_targets.push((kind, board_id, pos));
+            continue;
+        }
 
-            parse_line(0, line)?;
-            let mut height = 1;
-            while let Some(line) = lines.next().filter(|line| !line.is_empty()) {
-                ensure!(
-                    line.chars().count() == width,
-                    "Width mismatch of board {}, line {height}, expecting width {width}",
+        // Board ids are hexadecimal digits.
+        let board_id = usize::from_str_radix(&id_line, 16)?
+            .try_into()
+            .map_err(|()| anyhow!("Too many boards"))?;
+        ensure!(
+            board_id as usize == boards.len(),
+            "Invalid board id: {board_id}"
+        );
+
+        let line = lines.next().context("Missing board content")?;
+        let width = line.chars().count();
+
+        let mut grid = Vec::new();
+        let mut board_walls = Vec::new();
+        let mut parse_line = |i: usize, line: &str| -> Result<_> {
+            for (j, ch) in line.chars().enumerate() {
+                let gpos = GlobalPos {
                     board_id,
-                );
-                parse_line(height, line)?;
-                height += 1;
+                    pos: Vec2(i as _, j as _),
+                };
+                let glyph = glyphs
+                    .lookup(ch)
+                    .with_context(|| format!("Invalid cell: {ch:?}"))?;
+                let is_player = matches!(glyph, Glyph::Player | Glyph::PlayerOnBoxTarget);
+                if is_player {
+                    ensure!(player.is_none(), "Multiple players");
+                    player = Some(gpos);
+                }
+                if matches!(
+                    glyph,
+                    Glyph::BoxTarget | Glyph::BoxOnTarget | Glyph::PlayerOnBoxTarget
+                ) {
+                    box_targets.push(gpos);
+                }
+                let cell = match glyph {
+                    // Walls are stored separately.
+                    Glyph::Cell(Cell::Wall) => Cell::Empty,
+                    Glyph::Cell(Cell::Board(board_id)) => {
+                        max_board_id = max_board_id.max(board_id);
+                        Cell::Board(board_id)
+                    }
+                    Glyph::Cell(cell) => cell,
+                    Glyph::PlayerTarget => {
+                        ensure!(player_target.is_none(), "Multiple player targets");
+                        player_target = Some(gpos);
+                        Cell::Empty
+                    }
+                    Glyph::BoxTarget => Cell::Empty,
+                    Glyph::Player | Glyph::BoxOnTarget | Glyph::PlayerOnBoxTarget => Cell::Box,
+                };
+                grid.push(cell);
+                board_walls.push(glyph == Glyph::Cell(Cell::Wall));
             }
+            Ok(())
+        };
 
+        parse_line(0, &line)?;
+        let mut height = 1;
+        while let Some(line) = lines.next().filter(|line| !line.is_empty()) {
             ensure!(
-                width < MAX_BOARD_WIDTH && height < MAX_BOARD_WIDTH,
-                "Board too big",
+                line.chars().count() == width,
+                "Width mismatch of board {}, line {height}, expecting width {width}",
+                board_id,
             );
+            parse_line(height, &line)?;
+            height += 1;
+        }
+
+        ensure!(
+            width < MAX_BOARD_WIDTH && height < MAX_BOARD_WIDTH,
+            "Board too big",
+        );
+
+        boards.push(Board {
+            height: height as _,
+            width: width as _,
+            grid: grid.into(),
+        });
+        walls.push(board_walls.into());
+    }
 
-            boards.push(Board {
-                height: height as _,
-                width: width as _,
-                grid: grid.into(),
-            });
+    ensure!(
+        (max_board_id as usize) < boards.len(),
+        "Board id {} out of bound {}",
+        max_board_id,
+        boards.len(),
+    );
+
+    for (kind, board_id, pos) in covered_targets {
+        let board = boards
+            .get(board_id)
+            .with_context(|| format!("Target out of bound: {board_id:X} {pos:?}"))?;
+        ensure!(
+            pos.0 < board.height && pos.1 < board.width,
+            "Target out of bound: {board_id:X} {pos:?}",
+        );
+        let gpos = GlobalPos {
+            board_id: board_id.try_into().unwrap(),
+            pos,
+        };
+        if kind == '=' {
+            ensure!(player_target.is_none(), "Multiple player targets");
+            player_target = Some(gpos);
+        } else {
+            box_targets.push(gpos);
         }
+    }
 
+    let player_target = if has_player_target {
+        Some(player_target.context("Missing player target")?)
+    } else {
         ensure!(
-            (max_board_id as usize) < boards.len(),
-            "Board id {} out of bound {}",
-            max_board_id,
-            boards.len(),
+            player_target.is_none(),
+            "Player target in a level with `player-target: none`",
         );
+        None
+    };
+    let mut game = build_game(
+        boards,
+        walls,
+        player.context("Missing player")?,
+        player_target,
+        box_targets,
+    )?;
+    drop(lines);
+    metadata.comments = comments;
+    game.metadata = metadata;
+    Ok(game)
+}
+
+/// Options of `Game::parse_with_options`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Tolerate sloppy formatting of files copied from chats and wikis, like CRLF line endings,
+    /// indentation, trailing whitespaces, extra blank lines and ragged lines of boards, reporting
+    /// them as warnings instead of failing.
+    pub lenient: bool,
+    /// The cell to pad short lines of boards with, in lenient mode.
+    pub padding: Padding,
+    pub glyphs: GlyphMap,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Padding {
+    #[default]
+    Wall,
+    Empty,
+}
+
+/// A tolerated formatting issue in lenient mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseWarning {
+    /// The 1-based line number.
+    pub line: usize,
+    pub message: String,
+}
 
-        ensure!(boards.len() < MAX_BOARD_CNT, "Too many boards");
+impl std::fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
 
-        let config = Config {
-            player_target: player_target.context("Missing player target")?,
-            box_targets: box_targets.into(),
+impl Game {
+    /// Parse a map like `Game::parse_with_glyphs`, and in lenient mode, also return warnings of
+    /// tolerated formatting issues.
+    pub fn parse_with_options(
+        s: &str,
+        options: &ParseOptions,
+    ) -> Result<(Self, Vec<ParseWarning>)> {
+        let glyphs = &options.glyphs;
+        if !options.lenient {
+            return Ok((Self::parse_with_glyphs(s, glyphs)?, Vec::new()));
+        }
+        let mut warnings = Vec::new();
+        let pad = match options.padding {
+            Padding::Wall => glyphs.wall,
+            Padding::Empty => glyphs.empty,
         };
-        let state = State {
-            player: player.context("Missing player")?,
-            boards: boards.into(),
+        let normalized = normalize(s, pad, &mut warnings);
+        // Ragged lines are only found at the end of boards.
+        warnings.sort_by_key(|warning| warning.line);
+        Ok((Self::parse_with_glyphs(&normalized, glyphs)?, warnings))
+    }
+}
+
+/// Rewrite a sloppy map into the strict format, recording fixes into `warnings`.
+fn normalize(s: &str, pad: char, warnings: &mut Vec<ParseWarning>) -> String {
+    let mut warn = |line: usize, message: &str| {
+        warnings.push(ParseWarning {
+            line,
+            message: message.to_owned(),
+        })
+    };
+    let mut out = String::new();
+    let mut block = Vec::new();
+    let mut blank_cnt = 0;
+    let mut has_crlf = false;
+    // Append an empty line as the end of the last block.
+    for (i, raw) in s.split('\n').chain([""]).enumerate() {
+        let line = i + 1;
+        let raw = match raw.strip_suffix('\r') {
+            Some(raw) => {
+                if !std::mem::replace(&mut has_crlf, true) {
+                    warn(line, "CRLF line ending");
+                }
+                raw
+            }
+            None => raw,
         };
-        Ok(Game { config, state })
+        let trimmed = raw.trim();
+        if trimmed.is_empty() {
+            if !block.is_empty() {
+                flush_block(&mut out, &mut block, pad, &mut warn);
+            }
+            blank_cnt += 1;
+            continue;
+        }
+        if blank_cnt > 1 && !out.is_empty() {
+            warn(line - 1, "Extra blank lines");
+        }
+        blank_cnt = 0;
+        if raw.trim_end().len() != raw.len() {
+            warn(line, "Trailing whitespaces");
+        }
+        if raw.trim_start().len() != raw.len() {
+            warn(line, "Indentation");
+        }
+        block.push((line, trimmed));
+    }
+    out
+}
+
+/// Write a block of consecutive non-blank lines, which are metadata lines followed by a board
+/// or covered targets.
+fn flush_block(
+    out: &mut String,
+    block: &mut Vec<(usize, &str)>,
+    pad: char,
+    warn: &mut impl FnMut(usize, &str),
+) {
+    let is_comment = |line: &str| line.starts_with(';') || line.starts_with("#!");
+    let mut lines = block.drain(..).peekable();
+    while let Some((_, header)) = lines.next_if(|(_, line)| is_comment(line)) {
+        *out += header;
+        out.push('\n');
     }
+    let Some(&(_, first)) = lines.peek() else {
+        out.push('\n');
+        return;
+    };
+    if first.starts_with(['_', '=']) {
+        for (_, line) in lines {
+            *out += line;
+            out.push('\n');
+        }
+        return;
+    }
+
+    let (_, id_line) = lines.next().unwrap();
+    *out += id_line;
+    out.push('\n');
+    // Split inline comments off rows, to be kept after padding.
+    let rows = lines
+        .map(|(line, row)| {
+            let (cells, comment) = match row.find(';') {
+                _ if is_comment(row) => ("", row),
+                Some(i) => (row[..i].trim_end(), &row[i..]),
+                None => (row, ""),
+            };
+            (line, cells, comment)
+        })
+        .collect::<Vec<_>>();
+    let width = rows
+        .iter()
+        .map(|(_, cells, _)| cells.chars().count())
+        .max()
+        .unwrap_or(0);
+    for (line, cells, comment) in rows {
+        *out += cells;
+        let len = cells.chars().count();
+        if len < width && !cells.is_empty() {
+            warn(line, &format!("Ragged line padded with {pad:?}"));
+            out.extend(std::iter::repeat_n(pad, width - len));
+        }
+        if !comment.is_empty() {
+            if !cells.is_empty() {
+                out.push(' ');
+            }
+            *out += comment;
+        }
+        out.push('\n');
+    }
+    out.push('\n');
+}
+
+const METADATA_KEYS: [&str; 4] = ["title", "author", "rules", "player-target"];
+
+/// Trim a line and remove its comment into `comments`, returning the remaining content, or
+/// `None` if the whole line is a comment. Comments are lines starting with `#!`, lines starting
+/// with `;` other than metadata entries, and trailing parts after `;` of other lines.
+fn strip_comment<'a>(line: &'a str, comments: &mut Vec<String>) -> Option<&'a str> {
+    let line = line.trim();
+    let (content, comment) = if let Some(comment) = line.strip_prefix("#!") {
+        (None, comment)
+    } else if let Some(entry) = line.strip_prefix(';') {
+        let is_metadata = entry
+            .split_once(':')
+            .is_some_and(|(key, _)| METADATA_KEYS.contains(&key.trim()));
+        if is_metadata {
+            return Some(line);
+        }
+        (None, entry)
+    } else if let Some((content, comment)) = line.split_once(';') {
+        (Some(content.trim_end()), comment)
+    } else {
+        return Some(line);
+    };
+    comments.push(comment.trim().to_owned());
+    content
+}
+
+/// Parse the optional header of `; key: value` lines, where blank lines are ignored.
+///
+/// Also returns whether the level has a player target, which is required unless the header has
+/// `; player-target: none`, as written for Sokoban levels imported from XSB.
+fn parse_metadata(lines: &mut Peekable<impl Iterator<Item = String>>) -> Result<(Metadata, bool)> {
+    let mut metadata = Metadata::default();
+    let mut has_player_target = true;
+    while let Some(line) = lines.next_if(|line| line.is_empty() || line.starts_with(';')) {
+        let Some(entry) = line.strip_prefix(';') else { continue };
+        let (key, value) = entry
+            .split_once(':')
+            .with_context(|| format!("Invalid metadata line: {line:?}"))?;
+        let value = value.trim();
+        match key.trim() {
+            "title" => metadata.title = Some(value.to_owned()),
+            "author" => metadata.author = Some(value.to_owned()),
+            "rules" => {
+                for rule in value.split([',', ' ']).filter(|rule| !rule.is_empty()) {
+                    let (name, toggle) = rule
+                        .split_once('=')
+                        .with_context(|| format!("Invalid rule: {rule:?}"))?;
+                    ensure!(
+                        metadata
+                            .rules
+                            .insert(name.to_owned(), toggle.to_owned())
+                            .is_none(),
+                        "Duplicated rule: {name:?}",
+                    );
+                }
+            }
+            "player-target" => {
+                ensure!(value == "none", "Invalid player target: {value:?}");
+                has_player_target = false;
+            }
+            key => bail!("Unknown metadata key: {key:?}"),
+        }
+    }
+    Ok((metadata, has_player_target))
+}
+
+/// Assemble a game from parsed boards, where the player is stored as a `Cell::Box`.
+pub(crate) fn build_game(
+    boards: Vec<Board>,
+    walls: Vec<Box<[bool]>>,
+    player: GlobalPos,
+    player_target: Option<GlobalPos>,
+    box_targets: Vec<GlobalPos>,
+) -> Result<Game> {
+    ensure!(boards.len() < MAX_BOARD_CNT, "Too many boards");
+    let mut box_targets = box_targets;
+    box_targets.sort();
+    ensure!(
+        box_targets.windows(2).all(|w| w[0] != w[1]),
+        "Duplicated box targets",
+    );
+
+    let mut config = Config {
+        player_target,
+        box_targets: box_targets.into(),
+        symmetries: Arc::new([]),
+    };
+    let mut state = State {
+        player,
+        walls: walls.into(),
+        boards: boards.into_iter().map(Arc::new).collect(),
+        zobrist: 0,
+    };
+    state.rehash();
+    config.symmetries = find_symmetries(&state, &config).into();
+    Ok(Game {
+        config,
+        state,
+        metadata: Metadata::default(),
+    })
 }
@@ -0,0 +1,39 @@
+//! Raster rendering of states by `NestedRenderer`, for thumbnails of levels.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use image::{Rgb, RgbImage};
+
+use crate::nested::Sampled;
+use crate::{BoardId, Cell, Config, NestedRenderer, State};
+
+impl NestedRenderer {
+    /// Render the root board of `state` as an image with colors of the theme, where `cell_size`
+    /// is in pixels.
+    pub fn render_image(&self, state: &State, config: &Config) -> RgbImage {
+        let size = self.size(state);
+        let (height, width) = size;
+        let colors = &self.theme.colors;
+        let board_color = |id: BoardId| colors.boards[id as usize % colors.boards.len()];
+        RgbImage::from_fn(width as u32, height as u32, |x, y| {
+            let sampled = self.sample_at(state, config, size, y as usize, x as usize);
+            Rgb(match sampled {
+                Sampled::Cell(Cell::Empty) => colors.empty,
+                Sampled::Cell(Cell::Wall) => colors.wall,
+                Sampled::Cell(Cell::Box) => colors.box_cell,
+                Sampled::Cell(Cell::Board(id)) => board_color(id),
+                Sampled::Player => colors.player,
+                Sampled::BoxTarget => colors.box_target,
+                Sampled::PlayerTarget => colors.player_target,
+            })
+        })
+    }
+
+    /// Render the root board of `state` into a PNG file.
+    pub fn save_png(&self, state: &State, config: &Config, path: &Path) -> Result<()> {
+        self.render_image(state, config)
+            .save_with_format(path, image::ImageFormat::Png)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
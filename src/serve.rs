@@ -0,0 +1,315 @@
+//! `serve --port <port>`: a minimal HTTP server exposing the engine to web and editor frontends.
+//!
+//! Every endpoint takes a `POST` of a JSON object, with the level in any format of
+//! `format_level` as `level`, and optional moves in LURD as `moves`, which are replayed before
+//! handling the request. The server is stateless, thus clients keep the moves themselves.
+//!
+//! - `/parse` returns the detected `format`, lint `diagnostics` and the level in `json`.
+//! - `/move` makes the move `move`, returning the new `moves`, whether it `pushed` anything,
+//!   and whether the level is `solved`.
+//! - `/legal` returns the `legal` moves from the current state.
+//! - `/render` returns the current state as the `text` of the map format, and `solved`.
+//! - `/solve` solves from the current state with `solver`, `bfs` by default, limited by
+//!   `max_nodes` and `timeout_ms`, which default to and are capped by server-side limits
+//!   (see `MAX_NODES` and `MAX_TIMEOUT`). The response is newline-delimited JSON of `progress`
+//!   objects, ended by an object of the outcome, like `--solve --json`.
+//!
+//! Errors are responded with status 400 and an object of `error`.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{ensure, Context, Result};
+use parabox_solver::solve::{solver_by_name, SolveOutcome, SolverOptions, SOLVER_NAMES};
+use parabox_solver::{Direction, Game};
+use serde_json::{json, Value};
+
+use crate::{parse_level, parse_moves, write_solution};
+
+/// The maximum size of request heads, that is the request line and headers.
+const MAX_HEAD_LEN: usize = 16 << 10;
+
+/// The maximum size of request bodies.
+const MAX_BODY_LEN: usize = 1 << 20;
+
+/// The default and maximum count of nodes of `/solve`.
+const DEFAULT_MAX_NODES: usize = 1 << 20;
+const MAX_NODES: usize = 1 << 24;
+
+/// The default and maximum duration of `/solve`.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The minimal interval between progress lines of `/solve`.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+pub fn serve(mut args: impl Iterator<Item = String>) -> Result<()> {
+    let mut port = 8080u16;
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--port" => {
+                port = args
+                    .next()
+                    .context("Missing argument of --port")?
+                    .parse()
+                    .context("Invalid port")?;
+            }
+            _ => anyhow::bail!("Unknown option {flag:?}"),
+        }
+    }
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .with_context(|| format!("Failed to listen on port {port}"))?;
+    eprintln!("Listening on http://{}", listener.local_addr()?);
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("Failed to accept: {err}");
+                continue;
+            }
+        };
+        std::thread::spawn(move || {
+            if let Err(err) = handle_connection(stream) {
+                eprintln!("Connection error: {err:#}");
+            }
+        });
+    }
+    Ok(())
+}
+
+fn handle_connection(stream: TcpStream) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let (request_line, content_len) = match read_head(&mut reader) {
+        Ok(head) => head,
+        Err(err) => {
+            return respond(
+                stream,
+                "400 Bad Request",
+                &json!({ "error": format!("{err:#}") }),
+            )
+        }
+    };
+
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    if method != "POST" {
+        return respond(
+            stream,
+            "405 Method Not Allowed",
+            &json!({ "error": "Expecting POST" }),
+        );
+    }
+    if content_len > MAX_BODY_LEN {
+        return respond(
+            stream,
+            "413 Payload Too Large",
+            &json!({ "error": "Body too large" }),
+        );
+    }
+    let mut body = vec![0u8; content_len];
+    reader.read_exact(&mut body)?;
+
+    let ret = serde_json::from_slice::<Value>(&body)
+        .context("Invalid JSON body")
+        .and_then(|req| match path {
+            "/solve" => solve(&stream, &req).map(|()| None),
+            "/parse" => parse(&req).map(Some),
+            "/move" => make_move(&req).map(Some),
+            "/legal" => legal(&req).map(Some),
+            "/render" => render(&req).map(Some),
+            _ => anyhow::bail!("Unknown endpoint {path:?}"),
+        });
+    match ret {
+        Ok(Some(resp)) => respond(stream, "200 OK", &resp),
+        Ok(None) => Ok(()),
+        Err(err) => respond(
+            stream,
+            "400 Bad Request",
+            &json!({ "error": format!("{err:#}") }),
+        ),
+    }
+}
+
+/// Read the request line and the Content-Length of a request head, which is limited by
+/// `MAX_HEAD_LEN` since lines are read until their ends.
+fn read_head(reader: &mut impl BufRead) -> Result<(String, usize)> {
+    let mut head = reader.take(MAX_HEAD_LEN as u64);
+    let mut read_line = || {
+        let mut line = String::new();
+        head.read_line(&mut line)?;
+        ensure!(
+            line.ends_with('\n') || head.limit() > 0,
+            "Request head too large",
+        );
+        anyhow::Ok(line)
+    };
+    let request_line = read_line()?;
+    let mut content_len = 0;
+    loop {
+        let line = read_line()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_len = value.trim().parse().context("Invalid Content-Length")?;
+            }
+        }
+    }
+    Ok((request_line, content_len))
+}
+
+fn respond(mut stream: TcpStream, status: &str, body: &Value) -> Result<()> {
+    let body = body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len(),
+    )?;
+    Ok(())
+}
+
+fn str_field<'a>(req: &'a Value, name: &str) -> Result<Option<&'a str>> {
+    match req.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s)),
+        Some(_) => anyhow::bail!("Field {name:?} should be a string"),
+    }
+}
+
+fn u64_field(req: &Value, name: &str) -> Result<Option<u64>> {
+    match req.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => value
+            .as_u64()
+            .map(Some)
+            .with_context(|| format!("Field {name:?} should be a non-negative integer")),
+    }
+}
+
+/// Parse the level of the request, with the state after replaying its moves.
+fn load(req: &Value) -> Result<(Game, Vec<Direction>)> {
+    let level = str_field(req, "level")?.context("Missing field \"level\"")?;
+    let (mut game, _) = parse_level(level.as_bytes()).context("Failed to parse the level")?;
+    let moves = parse_moves(str_field(req, "moves")?.unwrap_or(""))?;
+    for (i, &dir) in moves.iter().enumerate() {
+        game.state
+            .go(dir)
+            .with_context(|| format!("Invalid move {} of {dir:?}", i + 1))?;
+    }
+    Ok((game, moves))
+}
+
+fn to_lurd(moves: &[Direction]) -> String {
+    moves
+        .iter()
+        .map(|dir| match dir {
+            Direction::Left => 'L',
+            Direction::Up => 'U',
+            Direction::Right => 'R',
+            Direction::Down => 'D',
+        })
+        .collect()
+}
+
+fn parse(req: &Value) -> Result<Value> {
+    let level = str_field(req, "level")?.context("Missing field \"level\"")?;
+    let (game, format) = parse_level(level.as_bytes()).context("Failed to parse the level")?;
+    let diagnostics = game
+        .validate()
+        .iter()
+        .map(|diag| {
+            let level = if diag.is_error() { "error" } else { "warning" };
+            json!({ "level": level, "message": diag.to_string() })
+        })
+        .collect::<Vec<_>>();
+    let level = serde_json::from_str::<Value>(&game.to_json())?;
+    Ok(json!({ "format": format, "diagnostics": diagnostics, "json": level }))
+}
+
+fn make_move(req: &Value) -> Result<Value> {
+    let (mut game, mut moves) = load(req)?;
+    let dir = match parse_moves(str_field(req, "move")?.context("Missing field \"move\"")?)?[..] {
+        [dir] => dir,
+        _ => anyhow::bail!("Field \"move\" should be exactly one move"),
+    };
+    let pushed = game.state.go(dir).context("Illegal move")?;
+    moves.push(dir);
+    Ok(json!({
+        "moves": to_lurd(&moves),
+        "pushed": pushed,
+        "solved": game.state.is_success_on(&game.config),
+    }))
+}
+
+fn legal(req: &Value) -> Result<Value> {
+    let (game, _) = load(req)?;
+    let legal = Direction::ALL
+        .into_iter()
+        .filter(|&dir| game.state.clone().go(dir).is_ok())
+        .collect::<Vec<_>>();
+    Ok(json!({ "legal": to_lurd(&legal) }))
+}
+
+fn render(req: &Value) -> Result<Value> {
+    let (game, _) = load(req)?;
+    Ok(json!({
+        "text": game.to_string(),
+        "solved": game.state.is_success_on(&game.config),
+    }))
+}
+
+fn solve(stream: &TcpStream, req: &Value) -> Result<()> {
+    let (game, _) = load(req)?;
+    let name = str_field(req, "solver")?.unwrap_or("bfs");
+    let solver = solver_by_name(name)
+        .with_context(|| format!("Unknown solver {name:?}, expecting one of {SOLVER_NAMES:?}"))?;
+    let cancel = Arc::new(AtomicBool::new(false));
+    let options = SolverOptions {
+        max_nodes: Some(
+            u64_field(req, "max_nodes")?
+                .map_or(DEFAULT_MAX_NODES, |n| n.min(MAX_NODES as u64) as usize),
+        ),
+        max_duration: Some(
+            u64_field(req, "timeout_ms")?
+                .map_or(DEFAULT_TIMEOUT, Duration::from_millis)
+                .min(MAX_TIMEOUT),
+        ),
+        cancel: Some(cancel.clone()),
+        ..SolverOptions::default()
+    };
+
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nConnection: close\r\n\r\n",
+    )?;
+    // Stop solving once the client disconnects.
+    let mut last_report = Instant::now();
+    let outcome = solver.solve(&game, &options, &mut |progress| {
+        if last_report.elapsed() < PROGRESS_INTERVAL {
+            return;
+        }
+        last_report = Instant::now();
+        let line = json!({
+            "progress": {
+                "steps": progress.steps,
+                "expanded": progress.expanded,
+                "frontier": progress.frontier,
+                "depth": progress.depth,
+            },
+        });
+        if writeln!(stream, "{line}").is_err() {
+            cancel.store(true, Ordering::Relaxed);
+        }
+    });
+    let solution = match &outcome {
+        SolveOutcome::Solved(solution) => Some(solution),
+        _ => None,
+    };
+    write_solution(stream, &game, solution, true)
+}
@@ -0,0 +1,244 @@
+//! Saved sessions of interactive plays, holding the level, the move history and bookmarks.
+//!
+//! A session file starts with header lines, followed by a blank line and the level in the
+//! text format parsed by `Game::from_str`:
+//!
+//! ```text
+//! session 1
+//! steps RRD!LU
+//! redo DL
+//! bookmark 3 before the push
+//!
+//! 0
+//! p.b_=
+//! ```
+//!
+//! - `session 1` is the version, and must be the first line.
+//! - `steps` lists steps from the initial state, where `R`, `D`, `L` and `U` are moves and `!`
+//!   is a reset to the initial state. It may be omitted if there are no steps.
+//! - `redo` lists undone steps in the order of redoing them, in the same notation as `steps`.
+//!   It may be omitted if there are no undone steps.
+//! - Each `bookmark` line names the state after the given count of steps.
+//!
+//! Sessions are saved canonically, with bookmarks ordered by name.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{bail, ensure, Context, Result};
+
+use crate::{Direction, Game, State};
+
+const VERSION: &str = "session 1";
+
+/// A step of the move history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Step {
+    Go(Direction),
+    /// Restart from the initial state, which can be undone like moves.
+    Reset,
+}
+
+impl Step {
+    fn to_char(self) -> char {
+        match self {
+            Step::Go(Direction::Right) => 'R',
+            Step::Go(Direction::Down) => 'D',
+            Step::Go(Direction::Left) => 'L',
+            Step::Go(Direction::Up) => 'U',
+            Step::Reset => '!',
+        }
+    }
+
+    fn from_char(ch: char) -> Result<Self> {
+        Ok(match ch {
+            'R' => Step::Go(Direction::Right),
+            'D' => Step::Go(Direction::Down),
+            'L' => Step::Go(Direction::Left),
+            'U' => Step::Go(Direction::Up),
+            '!' => Step::Reset,
+            _ => bail!("Invalid step: {ch:?}"),
+        })
+    }
+}
+
+/// A play in progress of a level.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    pub game: Game,
+    /// Successful steps from the initial state.
+    pub steps: Vec<Step>,
+    /// Undone steps as a stack, where the last one is redone first. See `Session::undo`.
+    pub redo: Vec<Step>,
+    /// Named states, as counts of steps from the initial state.
+    pub bookmarks: BTreeMap<String, usize>,
+}
+
+impl Session {
+    pub fn new(game: Game) -> Self {
+        Self {
+            game,
+            steps: Vec::new(),
+            redo: Vec::new(),
+            bookmarks: BTreeMap::new(),
+        }
+    }
+
+    /// Append a new step, which discards undone steps.
+    pub fn push(&mut self, step: Step) {
+        self.steps.push(step);
+        self.redo.clear();
+    }
+
+    /// Undo the last step, keeping it for `Session::redo`. Returns the undone step, or `None`
+    /// if there is no step.
+    pub fn undo(&mut self) -> Option<Step> {
+        let step = self.steps.pop()?;
+        self.redo.push(step);
+        Some(step)
+    }
+
+    /// Redo the last undone step. Returns the redone step, or `None` if there is no undone
+    /// step, or any new step is pushed after the undo.
+    pub fn redo(&mut self) -> Option<Step> {
+        let step = self.redo.pop()?;
+        self.steps.push(step);
+        Some(step)
+    }
+
+    /// Replay all steps, returning the initial state followed by the state after each step.
+    ///
+    /// Fails if any move is invalid.
+    pub fn states(&self) -> Result<Vec<State>> {
+        let initial = &self.game.state;
+        let mut states = Vec::with_capacity(self.steps.len() + 1);
+        states.push(initial.clone());
+        for (i, &step) in self.steps.iter().enumerate() {
+            let mut state = states.last().unwrap().clone();
+            match step {
+                Step::Go(dir) => {
+                    state
+                        .go(dir)
+                        .with_context(|| format!("Step {i} ({dir:?}) failed"))?;
+                }
+                Step::Reset => state = initial.clone(),
+            }
+            states.push(state);
+        }
+        Ok(states)
+    }
+
+    /// Format the session in the session file format, which can be parsed back by
+    /// `Session::from_str`.
+    ///
+    /// Fails if the level cannot be expressed in the text format, or any bookmark name is
+    /// invalid. See `Game::to_text`.
+    pub fn to_text(&self) -> Result<String> {
+        let mut out = format!("{VERSION}\n");
+        if !self.steps.is_empty() {
+            let steps = self
+                .steps
+                .iter()
+                .map(|step| step.to_char())
+                .collect::<String>();
+            writeln!(out, "steps {steps}").unwrap();
+        }
+        if !self.redo.is_empty() {
+            let redo = self
+                .redo
+                .iter()
+                .rev()
+                .map(|step| step.to_char())
+                .collect::<String>();
+            writeln!(out, "redo {redo}").unwrap();
+        }
+        for (name, &count) in &self.bookmarks {
+            ensure!(
+                !name.is_empty() && name.trim() == name && !name.contains(['\n', '\r']),
+                "Invalid bookmark name: {name:?}",
+            );
+            ensure!(
+                count <= self.steps.len(),
+                "Bookmark {name:?} out of bound: {count}",
+            );
+            writeln!(out, "bookmark {count} {name}").unwrap();
+        }
+        out.push('\n');
+        out += &self.game.to_text()?;
+        Ok(out)
+    }
+
+    /// Save the session into a file. See `Session::to_text`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let text = self.to_text()?;
+        std::fs::write(path, text)
+            .with_context(|| format!("Failed to write the session to {}", path.display()))
+    }
+
+    /// Load a session saved by `Session::save`, checking that all steps, including undone
+    /// ones, are valid.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read the session from {}", path.display()))?;
+        let session = text.parse::<Self>()?;
+        let mut redone = session.clone();
+        while redone.redo().is_some() {}
+        redone.states().context("Invalid undone steps")?;
+        Ok(session)
+    }
+}
+
+impl FromStr for Session {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (header, level) = s.split_once("\n\n").context("Missing level")?;
+        let mut lines = header.lines().map(str::trim);
+        ensure!(lines.next() == Some(VERSION), "Unsupported session version");
+        let game = level.parse::<Game>().context("Invalid level")?;
+        let mut session = Self::new(game);
+        for line in lines {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "steps" => {
+                    ensure!(session.steps.is_empty(), "Duplicated steps");
+                    session.steps = value.chars().map(Step::from_char).collect::<Result<_>>()?;
+                }
+                "redo" => {
+                    ensure!(session.redo.is_empty(), "Duplicated redo");
+                    session.redo = value
+                        .chars()
+                        .rev()
+                        .map(Step::from_char)
+                        .collect::<Result<_>>()?;
+                }
+                "bookmark" => {
+                    let (count, name) = value
+                        .split_once(' ')
+                        .with_context(|| format!("Invalid bookmark: {line:?}"))?;
+                    let count = count
+                        .parse::<usize>()
+                        .with_context(|| format!("Invalid bookmark: {line:?}"))?;
+                    let name = name.trim();
+                    ensure!(!name.is_empty(), "Invalid bookmark: {line:?}");
+                    ensure!(
+                        session.bookmarks.insert(name.to_owned(), count).is_none(),
+                        "Duplicated bookmark: {name:?}",
+                    );
+                }
+                _ => bail!("Unknown session line: {line:?}"),
+            }
+        }
+        for (name, &count) in &session.bookmarks {
+            ensure!(
+                count <= session.steps.len(),
+                "Bookmark {name:?} out of bound: {count}",
+            );
+        }
+        Ok(session)
+    }
+}
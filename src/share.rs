@@ -0,0 +1,155 @@
+//! Compact URL-safe encoding of levels, for pasting into chats or URLs.
+//!
+//! A share string is the unpadded URL-safe base64 of the raw deflate of the following bytes:
+//!
+//! - The version, currently 1.
+//! - The count of boards.
+//! - For each board, its height and width, then a byte per cell in row-major order: 0 for
+//!   empty, 1 for walls, 2 for boxes and the player, and `3 + id` for the board `id`.
+//! - The location of the player, then the player target, as three bytes of board id, row
+//!   and column. A missing player target is three `0xFF` bytes.
+//! - The count of box targets as a little-endian `u16`, then their locations.
+
+use anyhow::{anyhow, ensure, Context, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+
+use crate::parse::build_game;
+use crate::{Board, BoardId, Cell, Game, GlobalPos, Vec2, MAX_BOARD_CNT, MAX_BOARD_WIDTH};
+
+const VERSION: u8 = 1;
+/// The bytes of the player target of levels without it.
+const NO_PLAYER_TARGET: u8 = 0xFF;
+/// The maximal length of decompressed bytes, to reject deflate bombs.
+const MAX_DECODED_LEN: usize = 1 << 16;
+
+impl Game {
+    /// Encode the level into a short URL-safe string, which can be decoded by
+    /// `Game::decode_share_string`.
+    pub fn encode_share_string(&self) -> String {
+        let state = &self.state;
+        let mut buf = vec![VERSION, state.boards.len() as u8];
+        for id in 0..state.boards.len() {
+            let board_id = BoardId::try_from(id).unwrap();
+            let board = &state[board_id];
+            buf.extend([board.height, board.width]);
+            buf.extend(state.board_cells(board_id).map(|(_, cell)| match cell {
+                Cell::Empty => 0,
+                Cell::Wall => 1,
+                Cell::Box => 2,
+                Cell::Board(id) => 3 + id as u8,
+            }));
+        }
+        let config = &self.config;
+        let push_gpos = |buf: &mut Vec<u8>, gpos: GlobalPos| {
+            buf.extend([gpos.board_id as u8, gpos.pos.0, gpos.pos.1]);
+        };
+        push_gpos(&mut buf, state.player);
+        match config.player_target {
+            Some(gpos) => push_gpos(&mut buf, gpos),
+            None => buf.extend([NO_PLAYER_TARGET; 3]),
+        }
+        buf.extend((config.box_targets.len() as u16).to_le_bytes());
+        for &gpos in config.box_targets.iter() {
+            push_gpos(&mut buf, gpos);
+        }
+
+        let compressed = miniz_oxide::deflate::compress_to_vec(&buf, 10);
+        URL_SAFE_NO_PAD.encode(compressed)
+    }
+
+    /// Decode a level from a string returned by `Game::encode_share_string`.
+    ///
+    /// Surrounding whitespaces are ignored.
+    pub fn decode_share_string(s: &str) -> Result<Self> {
+        let compressed = URL_SAFE_NO_PAD.decode(s.trim()).context("Invalid base64")?;
+        let buf = miniz_oxide::inflate::decompress_to_vec_with_limit(&compressed, MAX_DECODED_LEN)
+            .map_err(|err| anyhow!("Invalid deflate stream: {err}"))?;
+        let mut reader = Reader(buf.iter());
+
+        let version = reader.byte()?;
+        ensure!(version == VERSION, "Unsupported version: {version}");
+        let board_cnt = reader.byte()? as usize;
+        ensure!(
+            0 < board_cnt && board_cnt < MAX_BOARD_CNT,
+            "Invalid count of boards"
+        );
+        let mut boards = Vec::with_capacity(board_cnt);
+        let mut walls = Vec::with_capacity(board_cnt);
+        for board_id in 0..board_cnt {
+            let (height, width) = (reader.byte()?, reader.byte()?);
+            ensure!(
+                0 < height.min(width) && (height.max(width) as usize) < MAX_BOARD_WIDTH,
+                "Invalid size of board {board_id}",
+            );
+            let size = height as usize * width as usize;
+            let mut grid = Vec::with_capacity(size);
+            let mut board_walls = Vec::with_capacity(size);
+            for _ in 0..size {
+                let code = reader.byte()?;
+                let cell = match code {
+                    // Walls are stored separately.
+                    0 | 1 => Cell::Empty,
+                    2 => Cell::Box,
+                    _ => {
+                        let id = (code - 3) as usize;
+                        ensure!(id < board_cnt, "Board id {id} out of bound {board_cnt}");
+                        Cell::Board(id.try_into().unwrap())
+                    }
+                };
+                grid.push(cell);
+                board_walls.push(code == 1);
+            }
+            boards.push(Board {
+                height,
+                width,
+                grid: grid.into(),
+            });
+            walls.push(board_walls.into_boxed_slice());
+        }
+
+        let player = reader.gpos(&boards)?;
+        let player_target = if reader.0.as_slice().starts_with(&[NO_PLAYER_TARGET]) {
+            ensure!(
+                (0..3).all(|_| reader.byte().ok() == Some(NO_PLAYER_TARGET)),
+                "Invalid player target",
+            );
+            None
+        } else {
+            Some(reader.gpos(&boards)?)
+        };
+        let box_target_cnt = u16::from_le_bytes([reader.byte()?, reader.byte()?]);
+        let box_targets = (0..box_target_cnt)
+            .map(|_| reader.gpos(&boards))
+            .collect::<Result<Vec<_>>>()?;
+        ensure!(reader.0.len() == 0, "Trailing data");
+        ensure!(
+            boards[player.board_id as usize][player.pos] == Cell::Box,
+            "Player is not a box",
+        );
+        build_game(boards, walls, player, player_target, box_targets)
+    }
+}
+
+struct Reader<'a>(std::slice::Iter<'a, u8>);
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8> {
+        self.0.next().copied().context("Unexpected end of data")
+    }
+
+    fn gpos(&mut self, boards: &[Board]) -> Result<GlobalPos> {
+        let (id, row, col) = (self.byte()? as usize, self.byte()?, self.byte()?);
+        let board = boards
+            .get(id)
+            .with_context(|| format!("Board id {id} out of bound {}", boards.len()))?;
+        ensure!(
+            row < board.height && col < board.width,
+            "Position out of board {id}",
+        );
+        Ok(GlobalPos {
+            board_id: id.try_into().unwrap(),
+            pos: Vec2(row, col),
+        })
+    }
+}
@@ -1,105 +1,537 @@
-use crate::{Direction, Game, GlobalPos, State};
+use std::borrow::Borrow;
+
+// `std::time::Instant` panics on the web.
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+use self::options::LimitChecker;
+use self::table::StateTable;
+use crate::{Cell, Config, Direction, Game, GlobalPos, PackedState, State};
+
+mod all_optimal;
+mod anytime;
+mod approximate;
+mod astar;
+mod batch;
+mod beam;
+mod bidirectional;
+mod checkpoint;
+mod dataset;
+mod dead_squares;
+mod deadlock;
+mod difficulty;
+mod disk;
+mod explore;
+mod graph;
+mod heuristic;
+mod hint;
+mod incremental;
+mod options;
+mod parallel;
+mod partial;
+mod retrograde;
+mod simplify;
+mod solver;
+mod table;
+
+pub use all_optimal::all_optimal;
+pub use anytime::anytime;
+pub use approximate::bfs_approximate;
+pub use astar::astar;
+pub use batch::{solve_many, BatchProgress};
+pub use beam::{beam, BeamHeuristic};
+pub use bidirectional::bidirectional;
+pub use dataset::{dataset, Dataset, DatasetOptions};
+pub use dead_squares::{dead_squares, DeadSquares};
+pub use deadlock::DeadlockDetector;
+pub use difficulty::{difficulty, DifficultyScore};
+pub use disk::bfs_on_disk;
+pub use explore::{explore, ExplorationReport};
+pub use hint::{hint, Hint};
+pub use incremental::SolveCache;
+pub use options::{
+    Limit, Metrics, Progress, RecordStates, SearchStats, Solution, SolveOutcome, SolverOptions,
+    Unsolvability, UnsolvableReason,
+};
+pub use parallel::par_bfs;
+pub use partial::PartialSolution;
+pub use retrograde::{winning_states, WinningStates};
+pub use simplify::{simplify, Simplification};
+pub use solver::{
+    solver_by_name, Approximate, Astar, Beam, Bfs, BfsMoves, Bidirectional, ParBfs, Solver,
+    SOLVER_NAMES,
+};
 
 type IndexMap<K, V> = indexmap::IndexMap<K, V, fxhash::FxBuildHasher>;
 
-pub fn bfs(game: Game, on_step: impl FnMut()) -> Option<Vec<Direction>> {
-    let states = bfs_big_step(game, on_step)?;
+/// The metric a solution is optimal in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Optimality {
+    /// Minimal count of pushes. Moves between pushes are minimal per push, but the total count
+    /// of moves may not be.
+    #[default]
+    Pushes,
+    /// Minimal total count of moves.
+    Moves,
+}
 
-    // Resolve intermediate steps.
+/// Solve the game with breadth-first search, optimal in the given metric.
+pub fn bfs_optimal(
+    game: Game,
+    optimality: Optimality,
+    options: &SolverOptions,
+    on_progress: impl FnMut(&Progress),
+) -> SolveOutcome {
+    match optimality {
+        Optimality::Pushes => bfs_with_options(game, options, on_progress),
+        Optimality::Moves => bfs_moves(game, options, on_progress),
+    }
+}
+
+pub fn bfs(game: Game, on_progress: impl FnMut(&Progress)) -> Option<Solution> {
+    bfs_with_options(game, &SolverOptions::default(), on_progress).into_solution()
+}
+
+/// Breadth-first search like `bfs`, but stops when any limit in `options` is reached.
+pub fn bfs_with_options(
+    game: Game,
+    options: &SolverOptions,
+    on_progress: impl FnMut(&Progress),
+) -> SolveOutcome {
+    BfsSearch::new(game).run(options, on_progress)
+}
+
+/// Breadth-first search over individual moves, giving the minimal total count of moves.
+///
+/// This explores much more states than `bfs`, since states are not canonicalized by
+/// trivially reachable locations.
+pub fn bfs_moves(
+    game: Game,
+    options: &SolverOptions,
+    mut on_progress: impl FnMut(&Progress),
+) -> SolveOutcome {
+    if game.is_success() {
+        return SolveOutcome::Solved(Solution::new(
+            &game.state,
+            Vec::new(),
+            SearchStats::default(),
+            options.record_states,
+        ));
+    }
+
+    let template = game.state.clone();
+    let mut state_parent = IndexMap::<PackedState, (usize, Direction)>::default();
+    let init_packed = game.state.pack();
+    let limits = LimitChecker::new(
+        options,
+        init_packed.estimated_entry_size::<(usize, Direction)>(),
+    );
+    state_parent.insert(init_packed, (!0usize, Direction::Right)); // Sentinel.
+    let mut state = template.clone();
+    let mut progress = Progress::default();
+    let mut depth_end = 1;
+    let mut cursor = 0;
+    let final_dir = 'bfs: loop {
+        let Some((packed, _)) = state_parent.get_index(cursor) else {
+            let stats = limits.stats(cursor, state_parent.len());
+            return SolveOutcome::Unsolvable(Unsolvability::new(&game, stats));
+        };
+        let packed = packed.clone();
+        if let Err(outcome) = limits.check(cursor, state_parent.len()) {
+            return outcome;
+        }
+        if cursor >= depth_end {
+            progress.depth += 1;
+            depth_end = state_parent.len();
+        }
+        progress.expanded = cursor;
+        progress.memory_bytes = limits.memory_bytes(state_parent.len());
+
+        for dir in options.move_order(packed.stable_hash(), packed.player()) {
+            progress.steps += 1;
+            progress.frontier = state_parent.len() - cursor;
+            on_progress(&progress);
+
+            state.unpack_from(&packed);
+            if state.go(dir).is_err() {
+                continue;
+            }
+            if state.is_success_on(&game.config) {
+                break 'bfs dir;
+            }
+            progress.generated += 1;
+            let len = state_parent.len();
+            state_parent.entry(state.pack()).or_insert((cursor, dir));
+            progress.duplicates += (len == state_parent.len()) as usize;
+        }
+        cursor += 1;
+    };
+
+    let mut steps = std::iter::successors(Some((cursor, final_dir)), |&(i, _)| {
+        let (parent, dir) = state_parent[i];
+        (parent != !0usize).then_some((parent, dir))
+    })
+    .map(|(_, dir)| dir)
+    .collect::<Vec<_>>();
+    steps.reverse();
+    let stats = limits.stats(cursor + 1, state_parent.len());
+    SolveOutcome::Solved(Solution::new(
+        &template,
+        steps,
+        stats,
+        options.record_states,
+    ))
+}
+
+/// Resolve intermediate steps between each pair of adjacent big-step states.
+fn resolve_small_steps(states: &[State]) -> Vec<Direction> {
     let mut solution = Vec::new();
     let mut state_parent = IndexMap::default();
     for w in states.windows(2) {
-        let substeps = bfs_small_step(&w[0], &w[1], &mut state_parent).expect("Must be reachable");
+        state_parent.clear();
+        let substeps =
+            bfs_small_step(&w[0], &w[1], false, &mut state_parent).expect("Must be reachable");
         solution.extend(substeps);
     }
-    Some(solution)
+    solution
 }
 
-fn bfs_big_step(game: Game, mut on_step: impl FnMut()) -> Option<Vec<State>> {
-    let mut state_parent = IndexMap::default();
-    let init_loc = game.state.player;
-    state_parent.insert(game.state, (!0usize, init_loc)); // Sentinel.
+/// Reconstruct big-step states from the parent table of packed canonical states,
+/// ending with `final_state` whose parent is at index `parent`.
+///
+/// `get_node` returns the packed state, its parent index and its precanonical location by index.
+fn reconstruct_big_steps<P, N>(
+    template: &State,
+    get_node: impl Fn(usize) -> Option<(P, N)>,
+    final_state: &State,
+    parent: usize,
+) -> Vec<State>
+where
+    P: Borrow<PackedState>,
+    N: Borrow<(usize, GlobalPos)>,
+{
+    let mut states = vec![final_state.clone()];
+    let mut node = get_node(parent);
+    while let Some((packed, node_info)) = node {
+        let (parent, precanonical_loc) = *node_info.borrow();
+        let mut state = template.clone();
+        state.unpack_from(packed.borrow());
+        state.set_player(precanonical_loc);
+        states.push(state);
+        node = get_node(parent);
+    }
+    states.reverse();
+    states
+}
 
-    // Non-pushing states reachable from the current state.
-    let mut trivial_visited = BucketIndexSet::<GlobalPos, { GlobalPos::TO_USIZE_LIMIT }>::new();
+/// Reconstruct big-step states by replaying pushes from `init`, to reach states with each
+/// canonical form of `path` in order, and finally a successful state.
+///
+/// The first item of `path` is the packed `init` itself, which is not canonicalized.
+fn replay_big_steps<'a>(
+    init: &State,
+    config: &Config,
+    path: impl Iterator<Item = &'a PackedState>,
+) -> Vec<State> {
+    let mut states = replay_pushes(init, config, path);
+    let last = find_successor(states.last().unwrap(), |state, _| {
+        state.is_success_on(config)
+    });
+    states.push(last.expect("Must be reachable"));
+    states
+}
 
-    let mut big_cursor = 0;
-    let final_state = 'bfs: loop {
-        #[cfg(feature = "coz")]
-        coz::scope!("Big step");
+/// Like `replay_big_steps`, but without the final successful state.
+fn replay_pushes<'a>(
+    init: &State,
+    config: &Config,
+    mut path: impl Iterator<Item = &'a PackedState>,
+) -> Vec<State> {
+    path.next();
+    let mut states = vec![init.clone()];
+    for packed in path {
+        let next = find_successor(states.last().unwrap(), |state, do_pushed| {
+            let mut state = state.clone();
+            do_pushed && {
+                state.canonicalize(config);
+                state.pack() == *packed
+            }
+        });
+        states.push(next.expect("Must be reachable"));
+    }
+    states
+}
 
-        if big_cursor >= state_parent.len() {
-            return None;
+/// Find the first state satisfying `f` after a move from any trivially reachable location.
+///
+/// `f` is called with each moved state and whether it pushed anything.
+fn find_successor(state: &State, mut f: impl FnMut(&State, bool) -> bool) -> Option<State> {
+    let mut state = state.clone();
+    let init_state = state.clone();
+    let mut trivial_visited = vec![state.player];
+    let mut small_cursor = 0;
+    while small_cursor < trivial_visited.len() {
+        let gpos = trivial_visited[small_cursor];
+        for dir in Direction::ALL {
+            state.set_player(gpos);
+            let Ok(do_pushed) = state.go(dir) else { continue };
+            if f(&state, do_pushed) {
+                return Some(state);
+            }
+            if !do_pushed {
+                if !trivial_visited.contains(&state.player) {
+                    trivial_visited.push(state.player);
+                }
+                continue;
+            }
+            state.clone_from(&init_state);
         }
+        small_cursor += 1;
+    }
+    None
+}
 
-        let get_init_state = |state_parent: &IndexMap<State, _>| {
-            state_parent.get_index(big_cursor).unwrap().0.clone()
-        };
+/// A resumable breadth-first search on pushes, which is the core of `bfs`.
+///
+/// The search can be stopped by limits or cancellation in `SolverOptions`, saved as a
+/// checkpoint by `save`, and continued later by calling `run` again.
+pub struct BfsSearch {
+    game: Game,
+    deadlock: DeadlockDetector,
+    /// Canonical states with their parents and precanonical locations.
+    /// States are stored packed to save memory, and are unpacked into `game.state` on use.
+    state_parent: StateTable,
+    /// The index of the next state to expand. All states before it are fully expanded.
+    big_cursor: usize,
+    /// The end index of states of the current depth.
+    depth_end: usize,
+    progress: Progress,
+    metrics: Metrics,
+    /// Explored pushes as (parent index, child index, direction), if `record_graph` is set.
+    graph_edges: Vec<(usize, usize, Direction)>,
+}
 
-        let mut state = get_init_state(&state_parent);
-        trivial_visited.clear();
-        trivial_visited.try_insert(state.player);
+impl BfsSearch {
+    pub fn new(game: Game) -> Self {
+        let init_packed = game.state.pack();
+        let mut state_parent = StateTable::new(init_packed.cells_len());
+        state_parent.insert(&init_packed, !0usize, game.state.player); // Sentinel.
+        Self {
+            deadlock: DeadlockDetector::new(&game),
+            game,
+            state_parent,
+            big_cursor: 0,
+            depth_end: 1,
+            progress: Progress::default(),
+            metrics: Metrics::default(),
+            graph_edges: Vec::new(),
+        }
+    }
 
-        let mut small_cursor = 0;
-        while small_cursor < trivial_visited.len() {
-            let gpos = trivial_visited[small_cursor];
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
 
-            for dir in Direction::ALL {
-                on_step();
+    /// Continue the search until it is solved, exhausted, or stopped by `options`.
+    pub fn run(
+        &mut self,
+        options: &SolverOptions,
+        on_progress: impl FnMut(&Progress),
+    ) -> SolveOutcome {
+        let start = Instant::now();
+        let ret = self.run_big_step(options, on_progress);
+        self.metrics.big_step_time += start.elapsed();
+        match ret {
+            Ok((states, stats)) => {
+                let start = Instant::now();
+                let moves = resolve_small_steps(&states);
+                self.metrics.small_step_time += start.elapsed();
+                SolveOutcome::Solved(Solution::new(
+                    &states[0],
+                    moves,
+                    stats,
+                    options.record_states,
+                ))
+            }
+            Err(outcome) => outcome,
+        }
+    }
 
-                #[cfg(feature = "coz")]
-                coz::progress!("Step");
+    /// Returns the big-step states of the solution with statistics, or the unsolved outcome.
+    fn run_big_step(
+        &mut self,
+        options: &SolverOptions,
+        mut on_progress: impl FnMut(&Progress),
+    ) -> Result<(Vec<State>, SearchStats), SolveOutcome> {
+        let Self {
+            game,
+            deadlock,
+            state_parent,
+            big_cursor,
+            depth_end,
+            progress,
+            metrics,
+            graph_edges,
+        } = self;
+        let template = &game.state;
+        let limits = LimitChecker::new(options, state_parent.estimated_entry_size());
 
-                state.set_player(gpos);
+        // Non-pushing states reachable from the current state.
+        let mut trivial_visited = BucketIndexSet::<GlobalPos, { GlobalPos::TO_USIZE_LIMIT }>::new();
 
-                let Ok(do_pushed) = state.go(dir) else { continue };
+        let final_state = 'bfs: loop {
+            #[cfg(feature = "coz")]
+            coz::scope!("Big step");
 
-                // Success.
-                if state.is_success_on(&game.config) {
-                    break 'bfs state;
-                }
+            if *big_cursor >= state_parent.len() {
+                return Err(SolveOutcome::Unsolvable(Unsolvability::new(
+                    game,
+                    limits.stats(*big_cursor, state_parent.len()),
+                )));
+            }
+            limits.check(*big_cursor, state_parent.len())?;
+            if *big_cursor >= *depth_end {
+                progress.depth += 1;
+                *depth_end = state_parent.len();
+            }
+            progress.expanded = *big_cursor;
+            progress.memory_bytes = limits.memory_bytes(state_parent.len());
+            if metrics.expansions_per_depth.len() <= progress.depth {
+                metrics.expansions_per_depth.resize(progress.depth + 1, 0);
+            }
+            metrics.expansions_per_depth[progress.depth] += 1;
 
-                // Trivial move.
-                if !do_pushed {
-                    trivial_visited.try_insert(state.player);
-                    continue;
-                }
+            let get_init_state = |state_parent: &StateTable| {
+                let mut state = template.clone();
+                state_parent.unpack_into(*big_cursor, &mut state);
+                state
+            };
+
+            let mut state = get_init_state(state_parent);
+            let init_hash = state.zobrist_hash();
+            trivial_visited.clear();
+            trivial_visited.try_insert(state.player);
+
+            let mut small_cursor = 0;
+            while small_cursor < trivial_visited.len() {
+                let gpos = trivial_visited[small_cursor];
+
+                for dir in options.move_order(init_hash, gpos) {
+                    progress.steps += 1;
+                    progress.frontier = state_parent.len() - *big_cursor;
+                    on_progress(progress);
+
+                    #[cfg(feature = "coz")]
+                    coz::progress!("Step");
 
-                // Non-trivial push.
-                // Here we canonicalize the player location to dedup, while saving the original
-                // one for step reconstruction.
-                let precanonical_loc = state.player;
-                let canonical_loc = state.trivially_reachable_locations().min().unwrap();
-                state.set_player(canonical_loc);
-                state_parent
-                    .entry(state)
-                    .or_insert((big_cursor, precanonical_loc));
-
-                // The state now cannot be reused.
-                state = get_init_state(&state_parent);
+                    state.set_player(gpos);
+
+                    let Ok(do_pushed) = state.go(dir) else { continue };
+
+                    // Success.
+                    if state.is_success_on(&game.config) {
+                        break 'bfs state;
+                    }
+
+                    // Trivial move.
+                    if !do_pushed {
+                        trivial_visited.try_insert(state.player);
+                        continue;
+                    }
+
+                    // Non-trivial push. Dead states can never succeed, thus are dropped.
+                    if deadlock.is_dead(&state) {
+                        state = get_init_state(state_parent);
+                        continue;
+                    }
+
+                    // Here we canonicalize the player location and symmetries to dedup, while
+                    // saving the original location for step reconstruction.
+                    let precanonical_loc = state.player;
+                    state.canonicalize(&game.config);
+                    progress.generated += 1;
+                    let (idx, is_new) =
+                        state_parent.insert(&state.pack(), *big_cursor, precanonical_loc);
+                    if options.record_graph {
+                        graph_edges.push((*big_cursor, idx, dir));
+                    }
+                    progress.duplicates += !is_new as usize;
+                    metrics.dedup_hits += !is_new as usize;
+
+                    // The state now cannot be reused.
+                    state = get_init_state(state_parent);
+                }
+                small_cursor += 1;
             }
-            small_cursor += 1;
-        }
-        big_cursor += 1;
-    };
+            let closure_size = trivial_visited.len();
+            if metrics.closure_sizes.len() <= closure_size {
+                metrics.closure_sizes.resize(closure_size + 1, 0);
+            }
+            metrics.closure_sizes[closure_size] += 1;
+            *big_cursor += 1;
+        };
 
-    let mut states = std::iter::successors(
-        Some((&final_state, &(big_cursor, final_state.player))),
-        |(_, &(i, _))| state_parent.get_index(i),
-    )
-    .map(|(state, (_, precanonical_loc))| {
-        let mut state = state.clone();
-        state.set_player(*precanonical_loc);
-        state
-    })
-    .collect::<Vec<_>>();
-    states.reverse();
-    Some(states)
+        let stats = limits.stats(*big_cursor + 1, state_parent.len());
+        let states = if game.config.symmetries.is_empty() {
+            reconstruct_big_steps(
+                template,
+                |i| (i != !0usize).then(|| (state_parent.packed(i), state_parent.node(i))),
+                &final_state,
+                *big_cursor,
+            )
+        } else {
+            // Stored states may be mirrored or permuted, thus precanonical locations are not
+            // meaningful. Replay pushes from the initial state instead.
+            let path = std::iter::successors(Some(*big_cursor), |&i| {
+                let (parent, _) = state_parent.node(i);
+                (parent != !0usize).then_some(parent)
+            })
+            .map(|i| state_parent.packed(i))
+            .collect::<Vec<_>>();
+            replay_big_steps(template, &game.config, path.iter().rev())
+        };
+        Ok((states, stats))
+    }
+}
+
+/// Find the move-optimal moves from `from` to exactly `to`, or `None` if it is unreachable.
+///
+/// Both states must be of `game`. Unlike solvers, states are searched by individual moves
+/// without canonicalization or deadlock pruning, thus it is only practical for states not too
+/// far apart, like segments of a route.
+pub fn path_to(game: &Game, from: &State, to: &State) -> Option<Vec<Direction>> {
+    assert!(
+        from.walls == game.state.walls && to.walls == game.state.walls,
+        "States must be of the game",
+    );
+    if from == to {
+        return Some(Vec::new());
+    }
+    bfs_small_step(from, to, true, &mut IndexMap::default())
+}
+
+/// Find the move-optimal walk of the player from `state` to the location `to`, without pushing
+/// anything, or `None` if it is unreachable that way. The walk may pass through other boards.
+pub fn walk_to(state: &State, to: GlobalPos) -> Option<Vec<Direction>> {
+    if to == state.player {
+        return Some(Vec::new());
+    }
+    if state.is_wall(to) || state[to] != Cell::Empty {
+        return None;
+    }
+    let mut target = state.clone();
+    target.set_player(to);
+    bfs_small_step(state, &target, false, &mut IndexMap::default())
 }
 
+/// Find the move-optimal moves from `before` to `after`, where all moves but the last one are
+/// trivial unless `allow_pushes` is set.
 fn bfs_small_step(
     before: &State,
     after: &State,
+    allow_pushes: bool,
     state_parent: &mut IndexMap<State, (usize, Direction)>,
 ) -> Option<Vec<Direction>> {
     state_parent.insert(before.clone(), (!0usize, Direction::Right)); // Sentinel.
@@ -116,7 +548,7 @@ fn bfs_small_step(
             if state == *after {
                 break 'bfs dir;
             }
-            if !do_pushed {
+            if allow_pushes || !do_pushed {
                 state_parent.entry(state).or_insert((cursor, dir));
             }
         }
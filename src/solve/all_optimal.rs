@@ -0,0 +1,171 @@
+//! Enumeration of all move-optimal solutions.
+
+use super::options::LimitChecker;
+use super::{IndexMap, RecordStates, SearchStats, Solution, SolverOptions};
+use crate::{Direction, Game, PackedState, State};
+
+/// Enumerate all distinct solutions with the minimal total count of moves.
+///
+/// It first searches over individual moves like `bfs_moves`, keeping all parents of each state
+/// on shortest paths. Then solutions are reconstructed lazily, thus taking only a few of them is
+/// cheap even if there are exponentially many.
+///
+/// The iterator is empty if the game is unsolvable, or a limit in `options` is reached.
+pub fn all_optimal(game: Game, options: &SolverOptions) -> impl Iterator<Item = Solution> {
+    let template = game.state.clone();
+    if game.is_success() {
+        let stats = SearchStats::default();
+        let solution = Solution::new(&template, Vec::new(), stats, options.record_states);
+        return AllOptimal::single(template, solution);
+    }
+
+    // Visited states with their depths.
+    let mut state_depth = IndexMap::<PackedState, usize>::default();
+    // Parents of each visited state on shortest paths.
+    let mut parents = Vec::<Vec<(usize, Direction)>>::new();
+    let init_packed = game.state.pack();
+    let limits = LimitChecker::new(
+        options,
+        init_packed.estimated_entry_size::<usize>()
+            + std::mem::size_of::<Vec<(usize, Direction)>>(),
+    );
+    state_depth.insert(init_packed, 0);
+    parents.push(Vec::new());
+
+    // Parents of successful states, which are not stored.
+    let mut final_parents = Vec::new();
+    // The depth of parents of successful states, once any is found.
+    let mut final_depth = None;
+    let mut state = template.clone();
+    let mut cursor = 0;
+    loop {
+        let Some((packed, &depth)) = state_depth.get_index(cursor) else {
+            if final_depth.is_some() {
+                break;
+            }
+            return AllOptimal::empty(template);
+        };
+        // All parents of successful states are expanded.
+        if final_depth.is_some_and(|d| depth > d) {
+            break;
+        }
+        if limits.check(cursor, state_depth.len()).is_err() {
+            return AllOptimal::empty(template);
+        }
+        let packed = packed.clone();
+
+        for dir in Direction::ALL {
+            state.unpack_from(&packed);
+            if state.go(dir).is_err() {
+                continue;
+            }
+            if state.is_success_on(&game.config) {
+                final_parents.push((cursor, dir));
+                final_depth = Some(depth);
+                continue;
+            }
+            let entry = state_depth.entry(state.pack());
+            let idx = entry.index();
+            match entry {
+                indexmap::map::Entry::Vacant(entry) => {
+                    entry.insert(depth + 1);
+                    parents.push(vec![(cursor, dir)]);
+                }
+                indexmap::map::Entry::Occupied(entry) => {
+                    if *entry.get() == depth + 1 {
+                        parents[idx].push((cursor, dir));
+                    }
+                }
+            }
+        }
+        cursor += 1;
+    }
+
+    // The virtual final node, whose parents are all successful moves.
+    parents.push(final_parents);
+    let stats = limits.stats(cursor, state_depth.len());
+    AllOptimal {
+        template,
+        stats,
+        record: options.record_states,
+        stack: vec![(parents.len() - 1, 0)],
+        parents,
+        single: None,
+    }
+}
+
+/// A lazy depth-first enumeration of paths from the virtual final node to the initial state.
+struct AllOptimal {
+    template: State,
+    stats: SearchStats,
+    record: Option<RecordStates>,
+    /// Parents of each node on shortest paths. The initial state is the first node.
+    parents: Vec<Vec<(usize, Direction)>>,
+    /// The current path as nodes with their chosen parent, from the final node.
+    /// It is empty when the enumeration is finished.
+    stack: Vec<(usize, usize)>,
+    /// The only solution for trivially successful games.
+    single: Option<Solution>,
+}
+
+impl AllOptimal {
+    fn empty(template: State) -> Self {
+        Self {
+            template,
+            stats: SearchStats::default(),
+            record: None,
+            parents: Vec::new(),
+            stack: Vec::new(),
+            single: None,
+        }
+    }
+
+    fn single(template: State, solution: Solution) -> Self {
+        Self {
+            single: Some(solution),
+            ..Self::empty(template)
+        }
+    }
+}
+
+impl Iterator for AllOptimal {
+    type Item = Solution;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(solution) = self.single.take() {
+            return Some(solution);
+        }
+        // Descend to the initial state through the chosen parents.
+        loop {
+            let &(node, choice) = self.stack.last()?;
+            if node == 0 {
+                break;
+            }
+            self.stack.push((self.parents[node][choice].0, 0));
+        }
+        let moves = self
+            .stack
+            .iter()
+            .rev()
+            .skip(1)
+            .map(|&(node, choice)| self.parents[node][choice].1)
+            .collect();
+
+        // Advance to the next choice for the next call.
+        self.stack.pop();
+        while let Some((node, choice)) = self.stack.last_mut() {
+            if *choice + 1 < self.parents[*node].len() {
+                *choice += 1;
+                break;
+            }
+            self.stack.pop();
+        }
+
+        Some(Solution::new(
+            &self.template,
+            moves,
+            self.stats,
+            self.record,
+        ))
+    }
+}
@@ -0,0 +1,62 @@
+//! Anytime solving, reporting improving solutions as early as possible.
+
+use super::{astar, beam, BeamHeuristic, Instant, Progress, Solution, SolveOutcome, SolverOptions};
+use crate::Game;
+
+/// Widths of beam searches before the exact search, each 16 times wider than the previous.
+const BEAM_WIDTHS: [usize; 4] = [1, 1 << 4, 1 << 8, 1 << 12];
+
+/// Solve the game with increasingly wide `beam` searches and finally an exact `astar`, and
+/// report each solution with fewer pushes than all previous ones to `on_solution`.
+///
+/// `SolverOptions::max_duration` and `SolverOptions::cancel` apply to the whole run, while
+/// other limits apply to each search separately. If the exact search is stopped, the best
+/// solution so far is returned, with `Solution::is_optimal` being `false`.
+pub fn anytime(
+    game: Game,
+    options: &SolverOptions,
+    mut on_solution: impl FnMut(&Solution),
+    mut on_progress: impl FnMut(&Progress),
+) -> SolveOutcome {
+    let deadline = options.max_duration.map(|max| Instant::now() + max);
+    let mut best = None::<Solution>;
+    let mut exact = false;
+    for width in BEAM_WIDTHS.into_iter().map(Some).chain([None]) {
+        let now = Instant::now();
+        let options = SolverOptions {
+            max_duration: deadline.map(|deadline| deadline.saturating_duration_since(now)),
+            ..options.clone()
+        };
+        let outcome = match width {
+            Some(width) => beam(
+                game.clone(),
+                width,
+                BeamHeuristic::Matching,
+                &options,
+                &mut on_progress,
+            ),
+            None => astar(game.clone(), &options, &mut on_progress),
+        };
+        match outcome {
+            SolveOutcome::Solved(solution) => {
+                if best.as_ref().is_none_or(|b| solution.pushes < b.pushes) {
+                    on_solution(&solution);
+                    best = Some(solution);
+                }
+                exact = width.is_none();
+            }
+            // Not a proof from beam searches.
+            SolveOutcome::Unsolvable(_) if width.is_some() => {}
+            // A limit is reached, or it is proven unsolvable by the exact search.
+            outcome => match best {
+                Some(_) => break,
+                None => return outcome,
+            },
+        }
+    }
+
+    let mut best = best.expect("The exact search must either succeed or stop");
+    // The exact search may find a solution with the same count of pushes.
+    best.is_optimal = exact;
+    SolveOutcome::Solved(best)
+}
@@ -0,0 +1,169 @@
+//! Breadth-first search with approximate deduplication by a Bloom filter.
+
+use super::options::LimitChecker;
+use super::{
+    resolve_small_steps, BucketIndexSet, Progress, Solution, SolveOutcome, SolverOptions,
+    Unsolvability,
+};
+use crate::{Direction, Game, GlobalPos, PackedState};
+
+/// The count of hash functions of the Bloom filter.
+const HASH_CNT: u32 = 4;
+
+struct BloomFilter {
+    bits: Box<[u64]>,
+}
+
+impl BloomFilter {
+    fn new(bytes: usize) -> Self {
+        Self {
+            bits: vec![0u64; (bytes / 8).max(1)].into(),
+        }
+    }
+
+    /// Insert a state, returns if it is possibly newly inserted.
+    /// It may return `false` for a new state, but never `true` for an inserted state.
+    fn insert(&mut self, packed: &PackedState) -> bool {
+        let bit_cnt = self.bits.len() as u64 * 64;
+        let h = packed.stable_hash();
+        // Double hashing.
+        let (h1, h2) = (h, h.rotate_left(32) | 1);
+        let mut is_new = false;
+        for i in 0..HASH_CNT {
+            let bit = h1.wrapping_add(h2.wrapping_mul(i as u64)) % bit_cnt;
+            let (word, mask) = (&mut self.bits[(bit / 64) as usize], 1u64 << (bit % 64));
+            is_new |= *word & mask == 0;
+            *word |= mask;
+        }
+        is_new
+    }
+}
+
+/// A push from a parent node: the exact player location before the push, and its direction.
+/// States are not stored, but replayed from the initial state when reconstructing.
+type Node = (usize, GlobalPos, Direction);
+
+/// Breadth-first search like `bfs`, but deduplicating states by a Bloom filter of
+/// `bloom_bytes` bytes, and only keeping states of the frontier in memory.
+///
+/// # Incompleteness
+///
+/// False positives of the Bloom filter prune genuinely new states, thus this search may miss
+/// the optimal solution or all solutions. `SolveOutcome::Unsolvable` from it is NOT a proof of
+/// unsolvability. The smaller `bloom_bytes` is relative to the count of states, the more likely
+/// it happens.
+pub fn bfs_approximate(
+    game: Game,
+    bloom_bytes: usize,
+    options: &SolverOptions,
+    mut on_progress: impl FnMut(&Progress),
+) -> SolveOutcome {
+    let template = game.state.clone();
+    let init_packed = game.state.pack();
+    let mut filter = BloomFilter::new(bloom_bytes);
+    // Frontier states are counted as if every node is in the frontier.
+    let limits = LimitChecker::new(
+        options,
+        std::mem::size_of::<Node>() + init_packed.estimated_entry_size::<usize>(),
+    )
+    .with_fixed_bytes(std::mem::size_of_val(&*filter.bits));
+    filter.insert(&init_packed);
+
+    let mut nodes = vec![(!0usize, game.state.player, Direction::Right)]; // Sentinel.
+    let mut frontier = vec![(init_packed, 0usize)];
+    let mut next_frontier = Vec::new();
+    let mut trivial_visited = BucketIndexSet::<GlobalPos, { GlobalPos::TO_USIZE_LIMIT }>::new();
+    let mut progress = Progress::default();
+    let mut expanded = 0;
+
+    let (final_node, final_state) = 'bfs: loop {
+        if frontier.is_empty() {
+            let stats = limits.stats(expanded, nodes.len());
+            return SolveOutcome::Unsolvable(Unsolvability::new(&game, stats));
+        }
+        for (packed, node) in frontier.drain(..) {
+            if let Err(outcome) = limits.check(expanded, nodes.len()) {
+                return outcome;
+            }
+            expanded += 1;
+            progress.expanded = expanded;
+            progress.memory_bytes = limits.memory_bytes(nodes.len());
+
+            let mut state = template.clone();
+            state.unpack_from(&packed);
+            trivial_visited.clear();
+            trivial_visited.try_insert(state.player);
+
+            let mut small_cursor = 0;
+            while small_cursor < trivial_visited.len() {
+                let gpos = trivial_visited[small_cursor];
+
+                for dir in options.move_order(packed.stable_hash(), gpos) {
+                    progress.steps += 1;
+                    progress.frontier = next_frontier.len();
+                    on_progress(&progress);
+
+                    state.set_player(gpos);
+                    let Ok(do_pushed) = state.go(dir) else { continue };
+
+                    if state.is_success_on(&game.config) {
+                        let final_node = if do_pushed {
+                            nodes.push((node, gpos, dir));
+                            nodes.len() - 1
+                        } else {
+                            node
+                        };
+                        break 'bfs (final_node, state);
+                    }
+
+                    if !do_pushed {
+                        trivial_visited.try_insert(state.player);
+                        continue;
+                    }
+
+                    let canonical_loc = state.trivially_reachable_locations().min().unwrap();
+                    state.set_player(canonical_loc);
+                    let new_packed = state.pack();
+                    progress.generated += 1;
+                    if filter.insert(&new_packed) {
+                        nodes.push((node, gpos, dir));
+                        next_frontier.push((new_packed, nodes.len() - 1));
+                    } else {
+                        progress.duplicates += 1;
+                    }
+
+                    // The state now cannot be reused.
+                    state.unpack_from(&packed);
+                }
+                small_cursor += 1;
+            }
+        }
+        std::mem::swap(&mut frontier, &mut next_frontier);
+        progress.depth += 1;
+    };
+
+    // Replay pushes from the initial state to get exact states right after each push.
+    let mut path = std::iter::successors(Some(final_node), |&i| {
+        let parent = nodes[i].0;
+        (parent != !0usize).then_some(parent)
+    })
+    .collect::<Vec<_>>();
+    path.reverse();
+    let mut states = vec![template.clone()];
+    let mut state = template;
+    for &i in &path[1..] {
+        let (_, loc, dir) = nodes[i];
+        state.set_player(loc);
+        assert_eq!(state.go(dir), Ok(true), "Replay must push");
+        states.push(state.clone());
+    }
+    if states.last() != Some(&final_state) {
+        states.push(final_state);
+    }
+
+    let stats = limits.stats(expanded, nodes.len());
+    let moves = resolve_small_steps(&states);
+    let mut solution = Solution::new(&states[0], moves, stats, options.record_states);
+    solution.is_optimal = false;
+    SolveOutcome::Solved(solution)
+}
@@ -0,0 +1,164 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use super::heuristic::Heuristic;
+use super::options::LimitChecker;
+use super::{
+    reconstruct_big_steps, resolve_small_steps, BucketIndexSet, IndexMap, Progress, SearchStats,
+    Solution, SolveOutcome, SolverOptions, Unsolvability,
+};
+use crate::{Game, GlobalPos, PackedState, State};
+
+/// Solve the game with A* search on pushes, guided by an admissible matching heuristic.
+///
+/// Like `bfs`, the returned solution has the minimal count of pushes.
+pub fn astar(
+    game: Game,
+    options: &SolverOptions,
+    on_progress: impl FnMut(&Progress),
+) -> SolveOutcome {
+    match astar_big_step(game, options, on_progress) {
+        Ok((states, stats)) => SolveOutcome::Solved(Solution::new(
+            &states[0],
+            resolve_small_steps(&states),
+            stats,
+            options.record_states,
+        )),
+        Err(outcome) => outcome,
+    }
+}
+
+fn astar_big_step(
+    game: Game,
+    options: &SolverOptions,
+    mut on_progress: impl FnMut(&Progress),
+) -> Result<(Vec<State>, SearchStats), SolveOutcome> {
+    let heuristic = Heuristic::new(&game);
+    let template = game.state.clone();
+
+    // Canonical states with their parents and precanonical locations, like `BfsSearch`.
+    let mut state_parent = IndexMap::<PackedState, (usize, GlobalPos)>::default();
+    // The best known count of pushes to each state in `state_parent`.
+    let mut costs = Vec::new();
+    // Ordered by (estimated total cost, insertion order).
+    let mut open = BinaryHeap::new();
+
+    let init_loc = game.state.player;
+    let init_packed = game.state.pack();
+    let limits = LimitChecker::new(
+        options,
+        init_packed.estimated_entry_size::<(usize, GlobalPos)>() + std::mem::size_of::<u32>(),
+    );
+    let Some(init_h) = heuristic.estimate(&game.state) else {
+        let stats = limits.stats(0, 0);
+        return Err(SolveOutcome::Unsolvable(Unsolvability::new(&game, stats)));
+    };
+    state_parent.insert(init_packed, (!0usize, init_loc)); // Sentinel.
+    costs.push(0u32);
+    open.push(Reverse((init_h, 0usize)));
+
+    let mut trivial_visited = BucketIndexSet::<GlobalPos, { GlobalPos::TO_USIZE_LIMIT }>::new();
+    let mut state = template.clone();
+
+    let mut progress = Progress::default();
+    let mut expanded = 0;
+    let (final_state, parent) = 'search: loop {
+        let Some(Reverse((_, cursor))) = open.pop() else {
+            return Err(SolveOutcome::Unsolvable(Unsolvability::new(
+                &game,
+                limits.stats(expanded, state_parent.len()),
+            )));
+        };
+        limits.check(expanded, state_parent.len())?;
+        expanded += 1;
+        let cost = costs[cursor];
+        progress.expanded = expanded;
+        progress.frontier = open.len();
+        progress.depth = cost as usize;
+        progress.memory_bytes = limits.memory_bytes(state_parent.len());
+
+        let (packed, _) = state_parent.get_index(cursor).unwrap();
+        state.unpack_from(packed);
+        let init_state = state.clone();
+        trivial_visited.clear();
+        trivial_visited.try_insert(state.player);
+
+        // The initial state itself may be already successful, or become successful by
+        // canonicalization. For the latter, the final walk is resolved from itself.
+        if state.is_success_on(&game.config) {
+            let (parent, precanonical_loc) = state_parent[cursor];
+            let parent = if state.player == precanonical_loc {
+                parent
+            } else {
+                cursor
+            };
+            break 'search (state, parent);
+        }
+
+        let mut small_cursor = 0;
+        while small_cursor < trivial_visited.len() {
+            let gpos = trivial_visited[small_cursor];
+
+            for dir in options.move_order(init_state.zobrist_hash(), gpos) {
+                progress.steps += 1;
+                on_progress(&progress);
+
+                state.set_player(gpos);
+                let Ok(do_pushed) = state.go(dir) else { continue };
+
+                // Trivial move.
+                if !do_pushed {
+                    // Trivial moves cost nothing, thus it is still optimal.
+                    if state.is_success_on(&game.config) {
+                        break 'search (state, cursor);
+                    }
+                    trivial_visited.try_insert(state.player);
+                    continue;
+                }
+
+                // Non-trivial push. Successful states are treated as normal ones, so that they
+                // are only accepted after all cheaper states are expanded.
+                let precanonical_loc = state.player;
+                if let Some(h) = heuristic.estimate(&state) {
+                    let is_success = state.is_success_on(&game.config);
+                    if !is_success {
+                        let canonical_loc = state.trivially_reachable_locations().min().unwrap();
+                        state.set_player(canonical_loc);
+                    }
+                    let new_cost = cost + 1;
+                    progress.generated += 1;
+                    let entry = state_parent.entry(state.pack());
+                    let idx = entry.index();
+                    match entry {
+                        indexmap::map::Entry::Vacant(entry) => {
+                            entry.insert((cursor, precanonical_loc));
+                            costs.push(new_cost);
+                            open.push(Reverse((new_cost + h, idx)));
+                        }
+                        indexmap::map::Entry::Occupied(mut entry) => {
+                            progress.duplicates += 1;
+                            if new_cost < costs[idx] {
+                                entry.insert((cursor, precanonical_loc));
+                                costs[idx] = new_cost;
+                                open.push(Reverse((new_cost + h, idx)));
+                            }
+                        }
+                    }
+                }
+
+                // The state now cannot be reused.
+                state.clone_from(&init_state);
+            }
+            small_cursor += 1;
+        }
+    };
+
+    let stats = limits.stats(expanded, state_parent.len());
+    let states = reconstruct_big_steps(
+        &template,
+        |i| state_parent.get_index(i),
+        &final_state,
+        parent,
+    );
+    Ok((states, stats))
+}
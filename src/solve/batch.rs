@@ -0,0 +1,50 @@
+//! Solving many independent levels at once.
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use rayon::prelude::*;
+
+use super::{bfs_with_options, SolveOutcome, SolverOptions};
+use crate::Game;
+
+/// Aggregated progress of `solve_many`, reported on every attempted move of any level.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BatchProgress {
+    /// The count of levels.
+    pub total: usize,
+    /// The count of finished levels, either solved or stopped.
+    pub finished: usize,
+    /// The total count of attempted moves of all levels.
+    pub steps: u64,
+}
+
+/// Solve levels independently with `bfs_with_options` on the rayon thread pool, returning
+/// outcomes in the same order as `games`.
+///
+/// `options` apply to each level separately, except that `SolverOptions::cancel` stops all
+/// of them.
+pub fn solve_many(
+    games: &[Game],
+    options: &SolverOptions,
+    on_progress: impl Fn(&BatchProgress) + Sync,
+) -> Vec<SolveOutcome> {
+    let finished = AtomicUsize::new(0);
+    let steps = AtomicU64::new(0);
+    let snapshot = || BatchProgress {
+        total: games.len(),
+        finished: finished.load(Ordering::Relaxed),
+        steps: steps.load(Ordering::Relaxed),
+    };
+    games
+        .par_iter()
+        .map(|game| {
+            let outcome = bfs_with_options(game.clone(), options, |_| {
+                steps.fetch_add(1, Ordering::Relaxed);
+                on_progress(&snapshot());
+            });
+            finished.fetch_add(1, Ordering::Relaxed);
+            on_progress(&snapshot());
+            outcome
+        })
+        .collect()
+}
@@ -0,0 +1,173 @@
+//! Beam search, an incomplete but fast solver for large levels.
+
+use super::deadlock::DeadlockDetector;
+use super::heuristic::Heuristic;
+use super::options::LimitChecker;
+use super::{
+    reconstruct_big_steps, resolve_small_steps, BucketIndexSet, IndexMap, Progress, Solution,
+    SolveOutcome, SolverOptions, Unsolvability,
+};
+use crate::{Game, GlobalPos, PackedState, State};
+
+/// How `beam` ranks states of a layer. States with lower estimations are kept.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BeamHeuristic {
+    /// The admissible lower bound of remaining pushes used by `astar`.
+    #[default]
+    Matching,
+    /// The count of box targets not occupied by any box-like cell.
+    UnfilledTargets,
+}
+
+impl BeamHeuristic {
+    fn estimate(self, game: &Game, matching: &Heuristic, state: &State) -> Option<u32> {
+        match self {
+            Self::Matching => matching.estimate(state),
+            Self::UnfilledTargets => {
+                let unfilled = game
+                    .config
+                    .box_targets
+                    .iter()
+                    .filter(|&&gpos| gpos == state.player || !state[gpos].is_box_like())
+                    .count();
+                Some(unfilled as u32)
+            }
+        }
+    }
+}
+
+/// Breadth-first search on pushes like `bfs`, but only keeping at most `width` states with
+/// the lowest estimations in each layer.
+///
+/// # Incompleteness
+///
+/// Pruned states are never revisited, thus the solution is not necessarily push-optimal, and
+/// `Solution::is_optimal` is always `false`. `SolveOutcome::Unsolvable` from it is NOT a proof
+/// of unsolvability.
+pub fn beam(
+    game: Game,
+    width: usize,
+    heuristic: BeamHeuristic,
+    options: &SolverOptions,
+    mut on_progress: impl FnMut(&Progress),
+) -> SolveOutcome {
+    let matching = Heuristic::new(&game);
+    let deadlock = DeadlockDetector::new(&game);
+    let template = game.state.clone();
+    let init_packed = game.state.pack();
+    let limits = LimitChecker::new(
+        options,
+        init_packed.estimated_entry_size::<(usize, GlobalPos)>(),
+    );
+
+    // Kept canonical states with their parents and precanonical locations, like `BfsSearch`.
+    // Each layer is a contiguous range.
+    let mut state_parent = IndexMap::<PackedState, (usize, GlobalPos)>::default();
+    state_parent.insert(init_packed, (!0usize, game.state.player)); // Sentinel.
+
+    // Candidates of the next layer with their estimations, parents and precanonical locations.
+    let mut candidates = IndexMap::<PackedState, (u32, usize, GlobalPos)>::default();
+    let mut trivial_visited = BucketIndexSet::<GlobalPos, { GlobalPos::TO_USIZE_LIMIT }>::new();
+    let mut state = template.clone();
+    let mut progress = Progress::default();
+    let mut cursor = 0;
+
+    let (final_state, parent) = 'search: loop {
+        let layer_end = state_parent.len();
+        if cursor == layer_end {
+            let stats = limits.stats(cursor, state_parent.len());
+            return SolveOutcome::Unsolvable(Unsolvability::new(&game, stats));
+        }
+        while cursor < layer_end {
+            if let Err(outcome) = limits.check(cursor, state_parent.len()) {
+                return outcome;
+            }
+            progress.expanded = cursor;
+            progress.memory_bytes = limits.memory_bytes(state_parent.len() + candidates.len());
+
+            let (packed, _) = state_parent.get_index(cursor).unwrap();
+            state.unpack_from(packed);
+            let init_state = state.clone();
+            trivial_visited.clear();
+            trivial_visited.try_insert(state.player);
+
+            if state.is_success_on(&game.config) {
+                let (parent, precanonical_loc) = state_parent[cursor];
+                let parent = if state.player == precanonical_loc {
+                    parent
+                } else {
+                    cursor
+                };
+                break 'search (state, parent);
+            }
+
+            let mut small_cursor = 0;
+            while small_cursor < trivial_visited.len() {
+                let gpos = trivial_visited[small_cursor];
+
+                for dir in options.move_order(init_state.zobrist_hash(), gpos) {
+                    progress.steps += 1;
+                    progress.frontier = layer_end - cursor + candidates.len();
+                    on_progress(&progress);
+
+                    state.set_player(gpos);
+                    let Ok(do_pushed) = state.go(dir) else { continue };
+
+                    if state.is_success_on(&game.config) {
+                        break 'search (state, cursor);
+                    }
+
+                    if !do_pushed {
+                        trivial_visited.try_insert(state.player);
+                        continue;
+                    }
+
+                    let precanonical_loc = state.player;
+                    let h = (!deadlock.is_dead(&state))
+                        .then(|| heuristic.estimate(&game, &matching, &state))
+                        .flatten();
+                    if let Some(h) = h {
+                        let canonical_loc = state.trivially_reachable_locations().min().unwrap();
+                        state.set_player(canonical_loc);
+                        let packed = state.pack();
+                        progress.generated += 1;
+                        if state_parent.contains_key(&packed) || candidates.contains_key(&packed) {
+                            progress.duplicates += 1;
+                        } else {
+                            candidates.insert(packed, (h, cursor, precanonical_loc));
+                        }
+                    }
+
+                    // The state now cannot be reused.
+                    state.clone_from(&init_state);
+                }
+                small_cursor += 1;
+            }
+            cursor += 1;
+        }
+
+        // Keep the best candidates, preferring earlier ones on ties for determinism.
+        let mut order = (0..candidates.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| (candidates[i].0, i));
+        order.truncate(width);
+        order.sort_unstable();
+        for (i, (packed, (_, parent, loc))) in candidates.drain(..).enumerate() {
+            if order.binary_search(&i).is_ok() {
+                state_parent.insert(packed, (parent, loc));
+            }
+        }
+        progress.depth += 1;
+    };
+
+    let stats = limits.stats(cursor + 1, state_parent.len());
+    let states = reconstruct_big_steps(
+        &template,
+        |i| state_parent.get_index(i),
+        &final_state,
+        parent,
+    );
+    let moves = resolve_small_steps(&states);
+    let mut solution = Solution::new(&states[0], moves, stats, options.record_states);
+    solution.is_optimal = false;
+    SolveOutcome::Solved(solution)
+}
@@ -0,0 +1,214 @@
+use super::options::LimitChecker;
+use super::retrograde::{goal_state, push_predecessors};
+use super::{
+    reconstruct_big_steps, resolve_small_steps, BucketIndexSet, IndexMap, Progress, SearchStats,
+    Solution, SolveOutcome, SolverOptions, Unsolvability,
+};
+use crate::{Game, GlobalPos, PackedState, State};
+
+/// Bidirectional breadth-first search on pushes, meeting in the middle.
+///
+/// The backward search starts from the goal state, and only follows pushes inside a single
+/// board (see `push_predecessors`). Thus only Sokoban levels, without board cells and with a
+/// unique goal state, are supported, others are rejected with `SolveOutcome::Unsupported`. The
+/// solution is valid but not guaranteed to have the minimal count of pushes.
+pub fn bidirectional(
+    game: Game,
+    options: &SolverOptions,
+    on_progress: impl FnMut(&Progress),
+) -> SolveOutcome {
+    match bidirectional_big_step(game, options, on_progress) {
+        Ok((mut states, stats)) => {
+            states.dedup();
+            let moves = resolve_small_steps(&states);
+            let mut solution = Solution::new(&states[0], moves, stats, options.record_states);
+            solution.is_optimal = false;
+            SolveOutcome::Solved(solution)
+        }
+        Err(outcome) => outcome,
+    }
+}
+
+fn canonicalize(state: &mut State) {
+    let canonical_loc = state.trivially_reachable_locations().min().unwrap();
+    state.set_player(canonical_loc);
+}
+
+fn bidirectional_big_step(
+    game: Game,
+    options: &SolverOptions,
+    mut on_progress: impl FnMut(&Progress),
+) -> Result<(Vec<State>, SearchStats), SolveOutcome> {
+    let template = game.state.clone();
+    let limits = LimitChecker::new(
+        options,
+        game.state
+            .pack()
+            .estimated_entry_size::<(usize, GlobalPos)>(),
+    );
+    let mut expanded = 0;
+    // The depth is the total count of expanded layers in both directions.
+    let mut progress = Progress::default();
+    let unpack = |packed: &PackedState, loc: GlobalPos| {
+        let mut state = template.clone();
+        state.unpack_from(packed);
+        state.set_player(loc);
+        state
+    };
+
+    // Canonical states with their parents and precanonical locations, like `BfsSearch`.
+    let mut forward = IndexMap::<PackedState, (usize, GlobalPos)>::default();
+    // Canonical states with their children towards the goal, and the exact player location in
+    // the child right after the push. Goal states have sentinel children.
+    let mut backward = IndexMap::<PackedState, (usize, GlobalPos)>::default();
+
+    {
+        let mut init = game.state.clone();
+        canonicalize(&mut init);
+        forward.insert(init.pack(), (!0usize, game.state.player)); // Sentinel.
+    }
+    {
+        let mut goal = goal_state(&game)?;
+        let loc = goal.player;
+        canonicalize(&mut goal);
+        backward.insert(goal.pack(), (!0usize, loc)); // Sentinel.
+    }
+
+    // Meeting point: the exact forward state with its parent, and the backward index.
+    let mut meet = (0..backward.len()).find_map(|i| {
+        let idx = forward.get_index_of(backward.get_index(i).unwrap().0)?;
+        let (packed, &(parent, loc)) = forward.get_index(idx).unwrap();
+        Some((unpack(packed, loc), parent, i))
+    });
+
+    let mut trivial_visited = BucketIndexSet::<GlobalPos, { GlobalPos::TO_USIZE_LIMIT }>::new();
+    let mut preds = Vec::new();
+    let (mut fwd_start, mut bwd_start) = (0, 0);
+    let (fwd_final, fwd_parent, bwd_idx) = 'search: loop {
+        if let Some(meet) = meet.take() {
+            break 'search meet;
+        }
+
+        let (fwd_end, bwd_end) = (forward.len(), backward.len());
+        if fwd_start == fwd_end {
+            return Err(SolveOutcome::Unsolvable(Unsolvability::new(
+                &game,
+                limits.stats(expanded, fwd_end + bwd_end),
+            )));
+        }
+
+        progress.frontier = (fwd_end - fwd_start) + (bwd_end - bwd_start);
+
+        // Expand the smaller frontier.
+        if bwd_start < bwd_end && bwd_end - bwd_start <= fwd_end - fwd_start {
+            for cursor in bwd_start..bwd_end {
+                limits.check(expanded, forward.len() + backward.len())?;
+                expanded += 1;
+                progress.expanded = expanded;
+                progress.memory_bytes = limits.memory_bytes(forward.len() + backward.len());
+                let mut state = template.clone();
+                state.unpack_from(backward.get_index(cursor).unwrap().0);
+                for loc in state.trivially_reachable_locations().collect::<Vec<_>>() {
+                    progress.steps += 1;
+                    on_progress(&progress);
+                    state.set_player(loc);
+                    preds.clear();
+                    push_predecessors(&state, &mut preds);
+                    for (mut pred, _) in preds.drain(..) {
+                        canonicalize(&mut pred);
+                        let packed = pred.pack();
+                        progress.generated += 1;
+                        if backward.contains_key(&packed) {
+                            progress.duplicates += 1;
+                            continue;
+                        }
+                        let fwd_idx = forward.get_index_of(&packed);
+                        let (bwd_idx, _) = backward.insert_full(packed, (cursor, loc));
+                        if let Some(fwd_idx) = fwd_idx {
+                            let (packed, &(parent, loc)) = forward.get_index(fwd_idx).unwrap();
+                            meet = Some((unpack(packed, loc), parent, bwd_idx));
+                            continue 'search;
+                        }
+                    }
+                }
+            }
+            bwd_start = bwd_end;
+            progress.depth += 1;
+            continue;
+        }
+
+        for cursor in fwd_start..fwd_end {
+            limits.check(expanded, forward.len() + backward.len())?;
+            expanded += 1;
+            progress.expanded = expanded;
+            progress.memory_bytes = limits.memory_bytes(forward.len() + backward.len());
+            let mut init_state = template.clone();
+            init_state.unpack_from(forward.get_index(cursor).unwrap().0);
+            let mut state = init_state.clone();
+            trivial_visited.clear();
+            trivial_visited.try_insert(state.player);
+
+            let mut small_cursor = 0;
+            while small_cursor < trivial_visited.len() {
+                let gpos = trivial_visited[small_cursor];
+
+                for dir in options.move_order(init_state.zobrist_hash(), gpos) {
+                    progress.steps += 1;
+                    on_progress(&progress);
+
+                    state.set_player(gpos);
+                    let Ok(do_pushed) = state.go(dir) else { continue };
+
+                    // Success without meeting the backward search.
+                    if state.is_success_on(&game.config) {
+                        let states = reconstruct_big_steps(
+                            &template,
+                            |i| forward.get_index(i),
+                            &state,
+                            cursor,
+                        );
+                        let stats = limits.stats(expanded, forward.len() + backward.len());
+                        return Ok((states, stats));
+                    }
+
+                    if !do_pushed {
+                        trivial_visited.try_insert(state.player);
+                        continue;
+                    }
+
+                    let exact = state.clone();
+                    canonicalize(&mut state);
+                    let packed = state.pack();
+                    if let Some(bwd_idx) = backward.get_index_of(&packed) {
+                        break 'search (exact, cursor, bwd_idx);
+                    }
+                    progress.generated += 1;
+                    let len = forward.len();
+                    forward.entry(packed).or_insert((cursor, exact.player));
+                    progress.duplicates += (len == forward.len()) as usize;
+
+                    // The state now cannot be reused.
+                    state.clone_from(&init_state);
+                }
+                small_cursor += 1;
+            }
+        }
+        fwd_start = fwd_end;
+        progress.depth += 1;
+    };
+
+    let mut states =
+        reconstruct_big_steps(&template, |i| forward.get_index(i), &fwd_final, fwd_parent);
+    let mut cur = bwd_idx;
+    loop {
+        let (_, &(child, loc)) = backward.get_index(cur).unwrap();
+        if child == !0usize {
+            states.push(unpack(backward.get_index(cur).unwrap().0, loc));
+            break;
+        }
+        states.push(unpack(backward.get_index(child).unwrap().0, loc));
+        cur = child;
+    }
+    let stats = limits.stats(expanded, forward.len() + backward.len());
+    Ok((states, stats))
+}
@@ -0,0 +1,143 @@
+//! Saving and restoring `BfsSearch` as checkpoints.
+//!
+//! The format is a little-endian binary stream of a header, followed by all visited states with
+//! their parents in insertion order. It is only valid for the same game and the same version of
+//! this crate.
+
+use std::hash::{Hash, Hasher};
+use std::io::{self, Read, Write};
+
+use super::{BfsSearch, DeadlockDetector, Metrics, Progress, StateTable};
+use crate::pack::{read_gpos, write_gpos};
+use crate::{Game, PackedState};
+
+const MAGIC: &[u8; 8] = b"PBXCKPT1";
+/// The maximal count of states reserved before reading them.
+const MAX_RESERVED_STATES: usize = 1 << 20;
+
+/// A fingerprint of the game to reject checkpoints of other games.
+fn fingerprint(game: &Game) -> u64 {
+    let mut h = fxhash::FxHasher64::default();
+    game.hash(&mut h);
+    game.state.walls.hash(&mut h);
+    h.finish()
+}
+
+fn write_u64(w: &mut impl Write, x: u64) -> io::Result<()> {
+    w.write_all(&x.to_le_bytes())
+}
+
+fn read_u64(r: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn corrupted(reason: &str) -> io::Error {
+    invalid_data(&format!("Corrupted checkpoint: {reason}"))
+}
+
+impl BfsSearch {
+    /// Save the search as a checkpoint.
+    pub fn save(&self, mut w: impl Write) -> io::Result<()> {
+        let w = &mut w;
+        w.write_all(MAGIC)?;
+        write_u64(w, fingerprint(&self.game))?;
+        write_u64(w, self.big_cursor as u64)?;
+        write_u64(w, self.depth_end as u64)?;
+        write_u64(w, self.progress.depth as u64)?;
+        write_u64(w, self.progress.steps)?;
+        write_u64(w, self.progress.generated as u64)?;
+        write_u64(w, self.progress.duplicates as u64)?;
+        let cells_len = self.state_parent.packed(0).cells_len();
+        write_u64(w, cells_len as u64)?;
+        write_u64(w, self.state_parent.len() as u64)?;
+        for i in 0..self.state_parent.len() {
+            let (parent, loc) = self.state_parent.node(i);
+            self.state_parent.packed(i).write_to(w)?;
+            write_u64(w, parent as u64)?;
+            write_gpos(w, loc)?;
+        }
+        w.flush()
+    }
+
+    /// Load a checkpoint saved by `save` of the same game.
+    ///
+    /// Damaged or truncated checkpoints fail with `io::ErrorKind::InvalidData`.
+    pub fn load(game: Game, mut r: impl Read) -> io::Result<Self> {
+        Self::read_checkpoint(game, &mut r).map_err(|err| match err.kind() {
+            io::ErrorKind::UnexpectedEof => corrupted("Truncated"),
+            _ => err,
+        })
+    }
+
+    fn read_checkpoint(game: Game, r: &mut impl Read) -> io::Result<Self> {
+        let mut magic = [0u8; MAGIC.len()];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("Not a checkpoint"));
+        }
+        if read_u64(r)? != fingerprint(&game) {
+            return Err(invalid_data("Checkpoint of a different game"));
+        }
+        let big_cursor = read_u64(r)? as usize;
+        let depth_end = read_u64(r)? as usize;
+        let progress = Progress {
+            depth: read_u64(r)? as usize,
+            steps: read_u64(r)?,
+            generated: read_u64(r)? as usize,
+            duplicates: read_u64(r)? as usize,
+            ..Progress::default()
+        };
+        let cells_len = read_u64(r)? as usize;
+        if cells_len != game.state.pack().cells_len() {
+            return Err(invalid_data("State size mismatch"));
+        }
+        let len = usize::try_from(read_u64(r)?)
+            .ok()
+            .filter(|&len| len <= StateTable::max_len())
+            .ok_or_else(|| corrupted("Too many states"))?;
+        // The length may be damaged, thus the table grows as states are read beyond this.
+        let mut state_parent = StateTable::new(cells_len);
+        state_parent
+            .try_reserve(len.min(MAX_RESERVED_STATES))
+            .map_err(|_| corrupted("Too many states"))?;
+        for i in 0..len {
+            let packed = PackedState::read_from(r, cells_len)?;
+            let parent = read_u64(r)?;
+            let loc = read_gpos(r)?;
+            // Only the first state is a root, and parents come before their children.
+            let parent = match parent {
+                u64::MAX if i == 0 => !0usize,
+                parent if parent < i as u64 => parent as usize,
+                _ => return Err(corrupted("Invalid parent")),
+            };
+            if !game.state.is_valid_packed(&packed) || !game.state.is_open(loc) {
+                return Err(corrupted("Invalid state"));
+            }
+            if i == 0 && packed != game.state.pack() {
+                return Err(corrupted("Invalid initial state"));
+            }
+            if !state_parent.insert(&packed, parent, loc).1 {
+                return Err(corrupted("Duplicated state"));
+            }
+        }
+        if len == 0 || big_cursor > depth_end || depth_end > len {
+            return Err(corrupted("Invalid cursor"));
+        }
+        Ok(Self {
+            deadlock: DeadlockDetector::new(&game),
+            game,
+            state_parent,
+            big_cursor,
+            depth_end,
+            progress,
+            metrics: Metrics::default(),
+            graph_edges: Vec::new(),
+        })
+    }
+}
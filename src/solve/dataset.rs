@@ -0,0 +1,233 @@
+//! Datasets of the explored state graph for machine learning, like training policies or value
+//! functions.
+//!
+//! Unlike other solvers, states are individual moves rather than pushes, with the player
+//! located exactly, and symmetric states are not merged. All reachable states are explored, and
+//! successful states are terminal.
+//!
+//! `Dataset::write_npy` writes a directory of NumPy `.npy` arrays, with `B` boards padded to the
+//! largest height `H` and width `W`:
+//!
+//! - `states.npy`: `uint8` of shape `(N, B, H, W)`, cells of states in discovery order, where
+//!   `states[0]` is the initial state. Cells are 0 for padding, 1 for empty, 2 for walls, 3 for
+//!   boxes, 4 for the player, and `5 + id` for references to the board `id`.
+//! - `distances.npy`: `int32` of shape `(N,)`, the minimal count of moves to succeed, or -1 if
+//!   the level can never succeed from the state.
+//! - `transitions.npy`: `int32` of shape `(M, 3)`, rows of the index of a state, the action as
+//!   `Direction as u8` (0 right, 1 down, 2 left, 3 up), and the index of the next state, for
+//!   all legal moves of non-terminal states.
+//! - `targets.npy`: `uint8` of shape `(B, H, W)`, 1 for box targets and 2 for the player target.
+//!
+//! Tuples of `(state, action, next state, distance to goal)` are then:
+//!
+//! ```python
+//! s, a, t = np.load("transitions.npy").T
+//! states, distances = np.load("states.npy"), np.load("distances.npy")
+//! tuples = (states[s], a, states[t], distances[s])
+//! ```
+
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::path::Path;
+
+use anyhow::{ensure, Context, Result};
+
+use super::IndexMap;
+use crate::{BoardId, Cell, Direction, Game, PackedState};
+
+/// Options of `dataset`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DatasetOptions {
+    /// Fail if more states are reachable, since distances need exhaustive exploration.
+    pub max_states: usize,
+}
+
+impl Default for DatasetOptions {
+    fn default() -> Self {
+        Self {
+            max_states: 1_000_000,
+        }
+    }
+}
+
+/// A dataset built by `dataset`. See the module documentation for fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dataset {
+    /// The shape `(B, H, W)` of a state.
+    pub shape: [usize; 3],
+    /// Cells of all states, flattened.
+    pub states: Vec<u8>,
+    pub distances: Vec<i32>,
+    pub transitions: Vec<[i32; 3]>,
+    pub targets: Vec<u8>,
+}
+
+/// Explore all states reachable by moves, and compute their distances to success by a backward
+/// breadth-first search.
+pub fn dataset(game: &Game, options: &DatasetOptions) -> Result<Dataset> {
+    let state = &game.state;
+    let board_ids = (0..state.boards.len()).map(|id| BoardId::try_from(id).unwrap());
+    let height = board_ids.clone().map(|id| state[id].height as usize).max();
+    let width = board_ids.clone().map(|id| state[id].width as usize).max();
+    let shape = [state.boards.len(), height.unwrap_or(0), width.unwrap_or(0)];
+    let offset = |board_id: BoardId, (row, col): (usize, usize)| {
+        (board_id as usize * shape[1] + row) * shape[2] + col
+    };
+
+    let mut targets = vec![0u8; shape.iter().product()];
+    for gpos in &game.config.box_targets {
+        targets[offset(gpos.board_id, (gpos.pos.0 as usize, gpos.pos.1 as usize))] = 1;
+    }
+    if let Some(gpos) = game.config.player_target {
+        targets[offset(gpos.board_id, (gpos.pos.0 as usize, gpos.pos.1 as usize))] = 2;
+    }
+
+    let mut seen = IndexMap::<PackedState, ()>::default();
+    seen.insert(state.pack(), ());
+    let mut states = Vec::new();
+    let mut is_success = Vec::new();
+    let mut transitions = Vec::new();
+    let mut cur = state.clone();
+    let mut next = state.clone();
+    let mut cursor = 0;
+    while let Some((packed, ())) = seen.get_index(cursor) {
+        cur.unpack_from(packed);
+        let base = states.len();
+        states.resize(base + targets.len(), 0);
+        for board_id in board_ids.clone() {
+            for (pos, cell) in cur.board_cells(board_id) {
+                let code = match cell {
+                    _ if pos == cur.player.pos && board_id == cur.player.board_id => 4,
+                    Cell::Empty => 1,
+                    Cell::Wall => 2,
+                    Cell::Box => 3,
+                    Cell::Board(id) => 5 + id as u8,
+                };
+                states[base + offset(board_id, (pos.0 as usize, pos.1 as usize))] = code;
+            }
+        }
+
+        let success = cur.is_success_on(&game.config);
+        is_success.push(success);
+        if !success {
+            for dir in Direction::ALL {
+                next.clone_from(&cur);
+                if next.go(dir).is_err() {
+                    continue;
+                }
+                let (idx, _) = seen.insert_full(next.pack(), ());
+                ensure!(
+                    seen.len() <= options.max_states,
+                    "More than {} states are reachable",
+                    options.max_states,
+                );
+                transitions.push([cursor as i32, dir as i32, idx as i32]);
+            }
+        }
+        cursor += 1;
+    }
+
+    // Backward search from successful states over reversed transitions.
+    let mut predecessors = vec![Vec::new(); seen.len()];
+    for &[from, _, to] in &transitions {
+        predecessors[to as usize].push(from as usize);
+    }
+    let mut distances = vec![-1i32; seen.len()];
+    let mut queue = VecDeque::new();
+    for (i, &success) in is_success.iter().enumerate() {
+        if success {
+            distances[i] = 0;
+            queue.push_back(i);
+        }
+    }
+    while let Some(i) = queue.pop_front() {
+        for &j in &predecessors[i] {
+            if distances[j] < 0 {
+                distances[j] = distances[i] + 1;
+                queue.push_back(j);
+            }
+        }
+    }
+
+    Ok(Dataset {
+        shape,
+        states,
+        distances,
+        transitions,
+        targets,
+    })
+}
+
+/// Write an array in the NumPy `.npy` format version 1.0.
+fn write_npy(mut w: impl Write, descr: &str, shape: &[usize], data: &[u8]) -> io::Result<()> {
+    let shape = match shape {
+        [len] => format!("({len},)"),
+        _ => format!(
+            "({})",
+            shape
+                .iter()
+                .map(usize::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut header = format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': {shape}, }}");
+    // Pad the header with spaces, ended by a newline, to align the data by 64 bytes.
+    let len = 10 + header.len() + 1;
+    header.extend(std::iter::repeat_n(' ', len.next_multiple_of(64) - len));
+    header.push('\n');
+    w.write_all(b"\x93NUMPY\x01\x00")?;
+    w.write_all(&(header.len() as u16).to_le_bytes())?;
+    w.write_all(header.as_bytes())?;
+    w.write_all(data)?;
+    w.flush()
+}
+
+fn i32_bytes(values: impl Iterator<Item = i32>) -> Vec<u8> {
+    values.flat_map(i32::to_le_bytes).collect()
+}
+
+impl Dataset {
+    /// The count of states.
+    pub fn len(&self) -> usize {
+        self.distances.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.distances.is_empty()
+    }
+
+    /// Write arrays into the directory, which is created if missing. See the module
+    /// documentation for the format.
+    pub fn write_npy(&self, dir: &Path) -> Result<()> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create the directory {}", dir.display()))?;
+        let [boards, height, width] = self.shape;
+        let write = |name: &str, descr: &str, shape: &[usize], data: &[u8]| {
+            let path = dir.join(name);
+            let file = std::fs::File::create(&path)
+                .with_context(|| format!("Failed to create {}", path.display()))?;
+            write_npy(io::BufWriter::new(file), descr, shape, data)
+                .with_context(|| format!("Failed to write {}", path.display()))
+        };
+        let states_shape = [self.len(), boards, height, width];
+        write("states.npy", "|u1", &states_shape, &self.states)?;
+        let distances = self.distances.iter().copied();
+        write("distances.npy", "<i4", &[self.len()], &i32_bytes(distances))?;
+        let transitions = self.transitions.iter().flatten().copied();
+        let transitions_shape = [self.transitions.len(), 3];
+        write(
+            "transitions.npy",
+            "<i4",
+            &transitions_shape,
+            &i32_bytes(transitions),
+        )?;
+        write(
+            "targets.npy",
+            "|u1",
+            &[boards, height, width],
+            &self.targets,
+        )?;
+        Ok(())
+    }
+}
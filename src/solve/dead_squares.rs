@@ -0,0 +1,165 @@
+//! Static analysis of squares from which a plain box can never reach any box target.
+
+use crate::{
+    BoardId, BoardSet, Cell, Direction, Game, GlobalPos, Vec2, MAX_BOARD_SIZE, MAX_BOARD_WIDTH,
+};
+
+/// Squares from which a plain box can never be pushed onto any box target.
+///
+/// It is computed on static walls only, over-approximating possible box moves. Boxes may exit a
+/// referenced board next to any possible location of its board cell, and may enter or be eaten
+/// by any referenced board. Thus the result is exact for plain Sokoban levels, and conservative
+/// for nested ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeadSquares {
+    /// Indexed by `usize::from(GlobalPos)`.
+    dead: Box<[bool]>,
+}
+
+impl DeadSquares {
+    /// Check if a square is dead. Walls are never dead squares.
+    pub fn contains(&self, gpos: GlobalPos) -> bool {
+        self.dead[usize::from(gpos)]
+    }
+
+    /// Iterate over all dead squares.
+    pub fn iter(&self) -> impl Iterator<Item = GlobalPos> + '_ {
+        self.dead
+            .iter()
+            .enumerate()
+            .filter(|(_, &dead)| dead)
+            .map(|(i, _)| from_index(i))
+    }
+}
+
+/// Compute dead squares of a game.
+pub fn dead_squares(game: &Game) -> DeadSquares {
+    let live = BoxMoves::new(game).reaching(game.config.box_targets.iter().copied());
+    let mut dead = vec![false; GlobalPos::TO_USIZE_LIMIT].into_boxed_slice();
+    for board_id in 0..game.state.boards.len() {
+        let board_id = BoardId::try_from(board_id).unwrap();
+        for (pos, cell) in game.state.board_cells(board_id) {
+            let i = usize::from(GlobalPos { board_id, pos });
+            dead[i] = cell != Cell::Wall && !live[i];
+        }
+    }
+    DeadSquares { dead }
+}
+
+/// An over-approximated graph of possible moves of a plain box, on static walls only.
+pub(crate) struct BoxMoves {
+    /// Reversed edges, indexed by `usize::from(GlobalPos)` of destinations.
+    sources: Vec<Vec<GlobalPos>>,
+}
+
+impl BoxMoves {
+    pub(crate) fn new(game: &Game) -> Self {
+        let state = &game.state;
+        let mut referenced = BoardSet::default();
+        for board in state.boards.iter() {
+            for cell in board.grid.iter() {
+                if let Cell::Board(id) = *cell {
+                    referenced.insert(id);
+                }
+            }
+        }
+        let board_ids = || (0..state.boards.len()).map(|id| BoardId::try_from(id).unwrap());
+        let positions = || {
+            board_ids().flat_map(|board_id| {
+                state.board_cells(board_id).filter_map(move |(pos, cell)| {
+                    (cell != Cell::Wall).then_some(GlobalPos { board_id, pos })
+                })
+            })
+        };
+        let is_free = |gpos: GlobalPos| !state.is_wall(gpos);
+        let inner_entries = |dir: Direction| {
+            board_ids()
+                .filter(|&id| referenced.contains(id))
+                .map(move |board_id| GlobalPos {
+                    board_id,
+                    pos: state[board_id].inner_sibling_pos(dir),
+                })
+                .filter(move |&gpos| is_free(gpos))
+        };
+        // Locations next to a possible board cell, when exiting its board towards a direction.
+        let exit_targets = Direction::ALL.map(|dir| {
+            positions()
+                .filter(|gpos| {
+                    state[gpos.board_id]
+                        .sibling_pos(gpos.pos, dir.reversed())
+                        .is_some_and(|pos| is_free(GlobalPos { pos, ..*gpos }))
+                })
+                .collect::<Vec<_>>()
+        });
+
+        // Reversed edges of possible box moves.
+        let mut sources = vec![Vec::new(); GlobalPos::TO_USIZE_LIMIT];
+        for gpos in positions() {
+            let board = &state[gpos.board_id];
+            let is_referenced = referenced.contains(gpos.board_id);
+            for dir in Direction::ALL {
+                // The player must be able to stand behind, or enter from outside.
+                let pushable = match board.sibling_pos(gpos.pos, dir.reversed()) {
+                    Some(pos) => is_free(GlobalPos { pos, ..gpos }),
+                    None => is_referenced,
+                };
+                if !pushable {
+                    continue;
+                }
+                let mut add = |dest: GlobalPos| sources[usize::from(dest)].push(gpos);
+                let front_free = match board.sibling_pos(gpos.pos, dir) {
+                    Some(pos) => {
+                        let front = GlobalPos { pos, ..gpos };
+                        if is_free(front) {
+                            add(front);
+                        }
+                        is_free(front)
+                    }
+                    None if is_referenced => {
+                        exit_targets[dir as usize].iter().for_each(|&g| add(g));
+                        true
+                    }
+                    None => false,
+                };
+                // Enter a board cell in front.
+                if front_free {
+                    inner_entries(dir).for_each(&mut add);
+                }
+                // Be eaten by a board cell behind.
+                inner_entries(dir.reversed()).for_each(&mut add);
+            }
+        }
+
+        Self { sources }
+    }
+
+    /// Returns locations from which a box can possibly reach any of `targets`, indexed by
+    /// `usize::from(GlobalPos)`.
+    pub(crate) fn reaching(&self, targets: impl IntoIterator<Item = GlobalPos>) -> Vec<bool> {
+        let mut live = vec![false; GlobalPos::TO_USIZE_LIMIT];
+        let mut queue = Vec::new();
+        for gpos in targets {
+            if !std::mem::replace(&mut live[usize::from(gpos)], true) {
+                queue.push(gpos);
+            }
+        }
+        while let Some(gpos) = queue.pop() {
+            for &src in &self.sources[usize::from(gpos)] {
+                if !std::mem::replace(&mut live[usize::from(src)], true) {
+                    queue.push(src);
+                }
+            }
+        }
+        live
+    }
+}
+
+fn from_index(i: usize) -> GlobalPos {
+    GlobalPos {
+        board_id: BoardId::try_from(i / MAX_BOARD_SIZE).unwrap(),
+        pos: Vec2(
+            (i / MAX_BOARD_WIDTH % MAX_BOARD_WIDTH) as u8,
+            (i % MAX_BOARD_WIDTH) as u8,
+        ),
+    }
+}
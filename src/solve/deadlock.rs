@@ -0,0 +1,154 @@
+//! Detection of dead states, from which the game can never succeed.
+
+use super::dead_squares::{dead_squares, BoxMoves, DeadSquares};
+use super::UnsolvableReason;
+use crate::{BoardSet, Cell, Direction, Game, GlobalPos, State};
+
+/// Detects states with too few plain boxes which can still reach box targets.
+///
+/// A plain box is stuck if it is not on a box target, and either on a dead square, or frozen in
+/// a corner of a closed board.
+///
+/// A board is "closed" if it is not referenced by any board cell, thus nothing can exit or
+/// enter it, and its edges act as walls. In a closed board containing no board cells, boxes
+/// can never be eaten either, so a box blocked on both axes can never move again.
+///
+/// Boards cells are never stuck, and are never created or destroyed like plain boxes. The
+/// state is dead if the other box-like cells are fewer than box targets. The box target at the
+/// player target, if any, is always occupied by the player itself on success.
+///
+/// The detection is conservative: dead states are not always detected, but detected ones are
+/// always dead. It is cheap enough to check every move of interactive plays.
+pub struct DeadlockDetector {
+    /// Closed boards without board cells inside.
+    checked_boards: BoardSet,
+    dead_squares: DeadSquares,
+    box_targets: Box<[GlobalPos]>,
+    /// The count of box targets other than the player target.
+    needed: usize,
+}
+
+impl DeadlockDetector {
+    pub fn new(game: &Game) -> Self {
+        // Board cells only move around but are never created or destroyed, thus references
+        // and closed boards never change.
+        let mut referenced = BoardSet::default();
+        let mut has_board_cell = BoardSet::default();
+        for (board_id, board) in game.state.boards.iter().enumerate() {
+            for cell in board.grid.iter() {
+                if let Cell::Board(id) = *cell {
+                    referenced.insert(id);
+                    has_board_cell.insert(board_id.try_into().unwrap());
+                }
+            }
+        }
+        let mut checked_boards = BoardSet::default();
+        for board_id in 0..game.state.boards.len() {
+            let board_id = board_id.try_into().unwrap();
+            if !referenced.contains(board_id) && !has_board_cell.contains(board_id) {
+                checked_boards.insert(board_id);
+            }
+        }
+        Self {
+            checked_boards,
+            dead_squares: dead_squares(game),
+            box_targets: game.config.box_targets.clone(),
+            needed: needed_boxes(game),
+        }
+    }
+
+    /// Whether `state`, a state of the level passed to `DeadlockDetector::new`, is detected
+    /// to be dead.
+    pub fn is_dead(&self, state: &State) -> bool {
+        let mut movable = 0usize;
+        for (board_id, board) in state.boards.iter().enumerate() {
+            let board_id = board_id.try_into().unwrap();
+            for (pos, cell) in board.cells() {
+                let gpos = GlobalPos { board_id, pos };
+                match cell {
+                    Cell::Board(_) => movable += 1,
+                    Cell::Box if gpos != state.player && !self.is_stuck(state, gpos) => {
+                        movable += 1;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        movable < self.needed
+    }
+
+    fn is_stuck(&self, state: &State, gpos: GlobalPos) -> bool {
+        if self.box_targets.contains(&gpos) {
+            return false;
+        }
+        if self.dead_squares.contains(gpos) {
+            return true;
+        }
+        if !self.checked_boards.contains(gpos.board_id) {
+            return false;
+        }
+        let board = &state[gpos.board_id];
+        let blocked = |dir| match board.sibling_pos(gpos.pos, dir) {
+            Some(pos) => state.is_wall(GlobalPos { pos, ..gpos }),
+            // Closed board.
+            None => true,
+        };
+        let blocked_h = blocked(Direction::Left) || blocked(Direction::Right);
+        let blocked_v = blocked(Direction::Up) || blocked(Direction::Down);
+        blocked_h && blocked_v
+    }
+}
+
+fn needed_boxes(game: &Game) -> usize {
+    let player_target = game.config.player_target;
+    game.config
+        .box_targets
+        .iter()
+        .filter(|&&gpos| Some(gpos) != player_target)
+        .count()
+}
+
+/// Find the earliest detectable reason why a game is unsolvable, if any.
+pub(crate) fn diagnose(game: &Game) -> Option<UnsolvableReason> {
+    let state = &game.state;
+    let mut boxes = Vec::new();
+    let mut board_cnt = 0usize;
+    for (board_id, board) in state.boards.iter().enumerate() {
+        let board_id = board_id.try_into().unwrap();
+        for (pos, cell) in board.cells() {
+            let gpos = GlobalPos { board_id, pos };
+            match cell {
+                Cell::Board(_) => board_cnt += 1,
+                Cell::Box if gpos != state.player => boxes.push(gpos),
+                _ => {}
+            }
+        }
+    }
+
+    let targets = needed_boxes(game);
+    if boxes.len() + board_cnt < targets {
+        return Some(UnsolvableReason::NotEnoughBoxes {
+            boxes: boxes.len() + board_cnt,
+            targets,
+        });
+    }
+
+    // Board cells may move in other ways than plain boxes, thus are assumed to reach anywhere.
+    if board_cnt == 0 {
+        let moves = BoxMoves::new(game);
+        for (i, &target) in game.config.box_targets.iter().enumerate() {
+            if Some(target) == game.config.player_target {
+                continue;
+            }
+            let reaching = moves.reaching([target]);
+            if !boxes.iter().any(|&gpos| reaching[usize::from(gpos)]) {
+                return Some(UnsolvableReason::UnreachableBoxTarget(i));
+            }
+        }
+    }
+
+    if DeadlockDetector::new(game).is_dead(state) {
+        return Some(UnsolvableReason::InitialDeadlock);
+    }
+    None
+}
@@ -0,0 +1,120 @@
+//! Difficulty estimation of levels, for sorting level packs.
+
+use super::deadlock::DeadlockDetector;
+use super::{bfs_with_options, BucketIndexSet, SolveOutcome, SolverOptions};
+use crate::zobrist::splitmix64;
+use crate::{Direction, Game, GlobalPos, State};
+
+/// The count of random playouts sampling branching factors and deadlocks.
+const PLAYOUT_CNT: usize = 256;
+/// The maximal count of pushes of each playout.
+const MAX_PLAYOUT_PUSHES: usize = 64;
+
+/// Measurements of a level, combined into a comparable `DifficultyScore::score`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DifficultyScore {
+    /// The count of pushes of the push-optimal solution, or `None` if the level is unsolvable.
+    pub pushes: Option<usize>,
+    /// The count of moves of the push-optimal solution, or `None` if the level is unsolvable.
+    pub moves: Option<usize>,
+    /// The count of push-states stored by `bfs` until it finishes.
+    pub states: usize,
+    /// The average count of available pushes per push-state, sampled by random playouts.
+    pub branching_factor: f64,
+    /// The ratio of available pushes leading to dead states, sampled by random playouts.
+    pub deadlock_density: f64,
+    /// The combined score, higher for harder levels, or infinity if the level is unsolvable.
+    ///
+    /// It is `pushes * log2(1 + branching_factor) * (1 + deadlock_density) + log2(states)`,
+    /// that is, bits of choices along the solution weighted by traps, plus bits of the search
+    /// space.
+    pub score: f64,
+}
+
+/// Estimate the difficulty of a level, by solving it with `bfs` and sampling random playouts
+/// of pushes.
+///
+/// The result is deterministic. Like `bfs`, it may take much time and memory on large levels.
+pub fn difficulty(game: &Game) -> DifficultyScore {
+    let outcome = bfs_with_options(game.clone(), &SolverOptions::default(), |_| {});
+    let (solution, states) = match outcome {
+        SolveOutcome::Solved(solution) => {
+            let states = solution.peak_states;
+            (Some(solution), states)
+        }
+        SolveOutcome::Unsolvable(ret) => (None, ret.stats.states),
+        SolveOutcome::LimitReached(..) | SolveOutcome::Cancelled(_) => {
+            unreachable!("Solving without limits")
+        }
+        SolveOutcome::Unsupported(_) => unreachable!("BFS supports all levels"),
+    };
+
+    let deadlock = DeadlockDetector::new(game);
+    let mut rng = game.state.zobrist_hash();
+    let (mut expanded, mut pushes, mut dead) = (0usize, 0usize, 0usize);
+    for _ in 0..PLAYOUT_CNT {
+        let mut state = game.state.clone();
+        for _ in 0..MAX_PLAYOUT_PUSHES {
+            if state.is_success_on(&game.config) {
+                break;
+            }
+            let succ = push_successors(&state);
+            let cnt = succ.len();
+            let alive = succ
+                .into_iter()
+                .filter(|next| !deadlock.is_dead(next))
+                .collect::<Vec<_>>();
+            expanded += 1;
+            pushes += cnt;
+            dead += cnt - alive.len();
+            if alive.is_empty() {
+                break;
+            }
+            rng = splitmix64(rng);
+            state = alive[(rng % alive.len() as u64) as usize].clone();
+        }
+    }
+
+    let ratio = |a: usize, b: usize| if b == 0 { 0.0 } else { a as f64 / b as f64 };
+    let branching_factor = ratio(pushes, expanded);
+    let deadlock_density = ratio(dead, pushes);
+    let score = match &solution {
+        Some(solution) => {
+            solution.pushes as f64 * (1.0 + branching_factor).log2() * (1.0 + deadlock_density)
+                + (states as f64).log2()
+        }
+        None => f64::INFINITY,
+    };
+    DifficultyScore {
+        pushes: solution.as_ref().map(|s| s.pushes),
+        moves: solution.as_ref().map(|s| s.moves.len()),
+        states,
+        branching_factor,
+        deadlock_density,
+        score,
+    }
+}
+
+/// All states right after a push from any trivially reachable location.
+fn push_successors(state: &State) -> Vec<State> {
+    let mut ret = Vec::new();
+    let mut cur = state.clone();
+    let mut trivial_visited = BucketIndexSet::<GlobalPos, { GlobalPos::TO_USIZE_LIMIT }>::new();
+    trivial_visited.try_insert(state.player);
+    let mut small_cursor = 0;
+    while small_cursor < trivial_visited.len() {
+        let gpos = trivial_visited[small_cursor];
+        for dir in Direction::ALL {
+            cur.set_player(gpos);
+            let Ok(do_pushed) = cur.go(dir) else { continue };
+            if !do_pushed {
+                trivial_visited.try_insert(cur.player);
+                continue;
+            }
+            ret.push(cur.clone());
+            cur.clone_from(state);
+        }
+        small_cursor += 1;
+    }
+    ret
+}
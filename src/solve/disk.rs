@@ -0,0 +1,238 @@
+//! Breadth-first search with the visited set stored on disk.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use super::options::LimitChecker;
+use super::{
+    resolve_small_steps, BucketIndexSet, Progress, Solution, SolveOutcome, SolverOptions,
+    Unsolvability,
+};
+use crate::pack::{read_gpos, write_gpos};
+use crate::{Game, GlobalPos, PackedState};
+
+/// The initial count of slots of the on-disk hash index.
+const INIT_SLOT_CNT: u64 = 1 << 16;
+
+/// An append-only log of states with their parents and precanonical locations, indexed by an
+/// on-disk open-addressing hash table.
+///
+/// The log has fixed-size records, so states are addressed by their insertion order like
+/// `IndexMap`. The index file stores `record index + 1` in each slot, or zero for empty slots.
+struct DiskTable {
+    log: File,
+    index: File,
+    cells_len: usize,
+    len: u64,
+    slot_cnt: u64,
+}
+
+impl DiskTable {
+    fn create(dir: &Path, cells_len: usize) -> io::Result<Self> {
+        let open = |name: &str| {
+            OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(dir.join(name))
+        };
+        let mut this = Self {
+            log: open("states.bin")?,
+            index: open("index.bin")?,
+            cells_len,
+            len: 0,
+            slot_cnt: 0,
+        };
+        this.resize_index(INIT_SLOT_CNT)?;
+        Ok(this)
+    }
+
+    fn record_size(&self) -> u64 {
+        // Player, cells, parent, precanonical location.
+        (3 + self.cells_len + 8 + 3) as u64
+    }
+
+    fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    fn get(&mut self, idx: usize) -> io::Result<(PackedState, (usize, GlobalPos))> {
+        self.log
+            .seek(SeekFrom::Start(idx as u64 * self.record_size()))?;
+        let mut r = io::BufReader::with_capacity(self.record_size() as usize, &self.log);
+        let packed = PackedState::read_from(&mut r, self.cells_len)?;
+        let mut buf = [0u8; 8];
+        r.read_exact(&mut buf)?;
+        let loc = read_gpos(&mut r)?;
+        Ok((packed, (u64::from_le_bytes(buf) as usize, loc)))
+    }
+
+    fn read_slot(&mut self, slot: u64) -> io::Result<u64> {
+        let mut buf = [0u8; 8];
+        self.index.seek(SeekFrom::Start(slot * 8))?;
+        self.index.read_exact(&mut buf)?;
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    fn write_slot(&mut self, slot: u64, value: u64) -> io::Result<()> {
+        self.index.seek(SeekFrom::Start(slot * 8))?;
+        self.index.write_all(&value.to_le_bytes())
+    }
+
+    /// Find the slot of a state, returns the slot and the record index if it exists.
+    fn find(&mut self, packed: &PackedState) -> io::Result<(u64, Option<usize>)> {
+        let mut slot = packed.stable_hash() % self.slot_cnt;
+        loop {
+            match self.read_slot(slot)? {
+                0 => return Ok((slot, None)),
+                v => {
+                    let idx = (v - 1) as usize;
+                    if self.get(idx)?.0 == *packed {
+                        return Ok((slot, Some(idx)));
+                    }
+                }
+            }
+            slot = (slot + 1) % self.slot_cnt;
+        }
+    }
+
+    /// Insert a state if it does not exist, returns if it is newly inserted.
+    fn insert(&mut self, packed: &PackedState, parent: usize, loc: GlobalPos) -> io::Result<bool> {
+        if (self.len + 1) * 2 > self.slot_cnt {
+            self.resize_index(self.slot_cnt * 2)?;
+        }
+        let (slot, existing) = self.find(packed)?;
+        if existing.is_some() {
+            return Ok(false);
+        }
+
+        let mut record = Vec::with_capacity(self.record_size() as usize);
+        packed.write_to(&mut record)?;
+        record.extend_from_slice(&(parent as u64).to_le_bytes());
+        write_gpos(&mut record, loc)?;
+        self.log
+            .seek(SeekFrom::Start(self.len * self.record_size()))?;
+        self.log.write_all(&record)?;
+        self.len += 1;
+        self.write_slot(slot, self.len)?;
+        Ok(true)
+    }
+
+    /// Rebuild the index with a new count of slots.
+    fn resize_index(&mut self, slot_cnt: u64) -> io::Result<()> {
+        self.slot_cnt = slot_cnt;
+        self.index.set_len(0)?;
+        self.index.set_len(slot_cnt * 8)?;
+        for idx in 0..self.len {
+            let (packed, _) = self.get(idx as usize)?;
+            let (slot, _) = self.find(&packed)?;
+            self.write_slot(slot, idx + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// Breadth-first search like `bfs`, but storing visited states in files under `dir`, which
+/// trades speed for finishing searches that exceed the memory.
+///
+/// Existing files `states.bin` and `index.bin` in `dir` are overwritten.
+pub fn bfs_on_disk(
+    game: Game,
+    dir: &Path,
+    options: &SolverOptions,
+    mut on_progress: impl FnMut(&Progress),
+) -> io::Result<SolveOutcome> {
+    let template = game.state.clone();
+    let init_packed = game.state.pack();
+    let mut table = DiskTable::create(dir, init_packed.cells_len())?;
+    // States are on disk. Only the in-memory buffers count.
+    let limits = LimitChecker::new(options, 0);
+    table.insert(&init_packed, !0usize, game.state.player)?; // Sentinel.
+
+    let mut trivial_visited = BucketIndexSet::<GlobalPos, { GlobalPos::TO_USIZE_LIMIT }>::new();
+    let mut progress = Progress::default();
+    let mut depth_end = 1;
+    let mut big_cursor = 0;
+    let final_state = 'bfs: loop {
+        if big_cursor >= table.len() {
+            return Ok(SolveOutcome::Unsolvable(Unsolvability::new(
+                &game,
+                limits.stats(big_cursor, table.len()),
+            )));
+        }
+        if let Err(outcome) = limits.check(big_cursor, table.len()) {
+            return Ok(outcome);
+        }
+        if big_cursor >= depth_end {
+            progress.depth += 1;
+            depth_end = table.len();
+        }
+        progress.expanded = big_cursor;
+
+        let (init_packed, _) = table.get(big_cursor)?;
+        let mut state = template.clone();
+        state.unpack_from(&init_packed);
+        trivial_visited.clear();
+        trivial_visited.try_insert(state.player);
+
+        let mut small_cursor = 0;
+        while small_cursor < trivial_visited.len() {
+            let gpos = trivial_visited[small_cursor];
+
+            for dir in options.move_order(init_packed.stable_hash(), gpos) {
+                progress.steps += 1;
+                progress.frontier = table.len() - big_cursor;
+                on_progress(&progress);
+
+                state.set_player(gpos);
+                let Ok(do_pushed) = state.go(dir) else { continue };
+
+                if state.is_success_on(&game.config) {
+                    break 'bfs state;
+                }
+
+                if !do_pushed {
+                    trivial_visited.try_insert(state.player);
+                    continue;
+                }
+
+                let precanonical_loc = state.player;
+                let canonical_loc = state.trivially_reachable_locations().min().unwrap();
+                state.set_player(canonical_loc);
+                progress.generated += 1;
+                if !table.insert(&state.pack(), big_cursor, precanonical_loc)? {
+                    progress.duplicates += 1;
+                }
+
+                // The state now cannot be reused.
+                state.unpack_from(&init_packed);
+            }
+            small_cursor += 1;
+        }
+        big_cursor += 1;
+    };
+
+    // Reconstruct big steps like `reconstruct_big_steps`, reading states from disk.
+    let mut states = vec![final_state];
+    let mut parent = big_cursor;
+    while parent != !0usize {
+        let (packed, (grandparent, precanonical_loc)) = table.get(parent)?;
+        let mut state = template.clone();
+        state.unpack_from(&packed);
+        state.set_player(precanonical_loc);
+        states.push(state);
+        parent = grandparent;
+    }
+    states.reverse();
+
+    let stats = limits.stats(big_cursor + 1, table.len());
+    let moves = resolve_small_steps(&states);
+    Ok(SolveOutcome::Solved(Solution::new(
+        &states[0],
+        moves,
+        stats,
+        options.record_states,
+    )))
+}
@@ -0,0 +1,88 @@
+//! Exhaustive exploration of the state space.
+
+use super::deadlock::DeadlockDetector;
+use super::{BucketIndexSet, IndexMap};
+use crate::{Direction, Game, GlobalPos, PackedState};
+
+/// Statistics of all reachable push-states of a game.
+///
+/// Push-states are states right after each push, with the player canonicalized to the minimal
+/// trivially reachable location, like the visited states of `bfs`. Symmetric states are not
+/// merged.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ExplorationReport {
+    /// The count of reachable push-states, including the initial state.
+    pub states: usize,
+    /// The count of push-states by their minimal count of pushes from the initial state.
+    pub depth_histogram: Vec<usize>,
+    /// The count of push-states from which the game succeeds without any more push.
+    pub winning: usize,
+    /// The count of push-states detected as dead, from which the game can never succeed.
+    /// Detection is incomplete, thus some non-winning states may not be counted.
+    pub dead: usize,
+}
+
+impl ExplorationReport {
+    /// Returns if the game is solvable. If not, the exploration is a proof of unsolvability.
+    pub fn is_solvable(&self) -> bool {
+        self.winning != 0
+    }
+
+    /// The maximal count of pushes to reach any push-state.
+    pub fn max_depth(&self) -> usize {
+        self.depth_histogram.len() - 1
+    }
+}
+
+/// Exhaustively enumerate all reachable push-states of a game.
+///
+/// Unlike solvers, it neither stops at successful states nor prunes dead states, thus it may
+/// take much more time and memory on large levels.
+pub fn explore(game: Game) -> ExplorationReport {
+    let deadlock = DeadlockDetector::new(&game);
+    let template = &game.state;
+    let mut state_depth = IndexMap::<PackedState, usize>::default();
+    state_depth.insert(game.state.pack(), 0);
+    let mut report = ExplorationReport::default();
+    let mut trivial_visited = BucketIndexSet::<GlobalPos, { GlobalPos::TO_USIZE_LIMIT }>::new();
+
+    let mut big_cursor = 0;
+    while let Some((packed, &depth)) = state_depth.get_index(big_cursor) {
+        if report.depth_histogram.len() <= depth {
+            report.depth_histogram.push(0);
+        }
+        report.depth_histogram[depth] += 1;
+
+        let mut init_state = template.clone();
+        init_state.unpack_from(packed);
+        report.dead += deadlock.is_dead(&init_state) as usize;
+        let mut is_winning = init_state.is_success_on(&game.config);
+
+        let mut state = init_state.clone();
+        trivial_visited.clear();
+        trivial_visited.try_insert(state.player);
+        let mut small_cursor = 0;
+        while small_cursor < trivial_visited.len() {
+            let gpos = trivial_visited[small_cursor];
+            for dir in Direction::ALL {
+                state.set_player(gpos);
+                let Ok(do_pushed) = state.go(dir) else { continue };
+                if !do_pushed {
+                    is_winning |= state.is_success_on(&game.config);
+                    trivial_visited.try_insert(state.player);
+                    continue;
+                }
+                let canonical_loc = state.trivially_reachable_locations().min().unwrap();
+                state.set_player(canonical_loc);
+                state_depth.entry(state.pack()).or_insert(depth + 1);
+                state.clone_from(&init_state);
+            }
+            small_cursor += 1;
+        }
+        report.winning += is_winning as usize;
+        big_cursor += 1;
+    }
+
+    report.states = state_depth.len();
+    report
+}
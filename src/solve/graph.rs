@@ -0,0 +1,93 @@
+//! Dumping the explored state graph of `BfsSearch`, for visualization.
+//!
+//! Nodes are canonical push-states, indexed by their discovery order, and edges are pushes
+//! recorded when `SolverOptions::record_graph` is set.
+
+use std::io::{self, Write};
+
+use super::BfsSearch;
+use crate::Direction;
+
+fn fmt_direction(dir: Direction) -> &'static str {
+    match dir {
+        Direction::Right => "R",
+        Direction::Down => "D",
+        Direction::Left => "L",
+        Direction::Up => "U",
+    }
+}
+
+/// Escape a string for both DOT and JSON string literals.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+impl BfsSearch {
+    /// Rendered states of all nodes with their depths, in discovery order.
+    fn graph_nodes(&self) -> impl Iterator<Item = (usize, String)> + '_ {
+        let mut depths = Vec::with_capacity(self.state_parent.len());
+        let mut state = self.game.state.clone();
+        (0..self.state_parent.len()).map(move |i| {
+            let (parent, _) = self.state_parent.node(i);
+            let depth = depths.get(parent).map_or(0, |d| d + 1);
+            depths.push(depth);
+            self.state_parent.unpack_into(i, &mut state);
+            (depth, state.to_string().trim_end().to_owned())
+        })
+    }
+
+    /// Deduplicated recorded edges, sorted by their parents.
+    fn graph_edges(&self) -> Vec<(usize, usize, Direction)> {
+        let mut edges = self.graph_edges.clone();
+        edges.sort_by_key(|&(from, to, dir)| (from, to, dir as u8));
+        edges.dedup();
+        edges
+    }
+
+    /// Write the explored graph in Graphviz DOT format.
+    pub fn write_dot(&self, mut w: impl Write) -> io::Result<()> {
+        writeln!(w, "digraph {{")?;
+        writeln!(w, "  node [shape=box, fontname=monospace];")?;
+        for (i, (depth, text)) in self.graph_nodes().enumerate() {
+            // Left-justify lines.
+            let label = escape(&text).replace("\\n", "\\l") + "\\l";
+            writeln!(w, "  {i} [label=\"{label}\", depth={depth}];")?;
+        }
+        for (from, to, dir) in self.graph_edges() {
+            writeln!(w, "  {from} -> {to} [label=\"{}\"];", fmt_direction(dir))?;
+        }
+        writeln!(w, "}}")?;
+        w.flush()
+    }
+
+    /// Write the explored graph in JSON, with nodes and their adjacency lists in the same order.
+    pub fn write_json(&self, mut w: impl Write) -> io::Result<()> {
+        let edges = self.graph_edges();
+        write!(w, "{{\"nodes\":[")?;
+        for (i, (depth, text)) in self.graph_nodes().enumerate() {
+            let sep = if i == 0 { "" } else { "," };
+            let state = escape(&text);
+            write!(w, "{sep}{{\"depth\":{depth},\"state\":\"{state}\"}}")?;
+        }
+        write!(w, "],\"adjacency\":[")?;
+        let mut edges = edges.iter().peekable();
+        for from in 0..self.state_parent.len() {
+            write!(w, "{}[", if from == 0 { "" } else { "," })?;
+            let mut first = true;
+            while let Some((_, to, dir)) = edges.next_if(|&&(f, _, _)| f == from) {
+                let sep = if first { "" } else { "," };
+                write!(
+                    w,
+                    "{sep}{{\"to\":{to},\"dir\":\"{}\"}}",
+                    fmt_direction(*dir)
+                )?;
+                first = false;
+            }
+            write!(w, "]")?;
+        }
+        writeln!(w, "]}}")?;
+        w.flush()
+    }
+}
@@ -0,0 +1,281 @@
+//! Lower bounds of remaining pushes, for informed searches.
+
+use std::collections::VecDeque;
+
+use crate::{BoardSet, Cell, Direction, Game, GlobalPos, State};
+
+/// The distance of unreachable positions.
+pub(crate) const INF: u32 = u32::MAX;
+
+/// An admissible estimation of the remaining pushes from a state to success.
+///
+/// It is the minimum-cost matching from box-like cells to box targets, divided by the maximum
+/// count of boxes a single push can move, but no less than the distance of any single target
+/// to its nearest box.
+pub(crate) struct Heuristic {
+    /// `dists[i][usize::from(gpos)]` is a lower bound of pushes to move a box from `gpos` to
+    /// the `i`-th box target.
+    dists: Box<[Box<[u32]>]>,
+    /// For each box target, whether the player is allowed to fill it at success.
+    player_fills: Box<[bool]>,
+    max_chain_len: u32,
+}
+
+impl Heuristic {
+    pub(crate) fn new(game: &Game) -> Self {
+        let state = &game.state;
+        let box_cnt = state
+            .boards
+            .iter()
+            .flat_map(|board| board.grid.iter())
+            .filter(|cell| cell.is_box_like())
+            .count();
+        // If there are no boards as boxes, boxes never leave their boards and walls are
+        // the only obstacles. Otherwise, a chain of a single push can go through boards.
+        let mut referenced = BoardSet::default();
+        for cell in state.boards.iter().flat_map(|board| board.grid.iter()) {
+            if let Cell::Board(board_id) = *cell {
+                referenced.insert(board_id);
+            }
+        }
+        let max_board_width = state
+            .boards
+            .iter()
+            .map(|board| board.height.max(board.width) as usize)
+            .max()
+            .unwrap_or(1);
+        let max_chain_len = if referenced == BoardSet::default() {
+            box_cnt.min(max_board_width)
+        } else {
+            box_cnt
+        };
+
+        let exit_dist = exit_distances(state, referenced);
+        // Entering or being eaten puts a box on an inner sibling of any referenced board.
+        let entries = state
+            .boards
+            .iter()
+            .enumerate()
+            .flat_map(|(board_id, board)| {
+                let board_id = board_id.try_into().unwrap();
+                Direction::ALL.map(|dir| GlobalPos {
+                    board_id,
+                    pos: board.inner_sibling_pos(dir),
+                })
+            })
+            .filter(|&gpos| referenced.contains(gpos.board_id) && !state.is_wall(gpos))
+            .collect::<Vec<_>>();
+
+        let dists = game
+            .config
+            .box_targets
+            .iter()
+            .map(|&target| {
+                let mut dist = vec![INF; GlobalPos::TO_USIZE_LIMIT].into_boxed_slice();
+                bfs_in_board(state, target, &mut dist);
+                // Exiting boards, possibly multiple levels at once, can land a box next to
+                // any board reference, which may be pushed anywhere beforehand.
+                for (d, &exit_d) in dist.iter_mut().zip(exit_dist.iter()) {
+                    *d = (*d).min(exit_d);
+                }
+                let enter_d = entries
+                    .iter()
+                    .map(|&gpos| dist[usize::from(gpos)].saturating_add(1))
+                    .min()
+                    .unwrap_or(INF);
+                for (board_id, board) in state.boards.iter().enumerate() {
+                    let board_id = board_id.try_into().unwrap();
+                    for (pos, _) in board.cells() {
+                        let gpos = GlobalPos { board_id, pos };
+                        if !state.is_wall(gpos) {
+                            let d = &mut dist[usize::from(gpos)];
+                            *d = (*d).min(enter_d);
+                        }
+                    }
+                }
+                dist
+            })
+            .collect();
+        let player_fills = game
+            .config
+            .box_targets
+            .iter()
+            .map(|&target| game.config.player_target == Some(target))
+            .collect();
+
+        Self {
+            dists,
+            player_fills,
+            max_chain_len: max_chain_len.max(1) as u32,
+        }
+    }
+
+    /// Estimate the remaining pushes of a state, or `None` if it is unsolvable.
+    pub(crate) fn estimate(&self, state: &State) -> Option<u32> {
+        if self.dists.is_empty() {
+            return Some(0);
+        }
+        let sources = state
+            .boards
+            .iter()
+            .enumerate()
+            .flat_map(|(board_id, board)| {
+                let board_id = board_id.try_into().unwrap();
+                board
+                    .cells()
+                    .filter(|(_, cell)| cell.is_box_like())
+                    .map(move |(pos, _)| GlobalPos { board_id, pos })
+            })
+            .collect::<Vec<_>>();
+        if sources.len() < self.dists.len() {
+            return None;
+        }
+        let cost = |i: usize, j: usize| {
+            // The player moves for free, but only fills its own target.
+            if sources[j] == state.player {
+                if self.player_fills[i] {
+                    0
+                } else {
+                    INF
+                }
+            } else {
+                self.dists[i][usize::from(sources[j])]
+            }
+        };
+        let total = min_cost_matching(self.dists.len(), sources.len(), cost)?;
+        // Each box moves at most one step of `dists` per push.
+        let farthest = (0..self.dists.len())
+            .map(|i| (0..sources.len()).map(|j| cost(i, j)).min().unwrap_or(INF))
+            .max()
+            .unwrap_or(0);
+        Some(total.div_ceil(self.max_chain_len).max(farthest))
+    }
+}
+
+/// Breadth-first search inside the board of `target`, ignoring everything but walls.
+fn bfs_in_board(state: &State, target: GlobalPos, dist: &mut [u32]) {
+    let board = &state[target.board_id];
+    let mut queue = VecDeque::from([target.pos]);
+    dist[usize::from(target)] = 0;
+    while let Some(pos) = queue.pop_front() {
+        let d = dist[usize::from(GlobalPos { pos, ..target })];
+        for dir in Direction::ALL {
+            let Some(new_pos) = board.sibling_pos(pos, dir) else { continue };
+            let new_gpos = GlobalPos {
+                pos: new_pos,
+                ..target
+            };
+            if !state.is_wall(new_gpos) && dist[usize::from(new_gpos)] == INF {
+                dist[usize::from(new_gpos)] = d + 1;
+                queue.push_back(new_pos);
+            }
+        }
+    }
+}
+
+/// Pushes to move a box out of its board, for boards referenced somewhere. Others can never be
+/// exited, since their references never appear.
+fn exit_distances(state: &State, referenced: BoardSet) -> Box<[u32]> {
+    let mut dist = vec![INF; GlobalPos::TO_USIZE_LIMIT].into_boxed_slice();
+    let mut queue = VecDeque::new();
+    for (board_id, board) in state.boards.iter().enumerate() {
+        let board_id = board_id.try_into().unwrap();
+        if !referenced.contains(board_id) {
+            continue;
+        }
+        for (pos, _) in board.cells() {
+            let gpos = GlobalPos { board_id, pos };
+            let on_edge = Direction::ALL
+                .into_iter()
+                .any(|dir| board.sibling_pos(pos, dir).is_none());
+            if on_edge && !state.is_wall(gpos) {
+                dist[usize::from(gpos)] = 1;
+                queue.push_back(gpos);
+            }
+        }
+    }
+    while let Some(gpos) = queue.pop_front() {
+        let d = dist[usize::from(gpos)];
+        for dir in Direction::ALL {
+            let Some(pos) = state[gpos.board_id].sibling_pos(gpos.pos, dir) else { continue };
+            let new_gpos = GlobalPos { pos, ..gpos };
+            if !state.is_wall(new_gpos) && dist[usize::from(new_gpos)] == INF {
+                dist[usize::from(new_gpos)] = d + 1;
+                queue.push_back(new_gpos);
+            }
+        }
+    }
+    dist
+}
+
+/// Minimum-cost matching of `n` rows into distinct columns of `m` columns, where `n <= m`.
+/// Returns `None` if any row can only be matched with infinite cost.
+///
+/// This is the Hungarian algorithm with potentials, in `O(n^2 m)`.
+fn min_cost_matching(n: usize, m: usize, cost: impl Fn(usize, usize) -> u32) -> Option<u32> {
+    debug_assert!(n <= m);
+    // Use a large but non-overflowing weight for infinity.
+    const BIG: i64 = 1 << 40;
+    let cost = |i: usize, j: usize| match cost(i, j) {
+        INF => BIG,
+        c => c as i64,
+    };
+
+    // 1-based, with row/column 0 as the virtual sentinel.
+    let mut u = vec![0i64; n + 1];
+    let mut v = vec![0i64; m + 1];
+    let mut matched_row = vec![0usize; m + 1];
+    let mut way = vec![0usize; m + 1];
+    for i in 1..=n {
+        matched_row[0] = i;
+        let mut j0 = 0;
+        let mut minv = vec![i64::MAX; m + 1];
+        let mut used = vec![false; m + 1];
+        loop {
+            used[j0] = true;
+            let i0 = matched_row[j0];
+            let mut delta = i64::MAX;
+            let mut j1 = 0;
+            for j in 1..=m {
+                if used[j] {
+                    continue;
+                }
+                let cur = cost(i0 - 1, j - 1) - u[i0] - v[j];
+                if cur < minv[j] {
+                    minv[j] = cur;
+                    way[j] = j0;
+                }
+                if minv[j] < delta {
+                    delta = minv[j];
+                    j1 = j;
+                }
+            }
+            for j in 0..=m {
+                if used[j] {
+                    u[matched_row[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    minv[j] -= delta;
+                }
+            }
+            j0 = j1;
+            if matched_row[j0] == 0 {
+                break;
+            }
+        }
+        loop {
+            let j1 = way[j0];
+            matched_row[j0] = matched_row[j1];
+            j0 = j1;
+            if j0 == 0 {
+                break;
+            }
+        }
+    }
+
+    let total = (1..=m)
+        .filter(|&j| matched_row[j] != 0)
+        .map(|j| cost(matched_row[j] - 1, j - 1))
+        .sum::<i64>();
+    (total < BIG).then_some(total as u32)
+}
@@ -0,0 +1,42 @@
+//! Hints of the next move for players stuck in the middle of a level.
+
+use super::{bfs_with_options, SolveOutcome, SolverOptions};
+use crate::{Direction, Game, State};
+
+/// The result of `hint`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Hint {
+    /// The first move of a push-optimal solution from the state.
+    Move(Direction),
+    /// The state is already successful.
+    Solved,
+    /// There is no solution from the state, thus some moves should be undone.
+    Unsolvable,
+    /// Limits or cancellation of `SolverOptions` are reached before finding a solution.
+    Unknown,
+}
+
+/// Suggest the next move from `state`, an intermediate state of `game`, by solving with `bfs`.
+///
+/// Limits of `options` should be set for interactive use, since solving may take much time on
+/// large levels.
+pub fn hint(game: &Game, state: &State, options: &SolverOptions) -> Hint {
+    if state.is_success_on(&game.config) {
+        return Hint::Solved;
+    }
+    let game = Game {
+        config: game.config.clone(),
+        state: state.clone(),
+        metadata: game.metadata.clone(),
+    };
+    match bfs_with_options(game, options, |_| {}) {
+        SolveOutcome::Solved(solution) => match solution.moves.first() {
+            Some(&dir) => Hint::Move(dir),
+            None => Hint::Solved,
+        },
+        SolveOutcome::Unsolvable(_) => Hint::Unsolvable,
+        SolveOutcome::LimitReached(..)
+        | SolveOutcome::Cancelled(_)
+        | SolveOutcome::Unsupported(_) => Hint::Unknown,
+    }
+}
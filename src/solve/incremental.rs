@@ -0,0 +1,282 @@
+//! Incremental re-solving of levels after small edits of walls.
+
+use fxhash::FxHashMap;
+
+use super::deadlock::DeadlockDetector;
+use super::options::LimitChecker;
+use super::{
+    reconstruct_big_steps, replay_big_steps, resolve_small_steps, BucketIndexSet, IndexMap,
+    Progress, Solution, SolveOutcome, SolverOptions, Unsolvability,
+};
+use crate::{Cell, Config, Direction, Game, GlobalPos, PackedState, State};
+
+/// The cached result of expanding a push-state, which only depends on the state itself, walls
+/// of `deps`, and targets.
+struct Expansion {
+    /// Sorted `usize::from(GlobalPos)` of cells whose walls are read during the expansion, or
+    /// `None` if some move may cross boards, where it depends on walls of other boards.
+    deps: Option<Box<[u16]>>,
+    /// Exact states right after each push, in order.
+    pushes: Vec<PackedState>,
+    /// The first move reaching a successful state, which stops the expansion.
+    success: Option<(GlobalPos, Direction)>,
+    /// The count of attempted moves.
+    steps: u64,
+}
+
+/// A cache of expanded states for `bfs`, reusable across edits of walls.
+///
+/// After an edit, only expansions reading walls of edited cells are invalidated. Expansions
+/// with moves crossing boards are invalidated by any edit. Edits of targets or board sizes, or
+/// changes of `SolverOptions::seed`, invalidate everything.
+#[derive(Default)]
+pub struct SolveCache {
+    /// The last solved game and the seed of move order.
+    last: Option<(Game, Option<u64>)>,
+    expansions: FxHashMap<PackedState, Expansion>,
+    reused: usize,
+}
+
+impl SolveCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The count of cached expansions.
+    pub fn len(&self) -> usize {
+        self.expansions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.expansions.is_empty()
+    }
+
+    /// The count of expansions reused from the cache by the last `SolveCache::solve`.
+    pub fn reused(&self) -> usize {
+        self.reused
+    }
+
+    pub fn clear(&mut self) {
+        self.last = None;
+        self.expansions.clear();
+    }
+
+    /// Solve the game like `bfs_with_options`, reusing still valid expansions of previously
+    /// solved games, and caching new ones.
+    ///
+    /// The solution is the same as `bfs_with_options`.
+    pub fn solve(
+        &mut self,
+        game: &Game,
+        options: &SolverOptions,
+        mut on_progress: impl FnMut(&Progress),
+    ) -> SolveOutcome {
+        self.invalidate(game, options.seed);
+        self.last = Some((game.clone(), options.seed));
+        self.reused = 0;
+
+        let deadlock = DeadlockDetector::new(game);
+        let template = &game.state;
+        let init_packed = template.pack();
+        let limits = LimitChecker::new(
+            options,
+            init_packed.estimated_entry_size::<(usize, GlobalPos)>(),
+        );
+        let mut state_parent = IndexMap::<PackedState, (usize, GlobalPos)>::default();
+        state_parent.insert(init_packed, (!0usize, template.player)); // Sentinel.
+        let mut progress = Progress::default();
+        let mut depth_end = 1;
+        let mut state = template.clone();
+
+        let mut cursor = 0;
+        let final_state = loop {
+            if cursor >= state_parent.len() {
+                let stats = limits.stats(cursor, state_parent.len());
+                return SolveOutcome::Unsolvable(Unsolvability::new(game, stats));
+            }
+            if let Err(outcome) = limits.check(cursor, state_parent.len()) {
+                return outcome;
+            }
+            if cursor >= depth_end {
+                progress.depth += 1;
+                depth_end = state_parent.len();
+            }
+            progress.expanded = cursor;
+            progress.memory_bytes = limits.memory_bytes(state_parent.len());
+            progress.frontier = state_parent.len() - cursor;
+
+            let packed = state_parent.get_index(cursor).unwrap().0;
+            if self.expansions.contains_key(packed) {
+                self.reused += 1;
+            } else {
+                state.unpack_from(packed);
+                let expansion = expand(&mut state, &game.config, options);
+                self.expansions.insert(packed.clone(), expansion);
+            }
+            let expansion = &self.expansions[packed];
+            progress.steps += expansion.steps;
+            on_progress(&progress);
+
+            for pushed in &expansion.pushes {
+                state.unpack_from(pushed);
+                if deadlock.is_dead(&state) {
+                    continue;
+                }
+                let precanonical_loc = state.player;
+                state.canonicalize(&game.config);
+                progress.generated += 1;
+                let len = state_parent.len();
+                state_parent
+                    .entry(state.pack())
+                    .or_insert((cursor, precanonical_loc));
+                progress.duplicates += (len == state_parent.len()) as usize;
+            }
+            if let Some((gpos, dir)) = expansion.success {
+                state.unpack_from(state_parent.get_index(cursor).unwrap().0);
+                state.set_player(gpos);
+                state.go(dir).expect("Cached move must be valid");
+                break state;
+            }
+            cursor += 1;
+        };
+
+        let stats = limits.stats(cursor + 1, state_parent.len());
+        let states = if game.config.symmetries.is_empty() {
+            reconstruct_big_steps(
+                template,
+                |i| state_parent.get_index(i),
+                &final_state,
+                cursor,
+            )
+        } else {
+            let path = std::iter::successors(Some(cursor), |&i| {
+                let (_, &(parent, _)) = state_parent.get_index(i).unwrap();
+                (parent != !0usize).then_some(parent)
+            })
+            .map(|i| state_parent.get_index(i).unwrap().0)
+            .collect::<Vec<_>>();
+            replay_big_steps(template, &game.config, path.into_iter().rev())
+        };
+        SolveOutcome::Solved(Solution::new(
+            &states[0],
+            resolve_small_steps(&states),
+            stats,
+            options.record_states,
+        ))
+    }
+
+    /// Drop expansions which may change in `game`.
+    fn invalidate(&mut self, game: &Game, seed: Option<u64>) {
+        let Some((last, last_seed)) = &self.last else { return };
+        let (old, new) = (&last.state, &game.state);
+        let same_shape = old.boards.len() == new.boards.len()
+            && old
+                .boards
+                .iter()
+                .zip(new.boards.iter())
+                .all(|(a, b)| (a.height, a.width) == (b.height, b.width));
+        if !same_shape
+            || *last_seed != seed
+            || last.config.player_target != game.config.player_target
+            || last.config.box_targets != game.config.box_targets
+        {
+            self.expansions.clear();
+            return;
+        }
+
+        let mut edited = Vec::new();
+        for (board_id, board) in new.boards.iter().enumerate() {
+            let board_id = board_id.try_into().unwrap();
+            for (pos, _) in board.cells() {
+                let gpos = GlobalPos { board_id, pos };
+                if old.is_wall(gpos) != new.is_wall(gpos) {
+                    edited.push(usize::from(gpos) as u16);
+                }
+            }
+        }
+        if edited.is_empty() {
+            return;
+        }
+        let is_affected = |deps: &[u16]| edited.iter().any(|gpos| deps.binary_search(gpos).is_ok());
+        self.expansions
+            .retain(|_, expansion| expansion.deps.as_deref().is_some_and(|d| !is_affected(d)));
+    }
+}
+
+/// Expand a push-state like `BfsSearch`, recording walls it depends on.
+fn expand(state: &mut State, config: &Config, options: &SolverOptions) -> Expansion {
+    let init_state = state.clone();
+    let mut deps = Some(Vec::new());
+    let mut pushes = Vec::new();
+    let mut steps = 0;
+    let mut trivial_visited = BucketIndexSet::<GlobalPos, { GlobalPos::TO_USIZE_LIMIT }>::new();
+    trivial_visited.try_insert(state.player);
+
+    let mut small_cursor = 0;
+    while small_cursor < trivial_visited.len() {
+        let gpos = trivial_visited[small_cursor];
+        for dir in options.move_order(init_state.zobrist_hash(), gpos) {
+            steps += 1;
+            state.set_player(gpos);
+            record_move_deps(state, dir, &mut deps);
+            let Ok(do_pushed) = state.go(dir) else { continue };
+            if state.is_success_on(config) {
+                return Expansion {
+                    deps: finish_deps(deps),
+                    pushes,
+                    success: Some((gpos, dir)),
+                    steps,
+                };
+            }
+            if !do_pushed {
+                trivial_visited.try_insert(state.player);
+                continue;
+            }
+            pushes.push(state.pack());
+            state.clone_from(&init_state);
+        }
+        small_cursor += 1;
+    }
+    Expansion {
+        deps: finish_deps(deps),
+        pushes,
+        success: None,
+        steps,
+    }
+}
+
+/// Record cells read by moving the player towards `dir`, or set `deps` to `None` if the move
+/// may cross boards.
+///
+/// A move inside a board only reads the chain of boxes in front of the player, and the first
+/// empty cell or wall after them.
+fn record_move_deps(state: &State, dir: Direction, deps: &mut Option<Vec<u16>>) {
+    let Some(cells) = deps else { return };
+    let player = state.player;
+    let board = &state[player.board_id];
+    let mut pos = player.pos;
+    loop {
+        let Some(next) = board.sibling_pos(pos, dir) else {
+            *deps = None;
+            return;
+        };
+        pos = next;
+        let gpos = GlobalPos { pos, ..player };
+        cells.push(usize::from(gpos) as u16);
+        match state[gpos] {
+            Cell::Box => {}
+            Cell::Board(_) => {
+                *deps = None;
+                return;
+            }
+            Cell::Empty | Cell::Wall => return,
+        }
+    }
+}
+
+fn finish_deps(deps: Option<Vec<u16>>) -> Option<Box<[u16]>> {
+    let mut deps = deps?;
+    deps.sort_unstable();
+    deps.dedup();
+    Some(deps.into())
+}
@@ -0,0 +1,315 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::deadlock::diagnose;
+use super::Instant;
+use crate::zobrist::splitmix64;
+use crate::{Direction, Game, GlobalPos, State};
+
+/// Limits and tweaks of a search. `None` limits mean unlimited.
+///
+/// Solvers are deterministic: the same game and options always give the same outcome, on any
+/// platform, except for limits of duration and memory and cancellation.
+#[derive(Debug, Default, Clone)]
+pub struct SolverOptions {
+    /// The maximum count of expanded states.
+    pub max_nodes: Option<usize>,
+    /// The maximum wall time of the search.
+    pub max_duration: Option<Duration>,
+    /// The maximum estimated memory of stored states, in bytes.
+    pub max_memory_bytes: Option<usize>,
+    /// The search is cancelled once it is set to `true`, possibly from another thread.
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// Record all explored pushes of `BfsSearch`, to be dumped by `BfsSearch::write_dot` or
+    /// `BfsSearch::write_json`. Other solvers ignore it.
+    pub record_graph: bool,
+    /// Record intermediate states in `Solution::states`.
+    pub record_states: Option<RecordStates>,
+    /// The seed to break ties among equally good moves, by shuffling the order of moves tried
+    /// from each location. `None` always tries moves in the order of `Direction::ALL`.
+    pub seed: Option<u64>,
+}
+
+impl SolverOptions {
+    /// The order of moves to try from `gpos` in the state with Zobrist hash `hash`.
+    pub(crate) fn move_order(&self, hash: u64, gpos: GlobalPos) -> [Direction; 4] {
+        let mut dirs = Direction::ALL;
+        let Some(seed) = self.seed else { return dirs };
+        let mut x = splitmix64(seed ^ hash) ^ usize::from(gpos) as u64;
+        // Fisher-Yates shuffle.
+        for i in (1..dirs.len()).rev() {
+            x = splitmix64(x);
+            dirs.swap(i, (x % (i as u64 + 1)) as usize);
+        }
+        dirs
+    }
+}
+
+/// Which intermediate states to record in `Solution::states`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RecordStates {
+    /// The state after each move.
+    Moves,
+    /// The state after each move pushing something other than the player.
+    Pushes,
+}
+
+/// The kind of limit reached by a search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Limit {
+    Nodes,
+    Duration,
+    Memory,
+}
+
+/// Statistics of a finished or stopped search.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SearchStats {
+    /// The count of expanded states.
+    pub expanded: usize,
+    /// The count of stored (visited) states.
+    pub states: usize,
+    /// The estimated memory of stored states, in bytes.
+    pub memory_bytes: usize,
+    pub elapsed: Duration,
+}
+
+/// A solution with metadata of the search finding it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution {
+    pub moves: Vec<Direction>,
+    /// The count of moves which push something other than the player.
+    pub pushes: usize,
+    pub nodes_expanded: usize,
+    pub duration: Duration,
+    /// The count of stored states when the search finished.
+    pub peak_states: usize,
+    /// Whether the solution is known to be optimal in the solver's metric, which is pushes for
+    /// most solvers, or moves for `bfs_moves` and `all_optimal`.
+    pub is_optimal: bool,
+    /// States after each move or push if `SolverOptions::record_states` is set, or empty
+    /// otherwise. The initial state is not included.
+    pub states: Vec<State>,
+}
+
+impl Solution {
+    /// Create a solution for moves from the initial state, counting pushes and recording
+    /// states by replaying them.
+    pub(crate) fn new(
+        init: &State,
+        moves: Vec<Direction>,
+        stats: SearchStats,
+        record: Option<RecordStates>,
+    ) -> Self {
+        let mut state = init.clone();
+        let mut states = Vec::new();
+        let mut pushes = 0;
+        for &dir in &moves {
+            let do_pushed = state.go(dir).expect("Solution must be valid");
+            pushes += do_pushed as usize;
+            match record {
+                Some(RecordStates::Moves) => states.push(state.clone()),
+                Some(RecordStates::Pushes) if do_pushed => states.push(state.clone()),
+                Some(RecordStates::Pushes) | None => {}
+            }
+        }
+        Self {
+            moves,
+            pushes,
+            nodes_expanded: stats.expanded,
+            duration: stats.elapsed,
+            peak_states: stats.states,
+            is_optimal: true,
+            states,
+        }
+    }
+
+    pub fn moves(&self) -> &[Direction] {
+        &self.moves
+    }
+}
+
+/// Why a game is unsolvable, detected by static analysis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnsolvableReason {
+    /// There are fewer box-like cells than box targets, excluding the player and the player
+    /// target.
+    NotEnoughBoxes { boxes: usize, targets: usize },
+    /// No box can ever reach the box target of this index, in the order of the map.
+    UnreachableBoxTarget(usize),
+    /// Some box is already stuck in the initial state.
+    InitialDeadlock,
+}
+
+impl std::fmt::Display for UnsolvableReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotEnoughBoxes { boxes, targets } => {
+                write!(f, "only {boxes} boxes for {targets} box targets")
+            }
+            Self::UnreachableBoxTarget(i) => write!(f, "box target {i} is unreachable"),
+            Self::InitialDeadlock => "the initial state is dead".fmt(f),
+        }
+    }
+}
+
+/// The result of a search proving a game unsolvable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unsolvability {
+    /// Statistics of the search. `SearchStats::states` is the count of reachable states.
+    pub stats: SearchStats,
+    /// The earliest detectable reason, if any.
+    pub reason: Option<UnsolvableReason>,
+}
+
+impl Unsolvability {
+    pub(crate) fn new(game: &Game, stats: SearchStats) -> Self {
+        Self {
+            stats,
+            reason: diagnose(game),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SolveOutcome {
+    Solved(Solution),
+    /// The whole state space is searched without any solution.
+    Unsolvable(Unsolvability),
+    LimitReached(Limit, SearchStats),
+    /// Cancelled by `SolverOptions::cancel`.
+    Cancelled(SearchStats),
+    /// The level is out of the scope of the search, for the given reason.
+    Unsupported(&'static str),
+}
+
+impl SolveOutcome {
+    /// Get the solution, if any.
+    pub fn into_solution(self) -> Option<Solution> {
+        match self {
+            Self::Solved(solution) => Some(solution),
+            Self::Unsolvable(_)
+            | Self::LimitReached(..)
+            | Self::Cancelled(_)
+            | Self::Unsupported(_) => None,
+        }
+    }
+}
+
+/// Tracks limits of `SolverOptions` during a search.
+pub(crate) struct LimitChecker<'a> {
+    options: &'a SolverOptions,
+    start: Instant,
+    /// The estimated memory of each stored state.
+    bytes_per_state: usize,
+    /// The memory allocated regardless of the count of states.
+    fixed_bytes: usize,
+}
+
+impl<'a> LimitChecker<'a> {
+    pub(crate) fn new(options: &'a SolverOptions, bytes_per_state: usize) -> Self {
+        Self {
+            options,
+            start: Instant::now(),
+            bytes_per_state,
+            fixed_bytes: 0,
+        }
+    }
+
+    /// Count `bytes` more memory, which is allocated up front.
+    pub(crate) fn with_fixed_bytes(mut self, bytes: usize) -> Self {
+        self.fixed_bytes = bytes;
+        self
+    }
+
+    pub(crate) fn memory_bytes(&self, states: usize) -> usize {
+        self.fixed_bytes + states * self.bytes_per_state
+    }
+
+    pub(crate) fn stats(&self, expanded: usize, states: usize) -> SearchStats {
+        SearchStats {
+            expanded,
+            states,
+            memory_bytes: self.memory_bytes(states),
+            elapsed: self.start.elapsed(),
+        }
+    }
+
+    /// Check limits and cancellation before expanding a new state.
+    pub(crate) fn check(&self, expanded: usize, states: usize) -> Result<(), SolveOutcome> {
+        if let Some(cancel) = &self.options.cancel {
+            if cancel.load(Ordering::Relaxed) {
+                return Err(SolveOutcome::Cancelled(self.stats(expanded, states)));
+            }
+        }
+        let limit = if self.options.max_nodes.is_some_and(|max| expanded >= max) {
+            Limit::Nodes
+        } else if self
+            .options
+            .max_memory_bytes
+            .is_some_and(|max| self.memory_bytes(states) > max)
+        {
+            Limit::Memory
+        } else if self
+            .options
+            .max_duration
+            .is_some_and(|max| self.start.elapsed() > max)
+        {
+            Limit::Duration
+        } else {
+            return Ok(());
+        };
+        Err(SolveOutcome::LimitReached(
+            limit,
+            self.stats(expanded, states),
+        ))
+    }
+}
+
+/// Progress of a running search, reported on every attempted move.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// The count of attempted moves.
+    pub steps: u64,
+    /// The count of expanded states.
+    pub expanded: usize,
+    /// The count of discovered but not yet expanded states.
+    pub frontier: usize,
+    /// The depth of the currently expanding state, in the solver's own cost unit.
+    pub depth: usize,
+    /// The count of generated successor states, including duplicated ones.
+    pub generated: usize,
+    /// The count of generated successor states which are already visited.
+    pub duplicates: usize,
+    /// The estimated memory of stored states, in bytes.
+    pub memory_bytes: usize,
+}
+
+/// Counters of `BfsSearch` for performance analysis, accumulated over all runs since it is
+/// created or loaded.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Metrics {
+    /// The count of expanded states of each depth.
+    pub expansions_per_depth: Vec<usize>,
+    /// The count of generated states which are already visited.
+    pub dedup_hits: usize,
+    /// The count of fully expanded states by the size of their trivial-move closures, that is,
+    /// the count of locations the player can walk to without pushing.
+    pub closure_sizes: Vec<usize>,
+    /// The time spent on searching pushes, including reconstruction of pushes of the solution.
+    pub big_step_time: Duration,
+    /// The time spent on resolving walks between pushes of the solution.
+    pub small_step_time: Duration,
+}
+
+impl Progress {
+    /// The ratio of generated states which are already visited.
+    pub fn dedup_hit_rate(&self) -> f64 {
+        if self.generated == 0 {
+            0.0
+        } else {
+            self.duplicates as f64 / self.generated as f64
+        }
+    }
+}
@@ -0,0 +1,339 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use fxhash::FxHashSet;
+use rayon::prelude::*;
+
+use super::deadlock::DeadlockDetector;
+use super::options::LimitChecker;
+use super::table::StateTable;
+use super::{
+    reconstruct_big_steps, replay_big_steps, resolve_small_steps, BucketIndexSet, Progress,
+    SearchStats, Solution, SolveOutcome, SolverOptions, Unsolvability,
+};
+use crate::{Game, GlobalPos, PackedState, State};
+
+/// Bits of shard indices of the visited table. There should be much more shards than threads
+/// to reduce lock contention.
+const SHARD_BITS: u32 = 8;
+const SHARD_CNT: usize = 1 << SHARD_BITS;
+
+/// A shard of `ShardedTable`.
+struct Shard {
+    table: StateTable,
+    /// The length of `table` before the current depth.
+    depth_start: usize,
+}
+
+/// A concurrent table of visited states, sharded by the high bits of Zobrist hashes, since the
+/// low bits already choose slots inside each `StateTable`.
+///
+/// A state is indexed by its index inside its shard shifted by `SHARD_BITS`, plus the index of
+/// the shard. Parents are stored as such indices.
+struct ShardedTable {
+    shards: Box<[Mutex<Shard>]>,
+    len: AtomicUsize,
+}
+
+/// The result of inserting a state into `ShardedTable`.
+enum Inserted {
+    New(usize),
+    /// Already inserted during the current depth, at the index.
+    SameDepth(usize),
+    Visited,
+}
+
+impl ShardedTable {
+    fn new(cells_len: usize) -> Self {
+        Self {
+            shards: (0..SHARD_CNT)
+                .map(|_| {
+                    Mutex::new(Shard {
+                        table: StateTable::new(cells_len),
+                        depth_start: 0,
+                    })
+                })
+                .collect(),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    fn estimated_entry_size(&self) -> usize {
+        self.shards[0].lock().unwrap().table.estimated_entry_size()
+    }
+
+    /// Start a new depth. States inserted before are no longer `Inserted::SameDepth`.
+    fn next_depth(&mut self) {
+        for shard in self.shards.iter_mut() {
+            let shard = shard.get_mut().unwrap();
+            shard.depth_start = shard.table.len();
+        }
+    }
+
+    /// Insert `packed` if it is absent, with its parent index, or `!0usize` for roots.
+    fn insert(&self, packed: &PackedState, parent: usize, loc: GlobalPos) -> Inserted {
+        let shard_idx = (packed.stable_hash() >> (u64::BITS - SHARD_BITS)) as usize;
+        let mut shard = self.shards[shard_idx].lock().unwrap();
+        let (i, is_new) = shard.table.insert(packed, parent, loc);
+        let idx = i << SHARD_BITS | shard_idx;
+        if !is_new {
+            return if i >= shard.depth_start {
+                Inserted::SameDepth(idx)
+            } else {
+                Inserted::Visited
+            };
+        }
+        self.len.fetch_add(1, Ordering::Relaxed);
+        assert!(idx < StateTable::max_len(), "Too many states");
+        Inserted::New(idx)
+    }
+
+    /// Overwrite the parent index and precanonical location of a state.
+    fn set_node(&mut self, idx: usize, parent: usize, loc: GlobalPos) {
+        let shard = self.shards[idx % SHARD_CNT].get_mut().unwrap();
+        shard.table.set_node(idx >> SHARD_BITS, parent, loc);
+    }
+
+    fn packed(&self, idx: usize) -> PackedState {
+        let shard = self.shards[idx % SHARD_CNT].lock().unwrap();
+        shard.table.packed(idx >> SHARD_BITS)
+    }
+
+    /// Take tables out after the search, for lookups without locking.
+    fn into_tables(self) -> Vec<StateTable> {
+        self.shards
+            .into_vec()
+            .into_iter()
+            .map(|shard| shard.into_inner().unwrap().table)
+            .collect()
+    }
+}
+
+/// Progress counters shared by all threads.
+#[derive(Default)]
+struct Counters {
+    steps: AtomicU64,
+    generated: AtomicUsize,
+    duplicates: AtomicUsize,
+}
+
+/// A successor of the expanded state, not visited before the current depth.
+struct Successor {
+    idx: usize,
+    /// Whether it is inserted by this expansion, thus with this parent.
+    is_new: bool,
+    precanonical_loc: GlobalPos,
+}
+
+/// The outcome of expanding a single state.
+enum Expansion {
+    Success(State),
+    Successors(Vec<Successor>),
+    Stopped(SolveOutcome),
+}
+
+/// Parallel breadth-first search, expanding each depth of the frontier with rayon.
+///
+/// Successors are deduplicated concurrently in a sharded table while expanding. The returned
+/// solution has the same count of pushes as `bfs`, but may differ in moves. It is still
+/// deterministic regardless of thread scheduling, since states reached more than once in a
+/// depth are reassigned to their first parent in the order of the frontier after each depth.
+pub fn par_bfs(
+    game: Game,
+    options: &SolverOptions,
+    on_progress: impl Fn(&Progress) + Sync,
+) -> SolveOutcome {
+    match par_bfs_big_step(game, options, on_progress) {
+        Ok((states, stats)) => SolveOutcome::Solved(Solution::new(
+            &states[0],
+            resolve_small_steps(&states),
+            stats,
+            options.record_states,
+        )),
+        Err(outcome) => outcome,
+    }
+}
+
+fn par_bfs_big_step(
+    game: Game,
+    options: &SolverOptions,
+    on_progress: impl Fn(&Progress) + Sync,
+) -> Result<(Vec<State>, SearchStats), SolveOutcome> {
+    let template = game.state.clone();
+    let deadlock = DeadlockDetector::new(&game);
+    let init_packed = game.state.pack();
+    let mut visited = ShardedTable::new(init_packed.cells_len());
+    // Indices of the frontier are also stored, for at most two depths.
+    let limits = LimitChecker::new(
+        options,
+        visited.estimated_entry_size() + 2 * std::mem::size_of::<usize>(),
+    );
+    let Inserted::New(root) = visited.insert(&init_packed, !0usize, game.state.player) else {
+        unreachable!();
+    };
+
+    let counters = Counters::default();
+    let mut depth = 0;
+    let mut expanded = 0;
+    let mut frontier = vec![root];
+    let (final_state, parent) = loop {
+        if frontier.is_empty() {
+            return Err(SolveOutcome::Unsolvable(Unsolvability::new(
+                &game,
+                limits.stats(expanded, visited.len()),
+            )));
+        }
+        visited.next_depth();
+
+        let expansions = frontier
+            .par_iter()
+            .enumerate()
+            .map_init(
+                || {
+                    (
+                        template.clone(),
+                        BucketIndexSet::<GlobalPos, { GlobalPos::TO_USIZE_LIMIT }>::new(),
+                    )
+                },
+                |(state, trivial_visited), (i, &cursor)| {
+                    let stored = visited.len();
+                    if let Err(outcome) = limits.check(expanded + i, stored) {
+                        return (cursor, Expansion::Stopped(outcome));
+                    }
+                    let progress = Progress {
+                        expanded: expanded + i,
+                        frontier: frontier.len() - i,
+                        depth,
+                        memory_bytes: limits.memory_bytes(stored),
+                        ..Progress::default()
+                    };
+                    let expansion = expand(
+                        (&game, options, &deadlock),
+                        state,
+                        trivial_visited,
+                        (&visited.packed(cursor), cursor),
+                        &visited,
+                        (progress, &counters, &on_progress),
+                    );
+                    (cursor, expansion)
+                },
+            )
+            .collect::<Vec<_>>();
+        expanded += frontier.len();
+
+        let mut found = None;
+        let mut next_frontier = Vec::new();
+        let mut seen = FxHashSet::default();
+        for (cursor, expansion) in expansions {
+            match expansion {
+                Expansion::Success(state) => {
+                    found = Some((state, cursor));
+                    break;
+                }
+                Expansion::Successors(succ) => {
+                    for succ in succ {
+                        if !seen.insert(succ.idx) {
+                            continue;
+                        }
+                        // Another thread may have inserted it first from a later parent.
+                        if !succ.is_new {
+                            visited.set_node(succ.idx, cursor, succ.precanonical_loc);
+                        }
+                        next_frontier.push(succ.idx);
+                    }
+                }
+                Expansion::Stopped(outcome) => return Err(outcome),
+            }
+        }
+        if let Some(found) = found {
+            break found;
+        }
+        frontier = next_frontier;
+        depth += 1;
+    };
+
+    let stats = limits.stats(expanded, visited.len());
+    let tables = visited.into_tables();
+    let get_node = |i: usize| {
+        (i != !0usize).then(|| {
+            let table = &tables[i % SHARD_CNT];
+            (table.packed(i >> SHARD_BITS), table.node(i >> SHARD_BITS))
+        })
+    };
+    let states = if game.config.symmetries.is_empty() {
+        reconstruct_big_steps(&template, get_node, &final_state, parent)
+    } else {
+        // Stored states may be mirrored or permuted, thus precanonical locations are not
+        // meaningful. Replay pushes from the initial state instead.
+        let path = std::iter::successors(get_node(parent), |(_, (parent, _))| get_node(*parent))
+            .map(|(packed, _)| packed)
+            .collect::<Vec<_>>();
+        replay_big_steps(&template, &game.config, path.iter().rev())
+    };
+    Ok((states, stats))
+}
+
+fn expand(
+    (game, options, deadlock): (&Game, &SolverOptions, &DeadlockDetector),
+    state: &mut State,
+    trivial_visited: &mut BucketIndexSet<GlobalPos, { GlobalPos::TO_USIZE_LIMIT }>,
+    (packed, cursor): (&PackedState, usize),
+    visited: &ShardedTable,
+    (mut progress, counters, on_progress): (Progress, &Counters, &(impl Fn(&Progress) + Sync)),
+) -> Expansion {
+    let mut successors = Vec::new();
+    state.unpack_from(packed);
+    trivial_visited.clear();
+    trivial_visited.try_insert(state.player);
+
+    let mut small_cursor = 0;
+    while small_cursor < trivial_visited.len() {
+        let gpos = trivial_visited[small_cursor];
+
+        for dir in options.move_order(packed.stable_hash(), gpos) {
+            progress.steps = counters.steps.fetch_add(1, Ordering::Relaxed) + 1;
+            progress.generated = counters.generated.load(Ordering::Relaxed);
+            progress.duplicates = counters.duplicates.load(Ordering::Relaxed);
+            on_progress(&progress);
+
+            state.set_player(gpos);
+            let Ok(do_pushed) = state.go(dir) else { continue };
+
+            if state.is_success_on(&game.config) {
+                return Expansion::Success(state.clone());
+            }
+
+            if !do_pushed {
+                trivial_visited.try_insert(state.player);
+                continue;
+            }
+
+            // Dead states can never succeed, thus are dropped like in `BfsSearch`.
+            if !deadlock.is_dead(state) {
+                let precanonical_loc = state.player;
+                state.canonicalize(&game.config);
+                counters.generated.fetch_add(1, Ordering::Relaxed);
+                let inserted = visited.insert(&state.pack(), cursor, precanonical_loc);
+                if !matches!(inserted, Inserted::New(_)) {
+                    counters.duplicates.fetch_add(1, Ordering::Relaxed);
+                }
+                if let Inserted::New(idx) | Inserted::SameDepth(idx) = inserted {
+                    successors.push(Successor {
+                        idx,
+                        is_new: matches!(inserted, Inserted::New(_)),
+                        precanonical_loc,
+                    });
+                }
+            }
+
+            // The state now cannot be reused.
+            state.unpack_from(packed);
+        }
+        small_cursor += 1;
+    }
+    Expansion::Successors(successors)
+}
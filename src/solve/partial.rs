@@ -0,0 +1,57 @@
+//! Best-effort results of truncated searches.
+
+use super::heuristic::Heuristic;
+use super::{reconstruct_big_steps, replay_pushes, resolve_small_steps, BfsSearch};
+use crate::{Direction, State};
+
+/// An explored state with the moves reaching it from the initial state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartialSolution {
+    pub moves: Vec<Direction>,
+    /// The state after all moves.
+    pub state: State,
+    /// The lower bound of remaining pushes to success, by the heuristic of `astar`.
+    pub estimate: u32,
+}
+
+impl BfsSearch {
+    /// Find the discovered state closest to success by the heuristic of `astar`, preferring
+    /// shallower ones on ties, along with moves reaching it.
+    ///
+    /// This is useful after the search is stopped by limits, to get a promising prefix of
+    /// moves. Returns `None` if all discovered states are known to be unsolvable.
+    pub fn closest(&self) -> Option<PartialSolution> {
+        let heuristic = Heuristic::new(&self.game);
+        let template = &self.game.state;
+        let mut state = template.clone();
+        let table = &self.state_parent;
+        let (idx, estimate) = (0..table.len())
+            .filter_map(|i| {
+                table.unpack_into(i, &mut state);
+                Some((i, heuristic.estimate(&state)?))
+            })
+            .min_by_key(|&(i, estimate)| (estimate, i))?;
+
+        let states = if self.game.config.symmetries.is_empty() {
+            let (parent, loc) = table.node(idx);
+            table.unpack_into(idx, &mut state);
+            state.set_player(loc);
+            let get_node = |i| (i != !0usize).then(|| (table.packed(i), table.node(i)));
+            reconstruct_big_steps(template, get_node, &state, parent)
+        } else {
+            // See `BfsSearch::run_big_step`.
+            let path = std::iter::successors(Some(idx), |&i| {
+                let (parent, _) = table.node(i);
+                (parent != !0usize).then_some(parent)
+            })
+            .map(|i| table.packed(i))
+            .collect::<Vec<_>>();
+            replay_pushes(template, &self.game.config, path.iter().rev())
+        };
+        Some(PartialSolution {
+            moves: resolve_small_steps(&states),
+            state: states.last().unwrap().clone(),
+            estimate,
+        })
+    }
+}
@@ -0,0 +1,192 @@
+//! Retrograde move model for Sokoban levels: generating predecessors of states and the goal
+//! state, and the backward reachability analysis from the goal state.
+
+use std::sync::Arc;
+
+use super::options::LimitChecker;
+use super::{IndexMap, Progress, SolveOutcome, SolverOptions};
+use crate::{Cell, Config, Direction, Game, GlobalPos, PackedState, State};
+
+/// Generate the successful state of a game with the player at the player target, and all
+/// boxes on box targets.
+///
+/// The retrograde model only covers Sokoban levels, see `push_predecessors`, where all boxes
+/// are interchangeable and thus the goal state is unique. Returns `SolveOutcome::Unsupported`
+/// for levels with board cells, levels without player targets where the player may end
+/// anywhere, or levels with spare boxes.
+pub(crate) fn goal_state(game: &Game) -> Result<State, SolveOutcome> {
+    let state = &game.state;
+    let has_board_cell = state
+        .boards
+        .iter()
+        .any(|board| board.grid.iter().any(|cell| matches!(cell, Cell::Board(_))));
+    if has_board_cell {
+        return Err(SolveOutcome::Unsupported("board cells"));
+    }
+    let player_target = game
+        .config
+        .player_target
+        .ok_or(SolveOutcome::Unsupported("no player target"))?;
+    let box_cnt = state
+        .boards
+        .iter()
+        .map(|board| board.grid.iter().filter(|cell| cell.is_box_like()).count())
+        .sum::<usize>()
+        - state[state.player].is_box_like() as usize;
+    let slots = game
+        .config
+        .box_targets
+        .iter()
+        .copied()
+        .filter(|&gpos| gpos != player_target)
+        .collect::<Vec<_>>();
+    if box_cnt != slots.len() {
+        return Err(SolveOutcome::Unsupported("spare boxes"));
+    }
+
+    let mut goal = state.clone();
+    for board in goal.boards.iter_mut() {
+        Arc::make_mut(board).grid.fill(Cell::Empty);
+    }
+    goal.player = player_target;
+    goal.rehash();
+    goal.replace_cell(player_target, Cell::Box);
+    for slot in slots {
+        goal.replace_cell(slot, Cell::Box);
+    }
+    Ok(goal)
+}
+
+/// Generate predecessors of `state` whose last move is a push inside a single board, into
+/// `out`. Each item is the predecessor and the direction of the push.
+///
+/// Pushes involving entering, exiting or eating are not generated, thus this is only complete
+/// for levels without board cells. Every generated predecessor is verified by the forward move.
+pub(crate) fn push_predecessors(state: &State, out: &mut Vec<(State, Direction)>) {
+    let p = state.player;
+    let board = &state[p.board_id];
+    for dir in Direction::ALL {
+        let back = dir.reversed();
+        let Some(q) = board.sibling_pos(p.pos, back) else { continue };
+        let q = GlobalPos { pos: q, ..p };
+        if state[q] != Cell::Empty {
+            continue;
+        }
+
+        // The chain of box-like cells in front of the player.
+        let mut chain = Vec::new();
+        let mut cur = p.pos;
+        while let Some(next) = board.sibling_pos(cur, dir) {
+            let next = GlobalPos { pos: next, ..p };
+            if !state[next].is_box_like() {
+                break;
+            }
+            chain.push(next);
+            cur = next.pos;
+        }
+
+        // Pull the first `k` boxes of the chain back.
+        for k in 1..=chain.len() {
+            let mut pred = state.clone();
+            pred.set_player(q);
+            let mut prev = p;
+            for &gpos in &chain[..k] {
+                let cell = pred.replace_cell(gpos, Cell::Empty);
+                pred.replace_cell(prev, cell);
+                prev = gpos;
+            }
+
+            let mut check = pred.clone();
+            if check.go(dir) == Ok(true) && check == *state {
+                out.push((pred, dir));
+            }
+        }
+    }
+}
+
+/// Push-states from which the game can be won, with their minimal counts of pushes to win.
+///
+/// States are canonicalized like the visited states of `bfs`.
+#[derive(Debug, Clone)]
+pub struct WinningStates {
+    config: Config,
+    /// Canonical states with their distances to the goal state in pushes.
+    dists: IndexMap<PackedState, usize>,
+}
+
+impl WinningStates {
+    /// The count of canonical winning states found.
+    pub fn len(&self) -> usize {
+        self.dists.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dists.is_empty()
+    }
+
+    /// Check if the game can be won from a state of the same game. States not contained are
+    /// dead.
+    pub fn contains(&self, state: &State) -> bool {
+        self.distance(state).is_some()
+    }
+
+    /// The minimal count of pushes to win from a state of the same game, or `None` if the
+    /// state is not found to be winning.
+    pub fn distance(&self, state: &State) -> Option<usize> {
+        let mut state = state.clone();
+        state.canonicalize(&self.config);
+        self.dists.get(&state.pack()).copied()
+    }
+}
+
+/// Backward breadth-first search on pushes from the goal state, finding all states from which
+/// the game can be won.
+///
+/// Only levels without board cells are supported, where all pushes are inside single boards
+/// (see `push_predecessors`). Others are rejected with `SolveOutcome::Unsupported`, as well as
+/// levels without a unique goal state (see `goal_state`).
+pub fn winning_states(
+    game: &Game,
+    options: &SolverOptions,
+    mut on_progress: impl FnMut(&Progress),
+) -> Result<WinningStates, SolveOutcome> {
+    let mut goal = goal_state(game)?;
+    let limits = LimitChecker::new(options, game.state.pack().estimated_entry_size::<usize>());
+
+    let mut dists = IndexMap::<PackedState, usize>::default();
+    goal.canonicalize(&game.config);
+    dists.insert(goal.pack(), 0);
+
+    let mut state = game.state.clone();
+    let mut preds = Vec::new();
+    let mut progress = Progress::default();
+    let mut cursor = 0;
+    while let Some((packed, &dist)) = dists.get_index(cursor) {
+        limits.check(cursor, dists.len())?;
+        progress.expanded = cursor;
+        progress.frontier = dists.len() - cursor;
+        progress.depth = dist;
+        progress.memory_bytes = limits.memory_bytes(dists.len());
+
+        state.unpack_from(packed);
+        for loc in state.trivially_reachable_locations().collect::<Vec<_>>() {
+            progress.steps += 1;
+            on_progress(&progress);
+            state.set_player(loc);
+            push_predecessors(&state, &mut preds);
+        }
+        for (mut pred, _) in preds.drain(..) {
+            pred.canonicalize(&game.config);
+            progress.generated += 1;
+            let len = dists.len();
+            dists.entry(pred.pack()).or_insert(dist + 1);
+            progress.duplicates += (len == dists.len()) as usize;
+        }
+        cursor += 1;
+    }
+
+    Ok(WinningStates {
+        config: game.config.clone(),
+        dists,
+    })
+}
@@ -0,0 +1,71 @@
+//! Finding the core of a level, by removing elements which do not affect the optimal solution.
+
+use super::{bfs_with_options, SolveOutcome, SolverOptions};
+use crate::edit::Parts;
+use crate::{Cell, Game, GlobalPos};
+
+/// The result of `simplify`.
+#[derive(Debug, Clone)]
+pub struct Simplification {
+    /// The level with all removable boxes and walls removed.
+    pub game: Game,
+    /// The count of pushes of the push-optimal solution, the same for the original and the
+    /// simplified level.
+    pub pushes: usize,
+    /// Locations of removed boxes, in the order of removal.
+    pub removed_boxes: Vec<GlobalPos>,
+    /// Locations of removed walls, in the order of removal.
+    pub removed_walls: Vec<GlobalPos>,
+}
+
+/// Simplify a level by trying to remove boxes, other than the player, and then walls, one at a
+/// time in the order of the map, and re-solving with `bfs`. A removal is kept if the count of
+/// pushes of the push-optimal solution is unchanged, thus remaining elements are all essential
+/// to the solution.
+///
+/// Returns `None` if the level is not solved within limits of `options`. Removals whose
+/// re-solving reaches limits are not kept. This solves the level once per box and wall, which
+/// may take much time on large levels.
+pub fn simplify(game: &Game, options: &SolverOptions) -> Option<Simplification> {
+    let pushes = |game: &Game| match bfs_with_options(game.clone(), options, |_| {}) {
+        SolveOutcome::Solved(solution) => Some(solution.pushes),
+        _ => None,
+    };
+    let expected = pushes(game)?;
+
+    let mut ret = Simplification {
+        game: game.clone(),
+        pushes: expected,
+        removed_boxes: Vec::new(),
+        removed_walls: Vec::new(),
+    };
+    let cells = Parts::new(game).cells();
+    let boxes = cells
+        .iter()
+        .filter(|&&(gpos, cell, _)| cell == Cell::Box && gpos != game.state.player)
+        .map(|&(gpos, ..)| (gpos, false));
+    let walls = cells
+        .iter()
+        .filter(|&&(.., is_wall)| is_wall)
+        .map(|&(gpos, ..)| (gpos, true));
+    for (gpos, is_wall) in boxes.chain(walls) {
+        let mut parts = Parts::new(&ret.game);
+        if is_wall {
+            parts.set_wall(gpos, false);
+        } else {
+            parts.set_cell(gpos, Cell::Empty);
+        }
+        let Some(edited) = parts.build(&ret.game) else {
+            continue;
+        };
+        if pushes(&edited) == Some(expected) {
+            ret.game = edited;
+            if is_wall {
+                ret.removed_walls.push(gpos);
+            } else {
+                ret.removed_boxes.push(gpos);
+            }
+        }
+    }
+    Some(ret)
+}
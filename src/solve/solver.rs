@@ -0,0 +1,166 @@
+//! A common interface of solvers, to be chosen at runtime.
+
+use std::sync::Mutex;
+
+use super::{
+    astar, beam, bfs_approximate, bfs_moves, bfs_with_options, bidirectional, par_bfs,
+    BeamHeuristic, Progress, SolveOutcome, SolverOptions,
+};
+use crate::Game;
+
+/// A solver sharing `SolverOptions` and progress reporting with others.
+///
+/// Progress may be reported from other threads, but never concurrently.
+pub trait Solver {
+    fn solve(
+        &self,
+        game: &Game,
+        options: &SolverOptions,
+        on_progress: &mut (dyn FnMut(&Progress) + Send),
+    ) -> SolveOutcome;
+}
+
+/// `bfs`, push-optimal.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Bfs;
+
+/// `bfs_moves`, move-optimal.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BfsMoves;
+
+/// `astar`, push-optimal.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Astar;
+
+/// `par_bfs`, push-optimal.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ParBfs;
+
+/// `bidirectional`, not necessarily optimal, for levels without board cells.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Bidirectional;
+
+/// `bfs_approximate` with a Bloom filter of `bloom_bytes` bytes, incomplete.
+#[derive(Debug, Clone, Copy)]
+pub struct Approximate {
+    pub bloom_bytes: usize,
+}
+
+/// `beam` keeping `width` states in each layer, incomplete.
+#[derive(Debug, Clone, Copy)]
+pub struct Beam {
+    pub width: usize,
+    pub heuristic: BeamHeuristic,
+}
+
+impl Solver for Bfs {
+    fn solve(
+        &self,
+        game: &Game,
+        options: &SolverOptions,
+        on_progress: &mut (dyn FnMut(&Progress) + Send),
+    ) -> SolveOutcome {
+        bfs_with_options(game.clone(), options, on_progress)
+    }
+}
+
+impl Solver for BfsMoves {
+    fn solve(
+        &self,
+        game: &Game,
+        options: &SolverOptions,
+        on_progress: &mut (dyn FnMut(&Progress) + Send),
+    ) -> SolveOutcome {
+        bfs_moves(game.clone(), options, on_progress)
+    }
+}
+
+impl Solver for Astar {
+    fn solve(
+        &self,
+        game: &Game,
+        options: &SolverOptions,
+        on_progress: &mut (dyn FnMut(&Progress) + Send),
+    ) -> SolveOutcome {
+        astar(game.clone(), options, on_progress)
+    }
+}
+
+impl Solver for ParBfs {
+    fn solve(
+        &self,
+        game: &Game,
+        options: &SolverOptions,
+        on_progress: &mut (dyn FnMut(&Progress) + Send),
+    ) -> SolveOutcome {
+        // Progress is reported from worker threads.
+        let on_progress = Mutex::new(on_progress);
+        par_bfs(game.clone(), options, |progress| {
+            (on_progress.lock().unwrap())(progress)
+        })
+    }
+}
+
+impl Solver for Bidirectional {
+    fn solve(
+        &self,
+        game: &Game,
+        options: &SolverOptions,
+        on_progress: &mut (dyn FnMut(&Progress) + Send),
+    ) -> SolveOutcome {
+        bidirectional(game.clone(), options, on_progress)
+    }
+}
+
+impl Solver for Approximate {
+    fn solve(
+        &self,
+        game: &Game,
+        options: &SolverOptions,
+        on_progress: &mut (dyn FnMut(&Progress) + Send),
+    ) -> SolveOutcome {
+        bfs_approximate(game.clone(), self.bloom_bytes, options, on_progress)
+    }
+}
+
+impl Solver for Beam {
+    fn solve(
+        &self,
+        game: &Game,
+        options: &SolverOptions,
+        on_progress: &mut (dyn FnMut(&Progress) + Send),
+    ) -> SolveOutcome {
+        let Self { width, heuristic } = *self;
+        beam(game.clone(), width, heuristic, options, on_progress)
+    }
+}
+
+/// Names of solvers accepted by `solver_by_name`.
+pub const SOLVER_NAMES: [&str; 7] = [
+    "bfs",
+    "bfs-moves",
+    "astar",
+    "par-bfs",
+    "bidirectional",
+    "approximate",
+    "beam",
+];
+
+/// Get a solver by its name in `SOLVER_NAMES`, with default parameters.
+pub fn solver_by_name(name: &str) -> Option<Box<dyn Solver + Send + Sync>> {
+    Some(match name {
+        "bfs" => Box::new(Bfs),
+        "bfs-moves" => Box::new(BfsMoves),
+        "astar" => Box::new(Astar),
+        "par-bfs" => Box::new(ParBfs),
+        "bidirectional" => Box::new(Bidirectional),
+        "approximate" => Box::new(Approximate {
+            bloom_bytes: 1 << 28,
+        }),
+        "beam" => Box::new(Beam {
+            width: 1 << 12,
+            heuristic: BeamHeuristic::default(),
+        }),
+        _ => return None,
+    })
+}
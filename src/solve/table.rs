@@ -0,0 +1,175 @@
+//! A compact open-addressing table of visited states for `BfsSearch` and `par_bfs`.
+
+use std::collections::TryReserveError;
+
+use crate::{GlobalPos, PackedState, State};
+
+/// The sentinel of parents of root states.
+const NO_PARENT: u32 = u32::MAX;
+
+/// Packed states with their parents and precanonical locations, indexed by insertion order.
+///
+/// Cells of all states are stored back to back in one arena, and the hash table only holds
+/// `u32` indices into it. Probing uses the Zobrist hashes carried by `PackedState`, thus keys
+/// are never rehashed, and at most `u32::MAX - 1` states can be stored.
+pub(crate) struct StateTable {
+    /// The length of cells of every state.
+    cells_len: usize,
+    cells: Vec<u8>,
+    players: Vec<GlobalPos>,
+    hashes: Vec<u64>,
+    parents: Vec<u32>,
+    locs: Vec<GlobalPos>,
+    /// Indices plus one of states, or zero for empty slots. The length is a power of two.
+    slots: Box<[u32]>,
+}
+
+impl StateTable {
+    pub fn new(cells_len: usize) -> Self {
+        Self {
+            cells_len,
+            cells: Vec::new(),
+            players: Vec::new(),
+            hashes: Vec::new(),
+            parents: Vec::new(),
+            locs: Vec::new(),
+            slots: vec![0; 16].into(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    /// Reserve space for `additional` more states, failing instead of aborting if it cannot
+    /// be allocated, like for lengths read from untrusted data.
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.cells
+            .try_reserve(additional.saturating_mul(self.cells_len))?;
+        self.players.try_reserve(additional)?;
+        self.hashes.try_reserve(additional)?;
+        self.parents.try_reserve(additional)?;
+        self.locs.try_reserve(additional)?;
+        let want = self.len().saturating_add(additional).saturating_mul(2);
+        if want > self.slots.len() {
+            let slot_cnt = want.checked_next_power_of_two().unwrap_or(usize::MAX);
+            let mut slots = Vec::new();
+            slots.try_reserve_exact(slot_cnt)?;
+            slots.resize(slot_cnt, 0);
+            self.rehash_into(slots.into());
+        }
+        Ok(())
+    }
+
+    /// The maximal count of states.
+    pub fn max_len() -> usize {
+        NO_PARENT as usize - 1
+    }
+
+    /// Estimate the memory per state, with the slot table at its sparsest.
+    pub fn estimated_entry_size(&self) -> usize {
+        use std::mem::size_of;
+        self.cells_len
+            + 2 * size_of::<GlobalPos>()
+            + size_of::<u64>()
+            + size_of::<u32>()
+            + 4 * size_of::<u32>()
+    }
+
+    fn cells_of(&self, i: usize) -> &[u8] {
+        &self.cells[i * self.cells_len..(i + 1) * self.cells_len]
+    }
+
+    /// Find the slot of `packed`, either holding it or empty.
+    fn find_slot(&self, packed: &PackedState) -> usize {
+        let mask = self.slots.len() - 1;
+        let mut slot = packed.stable_hash() as usize & mask;
+        loop {
+            let Some(i) = self.slots[slot].checked_sub(1) else {
+                return slot;
+            };
+            let i = i as usize;
+            if self.hashes[i] == packed.stable_hash()
+                && self.players[i] == packed.player()
+                && self.cells_of(i) == packed.cells()
+            {
+                return slot;
+            }
+            slot = (slot + 1) & mask;
+        }
+    }
+
+    fn rehash(&mut self, slot_cnt: usize) {
+        self.rehash_into(vec![0u32; slot_cnt].into_boxed_slice());
+    }
+
+    /// Rebuild the slot table into `slots` of zeros, whose length is a power of two.
+    fn rehash_into(&mut self, mut slots: Box<[u32]>) {
+        let mask = slots.len() - 1;
+        for (i, &hash) in self.hashes.iter().enumerate() {
+            let mut slot = hash as usize & mask;
+            while slots[slot] != 0 {
+                slot = (slot + 1) & mask;
+            }
+            slots[slot] = i as u32 + 1;
+        }
+        self.slots = slots;
+    }
+
+    /// Insert `packed` if it is absent, with its parent index, or `!0usize` for roots.
+    /// Returns the index of the state and whether it is newly inserted.
+    pub fn insert(&mut self, packed: &PackedState, parent: usize, loc: GlobalPos) -> (usize, bool) {
+        debug_assert_eq!(packed.cells_len(), self.cells_len);
+        let slot = self.find_slot(packed);
+        if let Some(i) = self.slots[slot].checked_sub(1) {
+            return (i as usize, false);
+        }
+
+        let i = self.len();
+        assert!(i < Self::max_len(), "Too many states");
+        self.cells.extend_from_slice(packed.cells());
+        self.players.push(packed.player());
+        self.hashes.push(packed.stable_hash());
+        self.parents.push(if parent == !0usize {
+            NO_PARENT
+        } else {
+            parent as u32
+        });
+        self.locs.push(loc);
+        self.slots[slot] = i as u32 + 1;
+        // Keep the load factor at most 1/2.
+        if self.len() * 2 > self.slots.len() {
+            self.rehash(self.slots.len() * 2);
+        }
+        (i, true)
+    }
+
+    /// Overwrite the parent index, or `!0usize` for roots, and the precanonical location of the
+    /// state at index `i`.
+    pub fn set_node(&mut self, i: usize, parent: usize, loc: GlobalPos) {
+        self.parents[i] = if parent == !0usize {
+            NO_PARENT
+        } else {
+            parent as u32
+        };
+        self.locs[i] = loc;
+    }
+
+    /// The parent index, or `!0usize` for roots, and the precanonical location of a state.
+    pub fn node(&self, i: usize) -> (usize, GlobalPos) {
+        let parent = match self.parents[i] {
+            NO_PARENT => !0usize,
+            parent => parent as usize,
+        };
+        (parent, self.locs[i])
+    }
+
+    pub fn packed(&self, i: usize) -> PackedState {
+        PackedState::from_parts(self.players[i], self.cells_of(i).into(), self.hashes[i])
+    }
+
+    /// Overwrite the dynamic content of `state` by the state at index `i`.
+    pub fn unpack_into(&self, i: usize, state: &mut State) {
+        state.unpack_parts(self.players[i], self.cells_of(i), self.hashes[i]);
+    }
+}
@@ -0,0 +1,78 @@
+//! Static statistics of levels, for describing and comparing them.
+
+use crate::{BoardId, Cell, Game, GlobalPos};
+
+/// Statistics of a board, found by `Game::stats`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardStats {
+    pub height: u8,
+    pub width: u8,
+    pub walls: usize,
+    /// Boards referenced by cells of this board, with duplicates, in the order of the map.
+    pub contains: Vec<BoardId>,
+}
+
+/// Statistics of a level, found by `Game::stats`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelStats {
+    pub boards: Vec<BoardStats>,
+    /// The count of plain boxes, other than the player.
+    pub boxes: usize,
+    pub box_targets: usize,
+    pub has_player_target: bool,
+    /// The base-2 logarithm of an upper bound of the count of states, which is the count of
+    /// ways to place the player, boxes and board cells on non-wall cells, where boxes are
+    /// indistinguishable.
+    pub state_space_log2: f64,
+}
+
+impl Game {
+    /// Collect statistics of the level, without solving it.
+    pub fn stats(&self) -> LevelStats {
+        let (state, config) = (&self.state, &self.config);
+        let mut boards = Vec::with_capacity(state.boards.len());
+        let (mut boxes, mut movables, mut free) = (0usize, 0usize, 0usize);
+        for (id, board) in state.boards.iter().enumerate() {
+            let board_id = id.try_into().unwrap();
+            let mut walls = 0;
+            let mut contains = Vec::new();
+            for (pos, cell) in board.cells() {
+                let gpos = GlobalPos { board_id, pos };
+                if state.is_wall(gpos) {
+                    walls += 1;
+                    continue;
+                }
+                free += 1;
+                match cell {
+                    Cell::Box if gpos != state.player => boxes += 1,
+                    Cell::Board(inner) => {
+                        contains.push(inner);
+                        movables += 1;
+                    }
+                    _ => {}
+                }
+            }
+            boards.push(BoardStats {
+                height: board.height,
+                width: board.width,
+                walls,
+                contains,
+            });
+        }
+
+        // Distinguishable movables, including the player, are placed first, then boxes.
+        movables += 1;
+        let log2_falling =
+            |n: usize, k: usize| (0..k).map(|i| ((n - i) as f64).log2()).sum::<f64>();
+        let log2_factorial = |n: usize| log2_falling(n, n);
+        let state_space_log2 = log2_falling(free, movables + boxes) - log2_factorial(boxes);
+
+        LevelStats {
+            boards,
+            boxes,
+            box_targets: config.box_targets.len(),
+            has_player_target: config.player_target.is_some(),
+            state_space_log2,
+        }
+    }
+}
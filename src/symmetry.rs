@@ -0,0 +1,223 @@
+//! Symmetries of a level, used to deduplicate symmetric states.
+
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use arrayvec::ArrayVec;
+
+use crate::{Board, BoardId, BoardSet, Cell, Config, GlobalPos, State, Vec2, MAX_BOARD_CNT};
+
+/// The maximum size of the symmetry group to search. Larger groups fall back to mirrors only.
+const MAX_SYMMETRIES: usize = 256;
+
+/// A symmetry of a level: a permutation of interchangeable boards, combined with mirroring of
+/// all boards. Moves on symmetric states are symmetric, with directions mirrored.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Symmetry {
+    /// The new id of each board.
+    board_map: ArrayVec<BoardId, MAX_BOARD_CNT>,
+    /// Mirror rows upside down.
+    flip_rows: bool,
+    /// Mirror columns left to right.
+    flip_cols: bool,
+}
+
+impl Symmetry {
+    fn map_pos(&self, board: &Board, pos: Vec2) -> Vec2 {
+        Vec2(
+            if self.flip_rows {
+                board.height - 1 - pos.0
+            } else {
+                pos.0
+            },
+            if self.flip_cols {
+                board.width - 1 - pos.1
+            } else {
+                pos.1
+            },
+        )
+    }
+
+    fn map_gpos(&self, state: &State, gpos: GlobalPos) -> GlobalPos {
+        GlobalPos {
+            board_id: self.board_map[gpos.board_id as usize],
+            pos: self.map_pos(&state[gpos.board_id], gpos.pos),
+        }
+    }
+
+    fn map_cell(&self, cell: Cell) -> Cell {
+        match cell {
+            Cell::Board(id) => Cell::Board(self.board_map[id as usize]),
+            cell => cell,
+        }
+    }
+
+    fn is_identity(&self) -> bool {
+        !self.flip_rows
+            && !self.flip_cols
+            && self
+                .board_map
+                .iter()
+                .enumerate()
+                .all(|(i, &id)| i == id as usize)
+    }
+
+    /// Check if walls and targets are invariant.
+    ///
+    /// Exiting a board goes through its first reference in board id then row-major order, which
+    /// no non-identity symmetry keeps, once any board is referenced more than once.
+    fn preserves(&self, state: &State, config: &Config) -> bool {
+        let mut referenced = BoardSet::default();
+        let is_shared = state
+            .boards
+            .iter()
+            .flat_map(|board| board.grid.iter())
+            .any(|cell| matches!(*cell, Cell::Board(id) if !referenced.insert(id)));
+        if is_shared && !self.is_identity() {
+            return false;
+        }
+        let mut box_targets = config
+            .box_targets
+            .iter()
+            .map(|&gpos| self.map_gpos(state, gpos))
+            .collect::<Vec<_>>();
+        box_targets.sort();
+        let mut expected = config.box_targets.to_vec();
+        expected.sort();
+        config
+            .player_target
+            .is_none_or(|gpos| self.map_gpos(state, gpos) == gpos)
+            && box_targets == expected
+            && (0..state.boards.len()).all(|board_id| {
+                let board_id = BoardId::try_from(board_id).unwrap();
+                state[board_id].cells().all(|(pos, _)| {
+                    let gpos = GlobalPos { board_id, pos };
+                    state.is_wall(gpos) == state.is_wall(self.map_gpos(state, gpos))
+                })
+            })
+    }
+
+    fn apply(&self, state: &State) -> State {
+        let mut ret = state.clone();
+        for (board_id, board) in state.boards.iter().enumerate() {
+            let new_board = Arc::make_mut(&mut ret.boards[self.board_map[board_id] as usize]);
+            for (pos, cell) in board.cells() {
+                new_board[self.map_pos(board, pos)] = self.map_cell(cell);
+            }
+        }
+        ret.player = self.map_gpos(state, state.player);
+        ret.rehash();
+        ret
+    }
+}
+
+/// Find all non-identity symmetries of a level.
+pub(crate) fn find_symmetries(state: &State, config: &Config) -> Vec<Symmetry> {
+    // Mirroring keeps entering positions only if the mirrored axis has odd lengths.
+    let all_odd = |f: fn(&Board) -> u8| state.boards.iter().all(|board| f(board) % 2 == 1);
+    let flip_rows = [false, true]
+        .into_iter()
+        .filter(|&f| !f || all_odd(|b| b.height));
+    let flip_cols = [false, true]
+        .into_iter()
+        .filter(|&f| !f || all_odd(|b| b.width))
+        .collect::<Vec<_>>();
+
+    // Classes of boards with the same size and walls.
+    let mut classes = Vec::<Vec<usize>>::new();
+    for i in 0..state.boards.len() {
+        let same = |&j: &usize| {
+            let (a, b) = (&state.boards[i], &state.boards[j]);
+            (a.height, a.width) == (b.height, b.width) && state.walls[i] == state.walls[j]
+        };
+        match classes.iter_mut().find(|class| same(&class[0])) {
+            Some(class) => class.push(i),
+            None => classes.push(vec![i]),
+        }
+    }
+    let group_size = classes
+        .iter()
+        .map(|class| (1..=class.len()).product::<usize>())
+        .try_fold(1usize, |acc, x| acc.checked_mul(x))
+        .filter(|&size| size <= MAX_SYMMETRIES);
+    if group_size.is_none() {
+        classes = (0..state.boards.len()).map(|i| vec![i]).collect();
+    }
+
+    let mut perms = classes.clone();
+    let mut ret = Vec::new();
+    loop {
+        let mut board_map = (0..state.boards.len())
+            .map(|i| BoardId::try_from(i).unwrap())
+            .collect::<ArrayVec<_, MAX_BOARD_CNT>>();
+        for (class, perm) in classes.iter().zip(&perms) {
+            for (&from, &to) in class.iter().zip(perm) {
+                board_map[from] = BoardId::try_from(to).unwrap();
+            }
+        }
+        for flip_rows in flip_rows.clone() {
+            for &flip_cols in &flip_cols {
+                let sym = Symmetry {
+                    board_map: board_map.clone(),
+                    flip_rows,
+                    flip_cols,
+                };
+                if !sym.is_identity() && sym.preserves(state, config) {
+                    ret.push(sym);
+                }
+            }
+        }
+        // Advance the permutations like an odometer.
+        if !perms.iter_mut().any(|perm| next_permutation(perm)) {
+            break;
+        }
+    }
+    ret
+}
+
+/// Advance to the next lexicographical permutation, returns `false` and resets it to the first
+/// one if it is the last one.
+fn next_permutation(perm: &mut [usize]) -> bool {
+    let Some(i) = (1..perm.len()).rev().find(|&i| perm[i - 1] < perm[i]) else {
+        perm.reverse();
+        return false;
+    };
+    let j = (i..perm.len())
+        .rev()
+        .find(|&j| perm[i - 1] < perm[j])
+        .unwrap();
+    perm.swap(i - 1, j);
+    perm[i..].reverse();
+    true
+}
+
+impl State {
+    /// Canonicalize the state for deduplication: move the player to the minimal trivially
+    /// reachable location, then choose the minimal one among all symmetric states.
+    ///
+    /// Canonical states of symmetric states are the same.
+    pub fn canonicalize(&mut self, config: &Config) {
+        let canonical_loc = self.trivially_reachable_locations().min().unwrap();
+        self.set_player(canonical_loc);
+        let mut best = None::<State>;
+        for sym in config.symmetries.iter() {
+            let mut state = sym.apply(self);
+            let canonical_loc = state.trivially_reachable_locations().min().unwrap();
+            state.set_player(canonical_loc);
+            if state.canonical_cmp(best.as_ref().unwrap_or(self)).is_lt() {
+                best = Some(state);
+            }
+        }
+        if let Some(best) = best {
+            *self = best;
+        }
+    }
+
+    fn canonical_cmp(&self, other: &State) -> Ordering {
+        self.player.cmp(&other.player).then_with(|| {
+            let lhs = self.boards.iter().map(|board| board.as_raw_grid());
+            let rhs = other.boards.iter().map(|board| board.as_raw_grid());
+            lhs.cmp(rhs)
+        })
+    }
+}
@@ -0,0 +1,60 @@
+//! Appearance shared by renderers, so that terminal and raster outputs are consistent.
+
+use crate::GlyphMap;
+
+/// Colors of renderers, in RGB.
+///
+/// Terminals only color targets, by the nearest colors of the 256-color palette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColorMap {
+    pub empty: [u8; 3],
+    pub wall: [u8; 3],
+    pub box_cell: [u8; 3],
+    pub player: [u8; 3],
+    pub box_target: [u8; 3],
+    pub player_target: [u8; 3],
+    /// Colors of board references which are not drawn as boards, cycled by board id.
+    pub boards: [[u8; 3]; 4],
+    /// Targets covered as required by `State::is_success_on`.
+    pub filled_target: [u8; 3],
+    /// Targets not yet covered as required.
+    pub unfilled_target: [u8; 3],
+}
+
+impl Default for ColorMap {
+    fn default() -> Self {
+        Self {
+            empty: [0xE8, 0xE0, 0xC8],
+            wall: [0x5A, 0x4A, 0x3A],
+            box_cell: [0xD8, 0x98, 0x38],
+            player: [0xD0, 0x40, 0x60],
+            box_target: [0xA0, 0xC0, 0xE0],
+            player_target: [0xE8, 0xA8, 0xC0],
+            boards: [
+                [0x58, 0x90, 0xC8],
+                [0x60, 0xB0, 0x70],
+                [0x98, 0x70, 0xC0],
+                [0xC0, 0x78, 0x58],
+            ],
+            filled_target: [0x00, 0xC0, 0x00],
+            unfilled_target: [0xC0, 0xC0, 0x00],
+        }
+    }
+}
+
+impl ColorMap {
+    /// The nearest color of the 6x6x6 cube of the 256-color terminal palette.
+    pub fn to_ansi256(rgb: [u8; 3]) -> u8 {
+        let [r, g, b] = rgb.map(|c| (c as u16 * 5 + 127) / 255);
+        (16 + 36 * r + 6 * g + b) as u8
+    }
+}
+
+/// The appearance of levels, consumed by `State::display_on_with`, `NestedRenderer` and its
+/// raster output.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// Characters of text renderers. `RenderStyle::Unicode` uses its own characters instead.
+    pub glyphs: GlyphMap,
+    pub colors: ColorMap,
+}
@@ -0,0 +1,344 @@
+//! Export of levels to maps of the Tiled editor, in TMX or JSON, for reusing levels in game
+//! engines which read Tiled maps.
+//!
+//! Every board is a tile layer named `Board <id>` with an integer property `board`, placed
+//! side by side by layer offsets, and padded with no tiles to the size of the largest board.
+//! Tiles of the embedded tileset have types `floor`, `wall`, `box`, `player`, and `board` with
+//! an integer property `board` of the referenced board. Since targets may lie under boxes,
+//! they are point objects of types `box_target` and `player_target` in the object layer
+//! `Targets`, with integer properties `board`, `row` and `col`. The title and the author are
+//! string properties of the map.
+//!
+//! The tileset has no images, thus maps need one assigned before being drawn by Tiled.
+
+use std::fmt::Write;
+
+use crate::html::html_escape;
+use crate::{BoardId, Cell, Game, GlobalPos, MAX_BOARD_CNT};
+
+/// The size of tiles in pixels.
+const TILE_SIZE: usize = 16;
+/// The count of empty tiles between boards.
+const GAP: usize = 1;
+const FIRST_GID: usize = 1;
+/// Types of tiles other than board references, by their ids.
+const TILE_TYPES: [&str; 4] = ["floor", "wall", "box", "player"];
+/// The id of the tile of the reference to the board 0, followed by those of other boards.
+const FIRST_BOARD_TILE: usize = TILE_TYPES.len();
+const TILE_CNT: usize = FIRST_BOARD_TILE + MAX_BOARD_CNT;
+
+/// The tile layer of a board.
+struct Layer {
+    board_id: usize,
+    offset_x: usize,
+    /// Global tile ids in row-major order, 0 for no tile.
+    data: Vec<usize>,
+}
+
+/// A target object.
+struct Target {
+    kind: &'static str,
+    gpos: GlobalPos,
+    x: usize,
+    y: usize,
+}
+
+/// The content of a map, shared by formats.
+struct Map {
+    width: usize,
+    height: usize,
+    properties: Vec<(&'static str, String)>,
+    layers: Vec<Layer>,
+    targets: Vec<Target>,
+}
+
+impl Map {
+    fn new(game: &Game) -> Self {
+        let (state, config) = (&game.state, &game.config);
+        let board_ids = (0..state.boards.len()).map(|id| BoardId::try_from(id).unwrap());
+        let width = board_ids
+            .clone()
+            .map(|id| state[id].width as usize)
+            .max()
+            .unwrap_or(0);
+        let height = board_ids
+            .clone()
+            .map(|id| state[id].height as usize)
+            .max()
+            .unwrap_or(0);
+
+        let mut layers = Vec::new();
+        let mut offsets = Vec::new();
+        let mut offset_x = 0;
+        for board_id in board_ids {
+            let mut data = vec![0; width * height];
+            for (pos, cell) in state.board_cells(board_id) {
+                let tile = match cell {
+                    _ if (GlobalPos { board_id, pos }) == state.player => 3,
+                    Cell::Empty => 0,
+                    Cell::Wall => 1,
+                    Cell::Box => 2,
+                    Cell::Board(id) => FIRST_BOARD_TILE + id as usize,
+                };
+                data[pos.0 as usize * width + pos.1 as usize] = FIRST_GID + tile;
+            }
+            offsets.push(offset_x);
+            layers.push(Layer {
+                board_id: board_id as usize,
+                offset_x,
+                data,
+            });
+            offset_x += (state[board_id].width as usize + GAP) * TILE_SIZE;
+        }
+
+        let targets = config
+            .player_target
+            .map(|gpos| ("player_target", gpos))
+            .into_iter()
+            .chain(config.box_targets.iter().map(|&gpos| ("box_target", gpos)))
+            .map(|(kind, gpos)| Target {
+                kind,
+                gpos,
+                x: offsets[gpos.board_id as usize] + (gpos.pos.1 as usize * TILE_SIZE),
+                y: gpos.pos.0 as usize * TILE_SIZE,
+            })
+            .collect();
+
+        let metadata = &game.metadata;
+        let properties = [("title", &metadata.title), ("author", &metadata.author)]
+            .into_iter()
+            .filter_map(|(name, value)| Some((name, value.clone()?)))
+            .collect();
+        Self {
+            width,
+            height,
+            properties,
+            layers,
+            targets,
+        }
+    }
+}
+
+impl Game {
+    /// Export the level as a map in the TMX format of Tiled. See the module documentation for
+    /// the layout of the map.
+    pub fn to_tmx(&self) -> String {
+        let map = Map::new(self);
+        let mut out = String::new();
+        let w = &mut out;
+        let layer_cnt = map.layers.len() + 1;
+        writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+        writeln!(
+            w,
+            r#"<map version="1.8" orientation="orthogonal" renderorder="right-down" width="{}" height="{}" tilewidth="{TILE_SIZE}" tileheight="{TILE_SIZE}" infinite="0" nextlayerid="{}" nextobjectid="{}">"#,
+            map.width,
+            map.height,
+            layer_cnt + 1,
+            map.targets.len() + 1,
+        )
+        .unwrap();
+        if !map.properties.is_empty() {
+            writeln!(w, " <properties>").unwrap();
+            for (name, value) in &map.properties {
+                let value = html_escape(value);
+                writeln!(w, r#"  <property name="{name}" value="{value}"/>"#).unwrap();
+            }
+            writeln!(w, " </properties>").unwrap();
+        }
+
+        writeln!(
+            w,
+            r#" <tileset firstgid="{FIRST_GID}" name="parabox" tilewidth="{TILE_SIZE}" tileheight="{TILE_SIZE}" tilecount="{TILE_CNT}" columns="0">"#,
+        )
+        .unwrap();
+        for (id, ty) in TILE_TYPES.iter().enumerate() {
+            writeln!(w, r#"  <tile id="{id}" type="{ty}"/>"#).unwrap();
+        }
+        for board in 0..MAX_BOARD_CNT {
+            let id = FIRST_BOARD_TILE + board;
+            writeln!(w, r#"  <tile id="{id}" type="board">"#).unwrap();
+            writeln!(w, "   <properties>").unwrap();
+            writeln!(
+                w,
+                r#"    <property name="board" type="int" value="{board}"/>"#
+            )
+            .unwrap();
+            writeln!(w, "   </properties>").unwrap();
+            writeln!(w, "  </tile>").unwrap();
+        }
+        writeln!(w, " </tileset>").unwrap();
+
+        for (i, layer) in map.layers.iter().enumerate() {
+            writeln!(
+                w,
+                r#" <layer id="{}" name="Board {:X}" width="{}" height="{}" offsetx="{}">"#,
+                i + 1,
+                layer.board_id,
+                map.width,
+                map.height,
+                layer.offset_x,
+            )
+            .unwrap();
+            writeln!(w, "  <properties>").unwrap();
+            writeln!(
+                w,
+                r#"   <property name="board" type="int" value="{}"/>"#,
+                layer.board_id,
+            )
+            .unwrap();
+            writeln!(w, "  </properties>").unwrap();
+            writeln!(w, r#"  <data encoding="csv">"#).unwrap();
+            let rows = layer.data.chunks(map.width.max(1)).collect::<Vec<_>>();
+            for (row, tiles) in rows.iter().enumerate() {
+                let tiles = tiles.iter().map(usize::to_string).collect::<Vec<_>>();
+                let sep = if row + 1 < rows.len() { "," } else { "" };
+                writeln!(w, "{}{sep}", tiles.join(",")).unwrap();
+            }
+            writeln!(w, "</data>").unwrap();
+            writeln!(w, " </layer>").unwrap();
+        }
+
+        writeln!(w, r#" <objectgroup id="{layer_cnt}" name="Targets">"#).unwrap();
+        for (i, target) in map.targets.iter().enumerate() {
+            writeln!(
+                w,
+                r#"  <object id="{}" type="{}" x="{}" y="{}" width="{TILE_SIZE}" height="{TILE_SIZE}">"#,
+                i + 1,
+                target.kind,
+                target.x,
+                target.y,
+            )
+            .unwrap();
+            writeln!(w, "   <properties>").unwrap();
+            let gpos = target.gpos;
+            let fields = [
+                ("board", gpos.board_id as usize),
+                ("row", gpos.pos.0 as usize),
+                ("col", gpos.pos.1 as usize),
+            ];
+            for (name, value) in fields {
+                writeln!(
+                    w,
+                    r#"    <property name="{name}" type="int" value="{value}"/>"#
+                )
+                .unwrap();
+            }
+            writeln!(w, "   </properties>").unwrap();
+            writeln!(w, "  </object>").unwrap();
+        }
+        writeln!(w, " </objectgroup>").unwrap();
+        writeln!(w, "</map>").unwrap();
+        out
+    }
+
+    /// Export the level as a map in the JSON format of Tiled, with the same content as
+    /// `Game::to_tmx`.
+    #[cfg(feature = "serde")]
+    pub fn to_tiled_json(&self) -> String {
+        use serde_json::{json, Value};
+
+        let map = Map::new(self);
+        let int_property =
+            |name: &str, value: usize| json!({ "name": name, "type": "int", "value": value });
+        let properties = map
+            .properties
+            .iter()
+            .map(|(name, value)| json!({ "name": name, "type": "string", "value": value }))
+            .collect::<Vec<_>>();
+        let tiles = TILE_TYPES
+            .iter()
+            .enumerate()
+            .map(|(id, ty)| json!({ "id": id, "type": ty }))
+            .chain((0..MAX_BOARD_CNT).map(|board| {
+                json!({
+                    "id": FIRST_BOARD_TILE + board,
+                    "type": "board",
+                    "properties": [int_property("board", board)],
+                })
+            }))
+            .collect::<Vec<_>>();
+        let mut layers = map
+            .layers
+            .iter()
+            .enumerate()
+            .map(|(i, layer)| {
+                json!({
+                    "id": i + 1,
+                    "name": format!("Board {:X}", layer.board_id),
+                    "type": "tilelayer",
+                    "width": map.width,
+                    "height": map.height,
+                    "x": 0,
+                    "y": 0,
+                    "offsetx": layer.offset_x,
+                    "offsety": 0,
+                    "opacity": 1,
+                    "visible": true,
+                    "properties": [int_property("board", layer.board_id)],
+                    "data": layer.data,
+                })
+            })
+            .collect::<Vec<Value>>();
+        let objects = map
+            .targets
+            .iter()
+            .enumerate()
+            .map(|(i, target)| {
+                let gpos = target.gpos;
+                json!({
+                    "id": i + 1,
+                    "name": "",
+                    "type": target.kind,
+                    "x": target.x,
+                    "y": target.y,
+                    "width": TILE_SIZE,
+                    "height": TILE_SIZE,
+                    "rotation": 0,
+                    "visible": true,
+                    "properties": [
+                        int_property("board", gpos.board_id as usize),
+                        int_property("row", gpos.pos.0 as usize),
+                        int_property("col", gpos.pos.1 as usize),
+                    ],
+                })
+            })
+            .collect::<Vec<_>>();
+        let layer_cnt = layers.len() + 1;
+        layers.push(json!({
+            "id": layer_cnt,
+            "name": "Targets",
+            "type": "objectgroup",
+            "draworder": "topdown",
+            "x": 0,
+            "y": 0,
+            "opacity": 1,
+            "visible": true,
+            "objects": objects,
+        }));
+        let out = json!({
+            "type": "map",
+            "version": "1.8",
+            "orientation": "orthogonal",
+            "renderorder": "right-down",
+            "width": map.width,
+            "height": map.height,
+            "tilewidth": TILE_SIZE,
+            "tileheight": TILE_SIZE,
+            "infinite": false,
+            "nextlayerid": layer_cnt + 1,
+            "nextobjectid": map.targets.len() + 1,
+            "properties": properties,
+            "tilesets": [{
+                "firstgid": FIRST_GID,
+                "name": "parabox",
+                "tilewidth": TILE_SIZE,
+                "tileheight": TILE_SIZE,
+                "tilecount": TILE_CNT,
+                "columns": 0,
+                "tiles": tiles,
+            }],
+            "layers": layers,
+        });
+        serde_json::to_string_pretty(&out).unwrap() + "\n"
+    }
+}
@@ -0,0 +1,134 @@
+//! Trimming levels into canonical minimal forms, for cleaning up generated or hand-edited
+//! levels.
+
+use crate::lint::board_components;
+use crate::parse::build_game;
+use crate::{Board, BoardId, Cell, Game, GlobalPos, Vec2};
+
+impl Game {
+    /// Trim the level without changing its solutions:
+    ///
+    /// - Boards disconnected from the board of the player, and without targets, are removed,
+    ///   since they can never be interacted with. See `LintDiagnostic::UnreachableBoard`.
+    /// - Outermost rows and columns of walls without targets are cropped from boards which are
+    ///   not referenced, since edges of such boards block movement as well. Referenced boards are
+    ///   kept intact, since their edges are exits and entrances.
+    /// - Remaining boards are renumbered densely, in the original order.
+    ///
+    /// Trimming is idempotent. Metadata are kept.
+    pub fn trim(&self) -> Game {
+        let (state, config) = (&self.state, &self.config);
+        let board_cnt = state.boards.len();
+        let targets = config
+            .player_target
+            .iter()
+            .chain(config.box_targets.iter())
+            .copied()
+            .collect::<Vec<_>>();
+
+        let components = board_components(state);
+        let is_kept_component = |component: usize| {
+            components[state.player.board_id as usize] == component
+                || targets
+                    .iter()
+                    .any(|gpos| components[gpos.board_id as usize] == component)
+        };
+        let kept = (0..board_cnt)
+            .filter(|&id| is_kept_component(components[id]))
+            .collect::<Vec<_>>();
+        let mut new_ids = vec![None; board_cnt];
+        for (new_id, &id) in kept.iter().enumerate() {
+            new_ids[id] = Some(new_id.try_into().unwrap());
+        }
+
+        let mut is_referenced = vec![false; board_cnt];
+        for board in state.boards.iter() {
+            for (_, cell) in board.cells() {
+                if let Cell::Board(id) = cell {
+                    is_referenced[id as usize] = true;
+                }
+            }
+        }
+
+        // The top-left corner and the size of the kept part of each board.
+        let mut windows = vec![(Vec2(0, 0), 0u8, 0u8); board_cnt];
+        for &id in &kept {
+            let board_id = BoardId::try_from(id).unwrap();
+            let board = &state[board_id];
+            let (mut top, mut left, mut bottom, mut right) = (0, 0, board.height, board.width);
+            if !is_referenced[id] {
+                let is_wall = |row: u8, col: u8| {
+                    let gpos = GlobalPos {
+                        board_id,
+                        pos: Vec2(row, col),
+                    };
+                    state.is_wall(gpos) && !targets.contains(&gpos)
+                };
+                while bottom - top > 1 && (left..right).all(|col| is_wall(top, col)) {
+                    top += 1;
+                }
+                while bottom - top > 1 && (left..right).all(|col| is_wall(bottom - 1, col)) {
+                    bottom -= 1;
+                }
+                while right - left > 1 && (top..bottom).all(|row| is_wall(row, left)) {
+                    left += 1;
+                }
+                while right - left > 1 && (top..bottom).all(|row| is_wall(row, right - 1)) {
+                    right -= 1;
+                }
+            }
+            windows[id] = (Vec2(top, left), bottom - top, right - left);
+        }
+
+        let map_gpos = |gpos: GlobalPos| {
+            let (corner, ..) = windows[gpos.board_id as usize];
+            GlobalPos {
+                board_id: new_ids[gpos.board_id as usize].unwrap(),
+                pos: Vec2(gpos.pos.0 - corner.0, gpos.pos.1 - corner.1),
+            }
+        };
+        let mut boards = Vec::with_capacity(kept.len());
+        let mut walls = Vec::with_capacity(kept.len());
+        for &id in &kept {
+            let board_id = BoardId::try_from(id).unwrap();
+            let (corner, height, width) = windows[id];
+            let mut grid = Vec::with_capacity(height as usize * width as usize);
+            let mut board_walls = Vec::with_capacity(grid.capacity());
+            for row in corner.0..corner.0 + height {
+                for col in corner.1..corner.1 + width {
+                    let gpos = GlobalPos {
+                        board_id,
+                        pos: Vec2(row, col),
+                    };
+                    grid.push(match state[board_id][gpos.pos] {
+                        // References within a kept component are kept.
+                        Cell::Board(id) => Cell::Board(new_ids[id as usize].unwrap()),
+                        cell => cell,
+                    });
+                    board_walls.push(state.is_wall(gpos));
+                }
+            }
+            boards.push(Board {
+                height,
+                width,
+                grid: grid.into(),
+            });
+            walls.push(board_walls.into());
+        }
+
+        let mut game = build_game(
+            boards,
+            walls,
+            map_gpos(state.player),
+            config.player_target.map(map_gpos),
+            config
+                .box_targets
+                .iter()
+                .map(|&gpos| map_gpos(gpos))
+                .collect(),
+        )
+        .expect("Trimming keeps the level valid");
+        game.metadata = self.metadata.clone();
+        game
+    }
+}
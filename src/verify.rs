@@ -0,0 +1,86 @@
+//! Replaying and verifying solutions.
+
+use crate::{Direction, Error, Game, State};
+
+/// The result of a successful replay by `Game::verify`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// The count of moves which push something other than the player itself.
+    pub pushes: usize,
+    /// The state after all moves.
+    pub final_state: State,
+    /// Whether the final state is successful.
+    pub is_success: bool,
+}
+
+/// A move which fails during the replay.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyError {
+    /// The 0-based index of the failed move.
+    pub step: usize,
+    pub dir: Direction,
+    pub error: Error,
+}
+
+impl std::fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Move {} ({:?}) failed: {}",
+            self.step, self.dir, self.error
+        )
+    }
+}
+
+impl std::error::Error for VerifyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl Game {
+    /// Replay moves from the initial state, stopping at the first invalid one.
+    ///
+    /// A valid replay is not necessarily a solution, check `VerifyReport::is_success` for that.
+    pub fn verify(&self, moves: &[Direction]) -> Result<VerifyReport, VerifyError> {
+        let mut pushes = 0usize;
+        let state = self.replay(moves, |_, do_pushed| pushes += do_pushed as usize)?;
+        Ok(VerifyReport {
+            pushes,
+            is_success: state.is_success_on(&self.config),
+            final_state: state,
+        })
+    }
+
+    /// Format moves in the LURD notation of Sokoban, where walks are lowercase `lurd` and
+    /// pushes are uppercase `LURD`, for exchanging solutions with Sokoban tools.
+    pub fn to_lurd(&self, moves: &[Direction]) -> Result<String, VerifyError> {
+        let mut out = String::with_capacity(moves.len());
+        self.replay(moves, |dir, do_pushed| {
+            let ch = match dir {
+                Direction::Left => 'l',
+                Direction::Up => 'u',
+                Direction::Right => 'r',
+                Direction::Down => 'd',
+            };
+            out.push(if do_pushed { ch.to_ascii_uppercase() } else { ch });
+        })?;
+        Ok(out)
+    }
+
+    /// Replay moves, calling `on_move` with each move and whether it pushes something.
+    fn replay(
+        &self,
+        moves: &[Direction],
+        mut on_move: impl FnMut(Direction, bool),
+    ) -> Result<State, VerifyError> {
+        let mut state = self.state.clone();
+        for (step, &dir) in moves.iter().enumerate() {
+            match state.go(dir) {
+                Ok(do_pushed) => on_move(dir, do_pushed),
+                Err(error) => return Err(VerifyError { step, dir, error }),
+            }
+        }
+        Ok(state)
+    }
+}
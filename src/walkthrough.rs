@@ -0,0 +1,107 @@
+//! Annotated step-by-step walkthroughs of solutions, for level guides.
+
+use std::fmt::{self, Write};
+
+use anyhow::{Context, Result};
+
+use crate::{Cell, Direction, Game, GlobalPos, Metadata, State, Vec2};
+
+/// A thing moved by a move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Moved {
+    /// The cell moved, where the player is a `Cell::Box`.
+    pub cell: Cell,
+    pub from: GlobalPos,
+    pub to: GlobalPos,
+}
+
+/// What a move does, returned by `State::go_with_outcome`.
+///
+/// Formatted as a description like `move right to (2, 3), push box into board 2 at (3, 0)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveOutcome {
+    pub dir: Direction,
+    /// The player, then things pushed by it in the order of the push.
+    pub moved: Vec<Moved>,
+}
+
+impl MoveOutcome {
+    /// Whether the move moves something other than the player, like the result of `State::go`.
+    pub fn is_push(&self) -> bool {
+        self.moved.len() > 1
+    }
+}
+
+impl fmt::Display for MoveOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dir = match self.dir {
+            Direction::Right => "right",
+            Direction::Down => "down",
+            Direction::Left => "left",
+            Direction::Up => "up",
+        };
+        for (i, moved) in self.moved.iter().enumerate() {
+            let Vec2(row, col) = moved.to.pos;
+            match (i, moved.cell) {
+                (0, _) => write!(f, "move {dir}")?,
+                (_, Cell::Board(id)) => write!(f, ", push board {:X}", id as usize)?,
+                _ => f.write_str(", push box")?,
+            }
+            if moved.from.board_id == moved.to.board_id {
+                write!(f, " to ({row}, {col})")?;
+            } else {
+                let board = moved.to.board_id as usize;
+                write!(f, " into board {board:X} at ({row}, {col})")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl State {
+    /// Move the player like `State::go`, describing what is moved.
+    pub fn go_with_outcome(&mut self, dir: Direction) -> crate::Result<MoveOutcome> {
+        let push_seq = self.push_seq(dir)?;
+        let moved = push_seq
+            .windows(2)
+            .map(|w| Moved {
+                cell: self[w[1]],
+                from: w[0],
+                to: w[1],
+            })
+            .collect();
+        Ok(MoveOutcome { dir, moved })
+    }
+}
+
+impl Game {
+    /// Write a walkthrough of `moves` from the initial state: the level, then for each move its
+    /// step, its LURD character and a description by `MoveOutcome`, followed by the state after
+    /// it in the text format.
+    ///
+    /// Fails if any move is invalid.
+    pub fn walkthrough(&self, moves: &[Direction]) -> Result<String> {
+        let lurd = self.to_lurd(moves)?;
+        let mut frame = Game {
+            config: self.config.clone(),
+            state: self.state.clone(),
+            metadata: Metadata::default(),
+        };
+        let mut out = String::new();
+        if let Some(title) = &self.metadata.title {
+            writeln!(out, "; {title}").unwrap();
+        }
+        writeln!(out, "; {} moves: {lurd}\n\n{frame}", moves.len()).unwrap();
+        for (i, (&dir, ch)) in moves.iter().zip(lurd.chars()).enumerate() {
+            let outcome = frame
+                .state
+                .go_with_outcome(dir)
+                .with_context(|| format!("Move {} ({dir:?}) failed", i + 1))?;
+            writeln!(out, "; step {}: {ch}, {outcome}\n{frame}", i + 1).unwrap();
+        }
+        if frame.state.is_success_on(&frame.config) {
+            out += "; solved\n";
+        }
+        Ok(out)
+    }
+}
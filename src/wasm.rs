@@ -0,0 +1,177 @@
+//! Bindings for browsers by `wasm-bindgen`, behind the `wasm` feature.
+//!
+//! Build with `cargo rustc --lib --release --target wasm32-unknown-unknown --features wasm
+//! --crate-type cdylib`, then generate the JavaScript glue by `wasm-bindgen`. Structured
+//! results are returned as JSON strings.
+//!
+//! The solver runs for a budget of expanded states per call of `Level::solve_step`, so that it
+//! can be driven from `requestAnimationFrame` or timers without blocking the page.
+
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+
+use crate::solve::{BfsSearch, Progress, SolveOutcome, SolverOptions};
+use crate::{BoardId, Direction, Game, GlobalPos, GlyphMap, State};
+
+/// A level being played, with its current state and moves.
+#[wasm_bindgen]
+pub struct Level {
+    /// The level at its initial state.
+    game: Game,
+    state: State,
+    moves: Vec<Direction>,
+    /// The search from the current state, with its last progress.
+    search: Option<(BfsSearch, Progress)>,
+}
+
+fn to_js_error(err: impl std::fmt::Display) -> JsError {
+    JsError::new(&err.to_string())
+}
+
+fn parse_direction(dir: &str) -> Result<Direction, JsError> {
+    Ok(match &*dir.to_ascii_lowercase() {
+        "l" | "left" => Direction::Left,
+        "u" | "up" => Direction::Up,
+        "r" | "right" => Direction::Right,
+        "d" | "down" => Direction::Down,
+        _ => return Err(JsError::new(&format!("Invalid direction: {dir:?}"))),
+    })
+}
+
+fn location(gpos: GlobalPos) -> Value {
+    json!({ "board": gpos.board_id as usize, "row": gpos.pos.0, "col": gpos.pos.1 })
+}
+
+#[wasm_bindgen]
+impl Level {
+    /// Parse a level in the text format, or the JSON format if it starts with `{`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(text: &str) -> Result<Level, JsError> {
+        let game = if text.trim_start().starts_with('{') {
+            Game::from_json(text)
+        } else {
+            text.parse::<Game>()
+        };
+        let game = game.map_err(|err| to_js_error(format!("{err:#}")))?;
+        Ok(Self {
+            state: game.state.clone(),
+            game,
+            moves: Vec::new(),
+            search: None,
+        })
+    }
+
+    /// The current state as JSON for drawing: `boards` with `id`, `height`, `width` and `rows`
+    /// of glyphs of the text format without targets, the `player`, the `player_target` or
+    /// `null`, `box_targets` as locations of `board`, `row` and `col`, whether it is `solved`,
+    /// and `moves` made in LURD.
+    pub fn render_model(&self) -> String {
+        let (state, config) = (&self.state, &self.game.config);
+        let glyphs = GlyphMap::default();
+        let boards = (0..state.boards.len())
+            .map(|id| {
+                let board_id = BoardId::try_from(id).unwrap();
+                let board = &state[board_id];
+                let mut rows = vec![String::new(); board.height as usize];
+                for (pos, cell) in state.board_cells(board_id) {
+                    let ch = if (GlobalPos { board_id, pos }) == state.player {
+                        glyphs.player
+                    } else {
+                        glyphs.cell(cell)
+                    };
+                    rows[pos.0 as usize].push(ch);
+                }
+                json!({ "id": id, "height": board.height, "width": board.width, "rows": rows })
+            })
+            .collect::<Vec<_>>();
+        json!({
+            "boards": boards,
+            "player": location(state.player),
+            "player_target": config.player_target.map(location),
+            "box_targets": config.box_targets.iter().copied().map(location).collect::<Vec<_>>(),
+            "solved": state.is_success_on(config),
+            "moves": self.moves(),
+        })
+        .to_string()
+    }
+
+    /// Move the player by a direction, `l`, `u`, `r`, `d` or their full names, returning
+    /// whether it pushes something.
+    pub fn go(&mut self, dir: &str) -> Result<bool, JsError> {
+        let dir = parse_direction(dir)?;
+        let pushed = self.state.go(dir).map_err(to_js_error)?;
+        self.moves.push(dir);
+        self.search = None;
+        Ok(pushed)
+    }
+
+    /// Legal moves from the current state in LURD, in the order of `Direction::ALL`.
+    pub fn legal_moves(&self) -> String {
+        let legal = Direction::ALL
+            .into_iter()
+            .filter(|&dir| self.state.clone().go(dir).is_ok())
+            .collect::<Vec<_>>();
+        self.lurd_from(&self.state, &legal)
+    }
+
+    /// Moves made from the initial state in LURD.
+    pub fn moves(&self) -> String {
+        self.lurd_from(&self.game.state, &self.moves)
+    }
+
+    /// Restart from the initial state.
+    pub fn reset(&mut self) {
+        self.state = self.game.state.clone();
+        self.moves.clear();
+        self.search = None;
+    }
+
+    /// Continue solving from the current state by expanding up to `budget` states, returning
+    /// JSON with `status` of `running` with `expanded`, `frontier` and `depth`, `solved` with
+    /// the `solution` in LURD and counts of `moves` and `pushes`, or `unsolvable`.
+    ///
+    /// Moves of the player restart the search.
+    pub fn solve_step(&mut self, budget: u32) -> String {
+        let (search, progress) = self.search.get_or_insert_with(|| {
+            let game = Game {
+                config: self.game.config.clone(),
+                state: self.state.clone(),
+                metadata: Default::default(),
+            };
+            (BfsSearch::new(game), Progress::default())
+        });
+        let options = SolverOptions {
+            max_nodes: Some(progress.expanded + budget as usize),
+            ..SolverOptions::default()
+        };
+        let outcome = search.run(&options, |p| *progress = *p);
+        let ret = match outcome {
+            SolveOutcome::Solved(solution) => json!({
+                "status": "solved",
+                "solution": self.lurd_from(&self.state, &solution.moves),
+                "moves": solution.moves.len(),
+                "pushes": solution.pushes,
+            }),
+            SolveOutcome::LimitReached(..) | SolveOutcome::Cancelled(_) => json!({
+                "status": "running",
+                "expanded": progress.expanded,
+                "frontier": progress.frontier,
+                "depth": progress.depth,
+            }),
+            SolveOutcome::Unsolvable(_) => json!({ "status": "unsolvable" }),
+            SolveOutcome::Unsupported(_) => unreachable!("BFS supports all levels"),
+        };
+        ret.to_string()
+    }
+}
+
+impl Level {
+    fn lurd_from(&self, state: &State, moves: &[Direction]) -> String {
+        let game = Game {
+            config: self.game.config.clone(),
+            state: state.clone(),
+            metadata: Default::default(),
+        };
+        game.to_lurd(moves).unwrap_or_default()
+    }
+}
@@ -0,0 +1,291 @@
+//! Import and export of Sokoban levels in the XSB format, the common plain text format of Sokoban
+//! collections.
+//!
+//! A level is a block of consecutive rows of `#` for walls, `@` for the player, `+` for the
+//! player on a goal, `$` for boxes, `*` for boxes on goals, `.` for goals, and spaces, `-` or `_`
+//! for floors. Floors outside of the walls become walls, and the outermost rows and columns of
+//! walls are cropped, since edges of the only board block movement as well. Sokoban has no
+//! player target, thus the player of imported levels may end anywhere.
+//!
+//! In collections, `Title:` and `Author:` lines after a level describe it, lines starting with
+//! `;` before a level are its comments, and other lines after a level are its comments as well.
+//!
+//! ```text
+//! ; The first level
+//!   #####
+//! ###   #
+//! #.@$  #
+//! #######
+//! Title: First
+//! Author: Someone
+//! ```
+
+use std::fmt::Write;
+
+use anyhow::{bail, ensure, Context, Result};
+
+use crate::parse::build_game;
+use crate::{Board, BoardId, Cell, Game, GlobalPos, LevelPack, Metadata, Vec2, MAX_BOARD_WIDTH};
+
+const ROW_CHARS: &str = "#@+$*.-_ ";
+
+/// A level being split from a collection.
+#[derive(Default)]
+struct Level<'a> {
+    rows: Vec<&'a str>,
+    metadata: Metadata,
+}
+
+fn is_row(line: &str) -> bool {
+    line.contains('#') && line.chars().all(|ch| ROW_CHARS.contains(ch))
+}
+
+fn split_levels(s: &str) -> Vec<Level<'_>> {
+    let mut levels = Vec::<Level>::new();
+    // Comments before the next level.
+    let mut pending = Vec::new();
+    let mut in_rows = false;
+    for line in s.lines() {
+        let line = line.trim_end();
+        if is_row(line) {
+            if !in_rows {
+                levels.push(Level {
+                    metadata: Metadata {
+                        comments: std::mem::take(&mut pending),
+                        ..Metadata::default()
+                    },
+                    ..Level::default()
+                });
+                in_rows = true;
+            }
+            levels.last_mut().unwrap().rows.push(line);
+            continue;
+        }
+        in_rows = false;
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(comment) = line.strip_prefix(';') {
+            pending.push(comment.trim().to_owned());
+            continue;
+        }
+        let Some(level) = levels.last_mut() else {
+            continue;
+        };
+        let metadata = &mut level.metadata;
+        match line.split_once(':') {
+            Some((key, value)) if key.eq_ignore_ascii_case("title") => {
+                metadata.title = Some(value.trim().to_owned());
+            }
+            Some((key, value)) if key.eq_ignore_ascii_case("author") => {
+                metadata.author = Some(value.trim().to_owned());
+            }
+            _ => metadata.comments.push(line.to_owned()),
+        }
+    }
+    levels
+}
+
+impl Level<'_> {
+    fn build(self) -> Result<Game> {
+        let height = self.rows.len();
+        let width = self.rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        let grid = self
+            .rows
+            .iter()
+            .map(|row| {
+                let mut row = row.as_bytes().to_vec();
+                row.resize(width, b' ');
+                row
+            })
+            .collect::<Vec<_>>();
+
+        // Flood fill floors reachable from edges.
+        let mut outside = vec![vec![false; width]; height];
+        let mut stack = (0..height)
+            .flat_map(|r| [(r, 0), (r, width - 1)])
+            .chain((0..width).flat_map(|c| [(0, c), (height - 1, c)]))
+            .collect::<Vec<_>>();
+        while let Some((r, c)) = stack.pop() {
+            if grid[r][c] == b'#' || outside[r][c] {
+                continue;
+            }
+            ensure!(
+                matches!(grid[r][c], b' ' | b'-' | b'_'),
+                "Level is not enclosed by walls",
+            );
+            outside[r][c] = true;
+            if r > 0 {
+                stack.push((r - 1, c));
+            }
+            if r + 1 < height {
+                stack.push((r + 1, c));
+            }
+            if c > 0 {
+                stack.push((r, c - 1));
+            }
+            if c + 1 < width {
+                stack.push((r, c + 1));
+            }
+        }
+
+        // Crop rows and columns without any inner cells.
+        let is_inner = |r: usize, c: usize| grid[r][c] != b'#' && !outside[r][c];
+        let rows = (0..height)
+            .filter(|&r| (0..width).any(|c| is_inner(r, c)))
+            .collect::<Vec<_>>();
+        let cols = (0..width)
+            .filter(|&c| (0..height).any(|r| is_inner(r, c)))
+            .collect::<Vec<_>>();
+        let (&top, &bottom) = rows.first().zip(rows.last()).context("Empty level")?;
+        let (&left, &right) = cols.first().zip(cols.last()).unwrap();
+        let (height, width) = (bottom - top + 1, right - left + 1);
+        ensure!(
+            height.max(width) < MAX_BOARD_WIDTH,
+            "Level is too large: {height}x{width}",
+        );
+
+        let board_id = 0.try_into().unwrap();
+        let mut board = Board {
+            height: height as u8,
+            width: width as u8,
+            grid: vec![Cell::Empty; height * width].into(),
+        };
+        let mut walls = vec![false; height * width];
+        let mut player = None;
+        let mut box_targets = Vec::new();
+        for (r, row) in grid.iter().enumerate().take(bottom + 1).skip(top) {
+            for (c, &ch) in row.iter().enumerate().take(right + 1).skip(left) {
+                let pos = Vec2((r - top) as u8, (c - left) as u8);
+                let gpos = GlobalPos { board_id, pos };
+                let idx = board.grid_index(pos);
+                if matches!(ch, b'.' | b'*' | b'+') {
+                    box_targets.push(gpos);
+                }
+                match ch {
+                    b'@' | b'+' => {
+                        ensure!(player.is_none(), "Multiple players");
+                        player = Some(gpos);
+                        board.grid[idx] = Cell::Box;
+                    }
+                    b'$' | b'*' => board.grid[idx] = Cell::Box,
+                    _ if !is_inner(r, c) => walls[idx] = true,
+                    _ => {}
+                }
+            }
+        }
+
+        let mut game = build_game(
+            vec![board],
+            vec![walls.into()],
+            player.context("Missing player")?,
+            None,
+            box_targets,
+        )?;
+        game.metadata = self.metadata;
+        Ok(game)
+    }
+}
+
+impl Game {
+    /// Parse a single Sokoban level in the XSB format. See `LevelPack::from_xsb` for collections.
+    pub fn from_xsb(s: &str) -> Result<Self> {
+        let mut levels = split_levels(s);
+        ensure!(
+            levels.len() == 1,
+            "Expect exactly one level, got {}",
+            levels.len(),
+        );
+        levels.pop().unwrap().build()
+    }
+
+    /// Format a plain Sokoban level in the XSB format, surrounded by walls, with comments before
+    /// it, and the title and the author after it, which is parsed back by `Game::from_xsb`.
+    ///
+    /// Fails if the level has multiple boards, board cells or the player target, which Sokoban
+    /// does not have, or metadata are not single-line.
+    pub fn to_xsb(&self) -> Result<String> {
+        let (state, config, metadata) = (&self.state, &self.config, &self.metadata);
+        ensure!(
+            state.boards.len() == 1,
+            "Sokoban levels have only one board"
+        );
+        ensure!(
+            config.player_target.is_none(),
+            "Sokoban levels have no player target",
+        );
+        let entries = [("Title", &metadata.title), ("Author", &metadata.author)]
+            .into_iter()
+            .filter_map(|(key, value)| Some((key, value.as_ref()?)))
+            .chain(metadata.comments.iter().map(|comment| ("comment", comment)));
+        for (key, value) in entries {
+            ensure!(
+                !value.contains(['\n', '\r']) && value.trim() == value,
+                "Invalid metadata {key}: {value:?}",
+            );
+        }
+
+        let mut out = String::new();
+        for comment in &metadata.comments {
+            writeln!(out, "; {comment}").unwrap();
+        }
+        let board_id = BoardId::try_from(0).unwrap();
+        let board = &state[board_id];
+        let border = "#".repeat(board.width as usize + 2);
+        writeln!(out, "{border}").unwrap();
+        for (pos, cell) in state.board_cells(board_id) {
+            if pos.1 == 0 {
+                out.push('#');
+            }
+            let gpos = GlobalPos { board_id, pos };
+            let is_target = config.box_targets.contains(&gpos);
+            out.push(match (cell, gpos == state.player, is_target) {
+                (Cell::Board(_), ..) => bail!("Sokoban levels have no board cells"),
+                (Cell::Wall, ..) => '#',
+                (_, true, false) => '@',
+                (_, true, true) => '+',
+                (Cell::Box, _, false) => '$',
+                (Cell::Box, _, true) => '*',
+                (Cell::Empty, _, false) => ' ',
+                (Cell::Empty, _, true) => '.',
+            });
+            if pos.1 + 1 == board.width {
+                out.push_str("#\n");
+            }
+        }
+        writeln!(out, "{border}").unwrap();
+        if let Some(title) = &metadata.title {
+            writeln!(out, "Title: {title}").unwrap();
+        }
+        if let Some(author) = &metadata.author {
+            writeln!(out, "Author: {author}").unwrap();
+        }
+        Ok(out)
+    }
+}
+
+impl LevelPack {
+    /// Parse a collection of Sokoban levels in the XSB format.
+    ///
+    /// Levels are named by their titles, or by their 1-based indices if titles are missing,
+    /// invalid or duplicated.
+    pub fn from_xsb(s: &str) -> Result<Self> {
+        let mut pack = Self::new();
+        for (i, level) in split_levels(s).into_iter().enumerate() {
+            let name = level
+                .metadata
+                .title
+                .clone()
+                .filter(|title| !title.is_empty() && !title.contains(']'))
+                .filter(|title| pack.get(title).is_none())
+                .unwrap_or_else(|| (i + 1).to_string());
+            let game = level
+                .build()
+                .with_context(|| format!("Invalid level {name:?}"))?;
+            pack.push(name, game)?;
+        }
+        Ok(pack)
+    }
+}
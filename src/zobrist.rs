@@ -0,0 +1,86 @@
+//! Zobrist hashing of states, updated incrementally on each cell change.
+//!
+//! Keys are defined on nibbles of packed cells rather than on cells, so that the hash of a
+//! `PackedState` can be computed from its bytes alone, and equals the hash of the state it is
+//! packed from. Zero nibbles have zero keys, thus empty cells contribute nothing.
+
+use std::mem;
+use std::sync::Arc;
+
+use crate::pack::encode_cell;
+use crate::{Cell, GlobalPos, State};
+
+pub(crate) fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+fn nibble_key(idx: usize, value: u8) -> u64 {
+    if value == 0 {
+        0
+    } else {
+        splitmix64((idx as u64) << 4 | value as u64)
+    }
+}
+
+fn byte_key(idx: usize, value: u8) -> u64 {
+    nibble_key(2 * idx, value & 0xF) ^ nibble_key(2 * idx + 1, value >> 4)
+}
+
+fn player_key(gpos: GlobalPos) -> u64 {
+    // Disjoint from nibble keys.
+    splitmix64(1 << 32 | usize::from(gpos) as u64)
+}
+
+/// Compute the hash of packed cells with the player location.
+pub(crate) fn packed_hash(player: GlobalPos, cells: &[u8]) -> u64 {
+    cells
+        .iter()
+        .enumerate()
+        .fold(player_key(player), |h, (i, &v)| h ^ byte_key(i, v))
+}
+
+impl State {
+    /// The Zobrist hash of this state, maintained incrementally on each move.
+    ///
+    /// It is stable across runs and platforms, and only depends on the dynamic content, thus
+    /// can be used to identify states of the same game.
+    pub fn zobrist_hash(&self) -> u64 {
+        self.zobrist
+    }
+
+    fn cell_key(&self, gpos: GlobalPos, cell: Cell) -> u64 {
+        let offset = self.boards[..gpos.board_id as usize]
+            .iter()
+            .map(|board| board.grid.len())
+            .sum::<usize>();
+        let idx = offset + self[gpos.board_id].grid_index(gpos.pos);
+        if self.is_nibble_packable() {
+            nibble_key(idx, encode_cell(cell))
+        } else {
+            byte_key(idx, encode_cell(cell))
+        }
+    }
+
+    /// Replace a non-wall cell, returns the previous one.
+    pub(crate) fn replace_cell(&mut self, gpos: GlobalPos, cell: Cell) -> Cell {
+        assert!(!self.is_wall(gpos), "Walls are immutable");
+        let board = Arc::make_mut(&mut self.boards[gpos.board_id as usize]);
+        let prev = mem::replace(&mut board[gpos.pos], cell);
+        self.zobrist ^= self.cell_key(gpos, prev) ^ self.cell_key(gpos, cell);
+        prev
+    }
+
+    /// Set the player location, without moving any cell.
+    pub(crate) fn relocate_player(&mut self, gpos: GlobalPos) {
+        self.zobrist ^= player_key(self.player) ^ player_key(gpos);
+        self.player = gpos;
+    }
+
+    /// Recompute the hash from scratch, after changing cells in bulk.
+    pub(crate) fn rehash(&mut self) {
+        self.zobrist = packed_hash(self.player, &self.pack_cells());
+    }
+}
@@ -0,0 +1,89 @@
+use anyhow::Context;
+use parabox_solver::solve::{
+    bfs_with_options, dead_squares, simplify, SolveOutcome, SolverOptions,
+};
+use parabox_solver::{BoardId, Game, GlobalPos, Vec2};
+
+use crate::common::*;
+
+mod common;
+
+fn main() {
+    run_tests("analysis", true, |content| {
+        let map = content
+            .split_once(SEPARATOR)
+            .map_or(content, |(input, _)| input)
+            .trim();
+        let game = map.parse::<Game>().context("Invalid map")?;
+        let dead = dead_squares(&game);
+        let stats = game.stats();
+        let stats = format!(
+            "{} boards, {} boxes, {} box targets, estimated state space 2^{:.1}\n",
+            stats.boards.len(),
+            stats.boxes,
+            stats.box_targets,
+            stats.state_space_log2,
+        );
+        let lints = game
+            .validate()
+            .iter()
+            .map(|diag| {
+                let level = if diag.is_error() { "error" } else { "warning" };
+                format!("{level}: {diag}\n")
+            })
+            .collect::<String>();
+        let core = match simplify(&game, &SolverOptions::default()) {
+            Some(ret) => format!(
+                "Core after removing {} boxes and {} walls:\n{}",
+                ret.removed_boxes.len(),
+                ret.removed_walls.len(),
+                ret.game,
+            ),
+            None => String::new(),
+        };
+        let outcome = bfs_with_options(game, &SolverOptions::default(), |_| {});
+
+        // Mark empty dead squares by `x`.
+        let mut got = String::new();
+        let mut board_id = None;
+        let mut row = 0u8;
+        for line in map.lines().map(|line| line.trim()) {
+            match board_id {
+                // Metadata and covered targets.
+                None if line.is_empty() || line.starts_with([';', '_', '=']) => got += line,
+                None => {
+                    board_id = Some(BoardId::try_from(line.parse::<usize>()?).unwrap());
+                    row = 0;
+                    got += line;
+                }
+                Some(_) if line.is_empty() => board_id = None,
+                Some(board_id) => {
+                    for (col, ch) in line.chars().enumerate() {
+                        let gpos = GlobalPos {
+                            board_id,
+                            pos: Vec2(row, col as u8),
+                        };
+                        let is_dead = ch == '.' && dead.contains(gpos);
+                        got.push(if is_dead { 'x' } else { ch });
+                    }
+                    row += 1;
+                }
+            }
+            got += "\n";
+        }
+
+        let solvability = match outcome {
+            SolveOutcome::Solved(_) => "Solvable".to_owned(),
+            SolveOutcome::Unsolvable(ret) => match ret.reason {
+                Some(reason) => format!("Unsolvable after {} states: {reason}", ret.stats.states),
+                None => format!("Unsolvable after {} states", ret.stats.states),
+            },
+            outcome => anyhow::bail!("Unexpected outcome: {outcome:?}"),
+        };
+
+        Ok(format!(
+            "{map}\n\n{SEPARATOR}{}\n\n{solvability}\n{stats}{lints}{core}",
+            got.trim_end()
+        ))
+    });
+}
@@ -0,0 +1,110 @@
+use anyhow::Context;
+use parabox_solver::Game;
+
+use crate::common::*;
+
+mod common;
+
+/// Bindings replay the moves to the same final state, and solve the level. Each binding is only
+/// checked with its feature enabled.
+fn main() {
+    run_tests("bindings", true, |content| {
+        let input = content
+            .split_once(SEPARATOR)
+            .map_or(content, |(input, _)| input)
+            .trim();
+        let (moves, map) = input.split_once('\n').context("No moves")?;
+        let steps = parse_moves(moves)?;
+        let game = map.parse::<Game>().context("Invalid map")?;
+        let final_state = game.verify(&steps)?.final_state;
+        let solved = Game {
+            state: final_state.clone(),
+            ..game.clone()
+        };
+
+        #[cfg(feature = "bevy")]
+        {
+            use anyhow::ensure;
+            use bevy::prelude::*;
+            use parabox_solver::bevy::{Level, MoveInput, ParaboxPlugin, Piece};
+
+            let mut app = App::new();
+            app.add_plugins(ParaboxPlugin)
+                .insert_resource(Level::new(game.clone()));
+            let player = app.world_mut().spawn(Piece(game.state.player())).id();
+            for &dir in &steps {
+                app.world_mut().send_event(MoveInput(dir));
+            }
+            app.update();
+            let level = app.world().resource::<Level>();
+            ensure!(
+                level.state == final_state
+                    && level.is_success()
+                    && app.world().get::<Piece>(player) == Some(&Piece(final_state.player())),
+                "Invalid state of the Bevy plugin",
+            );
+        }
+
+        #[cfg(feature = "wasm")]
+        {
+            use anyhow::ensure;
+
+            let mut level = parabox_solver::wasm::Level::new(map)
+                .map_err(|_| anyhow::anyhow!("Level is rejected by the bindings"))?;
+            let status = loop {
+                let status = level.solve_step(100);
+                if !status.contains(r#""status":"running""#) {
+                    break status;
+                }
+            };
+            ensure!(
+                status.contains(r#""status":"solved""#),
+                "Invalid result of stepped solving: {status}",
+            );
+            for ch in moves.chars() {
+                level
+                    .go(&ch.to_string())
+                    .map_err(|_| anyhow::anyhow!("Move {ch} is rejected by the bindings"))?;
+            }
+            let model = level.render_model();
+            ensure!(
+                model.contains(r#""solved":true"#) && level.moves().eq_ignore_ascii_case(moves),
+                "Invalid state of the bindings: {model}",
+            );
+        }
+
+        #[cfg(feature = "capi")]
+        unsafe {
+            use anyhow::ensure;
+            use parabox_solver::capi::*;
+            use std::ffi::{CStr, CString};
+
+            let text = CString::new(map)?;
+            let level = pbx_parse(text.as_ptr());
+            ensure!(!level.is_null(), "Level is rejected by the C API");
+            let lurd = pbx_solve(level, 0);
+            ensure!(!lurd.is_null(), "No solution by the C API");
+            let solution = CStr::from_ptr(lurd).to_str()?.to_owned();
+            pbx_string_free(lurd);
+            let all_moved = moves
+                .bytes()
+                .all(|ch| pbx_go(level, ch as std::ffi::c_char) >= 0);
+            let is_success = pbx_is_success(level);
+            let rendered = pbx_render(level);
+            let text = CStr::from_ptr(rendered).to_str()?.to_owned();
+            pbx_string_free(rendered);
+            pbx_free(level);
+            let solution = parse_moves(&solution.to_ascii_uppercase())?;
+            ensure!(
+                game.verify(&solution)?.is_success,
+                "Invalid solution by the C API",
+            );
+            ensure!(
+                all_moved && is_success && text == solved.to_string(),
+                "Invalid state of the C API:\n{text}",
+            );
+        }
+
+        Ok(format!("{input}\n\n{SEPARATOR}{solved}"))
+    });
+}
@@ -18,6 +18,12 @@ pub fn parse_direction(ch: char) -> Result<Direction> {
     })
 }
 
+/// Parse a line of moves like `LURD`.
+#[allow(unused)]
+pub fn parse_moves(line: &str) -> Result<Vec<Direction>> {
+    line.chars().map(parse_direction).collect()
+}
+
 #[allow(unused)]
 pub fn fmt_direction(dir: Direction) -> &'static str {
     match dir {
@@ -37,7 +43,7 @@ pub fn run_tests(
         .unwrap()
         .filter_map(|ent| {
             let path = ent.unwrap().path();
-            if path.extension().map_or(true, |ext| ext != EXTENTION) {
+            if path.extension().is_none_or(|ext| ext != EXTENTION) {
                 return None;
             }
             let name = path.file_stem().unwrap().to_str().unwrap().to_owned();
@@ -46,7 +52,7 @@ pub fn run_tests(
         .collect::<Vec<_>>();
     tests.sort();
 
-    let do_update_tests = std::env::var("UPDATE_EXPECT").map_or(false, |v| v == "1");
+    let do_update_tests = std::env::var("UPDATE_EXPECT").is_ok_and(|v| v == "1");
     let mut filters = Vec::new();
     let mut is_enabled = enabled_by_default || !cfg!(debug_assertions);
     for arg in std::env::args().skip(1) {
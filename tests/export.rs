@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+use anyhow::{ensure, Context};
+use parabox_solver::{Game, MacroFormat, MacroOptions};
+
+use crate::common::*;
+
+mod common;
+
+fn main() {
+    run_tests("export", true, |content| {
+        let input = content
+            .split_once(SEPARATOR)
+            .map_or(content, |(input, _)| input)
+            .trim();
+        let (moves, map) = input.split_once('\n').context("No moves")?;
+        let steps = parse_moves(moves)?;
+        let game = map.parse::<Game>().context("Invalid map")?;
+        let pushes = game.verify(&steps)?.pushes;
+
+        let lurd = game.to_lurd(&steps)?;
+        ensure!(
+            lurd.to_ascii_uppercase() == moves
+                && lurd.chars().filter(char::is_ascii_uppercase).count() == pushes,
+            "Invalid LURD: {lurd}",
+        );
+
+        // HTML pages embed the solution, and a script closed only once.
+        let html = game.to_html(&steps)?;
+        ensure!(
+            html.contains(&format!("const moves = \"{lurd}\";"))
+                && html.matches("</script>").count() == 1,
+            "Invalid HTML export",
+        );
+
+        let walkthrough = game.walkthrough(&steps)?;
+        ensure!(
+            walkthrough.matches("; step ").count() == steps.len(),
+            "Invalid walkthrough",
+        );
+
+        // Macros press a key per move, held for `hold`.
+        let options = MacroOptions {
+            format: MacroFormat::Csv,
+            start_delay: Duration::ZERO,
+            ..MacroOptions::default()
+        };
+        let timeline = game.to_input_macro(&steps, &options)?;
+        let end = options.delay * steps.len().saturating_sub(1) as u32 + options.hold;
+        let last_time = timeline.lines().last().unwrap().split(',').next().unwrap();
+        ensure!(
+            timeline.matches(",down,").count() == steps.len()
+                && (steps.is_empty() || last_time == end.as_millis().to_string()),
+            "Invalid macro: {timeline}",
+        );
+
+        Ok(format!("{input}\n\n{SEPARATOR}{lurd}\n{walkthrough}"))
+    });
+}
@@ -0,0 +1,131 @@
+use std::io::{self, BufReader, Read};
+
+use anyhow::{ensure, Context};
+use parabox_solver::{Game, GlyphMap, LevelPack, Metadata, ReadError, State};
+
+use crate::common::*;
+
+mod common;
+
+/// A reader failing after the data it is chained with.
+struct BrokenReader;
+
+impl Read for BrokenReader {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Err(io::ErrorKind::BrokenPipe.into())
+    }
+}
+
+fn main() {
+    run_tests("format", true, |content| {
+        let input = content
+            .split_once(SEPARATOR)
+            .map_or(content, |(input, _)| input)
+            .trim();
+        let (moves, map) = input.split_once('\n').context("No moves")?;
+        let steps = parse_moves(moves)?;
+        let game = map.parse::<Game>().context("Invalid map")?;
+        let final_state = game.verify(&steps)?.final_state;
+
+        // Compact strings are single-line and round-trip.
+        for state in [&game.state, &final_state] {
+            let compact = state.to_compact_string();
+            ensure!(
+                !compact.contains('\n') && State::from_compact_string(&compact)? == *state,
+                "Compact string does not round-trip: {compact:?}",
+            );
+        }
+
+        let parabox = game.to_parabox();
+        let exported = Game::from_parabox(&parabox).context("Invalid exported level")?;
+        ensure!(exported == game, "Exported level mismatches: {exported}");
+        let share = game.encode_share_string();
+        let shared = Game::decode_share_string(&share).context("Invalid share string")?;
+        ensure!(shared == game, "Shared level mismatches: {shared}");
+        let decoded = Game::from_bytes(&game.to_bytes()).context("Invalid binary encoding")?;
+        ensure!(decoded == game, "Decoded level mismatches: {decoded}");
+
+        let mut pack = LevelPack::new();
+        pack.push("level", game.clone())?;
+        pack.push("copy", game.clone())?;
+        let parsed = pack
+            .to_text()?
+            .parse::<LevelPack>()
+            .context("Invalid level pack")?;
+        ensure!(
+            parsed == pack && parsed.get("copy") == Some(&game),
+            "Level pack mismatches",
+        );
+
+        let mut described = game.clone();
+        described.metadata = Metadata {
+            title: Some("A level".into()),
+            author: Some("Someone".into()),
+            rules: [("pull".into(), "off".into())].into(),
+            comments: vec!["A comment".into(), "title: not metadata".into()],
+        };
+        let parsed = described
+            .to_text()?
+            .parse::<Game>()
+            .context("Invalid map with metadata")?;
+        ensure!(
+            parsed == described,
+            "Metadata mismatches: {:?}",
+            parsed.metadata,
+        );
+        #[cfg(feature = "serde")]
+        {
+            let json = Game::from_json(&described.to_json()).context("Invalid JSON level")?;
+            ensure!(json == described, "JSON level mismatches: {json}");
+        }
+        // Tiled maps have a layer per board.
+        let tmx = described.to_tmx();
+        ensure!(
+            tmx.matches("<layer ").count() == game.state.to_compact_string().split('|').count()
+                && tmx.contains(r#"<property name="title" value="A level"/>"#),
+            "Invalid TMX map",
+        );
+
+        // All targets are covered in the final state.
+        let solved = Game {
+            state: final_state.clone(),
+            ..described.clone()
+        };
+        let parsed = solved
+            .to_string()
+            .parse::<Game>()
+            .context("Invalid solved map")?;
+        ensure!(
+            parsed.state == solved.state && parsed.to_string() == solved.to_string(),
+            "Solved map mismatches:\n{parsed}",
+        );
+        let read = Game::from_reader(map.as_bytes()).context("Invalid map from reader")?;
+        ensure!(read == game, "Map from reader mismatches:\n{read}");
+        let broken = BufReader::new(map.as_bytes().chain(BrokenReader));
+        let err = Game::from_reader(broken).unwrap_err();
+        ensure!(
+            matches!(err, ReadError::Io(_)),
+            "Reading error is not reported: {err}",
+        );
+
+        let glyphs = GlyphMap::sokoban();
+        for game in [&described, &solved] {
+            let text = game.display_with(&glyphs).to_string();
+            let parsed = Game::parse_with_glyphs(&text, &glyphs).context("Invalid Sokoban map")?;
+            ensure!(
+                parsed.state == game.state && parsed.to_string() == game.to_string(),
+                "Sokoban map mismatches:\n{text}",
+            );
+        }
+
+        let mut got = format!("{input}\n\n{SEPARATOR}{parabox}");
+        got += &format!("{SEPARATOR}{share}\n");
+        got += &format!(
+            "{}\n{}\n",
+            game.state.to_compact_string(),
+            final_state.to_compact_string(),
+        );
+        got += &format!("{SEPARATOR}{solved}");
+        Ok(got)
+    });
+}
@@ -0,0 +1,88 @@
+use anyhow::{bail, ensure, Context};
+use parabox_solver::generate::{generate, hill_climb, ClimbOptions, GenerateOptions, Mutation};
+use parabox_solver::solve::difficulty;
+
+use crate::common::*;
+
+mod common;
+
+fn main() {
+    run_tests("generate", false, |content| {
+        let input = content
+            .split_once(SEPARATOR)
+            .map_or(content, |(input, _)| input)
+            .trim();
+        // Options are lines of `key value`.
+        let mut options = GenerateOptions::default();
+        let mut climb = None;
+        for line in input.lines() {
+            let (key, value) = line
+                .split_once(' ')
+                .with_context(|| format!("Invalid option: {line:?}"))?;
+            let value = value.trim().parse()?;
+            match key {
+                "boards" => options.board_cnt = value,
+                "height" => options.height = value,
+                "width" => options.width = value,
+                "boxes" => options.box_cnt = value,
+                "min_pushes" => options.min_pushes = value,
+                "seed" => options.seed = value as u64,
+                "climb_to" => climb = Some(value as f64),
+                _ => bail!("Unknown option: {key:?}"),
+            }
+        }
+
+        let level = generate(&options)?;
+        let report = level.game.verify(&level.solution.moves)?;
+        ensure!(report.is_success, "Invalid solution");
+        ensure!(
+            report.pushes >= options.min_pushes,
+            "Too few pushes: {}",
+            report.pushes,
+        );
+        let again = generate(&options)?;
+        ensure!(again.game == level.game, "Generation is not deterministic");
+
+        for mutation in Mutation::ALL {
+            if let Some(mutated) = mutation.apply(&level.game, options.seed) {
+                // Walls are not compared by `Game::eq`.
+                ensure!(
+                    mutated.to_string() != level.game.to_string(),
+                    "{mutation:?} changes nothing",
+                );
+            }
+        }
+        let climbed = match climb {
+            Some(target_score) => {
+                let climb_options = ClimbOptions {
+                    target_score,
+                    max_iterations: 20,
+                    seed: options.seed,
+                    ..ClimbOptions::default()
+                };
+                let initial = difficulty(&level.game).score;
+                let ret = hill_climb(level.game.clone(), &climb_options);
+                ensure!(
+                    (ret.score.score - target_score).abs() <= (initial - target_score).abs(),
+                    "hill_climb moves away from the target",
+                );
+                format!(
+                    "Climbed from {initial:.2} to {:.2} in {} iterations\n{}",
+                    ret.score.score, ret.iterations, ret.game,
+                )
+            }
+            None => String::new(),
+        };
+
+        let steps = level
+            .solution
+            .moves
+            .iter()
+            .map(|&dir| fmt_direction(dir))
+            .collect::<String>();
+        Ok(format!(
+            "{input}\n\n{SEPARATOR}{}{steps}\nAttempts: {}\n{climbed}",
+            level.game, level.attempts,
+        ))
+    });
+}
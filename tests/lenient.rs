@@ -0,0 +1,31 @@
+use anyhow::Context;
+use parabox_solver::{Game, Padding, ParseOptions};
+
+use crate::common::*;
+
+mod common;
+
+fn main() {
+    run_tests("lenient", true, |content| {
+        let input = content
+            .split_once(SEPARATOR)
+            .map_or(content, |(input, _)| input)
+            .trim();
+        // Maps titled `empty padding` are padded with empty cells.
+        let options = ParseOptions {
+            lenient: true,
+            padding: if content.contains("; title: empty padding") {
+                Padding::Empty
+            } else {
+                Padding::Wall
+            },
+            ..ParseOptions::default()
+        };
+        let (game, warnings) = Game::parse_with_options(input, &options).context("Invalid map")?;
+        let warnings = warnings
+            .iter()
+            .map(|warning| format!("{warning}\n"))
+            .collect::<String>();
+        Ok(format!("{input}\n\n{SEPARATOR}{game}{warnings}"))
+    });
+}
@@ -0,0 +1,38 @@
+use anyhow::ensure;
+use parabox_solver::{lint, Game, Severity};
+
+use crate::common::*;
+
+mod common;
+
+fn main() {
+    run_tests("lint", true, |content| {
+        let map = content
+            .split_once(SEPARATOR)
+            .map_or(content, |(input, _)| input)
+            .trim();
+        let diagnostics = lint(map);
+        let has_error = diagnostics
+            .iter()
+            .any(|diag| diag.severity == Severity::Error);
+        // Maps with errors should fail to parse or to be solved, and be located where possible.
+        ensure!(
+            has_error || map.parse::<Game>().is_ok(),
+            "The map fails to parse without errors",
+        );
+        for diag in &diagnostics {
+            if let Some(span) = diag.span {
+                let line = map.lines().nth(span.line - 1).unwrap_or_default();
+                ensure!(
+                    span.start < span.end && span.end <= line.chars().count(),
+                    "Span out of the line: {diag}",
+                );
+            }
+        }
+        let got = diagnostics
+            .iter()
+            .map(|diag| format!("{diag}\n{}\n", diag.to_json()))
+            .collect::<String>();
+        Ok(format!("{map}\n\n{SEPARATOR}{got}"))
+    });
+}
@@ -1,6 +1,6 @@
 use std::fmt::Write;
 
-use anyhow::{bail, ensure, Context};
+use anyhow::{bail, ensure, Context, Result};
 use common::*;
 use parabox_solver::{Direction, Game};
 
@@ -16,6 +16,7 @@ fn main() {
         ensure!(!actions.is_empty(), "No actions");
 
         let mut game = map.parse::<Game>().context("Invalid map")?;
+        let initial = game.clone();
         let mut got = format!("{input}\n\n{SEPARATOR}");
         for (ch, i) in actions.chars().zip(1..) {
             (|| {
@@ -29,7 +30,27 @@ fn main() {
                 game.state.go(dir).context("Move failed")
             })()
             .with_context(|| format!("Failed to perform step {i} {ch}"))?;
-            write!(got, "{game}{SEPARATOR}").unwrap();
+            write!(got, "{}{SEPARATOR}", game.state).unwrap();
+        }
+
+        // Replaying stops at the first invalid move.
+        let steps = actions
+            .chars()
+            .map(parse_direction)
+            .collect::<Result<Vec<_>>>()?;
+        let invalid_dir = Direction::ALL
+            .into_iter()
+            .find(|&dir| game.state.clone().go(dir).is_err());
+        if let Some(dir) = invalid_dir {
+            let invalid_steps = [&steps[..], &[dir, dir]].concat();
+            let err = initial
+                .verify(&invalid_steps)
+                .err()
+                .context("Invalid move is not detected")?;
+            ensure!(
+                err.step == steps.len() && err.dir == dir,
+                "Invalid move is reported at a wrong step: {err}",
+            );
         }
 
         Ok(got)
@@ -0,0 +1,41 @@
+use anyhow::{ensure, Context};
+use parabox_solver::{find_levels, solve, Game};
+
+use crate::common::*;
+
+mod common;
+
+fn main() {
+    // A directory like Workshop downloads, with an item of the level and its notes.
+    let workshop = std::env::temp_dir().join(format!("parabox-native-{}", std::process::id()));
+    run_tests("native", true, |content| {
+        let input = content
+            .split_once(SEPARATOR)
+            .map_or(content, |(input, _)| input)
+            .trim();
+        let game = Game::from_parabox(input).context("Invalid level")?;
+        let exported = Game::from_parabox(&game.to_parabox()).context("Invalid exported level")?;
+        ensure!(exported == game, "Exported level mismatches: {exported}");
+
+        let item = workshop.join("1234");
+        std::fs::create_dir_all(&item)?;
+        std::fs::write(item.join("level.txt"), input)?;
+        std::fs::write(item.join("notes.txt"), "Not a level")?;
+        let discovery = find_levels(std::slice::from_ref(&workshop))?;
+        std::fs::remove_dir_all(&workshop)?;
+        ensure!(
+            discovery.skipped.is_empty()
+                && matches!(&discovery.levels[..], [level] if level.name == "1234/level" && level.game == game),
+            "Invalid discovery: {discovery:?}",
+        );
+        let solution = match solve::bfs(game.clone(), |_| {}) {
+            Some(solution) => solution
+                .moves
+                .iter()
+                .map(|&dir| fmt_direction(dir))
+                .collect(),
+            None => "Unsolvable".to_owned(),
+        };
+        Ok(format!("{input}\n\n{SEPARATOR}{game}{solution}\n"))
+    });
+}
@@ -0,0 +1,142 @@
+use std::fmt::Write;
+
+use anyhow::{ensure, Context};
+use parabox_solver::{BoardLayout, DisplayOptions, Game, NestedRenderer, RenderStyle, Theme};
+
+use crate::common::*;
+
+mod common;
+
+fn main() {
+    run_tests("render", true, |content| {
+        let map = content
+            .split_once(SEPARATOR)
+            .map_or(content, |(input, _)| input)
+            .trim();
+        let game = map.parse::<Game>().context("Invalid map")?;
+
+        // Without nesting and scaling, the board of the player renders as in the text format.
+        let renderer = NestedRenderer {
+            depth: 0,
+            cell_size: 1,
+            ..NestedRenderer::default()
+        };
+        let text = game.to_string();
+        let header = format!("{:X}", game.state.player().board_id as usize);
+        let block = text
+            .split("\n\n")
+            .find_map(|block| block.strip_prefix(&header)?.strip_prefix('\n'))
+            .context("Missing the board of the player")?;
+        ensure!(
+            renderer.render(&game.state, &game.config).trim_end() == block.trim_end(),
+            "NestedRenderer mismatches the text format",
+        );
+
+        // Without nesting, cells of 4x4 dots are either blank or full.
+        let braille = NestedRenderer {
+            cell_size: 4,
+            ..renderer.clone()
+        };
+        let expected = renderer
+            .render(&game.state, &game.config)
+            .chars()
+            .map(|ch| match ch {
+                '\n' => "\n",
+                '.' | '_' | '=' => "\u{2800}\u{2800}",
+                _ => "\u{28FF}\u{28FF}",
+            })
+            .collect::<String>();
+        ensure!(
+            braille.render_braille(&game.state, &game.config) == expected,
+            "Invalid Braille rendering",
+        );
+
+        // Render styles only replace characters.
+        let shape = |text: &str| {
+            text.lines()
+                .map(|line| line.chars().count())
+                .collect::<Vec<_>>()
+        };
+        let ascii = game
+            .state
+            .display_styled(&game.config, RenderStyle::Ascii)
+            .to_string();
+        for style in RenderStyle::ALL {
+            ensure!(
+                style.name().parse::<RenderStyle>()? == style,
+                "Invalid style name"
+            );
+            let text = game.state.display_styled(&game.config, style).to_string();
+            ensure!(
+                shape(&text) == shape(&ascii),
+                "{style:?} changes the layout"
+            );
+        }
+
+        // Horizontal layouts too narrow for two boards are vertical.
+        let display = |layout| {
+            let options = DisplayOptions {
+                layout,
+                ..DisplayOptions::default()
+            };
+            game.state
+                .display_on_with(&game.config, options)
+                .to_string()
+        };
+        ensure!(
+            display(BoardLayout::Horizontal { max_width: Some(0) })
+                == display(BoardLayout::Vertical),
+            "Narrow horizontal layout mismatches the vertical one",
+        );
+
+        // Rulers name the position of the player.
+        let options = DisplayOptions {
+            ruler: true,
+            ..DisplayOptions::default()
+        };
+        let ruled = game
+            .state
+            .display_on_with(&game.config, options)
+            .to_string();
+        ensure!(
+            ruled.contains(&format!("Player: {:?}\n", game.state.player())),
+            "Missing the player position in the ruler",
+        );
+
+        // Themes replace glyphs of both text renderers alike.
+        let mut theme = Theme::default();
+        theme.glyphs.wall = '@';
+        let themed = NestedRenderer {
+            theme,
+            ..renderer.clone()
+        };
+        ensure!(
+            themed.render(&game.state, &game.config)
+                == renderer.render(&game.state, &game.config).replace('#', "@"),
+            "Theme glyphs are not used by NestedRenderer",
+        );
+        let options = DisplayOptions {
+            theme,
+            ..DisplayOptions::default()
+        };
+        ensure!(
+            game.state
+                .display_on_with(&game.config, options)
+                .to_string()
+                == display(BoardLayout::Vertical).replace('#', "@"),
+            "Theme glyphs are not used by the text display",
+        );
+
+        let mut got = format!("{map}\n\n{SEPARATOR}");
+        let unicode = game
+            .state
+            .display_styled(&game.config, RenderStyle::Unicode);
+        write!(got, "{unicode}{SEPARATOR}").unwrap();
+        let nested = NestedRenderer {
+            cell_size: 3,
+            ..NestedRenderer::default()
+        };
+        got += &nested.render(&game.state, &game.config);
+        Ok(got)
+    });
+}
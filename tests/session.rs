@@ -0,0 +1,111 @@
+use anyhow::{ensure, Context};
+use parabox_solver::{solve, Game, Metadata, Paint, Session, Step};
+
+use crate::common::*;
+
+mod common;
+
+fn main() {
+    run_tests("session", true, |content| {
+        let input = content
+            .split_once(SEPARATOR)
+            .map_or(content, |(input, _)| input)
+            .trim();
+        let (moves, map) = input.split_once('\n').context("No moves")?;
+        let steps = parse_moves(moves)?;
+        let game = map.parse::<Game>().context("Invalid map")?;
+        let final_state = game.verify(&steps)?.final_state;
+
+        // Outcomes of moves agree with `State::go`.
+        let mut state = game.state.clone();
+        for &dir in &steps {
+            let mut next = state.clone();
+            let pushed = next.go(dir)?;
+            let outcome = state.go_with_outcome(dir)?;
+            ensure!(
+                state == next
+                    && outcome.is_push() == pushed
+                    && outcome.moved[0].to == state.player(),
+                "Invalid outcome of {dir:?}: {outcome:?}",
+            );
+        }
+
+        // Random play keeps invariants of the engine.
+        let report = game.fuzz(0, 200)?;
+        ensure!(
+            game.verify(&report.moves)?.pushes == report.pushes,
+            "fuzz miscounts pushes",
+        );
+
+        // Walks reach every trivially reachable location without pushing.
+        for loc in game.state.trivially_reachable_locations() {
+            let walk = solve::walk_to(&game.state, loc).context("walk_to")?;
+            let report = game.verify(&walk)?;
+            ensure!(
+                report.final_state.player() == loc && report.pushes == 0,
+                "walk_to reaches a wrong state",
+            );
+        }
+
+        // Painting is undone by painting back.
+        let player = game.state.player();
+        ensure!(
+            game.paint(player, Paint::Wall).is_err(),
+            "Painting overwrites the player",
+        );
+        let repainted = game.paint(player, Paint::Player)?;
+        ensure!(
+            repainted.to_string() == game.to_string(),
+            "Painting the player in place changes the level: {repainted}",
+        );
+        let toggled = game.paint(player, Paint::BoxTarget)?;
+        let toggled = toggled.paint(player, Paint::BoxTarget)?;
+        ensure!(
+            toggled.to_string() == game.to_string(),
+            "Toggling a box target twice changes the level: {toggled}",
+        );
+
+        // Walk a move, reset, then replay the solution.
+        let mut described = game.clone();
+        described.metadata = Metadata {
+            title: Some("A level".into()),
+            ..Metadata::default()
+        };
+        let mut session = Session::new(described);
+        session.steps = steps
+            .iter()
+            .take(1)
+            .map(|&dir| Step::Go(dir))
+            .chain([Step::Reset])
+            .chain(steps.iter().map(|&dir| Step::Go(dir)))
+            .collect();
+        session.bookmarks.insert("after reset".into(), 2);
+        let text = session.to_text()?;
+        let loaded = text.parse::<Session>()?;
+        ensure!(
+            loaded == session,
+            "Session mismatches:\n{}",
+            loaded.to_text()?,
+        );
+        let states = loaded.states()?;
+        ensure!(
+            states[loaded.bookmarks["after reset"]] == game.state
+                && states.last() == Some(&final_state),
+            "Session replays to wrong states",
+        );
+        // Undone steps are saved, and redone back to the same steps.
+        let mut undone = loaded.clone();
+        let undone_steps = [undone.undo(), undone.undo()];
+        let mut reloaded = undone.to_text()?.parse::<Session>()?;
+        ensure!(reloaded == undone, "Undone session mismatches");
+        let redone_steps = [reloaded.redo(), reloaded.redo()];
+        ensure!(
+            redone_steps == [undone_steps[1], undone_steps[0]] && reloaded == loaded,
+            "Redo mismatches undo",
+        );
+        undone.push(Step::Reset);
+        ensure!(undone.redo().is_none(), "New steps keep undone steps");
+
+        Ok(format!("{input}\n\n{SEPARATOR}{text}"))
+    });
+}
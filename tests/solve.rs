@@ -1,5 +1,14 @@
-use anyhow::{ensure, Context};
-use parabox_solver::{solve, Game};
+use std::collections::HashSet;
+use std::io;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, ensure, Context};
+use parabox_solver::solve::{
+    BatchProgress, BeamHeuristic, BfsSearch, DeadlockDetector, Hint, Limit, RecordStates, Solution,
+    SolveCache, SolveOutcome, SolverOptions,
+};
+use parabox_solver::{solve, Direction, Game};
 
 use crate::common::*;
 
@@ -11,18 +20,458 @@ fn main() {
             .split_once(SEPARATOR)
             .map_or(content, |(input, _)| input)
             .trim();
-        let mut game = map.parse::<Game>().context("Invalid map")?;
+        let game = map.parse::<Game>().context("Invalid map")?;
+
+        let validate = |steps: &[Direction]| {
+            let report = game.verify(steps)?;
+            ensure!(report.is_success, "Invalid solution");
+            Ok(report.pushes)
+        };
+
+        let solution = solve::bfs(game.clone(), |_| {}).context("No solution")?;
+        ensure!(solution.is_optimal, "bfs does not claim optimality");
+        let steps = solution.moves;
+        if !steps.is_empty() {
+            let options = SolverOptions {
+                max_nodes: Some(1),
+                ..SolverOptions::default()
+            };
+            let outcome = solve::bfs_with_options(game.clone(), &options, |_| {});
+            ensure!(
+                matches!(outcome, SolveOutcome::LimitReached(Limit::Nodes, _)),
+                "Node limit is not respected: {outcome:?}",
+            );
 
-        let steps = solve::bfs(game.clone(), || {}).context("No solution")?;
+            let options = SolverOptions {
+                cancel: Some(Arc::new(AtomicBool::new(true))),
+                ..SolverOptions::default()
+            };
+            let outcome = solve::bfs_with_options(game.clone(), &options, |_| {});
+            ensure!(
+                matches!(outcome, SolveOutcome::Cancelled(_)),
+                "Cancellation is not respected: {outcome:?}",
+            );
+
+            // Stop, save and resume.
+            let mut search = BfsSearch::new(game.clone());
+            let options = SolverOptions {
+                max_nodes: Some(1),
+                ..SolverOptions::default()
+            };
+            let outcome = search.run(&options, |_| {});
+            ensure!(matches!(outcome, SolveOutcome::LimitReached(..)));
+            let partial = search.closest().context("No closest state")?;
+            ensure!(
+                game.verify(&partial.moves)?.final_state == partial.state,
+                "Moves of the closest state reach a different state",
+            );
+            let mut checkpoint = Vec::new();
+            search.save(&mut checkpoint)?;
+            // Damaged checkpoints are rejected without panicking.
+            let is_rejected = |data: &[u8]| {
+                let err = BfsSearch::load(game.clone(), data).err();
+                err.is_some_and(|err| err.kind() == io::ErrorKind::InvalidData)
+            };
+            ensure!(
+                (0..checkpoint.len()).all(|len| is_rejected(&checkpoint[..len])),
+                "Truncated checkpoint is not rejected",
+            );
+            for bit in 0..checkpoint.len() * 8 {
+                let mut damaged = checkpoint.clone();
+                damaged[bit / 8] ^= 1 << (bit % 8);
+                if let Err(err) = BfsSearch::load(game.clone(), &damaged[..]) {
+                    ensure!(
+                        err.kind() == io::ErrorKind::InvalidData,
+                        "Unexpected error of a damaged checkpoint: {err}",
+                    );
+                }
+            }
+            let mut search = BfsSearch::load(game.clone(), &checkpoint[..])?;
+            let resumed = search
+                .run(&SolverOptions::default(), |_| {})
+                .into_solution()
+                .context("No solution after resuming")?;
+            ensure!(
+                resumed.moves == steps,
+                "Resumed search gives a different solution",
+            );
+        }
+        // Every discovered state except the initial one has an incoming push.
+        let mut search = BfsSearch::new(game.clone());
+        let options = SolverOptions {
+            record_graph: true,
+            ..SolverOptions::default()
+        };
+        let outcome = search.run(&options, |_| {});
+        let SolveOutcome::Solved(Solution {
+            peak_states,
+            nodes_expanded,
+            ..
+        }) = outcome
+        else {
+            bail!("No solution with the graph recorded");
+        };
+        let metrics = search.metrics();
+        ensure!(
+            metrics.expansions_per_depth.iter().sum::<usize>() == nodes_expanded
+                && metrics.closure_sizes.iter().sum::<usize>() + 1 == nodes_expanded,
+            "Metrics mismatch expanded states: {metrics:?}",
+        );
+        let mut dot = Vec::new();
+        search.write_dot(&mut dot)?;
+        let dot = String::from_utf8(dot)?;
+        let has_incoming = (1..peak_states).all(|i| dot.contains(&format!("-> {i} ")));
+        ensure!(has_incoming, "Recorded graph is incomplete");
 
-        // Validate.
+        let pushes = validate(&steps)?;
+        ensure!(
+            solution.pushes == pushes,
+            "Push count mismatch: got {}, expecting {pushes}",
+            solution.pushes,
+        );
+        let lurd = game.to_lurd(&steps)?;
+        let expected = steps
+            .iter()
+            .map(|&dir| fmt_direction(dir))
+            .collect::<String>();
+        ensure!(
+            lurd.to_ascii_uppercase() == expected
+                && lurd.chars().filter(char::is_ascii_uppercase).count() == pushes,
+            "Invalid LURD: {lurd}",
+        );
+
+        // Stitch two segments through the middle state of the solution.
+        let mid = steps.len() / 2;
+        let mid_state = game.verify(&steps[..mid])?.final_state;
+        let final_state = game.verify(&steps)?.final_state;
+        let first = solve::path_to(&game, &game.state, &mid_state).context("path_to")?;
+        ensure!(first.len() <= mid, "path_to is not move-optimal");
+        ensure!(
+            game.verify(&first)?.final_state == mid_state,
+            "path_to reaches a wrong state",
+        );
+        let second = solve::path_to(&game, &mid_state, &final_state).context("path_to")?;
+        validate(&[first, second].concat()).context("path_to")?;
+
+        // States along a solution are never dead.
+        let deadlock = DeadlockDetector::new(&game);
+        let mut state = game.state.clone();
         for &dir in &steps {
-            game.state.go(dir).context("Invalid move")?;
+            ensure!(!deadlock.is_dead(&state), "Solvable state is dead: {state}");
+            state.go(dir)?;
+        }
+
+        // Following hints keeps the level solvable.
+        let options = SolverOptions::default();
+        ensure!(
+            solve::hint(&game, &final_state, &options) == Hint::Solved,
+            "hint misses the success",
+        );
+        if let Hint::Move(dir) = solve::hint(&game, &mid_state, &options) {
+            let mut state = mid_state.clone();
+            state.go(dir)?;
+            ensure!(
+                solve::hint(&game, &state, &options) != Hint::Unsolvable,
+                "hint leads to an unsolvable state",
+            );
+        } else {
+            ensure!(mid_state == final_state, "No hint from the middle state");
         }
-        ensure!(game.is_success(), "Invalid solution");
+
+        for record in [RecordStates::Moves, RecordStates::Pushes] {
+            let options = SolverOptions {
+                record_states: Some(record),
+                ..SolverOptions::default()
+            };
+            let recorded = solve::bfs_with_options(game.clone(), &options, |_| {})
+                .into_solution()
+                .context("No solution when recording states")?;
+            // The player may still walk after the last push.
+            let (expected_len, is_last_final) = match record {
+                RecordStates::Moves => (recorded.moves.len(), true),
+                RecordStates::Pushes => (recorded.pushes, false),
+            };
+            ensure!(
+                recorded.moves == steps
+                    && recorded.states.len() == expected_len
+                    && (!is_last_final || recorded.states.last() == Some(&final_state)),
+                "Wrong states are recorded by {record:?}",
+            );
+        }
+
+        let options = SolverOptions::default();
+        let astar_steps = solve::astar(game.clone(), &options, |_| {})
+            .into_solution()
+            .context("No solution from A*")?
+            .moves;
+        let astar_pushes = validate(&astar_steps).context("A*")?;
+        ensure!(
+            astar_pushes == pushes,
+            "A* is not push-optimal: got {astar_pushes}, expecting {pushes}",
+        );
+
+        let par_steps = solve::par_bfs(game.clone(), &options, |_| {})
+            .into_solution()
+            .context("No solution from par_bfs")?
+            .moves;
+        let par_pushes = validate(&par_steps).context("par_bfs")?;
+        ensure!(
+            par_pushes == pushes,
+            "par_bfs is not push-optimal: got {par_pushes}, expecting {pushes}",
+        );
+
+        // The retrograde model rejects levels with board cells explicitly.
+        let is_retrograde_supported = match solve::bidirectional(game.clone(), &options, |_| {}) {
+            SolveOutcome::Solved(solution) => {
+                validate(&solution.moves).context("bidirectional")?;
+                true
+            }
+            SolveOutcome::Unsupported(_) => false,
+            outcome => bail!("No solution from bidirectional: {outcome:?}"),
+        };
+
+        let dir = std::env::temp_dir().join(format!("parabox-solver-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let disk_outcome = solve::bfs_on_disk(game.clone(), &dir, &options, |_| {});
+        std::fs::remove_dir_all(&dir)?;
+        let disk_steps = disk_outcome?
+            .into_solution()
+            .context("No solution from bfs_on_disk")?
+            .moves;
+        let disk_pushes = validate(&disk_steps).context("bfs_on_disk")?;
+        ensure!(
+            disk_pushes == pushes,
+            "bfs_on_disk is not push-optimal: got {disk_pushes}, expecting {pushes}",
+        );
+
+        let approx_steps = solve::bfs_approximate(game.clone(), 1 << 20, &options, |_| {})
+            .into_solution()
+            .context("No solution from bfs_approximate")?
+            .moves;
+        let approx_pushes = validate(&approx_steps).context("bfs_approximate")?;
+        // False positives of the Bloom filter may prune optimal solutions.
+        ensure!(
+            approx_pushes >= pushes,
+            "bfs_approximate is better than optimal: got {approx_pushes}, expecting {pushes}",
+        );
+        // The Bloom filter counts towards the memory limit.
+        let limited = SolverOptions {
+            max_memory_bytes: Some(1 << 19),
+            ..SolverOptions::default()
+        };
+        let outcome = solve::bfs_approximate(game.clone(), 1 << 20, &limited, |_| {});
+        ensure!(
+            matches!(outcome, SolveOutcome::LimitReached(Limit::Memory, _)),
+            "Memory limit of bfs_approximate is not respected: {outcome:?}",
+        );
+
+        for heuristic in [BeamHeuristic::Matching, BeamHeuristic::UnfilledTargets] {
+            let beam = solve::beam(game.clone(), 1 << 16, heuristic, &options, |_| {})
+                .into_solution()
+                .with_context(|| format!("No solution from beam with {heuristic:?}"))?;
+            validate(&beam.moves).context("beam")?;
+            ensure!(!beam.is_optimal, "beam claims optimality");
+        }
+        // A narrow beam may miss all solutions, but any found one must be valid.
+        let narrow = solve::beam(game.clone(), 1, BeamHeuristic::Matching, &options, |_| {});
+        if let Some(narrow) = narrow.into_solution() {
+            validate(&narrow.moves).context("narrow beam")?;
+        }
+
+        // Solvers are deterministic for the same seed, and still optimal if they claim so.
+        let seeded = SolverOptions {
+            seed: Some(42),
+            ..SolverOptions::default()
+        };
+        for name in solve::SOLVER_NAMES {
+            let solver = solve::solver_by_name(name).context("Missing solver")?;
+            let solution = match solver.solve(&game, &seeded, &mut |_| {}) {
+                SolveOutcome::Solved(solution) => solution,
+                SolveOutcome::Unsupported(_)
+                    if name == "bidirectional" && !is_retrograde_supported =>
+                {
+                    continue
+                }
+                outcome => bail!("No solution from solver {name}: {outcome:?}"),
+            };
+            let got_pushes = validate(&solution.moves).with_context(|| format!("solver {name}"))?;
+            ensure!(
+                !solution.is_optimal || name == "bfs-moves" || got_pushes == pushes,
+                "Seeded solver {name} is not push-optimal: got {got_pushes}, expecting {pushes}",
+            );
+            let again = solver.solve(&game, &seeded, &mut |_| {}).into_solution();
+            ensure!(
+                again.map(|s| s.moves) == Some(solution.moves),
+                "Solver {name} is not deterministic",
+            );
+        }
+
+        let last_progress = Mutex::new(BatchProgress::default());
+        let outcomes = solve::solve_many(&[game.clone(), game.clone()], &options, |progress| {
+            let mut last = last_progress.lock().unwrap();
+            last.finished = last.finished.max(progress.finished);
+        });
+        for outcome in outcomes {
+            let solution = outcome
+                .into_solution()
+                .context("No solution from solve_many")?;
+            ensure!(solution.pushes == pushes, "solve_many is not push-optimal");
+        }
+        ensure!(
+            last_progress.into_inner().unwrap().finished == 2,
+            "solve_many misses progress of finished levels",
+        );
+
+        // Re-solving with a cache gives the same solution, even after an edit of walls.
+        let mut cache = SolveCache::new();
+        let cached = cache.solve(&game, &options, |_| {}).into_solution();
+        ensure!(
+            cached.as_ref().map(|s| &s.moves) == Some(&steps),
+            "SolveCache gives a different solution",
+        );
+        let resolved = cache
+            .solve(&game, &options, |_| {})
+            .into_solution()
+            .context("No solution from SolveCache")?;
+        ensure!(
+            resolved.moves == steps && cache.reused() == resolved.nodes_expanded,
+            "SolveCache does not reuse all expansions of the same level",
+        );
+        let edited = map.replacen('.', "#", 1).parse::<Game>()?;
+        let expected = solve::bfs(edited.clone(), |_| {}).map(|s| s.moves);
+        let got = cache.solve(&edited, &options, |_| {}).into_solution();
+        ensure!(
+            got.map(|s| s.moves) == expected,
+            "SolveCache gives a different solution after an edit",
+        );
+
+        let mut improving = Vec::new();
+        let anytime = solve::anytime(
+            game.clone(),
+            &options,
+            |solution| improving.push(solution.pushes),
+            |_| {},
+        )
+        .into_solution()
+        .context("No solution from anytime")?;
+        validate(&anytime.moves).context("anytime")?;
+        ensure!(
+            anytime.is_optimal && anytime.pushes == pushes,
+            "anytime is not push-optimal: got {}, expecting {pushes}",
+            anytime.pushes,
+        );
+        ensure!(
+            improving.windows(2).all(|w| w[0] > w[1]) && improving.last() == Some(&pushes),
+            "anytime reports non-improving solutions: {improving:?}",
+        );
+
+        let moves_steps = solve::bfs_moves(game.clone(), &options, |_| {})
+            .into_solution()
+            .context("No move-optimal solution")?
+            .moves;
+        validate(&moves_steps).context("bfs_moves")?;
+        ensure!(
+            moves_steps.len() <= steps.len(),
+            "bfs_moves is not move-optimal: got {}, but bfs got {}",
+            moves_steps.len(),
+            steps.len(),
+        );
+
+        // Datasets agree with `bfs_moves` on small levels.
+        let dataset_options = solve::DatasetOptions {
+            max_states: 100_000,
+        };
+        if let Ok(dataset) = solve::dataset(&game, &dataset_options) {
+            let [boards, height, width] = dataset.shape;
+            let distance = |i: i32| dataset.distances[i as usize];
+            ensure!(
+                dataset.distances[0] == moves_steps.len() as i32
+                    && dataset.states.len() == dataset.len() * boards * height * width
+                    && dataset.transitions.iter().all(|&[from, _, to]| {
+                        distance(to) < 0 || (0..=distance(to) + 1).contains(&distance(from))
+                    }),
+                "Invalid dataset: distance {}",
+                dataset.distances[0],
+            );
+        }
+
+        match solve::winning_states(&game, &options, |_| {}) {
+            Ok(winning) => ensure!(
+                is_retrograde_supported && winning.distance(&game.state) == Some(pushes),
+                "winning_states gives a wrong distance: {:?}, expecting {pushes}",
+                winning.distance(&game.state),
+            ),
+            Err(SolveOutcome::Unsupported(_)) => ensure!(
+                !is_retrograde_supported,
+                "winning_states rejects a level supported by bidirectional",
+            ),
+            Err(outcome) => bail!("winning_states stopped: {outcome:?}"),
+        }
+
+        let score = solve::difficulty(&game);
+        ensure!(
+            score.pushes == Some(pushes) && score.score.is_finite(),
+            "difficulty mismatches the solution: {score:?}",
+        );
+        ensure!(
+            (0.0..=1.0).contains(&score.deadlock_density) && score == solve::difficulty(&game),
+            "difficulty is invalid or not deterministic: {score:?}",
+        );
+
+        let report = solve::explore(game.clone());
+        ensure!(report.is_solvable(), "explore finds no winning state");
+        ensure!(
+            report.depth_histogram.iter().sum::<usize>() == report.states,
+            "Depth histogram mismatches the count of states",
+        );
+        ensure!(
+            report.max_depth() >= pushes,
+            "explore misses states of the solution",
+        );
+
+        // Optimal solutions may be exponentially many, thus only check some of them.
+        let all_steps = solve::all_optimal(game.clone(), &options)
+            .take(64)
+            .map(|solution| solution.moves)
+            .collect::<Vec<_>>();
+        ensure!(
+            all_steps.contains(&moves_steps),
+            "all_optimal misses the solution of bfs_moves",
+        );
+        for all_steps in &all_steps {
+            validate(all_steps).context("all_optimal")?;
+            ensure!(
+                all_steps.len() == moves_steps.len(),
+                "all_optimal gives a non-optimal solution",
+            );
+        }
+        let distinct = all_steps.iter().collect::<HashSet<_>>();
+        ensure!(
+            distinct.len() == all_steps.len(),
+            "all_optimal gives duplicated solutions",
+        );
 
         let steps = steps.into_iter().map(fmt_direction).collect::<String>();
 
         Ok(format!("{map}\n\n{SEPARATOR}{steps}\n"))
     });
+
+    // The heuristic stays informed with boxes entering and exiting boards. BFS reduces
+    // symmetric states while A* does not, so compare them on a level without symmetries.
+    let content = include_str!("solve/challenge2.map");
+    let game = content
+        .split_once(SEPARATOR)
+        .unwrap()
+        .0
+        .parse::<Game>()
+        .unwrap();
+    let astar_expanded = solve::astar(game.clone(), &SolverOptions::default(), |_| {})
+        .into_solution()
+        .unwrap()
+        .nodes_expanded;
+    let bfs_expanded = solve::bfs(game, |_| {}).unwrap().nodes_expanded;
+    assert!(
+        astar_expanded < bfs_expanded,
+        "A* expands {astar_expanded} states, no fewer than {bfs_expanded} of BFS",
+    );
 }
@@ -0,0 +1,28 @@
+use anyhow::{ensure, Context};
+use parabox_solver::{solve, Game};
+
+use crate::common::*;
+
+mod common;
+
+fn main() {
+    run_tests("trim", true, |content| {
+        let map = content
+            .split_once(SEPARATOR)
+            .map_or(content, |(input, _)| input)
+            .trim();
+        let game = map.parse::<Game>().context("Invalid map")?;
+        let trimmed = game.trim();
+        let again = trimmed.trim();
+        ensure!(
+            again.to_string() == trimmed.to_string(),
+            "Trimming is not idempotent:\n{again}",
+        );
+        let moves = |game: &Game| solve::bfs(game.clone(), |_| {}).map(|s| s.moves);
+        ensure!(
+            moves(&trimmed) == moves(&game),
+            "Trimming changes the solution",
+        );
+        Ok(format!("{map}\n\n{SEPARATOR}{trimmed}"))
+    });
+}
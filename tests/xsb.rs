@@ -0,0 +1,41 @@
+use anyhow::{ensure, Context};
+use parabox_solver::{solve, Game, LevelPack};
+
+use crate::common::*;
+
+mod common;
+
+fn main() {
+    run_tests("xsb", true, |content| {
+        let input = content
+            .split_once(SEPARATOR)
+            .map_or(content, |(input, _)| input)
+            .trim_end();
+        let pack = LevelPack::from_xsb(input).context("Invalid collection")?;
+        let mut out = format!("{input}\n\n{SEPARATOR}");
+        for (name, game) in pack.iter() {
+            let reparsed = game
+                .to_string()
+                .parse::<Game>()
+                .context("Invalid display")?;
+            ensure!(&reparsed == game, "Display mismatches: {reparsed}");
+            let decoded = Game::decode_share_string(&game.encode_share_string())?;
+            // Share strings do not keep metadata.
+            ensure!(
+                decoded.state == game.state && decoded.config == game.config,
+                "Share string mismatches: {decoded}",
+            );
+            let exported = Game::from_xsb(&game.to_xsb()?).context("Invalid export")?;
+            ensure!(
+                exported.to_string() == game.to_string(),
+                "XSB export mismatches: {exported}",
+            );
+            let solution = match solve::bfs(game.clone(), |_| {}) {
+                Some(solution) => game.to_lurd(&solution.moves)?,
+                None => "Unsolvable".to_owned(),
+            };
+            out += &format!("[{name}]\n{game}{solution}\n\n");
+        }
+        Ok(out)
+    });
+}